@@ -0,0 +1,246 @@
+//! Administrative endpoints. All of these require [`Admin`] authentication.
+
+use std::sync::Arc;
+
+use log::{info, warn};
+use rocket::{Route, Shutdown, State, post, routes, tokio::sync::Mutex};
+use serde_json::{Map, Value, json};
+
+use crate::{
+    auth::Admin,
+    config::{CONFIG_PATH, Config, SharedConfig},
+    response::{Error, Success},
+    rotator::{self, Rotator, presets::Presets, registry::Rotators},
+};
+
+pub fn endpoints() -> Vec<Route> {
+    routes![shutdown, reload]
+}
+
+/// Parks the rotator, halts it, and then gracefully stops the server.
+///
+/// Failing to park or halt is logged but does not prevent the shutdown.
+#[post("/shutdown")]
+async fn shutdown(
+    _admin: Admin,
+    rotator_state: &State<Arc<Mutex<Rotator>>>,
+    shutdown: Shutdown,
+) -> Success {
+    info!("Shutdown requested, parking rotator");
+
+    if let Err(e) = Rotator::park(rotator_state).await {
+        warn!("Failed to park before shutdown: {e}");
+    }
+    if let Err(e) = rotator_state.lock().await.halt().await {
+        warn!("Failed to halt before shutdown: {e}");
+    }
+
+    shutdown.notify();
+
+    Success::empty()
+}
+
+/// Re-reads the configuration file and applies it to every rotator without
+/// interrupting them, responding with the settings which changed for each.
+/// The presets are re-read from `presets_path` too, and every later request
+/// sees the new configuration.
+///
+/// Nothing is applied if any setting which needs a restart has changed, such
+/// as the serial port settings. Those are listed in the error instead.
+#[post("/reload")]
+async fn reload(
+    _admin: Admin,
+    current: &State<SharedConfig>,
+    rotators: &State<Rotators>,
+    presets: &State<Presets>,
+) -> Result<Success, Error> {
+    let new = Config::load().map_err(|e| Error(format!("Failed to load {CONFIG_PATH}: {e}")))?;
+
+    apply_config(new, current, rotators, presets).await
+}
+
+/// Applies a newly loaded configuration, as described for [`reload`].
+async fn apply_config(
+    new: Config,
+    current: &SharedConfig,
+    rotators: &Rotators,
+    presets: &Presets,
+) -> Result<Success, Error> {
+    let restart = current.get().restart_required(&new);
+    if !restart.is_empty() {
+        return Err(Error(format!("Restart required to change: {}", restart.join(", "))));
+    }
+
+    let presets_changed = presets.reload().await.map_err(Error)?;
+
+    let mut changed = Map::new();
+    for (id, handle) in rotators.iter() {
+        let Some(config) = new.rotator(id) else {
+            continue;
+        };
+
+        let mut rotator = handle.lock().await;
+        let names = rotator.reload_config(config);
+
+        // Unset limits are filled from the firmware, as they are at startup
+        if names.contains(&"limits")
+            && let Err(e) = rotator.seed_limits().await
+            && !matches!(e, rotator::Error::Unsupported(_))
+        {
+            warn!("Failed to read the firmware limits of rotator `{id}`: {e}");
+        }
+
+        changed.insert(id.to_string(), json!(names));
+    }
+
+    current.replace(new);
+    info!("Reloaded {CONFIG_PATH}");
+
+    Ok(Success::data(json!({
+        "changed": Value::Object(changed),
+        "presets_changed": presets_changed,
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, sync::Arc, time::Duration};
+
+    use rocket::{
+        http::{Header, Status},
+        local::asynchronous::Client,
+        routes,
+        tokio::{self, sync::Mutex},
+    };
+
+    use serde_json::{Value, json};
+    use serialport::Parity;
+
+    use crate::{
+        config::{Config, SharedConfig},
+        rotator::{
+            config::{Limits, ParkConfig, PerAxis, RotatorConfig, RotatorEntry},
+            mock::{self, MockFirmware},
+            presets::Presets,
+            registry::{RotatorHandle, Rotators},
+        },
+    };
+
+    async fn client(firmware: &MockFirmware, park: ParkConfig) -> Client {
+        let rotator = firmware.rotator(RotatorConfig { park, ..mock::config() });
+        let rocket = rocket::build()
+            .manage(Arc::new(Mutex::new(rotator)))
+            .manage(SharedConfig::new(Config { admin_token: Some("secret".to_string()), ..Config::default() }))
+            .mount("/admin", routes![super::shutdown]);
+
+        Client::tracked(rocket).await.unwrap()
+    }
+
+    async fn shutdown_signaled(client: &Client) -> bool {
+        tokio::time::timeout(Duration::from_millis(500), client.rocket().shutdown()).await.is_ok()
+    }
+
+    #[rocket::async_test]
+    async fn shutdown_parks_then_halts_before_stopping() {
+        let firmware = MockFirmware::new();
+        let park = ParkConfig { vertical: 30.0, horizontal: 40.0, ..ParkConfig::default() };
+        let client = client(&firmware, park).await;
+
+        let response = client
+            .post("/admin/shutdown")
+            .header(Header::new("Authorization", "Bearer secret"))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+
+        let commands = firmware.commands();
+        assert_eq!(commands[..2], ["DVER", "DHOR"]);
+        assert_eq!(commands.last().unwrap(), "HALT");
+        assert!(commands[2..commands.len() - 1].iter().all(|c| c == "GETP"));
+        assert_eq!(firmware.lock().position.vertical, 30.0);
+        assert!(shutdown_signaled(&client).await);
+    }
+
+    #[rocket::async_test]
+    async fn shutdown_still_halts_if_parking_times_out() {
+        let firmware = MockFirmware::new();
+        firmware.lock().stalled.vertical = true;
+        let park = ParkConfig { vertical: 30.0, horizontal: 0.0, timeout_ms: 300 };
+        let client = client(&firmware, park).await;
+
+        let response = client
+            .post("/admin/shutdown")
+            .header(Header::new("Authorization", "Bearer secret"))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+
+        assert_eq!(firmware.commands().last().unwrap(), "HALT");
+        assert!(shutdown_signaled(&client).await);
+    }
+
+    #[rocket::async_test]
+    async fn shutdown_requires_the_admin_token() {
+        let firmware = MockFirmware::new();
+        let client = client(&firmware, ParkConfig::default()).await;
+
+        let response = client.post("/admin/shutdown").dispatch().await;
+        assert_eq!(response.status(), Status::Unauthorized);
+
+        assert!(firmware.commands().is_empty());
+        assert!(!shutdown_signaled(&client).await);
+    }
+
+    /// The state [`super::apply_config`] reloads, for a rotator connected to
+    /// `firmware`.
+    fn reloadable(firmware: &MockFirmware) -> (SharedConfig, Rotators, Presets) {
+        let config = Config { rotator: mock::config(), ..Config::default() };
+        let rotator = Arc::new(Mutex::new(firmware.rotator(config.rotator.clone())));
+        let presets = std::env::temp_dir().join(format!("archerd-reload-presets-{}.json", std::process::id()));
+
+        (SharedConfig::new(config), Rotators::new(RotatorHandle::unpolled(rotator)), Presets::load(presets))
+    }
+
+    #[rocket::async_test]
+    async fn reloading_applies_new_limits() {
+        let firmware = MockFirmware::new();
+        let (current, rotators, presets) = reloadable(&firmware);
+        let limits = PerAxis { vertical: Some(Limits { min: 0.0, max: 80.0 }), horizontal: None };
+        let new = Config { rotator: RotatorConfig { limits, ..mock::config() }, ..Config::default() };
+
+        let success = super::apply_config(new, &current, &rotators, &presets).await.unwrap();
+
+        let reply: Value = serde_json::from_str(&success.0).unwrap();
+        assert_eq!(reply["data"]["changed"], json!({"default": ["limits"]}));
+        assert_eq!(reply["data"]["presets_changed"], json!(false));
+        let handle = rotators.get("default").unwrap();
+        assert_eq!(handle.lock().await.config().limits, limits);
+        assert_eq!(current.get().rotator.limits, limits);
+        // Unconfigured limits are looked for in the firmware again
+        assert_eq!(firmware.commands(), ["GETL"]);
+    }
+
+    #[rocket::async_test]
+    async fn reloading_refuses_settings_which_need_a_restart() {
+        let firmware = MockFirmware::new();
+        let (current, rotators, presets) = reloadable(&firmware);
+        let entry = |port: &str| RotatorEntry { port: port.to_string(), config: mock::config() };
+        current.replace(Config {
+            rotators: HashMap::from([("b".to_string(), entry("/dev/ttyUSB1"))]),
+            ..Config::clone(&current.get())
+        });
+        let new = Config {
+            rotator: RotatorConfig { parity: Parity::Even, position_tolerance: 2.0, ..mock::config() },
+            rotators: HashMap::from([("b".to_string(), entry("/dev/ttyUSB2"))]),
+            ..Config::default()
+        };
+
+        let error = super::apply_config(new, &current, &rotators, &presets).await.err().unwrap();
+
+        assert_eq!(error.0, "Restart required to change: rotator.parity, rotators.b.port");
+        let handle = rotators.get("default").unwrap();
+        assert_eq!(handle.lock().await.config().position_tolerance, 0.5);
+        assert_eq!(current.get().rotators["b"].port, "/dev/ttyUSB1");
+        assert!(firmware.commands().is_empty());
+    }
+}
@@ -0,0 +1,144 @@
+//! Pointing the rotator once at a fixed location, such as a ground station
+//! or an aircraft whose position is known, rather than tracking it.
+
+use std::sync::Arc;
+
+use aerospace_rocketry_lib::geospatial::Point;
+use rocket::{State, post, serde::json::Json, tokio::sync::Mutex};
+use serde_json::json;
+
+use crate::{
+    config::SharedConfig,
+    control_loop::look_angles,
+    observer::Observer,
+    response::{BadRequest, Failure, Success},
+    rotator::Rotator,
+};
+
+/// Points the rotator at a location, given like the observer as `lat`, `lon`,
+/// and `alt_m`, from the observer, responding with the azimuth and elevation
+/// sent without waiting for them to be reached.
+///
+/// Locations below the horizon, or behind the horizon mask, are refused.
+#[post("/aim", data = "<target>")]
+pub async fn aim(
+    rotator: &State<Arc<Mutex<Rotator>>>,
+    observer: &State<Arc<Mutex<Option<Point>>>>,
+    config: &State<SharedConfig>,
+    target: Json<Observer>,
+) -> Result<Success, Failure> {
+    let target = target.into_inner();
+    target.validate().map_err(BadRequest::new)?;
+    let target = target.point().ok_or_else(|| BadRequest::new("invalid target location"))?;
+
+    let Some(observer) = *observer.lock().await else {
+        return Err(BadRequest::new("The observer's location isn't set, see `PUT /observer`").into());
+    };
+
+    let (azimuth, elevation) = look_angles(observer, target);
+    if elevation < 0.0 {
+        return Err(BadRequest::new(format!("The target is {:.1} degrees below the horizon", -elevation)).into());
+    }
+    if config.get().tracking.horizon_mask.is_obstructed(azimuth, elevation) {
+        return Err(BadRequest::new(format!("The target is behind the horizon mask at {azimuth:.1} degrees azimuth")).into());
+    }
+
+    let mut rotator = rotator.lock().await;
+    let azimuth = rotator.config().frame.azimuth_convention.wrap(azimuth as f32);
+    rotator.set_az_el(azimuth, elevation as f32).await?;
+
+    Ok(Success::data(json!({
+        "azimuth": azimuth,
+        "elevation": elevation,
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use rocket::{http::Status, local::asynchronous::Client, routes};
+    use serde_json::Value;
+
+    use super::*;
+    use crate::{
+        config::Config,
+        control_loop::{HorizonMask, MaskSegment},
+        rotator::mock::{self, MockFirmware},
+    };
+
+    const LINCOLN: Observer = Observer { lat: 40.8, lon: -96.7, alt_m: 358.0 };
+    /// A kilometre east of [`LINCOLN`], and a kilometre higher.
+    const EAST: Observer = Observer { lat: 40.8, lon: -96.68812, alt_m: 1358.0 };
+
+    async fn client(firmware: &MockFirmware, observer: Option<Observer>, config: Config) -> Client {
+        let rocket = rocket::build()
+            .manage(Arc::new(firmware.shared(mock::config())))
+            .manage(Arc::new(Mutex::new(observer.and_then(|observer| observer.point()))))
+            .manage(SharedConfig::new(config))
+            .mount("/", routes![aim]);
+
+        Client::tracked(rocket).await.unwrap()
+    }
+
+    async fn post(client: &Client, target: Observer) -> (Status, Value) {
+        let response = client.post("/aim").body(serde_json::to_string(&target).unwrap()).dispatch().await;
+        let status = response.status();
+
+        (status, serde_json::from_str(&response.into_string().await.unwrap()).unwrap())
+    }
+
+    #[rocket::async_test]
+    async fn the_rotator_is_pointed_at_the_target_once() {
+        let firmware = MockFirmware::new();
+        let client = client(&firmware, Some(LINCOLN), Config::default()).await;
+
+        let (status, body) = post(&client, EAST).await;
+
+        assert_eq!(status, Status::Ok);
+        let azimuth = body["data"]["azimuth"].as_f64().unwrap();
+        let elevation = body["data"]["elevation"].as_f64().unwrap();
+        assert!((azimuth - 90.0).abs() < 0.5, "{azimuth}");
+        assert!((elevation - 45.0).abs() < 0.5, "{elevation}");
+        assert_eq!(firmware.commands(), ["DVER", "DHOR"]);
+    }
+
+    #[rocket::async_test]
+    async fn targets_below_the_horizon_are_refused() {
+        let firmware = MockFirmware::new();
+        let client = client(&firmware, Some(LINCOLN), Config::default()).await;
+
+        let (status, body) = post(&client, Observer { alt_m: -642.0, ..EAST }).await;
+
+        assert_eq!(status, Status::BadRequest);
+        let message = body["message"].as_str().unwrap();
+        assert!(message.starts_with("The target is 45.0 degrees below the horizon"), "{message}");
+        assert!(firmware.received().is_empty());
+    }
+
+    #[rocket::async_test]
+    async fn targets_behind_the_horizon_mask_are_refused() {
+        let firmware = MockFirmware::new();
+        let mut config = Config::default();
+        config.tracking.horizon_mask = HorizonMask(vec![MaskSegment { from: 80.0, to: 100.0, min_elevation: 50.0 }]);
+        let client = client(&firmware, Some(LINCOLN), config).await;
+
+        let (status, body) = post(&client, EAST).await;
+
+        assert_eq!(status, Status::BadRequest);
+        assert!(body["message"].as_str().unwrap().contains("horizon mask"));
+        assert!(firmware.received().is_empty());
+    }
+
+    #[rocket::async_test]
+    async fn aiming_needs_a_valid_target_and_the_observer() {
+        let firmware = MockFirmware::new();
+        let client = client(&firmware, None, Config::default()).await;
+
+        let (status, body) = post(&client, EAST).await;
+        assert_eq!(status, Status::BadRequest);
+        assert!(body["message"].as_str().unwrap().contains("PUT /observer"));
+
+        let (status, _) = post(&client, Observer { lat: 91.0, ..EAST }).await;
+        assert_eq!(status, Status::BadRequest);
+        assert!(firmware.received().is_empty());
+    }
+}
@@ -0,0 +1,39 @@
+//! Authentication for administrative endpoints.
+//!
+//! Requests are authorized by sending `Authorization: Bearer <token>` with the
+//! `admin_token` from the configuration. If no token is configured, every
+//! authenticated endpoint is refused.
+
+use rocket::{
+    Request,
+    http::Status,
+    request::{FromRequest, Outcome},
+};
+
+use crate::config::SharedConfig;
+
+/// Request guard which only succeeds for requests carrying the admin token.
+pub struct Admin;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Admin {
+    type Error = &'static str;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let config = req.rocket().state::<SharedConfig>().map(SharedConfig::get);
+        let Some(token) = config.as_ref().and_then(|c| c.admin_token.as_deref()) else {
+            return Outcome::Error((Status::Forbidden, "no admin token configured"));
+        };
+
+        let provided = req
+            .headers()
+            .get_one("Authorization")
+            .and_then(|h| h.strip_prefix("Bearer "));
+
+        if provided == Some(token) {
+            Outcome::Success(Admin)
+        } else {
+            Outcome::Error((Status::Unauthorized, "invalid admin token"))
+        }
+    }
+}
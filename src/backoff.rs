@@ -0,0 +1,119 @@
+//! Retrying fallible operations with exponential backoff.
+
+use core::fmt::Display;
+use std::time::Duration;
+
+use log::{info, warn};
+use rocket::tokio::{self, time::Instant};
+use serde::{Deserialize, Serialize};
+
+/// Exponential backoff between retries, doubling from `initial_ms` up to `max_ms`.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Backoff {
+    pub initial_ms: u64,
+    pub max_ms: u64,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            initial_ms: 250,
+            max_ms: 5_000,
+        }
+    }
+}
+
+impl Backoff {
+    /// The delay before the retry following `delay`.
+    pub fn next(&self, delay: Duration) -> Duration {
+        (delay * 2).min(Duration::from_millis(self.max_ms))
+    }
+
+    pub fn initial(&self) -> Duration {
+        Duration::from_millis(self.initial_ms).min(Duration::from_millis(self.max_ms))
+    }
+}
+
+/// Retries `attempt` until it succeeds or `duration` has passed, logging each
+/// failure. Returns the last error when giving up.
+pub async fn retry_for<T, E, F, Fut>(what: &str, duration: Duration, backoff: &Backoff, mut attempt: F) -> Result<T, E>
+where
+    E: Display,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let deadline = Instant::now() + duration;
+    let mut delay = backoff.initial();
+    let mut tries = 1;
+
+    loop {
+        let error = match attempt().await {
+            Ok(value) => return Ok(value),
+            Err(e) => e,
+        };
+
+        let now = Instant::now();
+        if now >= deadline {
+            warn!("Giving up on {what} after {tries} attempts: {error}");
+            return Err(error);
+        }
+
+        info!("Attempt {tries} at {what} failed, retrying in {delay:?}: {error}");
+        tokio::time::sleep(delay.min(deadline - now)).await;
+
+        delay = backoff.next(delay);
+        tries += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+
+    const BACKOFF: Backoff = Backoff { initial_ms: 1, max_ms: 4 };
+
+    #[test]
+    fn delays_double_up_to_the_max() {
+        let delays: Vec<_> = std::iter::successors(Some(BACKOFF.initial()), |delay| Some(BACKOFF.next(*delay)))
+            .take(4)
+            .map(|delay| delay.as_millis())
+            .collect();
+
+        assert_eq!(delays, [1, 2, 4, 4]);
+        assert_eq!(Backoff { initial_ms: 10, max_ms: 4 }.initial(), Duration::from_millis(4));
+    }
+
+    #[rocket::async_test]
+    async fn retries_until_an_attempt_succeeds() {
+        let tries = Cell::new(0);
+
+        let result = retry_for("opening the port", Duration::from_secs(5), &BACKOFF, || {
+            tries.set(tries.get() + 1);
+            let tries = tries.get();
+            async move { if tries < 3 { Err(format!("attempt {tries} failed")) } else { Ok(tries) } }
+        })
+        .await;
+
+        assert_eq!(result, Ok(3));
+    }
+
+    #[rocket::async_test]
+    async fn gives_up_with_the_last_error_once_the_time_is_up() {
+        let tries = Cell::new(0);
+        let started = std::time::Instant::now();
+
+        let result: Result<(), _> = retry_for("opening the port", Duration::from_millis(50), &BACKOFF, || {
+            tries.set(tries.get() + 1);
+            let tries = tries.get();
+            async move { Err(format!("attempt {tries} failed")) }
+        })
+        .await;
+
+        assert!(started.elapsed() >= Duration::from_millis(50));
+        assert!(tries.get() > 1);
+        assert_eq!(result, Err(format!("attempt {} failed", tries.get())));
+    }
+}
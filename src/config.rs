@@ -0,0 +1,285 @@
+//! Server configuration, loaded from `archerd.toml` in the working directory
+//! and overridable by `ARCHERD_`-prefixed environment variables (nested keys
+//! are separated with `__`, e.g. `ARCHERD_ROTATOR__PARITY=Even`).
+
+use rocket::{
+    State,
+    figment::{
+        Figment,
+        providers::{Env, Format, Toml},
+    },
+    get,
+};
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::{Arc, PoisonError, RwLock},
+};
+
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::{Map, json};
+
+use crate::{
+    backoff::Backoff,
+    control_loop::TrackingConfig,
+    idempotency::IdempotencyConfig,
+    supervisor::SupervisorConfig,
+    observer::Observer,
+    rotator::{
+        Rotator,
+        config::{RotatorConfig, RotatorEntry},
+        registry::{DEFAULT_ROTATOR, Rotators},
+    },
+    response::{Error, Success},
+};
+
+/// Path of the configuration file, relative to the working directory.
+pub const CONFIG_PATH: &str = "archerd.toml";
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Config {
+    /// Settings for the default rotator, which is found automatically.
+    pub rotator: RotatorConfig,
+    /// Additional rotators, keyed by id.
+    pub rotators: HashMap<String, RotatorEntry>,
+    pub tracking: TrackingConfig,
+    pub startup: StartupConfig,
+    pub supervisor: SupervisorConfig,
+    pub idempotency: IdempotencyConfig,
+    /// Where named preset positions are saved, as JSON.
+    pub presets_path: String,
+    /// Where the rotator is. Replaced by the one saved at `observer_path` by
+    /// `PUT /observer`, if there is one.
+    pub observer: Option<Observer>,
+    /// Where the observer is saved, as JSON.
+    pub observer_path: String,
+    /// The port to serve the gRPC interface on, if built with the `grpc`
+    /// feature. It isn't served if this is unset.
+    pub grpc_port: Option<u16>,
+    /// Bearer token required by administrative endpoints. They are disabled
+    /// when this is unset.
+    #[serde(serialize_with = "redact")]
+    pub admin_token: Option<String>,
+}
+
+/// Serializes a secret as a placeholder, so it's clear whether one is set
+/// without giving it away.
+fn redact<S: Serializer>(secret: &Option<String>, serializer: S) -> Result<S::Ok, S::Error> {
+    secret.as_ref().map(|_| "<redacted>").serialize(serializer)
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            rotator: RotatorConfig::default(),
+            rotators: HashMap::new(),
+            tracking: TrackingConfig::default(),
+            startup: StartupConfig::default(),
+            supervisor: SupervisorConfig::default(),
+            idempotency: IdempotencyConfig::default(),
+            presets_path: "presets.json".to_string(),
+            observer: None,
+            observer_path: "observer.json".to_string(),
+            grpc_port: None,
+            admin_token: None,
+        }
+    }
+}
+
+impl Config {
+    /// Load the configuration. A missing file is not an error; every setting
+    /// has a default.
+    pub fn load() -> Result<Self, rocket::figment::Error> {
+        Figment::new()
+            .merge(Toml::file(CONFIG_PATH))
+            .merge(Env::prefixed("ARCHERD_").split("__"))
+            .extract()
+    }
+
+    /// The settings for the rotator with this id.
+    pub fn rotator(&self, id: &str) -> Option<&RotatorConfig> {
+        if id == DEFAULT_ROTATOR {
+            Some(&self.rotator)
+        } else {
+            self.rotators.get(id).map(|e| &e.config)
+        }
+    }
+
+    /// The settings which differ in `new` and need a restart to change,
+    /// including those of every rotator.
+    pub fn restart_required(&self, new: &Self) -> Vec<String> {
+        let mut names = Vec::new();
+
+        if self.tracking != new.tracking {
+            names.push("tracking".to_string());
+        }
+        if self.startup != new.startup {
+            names.push("startup".to_string());
+        }
+        if self.supervisor != new.supervisor {
+            names.push("supervisor".to_string());
+        }
+        if self.idempotency != new.idempotency {
+            names.push("idempotency".to_string());
+        }
+        if self.presets_path != new.presets_path {
+            names.push("presets_path".to_string());
+        }
+        if self.observer != new.observer {
+            names.push("observer".to_string());
+        }
+        if self.observer_path != new.observer_path {
+            names.push("observer_path".to_string());
+        }
+        if self.grpc_port != new.grpc_port {
+            names.push("grpc_port".to_string());
+        }
+        if self.admin_token != new.admin_token {
+            names.push("admin_token".to_string());
+        }
+
+        let rotator = self.rotator.restart_required(&new.rotator);
+        names.extend(rotator.into_iter().map(|n| format!("rotator.{n}")));
+
+        let ids: BTreeSet<_> = self.rotators.keys().chain(new.rotators.keys()).collect();
+        for id in ids {
+            match (self.rotators.get(id), new.rotators.get(id)) {
+                (Some(old), Some(new)) => {
+                    if old.port != new.port {
+                        names.push(format!("rotators.{id}.port"));
+                    }
+
+                    let rotator = old.config.restart_required(&new.config);
+                    names.extend(rotator.into_iter().map(|n| format!("rotators.{id}.{n}")));
+                }
+                _ => names.push(format!("rotators.{id}")),
+            }
+        }
+
+        names
+    }
+}
+
+/// The configuration in use, managed by Rocket in place of [`Config`] so that
+/// `POST /admin/reload` can swap in a new one for every later request.
+pub struct SharedConfig(RwLock<Arc<Config>>);
+
+impl SharedConfig {
+    pub fn new(config: Config) -> Self {
+        Self(RwLock::new(Arc::new(config)))
+    }
+
+    /// The configuration as of now. Requests keep the one they started with,
+    /// even if it is reloaded meanwhile.
+    pub fn get(&self) -> Arc<Config> {
+        Arc::clone(&self.0.read().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    pub fn replace(&self, config: Config) {
+        *self.0.write().unwrap_or_else(PoisonError::into_inner) = Arc::new(config);
+    }
+}
+
+/// How to handle devices which are not yet available when the server starts.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct StartupConfig {
+    /// How long to keep retrying to open a rotator's port before starting
+    /// without it.
+    pub retry_for_ms: u64,
+    pub backoff: Backoff,
+}
+
+impl Default for StartupConfig {
+    fn default() -> Self {
+        Self {
+            retry_for_ms: 30_000,
+            backoff: Backoff::default(),
+        }
+    }
+}
+
+/// Gets the configuration in effect, after the file and environment are
+/// merged and any reloads, with secrets redacted. Each rotator's serial port
+/// and baud rate are listed under `ports`.
+#[get("/config")]
+pub async fn effective_config(config: &State<SharedConfig>, rotators: &State<Rotators>) -> Result<Success, Error> {
+    let mut effective = Config::clone(&config.get());
+
+    let mut ports = Map::new();
+    for (id, handle) in rotators.iter() {
+        let rotator = handle.lock().await;
+        ports.insert(id.to_string(), json!({
+            "path": rotator.port().name(),
+            "baud": Rotator::BAUD,
+        }));
+
+        if id == DEFAULT_ROTATOR {
+            effective.rotator = rotator.config().clone();
+        } else if let Some(entry) = effective.rotators.get_mut(id) {
+            entry.config = rotator.config().clone();
+        }
+    }
+
+    let mut effective = serde_json::to_value(effective).map_err(|e| Error(e.to_string()))?;
+    effective["ports"] = ports.into();
+
+    Ok(Success::data(effective))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use rocket::{http::Status, local::asynchronous::Client, routes, tokio::sync::Mutex};
+    use serde_json::{Value, json};
+
+    use super::*;
+    use crate::rotator::{
+        config::{Limits, PerAxis},
+        mock::{self, MockFirmware},
+        registry::RotatorHandle,
+    };
+
+    async fn get_config(config: Config, rotator: RotatorConfig) -> (String, Value) {
+        let handle = RotatorHandle::unpolled(Arc::new(Mutex::new(MockFirmware::new().rotator(rotator))));
+        let rocket = rocket::build()
+            .manage(SharedConfig::new(config))
+            .manage(Rotators::new(handle))
+            .mount("/", routes![effective_config]);
+        let client = Client::tracked(rocket).await.unwrap();
+
+        let response = client.get("/config").dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        let body = response.into_string().await.unwrap();
+        let value: Value = serde_json::from_str(&body).unwrap();
+
+        (body, value["data"].clone())
+    }
+
+    #[rocket::async_test]
+    async fn the_admin_token_is_redacted() {
+        let config = Config { admin_token: Some("hunter2".to_string()), ..Config::default() };
+        let (body, data) = get_config(config, mock::config()).await;
+
+        assert_eq!(data["admin_token"], "<redacted>");
+        assert!(!body.contains("hunter2"), "{body}");
+
+        let (_, data) = get_config(Config::default(), mock::config()).await;
+        assert_eq!(data["admin_token"], Value::Null);
+    }
+
+    #[rocket::async_test]
+    async fn the_rotators_settings_and_port_are_shown() {
+        let limits = PerAxis { vertical: Some(Limits { min: 0.0, max: 90.0 }), horizontal: None };
+        let rotator = RotatorConfig { limits, position_tolerance: 0.25, ..mock::config() };
+        let (_, data) = get_config(Config::default(), rotator).await;
+
+        assert_eq!(data["rotator"]["limits"], json!({"vertical": {"min": 0.0, "max": 90.0}, "horizontal": null}));
+        assert_eq!(data["rotator"]["position_tolerance"], json!(0.25));
+        assert_eq!(data["ports"]["default"], json!({"path": "mock", "baud": Rotator::BAUD}));
+        for field in ["tracking", "supervisor", "idempotency", "presets_path", "observer_path"] {
+            assert!(data.get(field).is_some(), "{field} is missing");
+        }
+    }
+}
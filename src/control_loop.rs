@@ -1,18 +1,367 @@
-use std::{sync::Arc, time::Duration};
+use std::{sync::{self, Arc, PoisonError}, time::{Duration, Instant}};
 
 use aerospace_rocketry_lib::{geospatial::Point, utils::crc::crc8};
 use chrono::Utc;
 use log::{debug, info, warn};
 use rocket::tokio::{self, sync::Mutex};
 use rocket::tokio::io::AsyncWriteExt;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use serialport::SerialPort;
 
-use crate::rotator::Rotator;
+use crate::follow::FollowConfig;
+use crate::rotator::{
+    Axis, Rotator,
+    config::{Limits, PerAxis},
+    frame::AzimuthConvention,
+};
+
+/// Settings for tracking the rocket.
+#[derive(Debug, Clone, PartialEq, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TrackingConfig {
+    pub horizon_mask: HorizonMask,
+    pub axes: PerAxis<AxisTracking>,
+    pub follow: FollowConfig,
+    /// How far ahead of the rocket's last known position to aim, to make up
+    /// for the time the mount takes to slew. Zero aims where it was last seen.
+    pub lead_time_ms: u64,
+    pub flip: FlipConfig,
+}
+
+/// Settings for pointing "over the top" near the zenith, on mounts whose
+/// elevation goes past 90 degrees. Close to the zenith the azimuth changes
+/// quickly, and the mount would have to swing round by up to 180 degrees to
+/// keep up. Flipped, the mount points at the same place with the azimuth
+/// turned by 180 degrees and the elevation measured from the other horizon,
+/// so the azimuth barely moves.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct FlipConfig {
+    pub enabled: bool,
+    /// The elevation at which to flip over.
+    pub enter_elevation: f64,
+    /// The elevation below which to flip back. Keep this well below
+    /// `enter_elevation`, so the mount doesn't flip back and forth.
+    pub exit_elevation: f64,
+}
+
+impl Default for FlipConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            enter_elevation: 80.0,
+            exit_elevation: 60.0,
+        }
+    }
+}
+
+/// Decides when to point over the top, see [`FlipConfig`].
+#[derive(Debug, Clone)]
+pub struct Flip {
+    config: FlipConfig,
+    flipped: bool,
+}
+
+impl Flip {
+    pub const fn new(config: FlipConfig) -> Self {
+        Self { config, flipped: false }
+    }
+
+    /// The azimuth and elevation to point the rotator at to see `azimuth`
+    /// and `elevation`. Only flips if the rotator accepts the flipped
+    /// elevation, both under its limits and its elevation convention.
+    pub fn update(&mut self, azimuth: f64, elevation: f64, rotator: &Rotator) -> (f64, f64) {
+        if !self.config.enabled {
+            return (azimuth, elevation);
+        }
+
+        let supported = rotator.check_position(Axis::Vertical, (180.0 - elevation) as f32).is_ok();
+        let threshold = if self.flipped { self.config.exit_elevation } else { self.config.enter_elevation };
+        let flipped = supported && elevation >= threshold;
+
+        if flipped != self.flipped {
+            self.flipped = flipped;
+            if flipped {
+                info!("Target is near the zenith, pointing over the top");
+            } else {
+                info!("Pointing back the normal way up");
+            }
+        }
+
+        if self.flipped {
+            ((azimuth + 180.0).rem_euclid(360.0), 180.0 - elevation)
+        } else {
+            (azimuth, elevation)
+        }
+    }
+}
+
+/// How the tracking loop drives a single axis.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AxisTracking {
+    /// Leave this axis where it is, and only track with the other one.
+    pub pinned: bool,
+    /// The furthest this axis is sent in one update, in degrees, to pace
+    /// its slewing. Unlimited if unset.
+    pub max_step_degrees: Option<f32>,
+    /// How far from a limit, in degrees, to start slowing down as the axis
+    /// approaches it. Within this, `max_step_degrees` is scaled down in
+    /// proportion to the distance left. Not slowed if unset.
+    pub decel_zone_degrees: Option<f32>,
+}
+
+/// The smallest fraction of `max_step_degrees` an axis is slowed to near a
+/// limit, so that it still gets there.
+const MIN_APPROACH_FRACTION: f32 = 0.1;
+
+/// The furthest an axis at `last` heading for `target` may be sent, slowing
+/// from `max` within `zone` degrees of the limit it is heading for.
+fn approach_step(max: f32, zone: f32, last: f32, target: f32, limits: &Limits) -> f32 {
+    if zone <= 0.0 {
+        return max;
+    }
+
+    let remaining = if target > last { limits.max - last } else { last - limits.min };
+
+    max * (remaining.max(0.0) / zone).clamp(MIN_APPROACH_FRACTION, 1.0)
+}
+
+/// Works out the commands for one axis while tracking, independently of the
+/// other axis.
+#[derive(Debug, Clone)]
+pub struct AxisTracker {
+    axis: Axis,
+    settings: AxisTracking,
+    /// The last position this axis was sent to.
+    last: Option<f32>,
+}
+
+impl AxisTracker {
+    pub const fn new(axis: Axis, settings: AxisTracking) -> Self {
+        Self { axis, settings, last: None }
+    }
+
+    /// The position to send this axis to so it follows `target`, or `None`
+    /// if it should not be moved.
+    ///
+    /// The target is clamped to the axis limits rather than rejected, so the
+    /// axis waits at its limit until the target comes back into range.
+    /// Continuous azimuths take the shortest way round from the last command.
+    pub fn next(&self, target: f32, rotator: &mut Rotator) -> Option<f32> {
+        if self.settings.pinned {
+            return None;
+        }
+        let config = rotator.config();
+
+        let mut target = match (self.axis, self.last) {
+            (Axis::Horizontal, Some(last)) if config.frame.azimuth_convention == AzimuthConvention::Continuous => {
+                last + AzimuthConvention::Signed.wrap(target - last)
+            }
+            (Axis::Horizontal, _) => config.frame.azimuth_convention.wrap(target),
+            (Axis::Vertical, _) => target,
+        };
+
+        if let (Some(last), Some(max)) = (self.last, self.settings.max_step_degrees) {
+            let max = match (self.settings.decel_zone_degrees, config.limits.get(self.axis)) {
+                (Some(zone), Some(limits)) => approach_step(max, zone, last, target, limits),
+                _ => max,
+            };
+            target = last + (target - last).clamp(-max, max);
+        }
+        let tolerance = config.position_tolerance;
+        let target = rotator.clamp_to_limits(self.axis, target);
+
+        match self.last {
+            Some(last) if (target - last).abs() <= tolerance => None,
+            _ => Some(target),
+        }
+    }
+
+    pub const fn axis(&self) -> Axis {
+        self.axis
+    }
+
+    /// Record that the axis was sent to `position`.
+    pub const fn sent(&mut self, position: f32) {
+        self.last = Some(position);
+    }
+}
+
+/// A bearing and elevation of the rocket, and when it was seen there.
+#[derive(Debug, Clone, Copy)]
+struct Fix {
+    bearing: f64,
+    elevation: f64,
+    at: Instant,
+}
+
+/// Predicts where the rocket will appear a while ahead, by carrying on at the
+/// rate its bearing and elevation were last seen to change.
+#[derive(Debug, Clone)]
+pub struct LeadPredictor {
+    lead: Duration,
+    last: Option<Fix>,
+    /// Degrees per second of bearing and elevation between the last two fixes.
+    rate: Option<(f64, f64)>,
+}
+
+impl LeadPredictor {
+    pub const fn new(lead: Duration) -> Self {
+        Self { lead, last: None, rate: None }
+    }
+
+    /// Record the rocket's position at `now`, returning the bearing and
+    /// elevation to aim for. Only a changed position counts as a new fix, as
+    /// the same one is seen on every tick until the next packet arrives.
+    pub fn update(&mut self, bearing: f64, elevation: f64, now: Instant) -> (f64, f64) {
+        match self.last {
+            Some(last) if last.bearing == bearing && last.elevation == elevation => {}
+            Some(last) => {
+                let elapsed = now.duration_since(last.at).as_secs_f64();
+                if elapsed > 0.0 {
+                    let turned = (bearing - last.bearing + 180.0).rem_euclid(360.0) - 180.0;
+                    self.rate = Some((turned / elapsed, (elevation - last.elevation) / elapsed));
+                }
+                self.last = Some(Fix { bearing, elevation, at: now });
+            }
+            None => self.last = Some(Fix { bearing, elevation, at: now }),
+        }
+
+        let (Some(last), Some((bearing_rate, elevation_rate))) = (self.last, self.rate) else {
+            return (bearing, elevation);
+        };
+        if self.lead.is_zero() {
+            return (bearing, elevation);
+        }
+
+        // Aim ahead of the last fix, not just of now, as it may be a while old
+        let ahead = (self.lead + now.duration_since(last.at)).as_secs_f64();
+
+        (
+            (last.bearing + bearing_rate * ahead).rem_euclid(360.0),
+            last.elevation + elevation_rate * ahead,
+        )
+    }
+}
+
+/// An azimuth range within which the horizon is obstructed up to some
+/// elevation, e.g. by a building or trees.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct MaskSegment {
+    /// Start of the range, in degrees clockwise from north.
+    pub from: f64,
+    /// End of the range. If this is less than `from`, the range wraps past north.
+    pub to: f64,
+    /// Lowest visible elevation within the range, in degrees.
+    pub min_elevation: f64,
+}
+
+impl MaskSegment {
+    fn contains(&self, azimuth: f64) -> bool {
+        let (from, to) = (self.from.rem_euclid(360.0), self.to.rem_euclid(360.0));
+
+        if from <= to {
+            (from..=to).contains(&azimuth)
+        } else {
+            azimuth >= from || azimuth <= to
+        }
+    }
+}
+
+/// The obstructed parts of the horizon. Targets behind the mask are not tracked.
+#[derive(Debug, Clone, PartialEq, Default, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct HorizonMask(pub Vec<MaskSegment>);
+
+impl HorizonMask {
+    /// The lowest visible elevation at an azimuth, if any part of the mask covers it.
+    pub fn min_elevation(&self, azimuth: f64) -> Option<f64> {
+        let azimuth = azimuth.rem_euclid(360.0);
+
+        self.0
+            .iter()
+            .filter(|s| s.contains(azimuth))
+            .map(|s| s.min_elevation)
+            .reduce(f64::max)
+    }
+
+    pub fn is_obstructed(&self, azimuth: f64, elevation: f64) -> bool {
+        self.min_elevation(azimuth).is_some_and(|min| elevation < min)
+    }
+}
+
+/// What is tracking with the rotator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrackingMode {
+    /// Following the rocket's telemetry, in [`rotator_control_loop`].
+    Rocket,
+    /// Following pushed targets, in [`follow_loop`](crate::follow::follow_loop).
+    Follow,
+}
+
+/// Which [`TrackingMode`] owns the rotator, so that the rocket and pushed
+/// targets never both steer it at once.
+///
+/// Pushed targets are sent on purpose by a client, so following them takes
+/// the rotator over from rocket tracking, which pauses until they stop.
+#[derive(Debug, Default)]
+pub struct ActiveTracking(sync::Mutex<Option<TrackingMode>>);
+
+impl ActiveTracking {
+    /// The mode which owns the rotator, if any.
+    pub fn get(&self) -> Option<TrackingMode> {
+        *self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Takes the rotator for `mode` unless another mode owns it, returning
+    /// whether it now owns it.
+    pub fn claim(&self, mode: TrackingMode) -> bool {
+        let mut active = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        if active.is_some_and(|active| active != mode) {
+            return false;
+        }
+
+        *active = Some(mode);
+        true
+    }
+
+    /// Takes the rotator for `mode` whichever mode owns it, returning the one
+    /// it was taken from.
+    pub fn take_over(&self, mode: TrackingMode) -> Option<TrackingMode> {
+        let mut active = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+
+        active.replace(mode).filter(|&previous| previous != mode)
+    }
+
+    /// Gives up the rotator, if `mode` owns it.
+    pub fn release(&self, mode: TrackingMode) {
+        let mut active = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        if *active == Some(mode) {
+            *active = None;
+        }
+    }
+}
 
 pub struct ControlInfo {
     pub rocket_position: Arc<Mutex<Option<Point>>>,
     pub rotator_position: Arc<Mutex<Option<Point>>>,
+    pub horizon_mask: HorizonMask,
+    pub axes: PerAxis<AxisTracking>,
+    pub lead_time: Duration,
+    pub flip: FlipConfig,
+    pub active: Arc<ActiveTracking>,
+}
+
+/// The bearing (degrees clockwise from north) and elevation of `target` as
+/// seen from `ground`.
+pub fn look_angles(ground: Point, target: Point) -> (f64, f64) {
+    let bearing = ground.bearing_to(target, false);
+    let elevation = ground.elevation_to(target).unwrap();
+
+    (bearing.degrees(), elevation)
 }
 
 pub async fn rotator_control_loop(rotator: Arc<Mutex<Rotator>>, control_info: ControlInfo) {
@@ -21,6 +370,16 @@ pub async fn rotator_control_loop(rotator: Arc<Mutex<Rotator>>, control_info: Co
     let mut ticker = tokio::time::interval(Duration::from_millis(250));
     ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
 
+    let mut trackers = [
+        AxisTracker::new(Axis::Vertical, control_info.axes.vertical),
+        AxisTracker::new(Axis::Horizontal, control_info.axes.horizontal),
+    ];
+
+    let mut lead = LeadPredictor::new(control_info.lead_time);
+    let mut flip = Flip::new(control_info.flip);
+
+    let mut obstructed = false;
+    let mut paused = false;
     loop {
         ticker.tick().await;
 
@@ -38,11 +397,52 @@ pub async fn rotator_control_loop(rotator: Arc<Mutex<Rotator>>, control_info: Co
             continue;
         };
 
-        let bearing = ground.bearing_to(rocket, false);
-        let elevation = ground.elevation_to(rocket).unwrap();
+        let (bearing, elevation) = look_angles(ground, rocket);
 
-        let _ = rotator.lock().await.set_position_vertical(elevation as f32).await;
-        let _ = rotator.lock().await.set_position_horizontal(bearing.degrees() as f32).await;
+        // Hold position while the rocket is hidden behind the horizon mask
+        let now_obstructed = control_info.horizon_mask.is_obstructed(bearing, elevation);
+        if now_obstructed != obstructed {
+            obstructed = now_obstructed;
+            if obstructed {
+                info!("Rocket is behind the horizon mask, pausing tracking");
+            } else {
+                info!("Rocket is clear of the horizon mask, resuming tracking");
+            }
+        }
+        if obstructed {
+            continue;
+        }
+
+        // Pushed targets take priority, see `ActiveTracking`
+        let owned = control_info.active.claim(TrackingMode::Rocket);
+        if owned == paused {
+            paused = !owned;
+            if paused {
+                info!("Pushed targets are being followed, pausing rocket tracking");
+            } else {
+                info!("Pushed targets stopped, resuming rocket tracking");
+            }
+        }
+        if paused {
+            continue;
+        }
+
+        let (bearing, elevation) = lead.update(bearing, elevation, Instant::now());
+
+        let mut rotator_lock = rotator.lock().await;
+        let (bearing, elevation) = flip.update(bearing, elevation, &rotator_lock);
+        for tracker in &mut trackers {
+            let target = match tracker.axis {
+                Axis::Vertical => elevation as f32,
+                Axis::Horizontal => bearing as f32,
+            };
+
+            if let Some(position) = tracker.next(target, &mut rotator_lock)
+                && rotator_lock.set_position(tracker.axis, position).await.is_ok()
+            {
+                tracker.sent(position);
+            }
+        }
     }
 }
 
@@ -140,3 +540,274 @@ pub async fn rfd_receive_loop(
         *last_packet.lock().await = Some(packet);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rocket::figment::{Figment, providers::{Format, Toml}};
+
+    use super::*;
+    use crate::rotator::{config::RotatorConfig, frame::{ElevationConvention, Frame}, mock::{self, MockFirmware}};
+
+    fn mask() -> HorizonMask {
+        HorizonMask(vec![
+            MaskSegment { from: 80.0, to: 120.0, min_elevation: 20.0 },
+            MaskSegment { from: 100.0, to: 140.0, min_elevation: 10.0 },
+            // Past north
+            MaskSegment { from: 350.0, to: 10.0, min_elevation: 5.0 },
+        ])
+    }
+
+    #[test]
+    fn targets_below_the_mask_are_obstructed() {
+        let mask = mask();
+
+        assert!(mask.is_obstructed(90.0, 19.9));
+        assert!(mask.is_obstructed(130.0, 5.0));
+        assert!(mask.is_obstructed(355.0, 0.0));
+        assert!(mask.is_obstructed(5.0, 4.0));
+        assert!(mask.is_obstructed(-5.0, 4.0));
+    }
+
+    #[test]
+    fn targets_above_or_beside_the_mask_are_tracked() {
+        let mask = mask();
+
+        assert!(!mask.is_obstructed(90.0, 20.0));
+        assert!(!mask.is_obstructed(130.0, 45.0));
+        assert!(!mask.is_obstructed(5.0, 5.0));
+        assert!(!mask.is_obstructed(200.0, 0.0));
+        assert!(!HorizonMask::default().is_obstructed(90.0, -10.0));
+    }
+
+    #[test]
+    fn overlapping_segments_use_the_highest() {
+        let mask = mask();
+
+        assert_eq!(mask.min_elevation(110.0), Some(20.0));
+        assert_eq!(mask.min_elevation(130.0), Some(10.0));
+        assert_eq!(mask.min_elevation(370.0), Some(5.0));
+        assert_eq!(mask.min_elevation(200.0), None);
+    }
+
+    #[test]
+    fn mask_is_read_from_the_config() {
+        let config: TrackingConfig = Figment::from(Toml::string(
+            r#"
+            horizon_mask = [
+                { from = 80.0, to = 120.0, min_elevation = 20.0 },
+                { from = 350.0, to = 10.0, min_elevation = 5.0 },
+            ]
+            "#,
+        ))
+        .extract()
+        .unwrap();
+
+        assert_eq!(config.horizon_mask.0.len(), 2);
+        assert!(config.horizon_mask.is_obstructed(0.0, 1.0));
+    }
+
+    /// A rotator limited to `0..90` elevation and `-180..450` azimuth, with
+    /// azimuths in `convention`.
+    fn rotator(convention: AzimuthConvention) -> Rotator {
+        let limits = PerAxis {
+            vertical: Some(Limits { min: 0.0, max: 90.0 }),
+            horizontal: Some(Limits { min: -180.0, max: 450.0 }),
+        };
+        let frame = Frame { azimuth_convention: convention, ..Frame::default() };
+
+        MockFirmware::new().rotator(RotatorConfig { limits, frame, ..mock::config() })
+    }
+
+    fn trackers(axes: PerAxis<AxisTracking>) -> (AxisTracker, AxisTracker) {
+        (AxisTracker::new(Axis::Vertical, axes.vertical), AxisTracker::new(Axis::Horizontal, axes.horizontal))
+    }
+
+    #[test]
+    fn clamping_one_axis_leaves_the_other_alone() {
+        let mut rotator = rotator(AzimuthConvention::Compass);
+        let (elevation, azimuth) = trackers(PerAxis::default());
+
+        assert_eq!(elevation.next(120.0, &mut rotator), Some(90.0));
+        assert_eq!(azimuth.next(45.0, &mut rotator), Some(45.0));
+
+        assert_eq!(elevation.next(30.0, &mut rotator), Some(30.0));
+        assert_eq!(azimuth.next(-10.0, &mut rotator), Some(350.0));
+        assert_eq!(rotator.metrics().clamped, 1);
+    }
+
+    #[test]
+    fn continuous_azimuths_take_the_shortest_way_round() {
+        let mut rotator = rotator(AzimuthConvention::Continuous);
+        let (elevation, mut azimuth) = trackers(PerAxis::default());
+        azimuth.sent(350.0);
+
+        assert_eq!(azimuth.next(10.0, &mut rotator), Some(370.0));
+        assert_eq!(azimuth.next(-170.0, &mut rotator), Some(190.0));
+        // Elevations are never wrapped, only clamped
+        assert_eq!(elevation.next(-10.0, &mut rotator), Some(0.0));
+    }
+
+    #[test]
+    fn pinned_axes_and_pacing_only_apply_to_their_axis() {
+        let mut rotator = rotator(AzimuthConvention::Compass);
+        let axes = PerAxis {
+            vertical: AxisTracking { pinned: true, ..AxisTracking::default() },
+            horizontal: AxisTracking { max_step_degrees: Some(10.0), ..AxisTracking::default() },
+        };
+        let (elevation, mut azimuth) = trackers(axes);
+        azimuth.sent(100.0);
+
+        assert_eq!(elevation.next(45.0, &mut rotator), None);
+        assert_eq!(azimuth.next(150.0, &mut rotator), Some(110.0));
+        assert_eq!(azimuth.next(95.0, &mut rotator), Some(95.0));
+
+        // Small changes aren't sent at all
+        assert_eq!(azimuth.next(100.4, &mut rotator), None);
+    }
+
+    #[test]
+    fn axes_slow_down_approaching_a_limit() {
+        let limits = Limits { min: 0.0, max: 90.0 };
+
+        assert_eq!(approach_step(10.0, 20.0, 40.0, 80.0, &limits), 10.0);
+        assert_eq!(approach_step(10.0, 20.0, 80.0, 90.0, &limits), 5.0);
+        assert_eq!(approach_step(10.0, 20.0, 90.0, 95.0, &limits), 1.0);
+        assert_eq!(approach_step(10.0, 20.0, 10.0, 0.0, &limits), 5.0);
+        assert_eq!(approach_step(10.0, 0.0, 89.0, 95.0, &limits), 10.0);
+    }
+
+    #[test]
+    fn paced_setpoints_shrink_toward_a_limit() {
+        let mut rotator = rotator(AzimuthConvention::Compass);
+        let slowed = AxisTracking {
+            max_step_degrees: Some(10.0),
+            decel_zone_degrees: Some(20.0),
+            ..AxisTracking::default()
+        };
+        let (mut elevation, mut azimuth) = trackers(PerAxis { vertical: slowed, horizontal: slowed });
+        elevation.sent(60.0);
+
+        let mut setpoints = Vec::new();
+        while let Some(setpoint) = elevation.next(90.0, &mut rotator) {
+            elevation.sent(setpoint);
+            setpoints.push(setpoint);
+        }
+        // Never slower than a tenth of the step, so the limit is still reached
+        assert_eq!(setpoints, [70.0, 80.0, 85.0, 87.5, 88.75, 89.75]);
+
+        // Heading away from the limit isn't slowed
+        assert_eq!(elevation.next(40.0, &mut rotator), Some(79.75));
+
+        // Nor is an axis without limits
+        let mut rotator = MockFirmware::new().rotator(mock::config());
+        azimuth.sent(100.0);
+        assert_eq!(azimuth.next(150.0, &mut rotator), Some(110.0));
+    }
+
+    fn assert_near((bearing, elevation): (f64, f64), expected: (f64, f64)) {
+        assert!(
+            (bearing - expected.0).abs() < 1e-9 && (elevation - expected.1).abs() < 1e-9,
+            "{:?} != {expected:?}",
+            (bearing, elevation),
+        );
+    }
+
+    #[test]
+    fn the_lead_carries_on_at_the_last_rate() {
+        let start = Instant::now();
+        let mut lead = LeadPredictor::new(Duration::from_secs(2));
+
+        // Nothing to go on until there are two fixes
+        assert_near(lead.update(100.0, 10.0, start), (100.0, 10.0));
+        assert_near(lead.update(102.0, 11.0, start + Duration::from_secs(1)), (106.0, 13.0));
+
+        // Until the next fix, aim ahead of the last one by how old it is too
+        assert_near(lead.update(102.0, 11.0, start + Duration::from_millis(1500)), (107.0, 13.5));
+    }
+
+    #[test]
+    fn the_lead_turns_the_short_way_past_north() {
+        let start = Instant::now();
+        let mut lead = LeadPredictor::new(Duration::from_secs(1));
+
+        lead.update(359.0, 30.0, start);
+
+        assert_near(lead.update(1.0, 30.0, start + Duration::from_secs(1)), (3.0, 30.0));
+    }
+
+    #[test]
+    fn no_lead_aims_at_the_latest_fix() {
+        let start = Instant::now();
+        let mut lead = LeadPredictor::new(Duration::ZERO);
+
+        lead.update(100.0, 10.0, start);
+
+        assert_near(lead.update(102.0, 11.0, start + Duration::from_secs(1)), (102.0, 11.0));
+    }
+
+    fn flip() -> Flip {
+        Flip::new(FlipConfig { enabled: true, ..FlipConfig::default() })
+    }
+
+    #[test]
+    fn near_the_zenith_the_mount_points_over_the_top() {
+        let firmware = MockFirmware::new();
+        let rotator = firmware.rotator(mock::config());
+        let mut flip = flip();
+
+        assert_eq!(flip.update(10.0, 70.0, &rotator), (10.0, 70.0));
+        assert_eq!(flip.update(10.0, 85.0, &rotator), (190.0, 95.0));
+        assert_eq!(flip.update(350.0, 89.0, &rotator), (170.0, 91.0));
+
+        // It only flips back once well below where it flipped over
+        assert_eq!(flip.update(10.0, 70.0, &rotator), (190.0, 110.0));
+        assert_eq!(flip.update(10.0, 55.0, &rotator), (10.0, 55.0));
+    }
+
+    #[test]
+    fn flipping_needs_enabling() {
+        let firmware = MockFirmware::new();
+        let rotator = firmware.rotator(mock::config());
+        let mut flip = Flip::new(FlipConfig::default());
+
+        assert_eq!(flip.update(10.0, 85.0, &rotator), (10.0, 85.0));
+    }
+
+    #[test]
+    fn flipping_needs_the_mount_to_reach_past_the_zenith() {
+        let firmware = MockFirmware::new();
+        let limits = PerAxis { vertical: Some(Limits { min: 0.0, max: 90.0 }), horizontal: None };
+        let frame = Frame { elevation_convention: ElevationConvention::Horizon, ..Frame::default() };
+
+        for config in [RotatorConfig { limits, ..mock::config() }, RotatorConfig { frame, ..mock::config() }] {
+            let rotator = firmware.rotator(config);
+            let mut flip = flip();
+
+            assert_eq!(flip.update(10.0, 85.0, &rotator), (10.0, 85.0));
+        }
+    }
+
+    #[test]
+    fn look_angles_point_at_the_target() {
+        // About a kilometre away in each direction, and a kilometre up
+        const METRES_PER_DEGREE: f64 = 111_195.0;
+        let (lat, lon, alt) = (40.8, -96.7, 358.0);
+        let ground = Point::new_3d(lat, lon, alt).unwrap();
+        let east = 1000.0 / (METRES_PER_DEGREE * lat.to_radians().cos());
+
+        for (target, bearing, elevation) in [
+            (Point::new_3d(lat + 1000.0 / METRES_PER_DEGREE, lon, alt + 1000.0), 0.0, 45.0),
+            (Point::new_3d(lat, lon + east, alt + 1000.0), 90.0, 45.0),
+            (Point::new_3d(lat - 1000.0 / METRES_PER_DEGREE, lon, alt + 1000.0), 180.0, 45.0),
+            (Point::new_3d(lat, lon - east, alt), 270.0, 0.0),
+            (Point::new_3d(lat, lon + east, alt - 1000.0), 90.0, -45.0),
+        ] {
+            let (actual_bearing, actual_elevation) = look_angles(ground, target.unwrap());
+
+            // Either side of north is as good
+            let bearing_error = (actual_bearing - bearing + 180.0).rem_euclid(360.0) - 180.0;
+            assert!(bearing_error.abs() < 0.5, "{actual_bearing} != {bearing}");
+            assert!((actual_elevation - elevation).abs() < 0.5, "{actual_elevation} != {elevation}");
+        }
+    }
+}
@@ -0,0 +1,762 @@
+//! Following target positions pushed by a client, for external predictors
+//! which would rather stream azimuth and elevation than have the rocket
+//! tracked from its telemetry.
+//!
+//! Several targets can be pushed at once, e.g. for overlapping satellite
+//! passes, each under its own name and with a priority. The highest priority
+//! target which is clear of the horizon mask is followed, handing off to
+//! another as targets rise, set, or stop being pushed. Between targets of the
+//! same priority, the one already being followed is kept, and otherwise the
+//! one pushed first is chosen.
+
+use std::{
+    cmp::Reverse,
+    collections::BTreeMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use chrono::Utc;
+use log::{info, warn};
+use rocket::{
+    State, delete, get, post,
+    serde::json::Json,
+    tokio::{self, sync::{Mutex, oneshot}},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{
+    control_loop::{ActiveTracking, AxisTracker, AxisTracking, Flip, FlipConfig, HorizonMask, TrackingMode},
+    response::{BadRequest, Error, Failure, Success},
+    rotator::{Axis, Position, Rotator, config::PerAxis, frame::AzimuthConvention, units::check_position_input},
+};
+
+/// Settings for following pushed targets.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct FollowConfig {
+    /// How often the rotator is moved toward the latest target.
+    pub interval_ms: u64,
+    /// How long without a new target before the rotator is halted and the
+    /// target dropped.
+    pub stale_after_ms: u64,
+    /// What to do with the rotator when following is stopped with
+    /// `POST /track/stop`.
+    pub on_stop: StopAction,
+}
+
+impl Default for FollowConfig {
+    fn default() -> Self {
+        Self {
+            interval_ms: 250,
+            stale_after_ms: 2_000,
+            on_stop: StopAction::default(),
+        }
+    }
+}
+
+/// What to do with the rotator when following is stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StopAction {
+    /// Stop where it is.
+    #[default]
+    Halt,
+    /// Go to the configured park position.
+    Park,
+}
+
+/// Why following stopped, for the [`TrackingReport`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EndReason {
+    /// Every target stopped being pushed, or was removed.
+    Completed,
+    /// Following was stopped with `POST /track/stop`.
+    Stopped,
+}
+
+/// The name of the target pushed to `POST /track/target`.
+pub const DEFAULT_TARGET: &str = "default";
+
+/// The latest position pushed for a target.
+#[derive(Debug, Clone, Copy)]
+pub struct Target {
+    pub position: Position,
+    /// Higher priority targets are followed in preference to lower ones.
+    pub priority: i32,
+    /// When the position arrived.
+    pub received: Instant,
+    /// When this target was first pushed, to choose between targets of the
+    /// same priority.
+    first_received: Instant,
+}
+
+#[derive(Debug, Default)]
+struct Targets {
+    targets: BTreeMap<String, Target>,
+    /// The name of the target being followed.
+    active: Option<String>,
+    /// Set when following has been asked to stop, and answered with the
+    /// report once the rotator has been stopped.
+    stop: Option<oneshot::Sender<Option<TrackingReport>>>,
+}
+
+/// Every target being pushed, and which of them is being followed.
+#[derive(Debug, Default)]
+pub struct FollowTargets(Mutex<Targets>);
+
+impl FollowTargets {
+    pub async fn set(&self, name: &str, position: Position, priority: i32) {
+        let now = Instant::now();
+        let mut targets = self.0.lock().await;
+        let first_received = targets.targets.get(name).map_or(now, |t| t.first_received);
+
+        targets.targets.insert(name.to_string(), Target {
+            position,
+            priority,
+            received: now,
+            first_received,
+        });
+    }
+
+    pub async fn remove(&self, name: &str) -> Option<Target> {
+        self.0.lock().await.targets.remove(name)
+    }
+
+    /// Removes every target and asks [`follow_loop`] to stop the rotator,
+    /// returning a receiver for the report once it has. `None` if there were
+    /// no targets.
+    pub async fn stop(&self) -> Option<oneshot::Receiver<Option<TrackingReport>>> {
+        let mut targets = self.0.lock().await;
+        if targets.targets.is_empty() {
+            return None;
+        }
+
+        targets.targets.clear();
+        targets.active = None;
+        let (sender, receiver) = oneshot::channel();
+        targets.stop = Some(sender);
+
+        Some(receiver)
+    }
+
+    /// Whether following has been asked to stop.
+    async fn is_stopping(&self) -> bool {
+        self.0.lock().await.stop.is_some()
+    }
+
+    async fn take_stop(&self) -> Option<oneshot::Sender<Option<TrackingReport>>> {
+        self.0.lock().await.stop.take()
+    }
+
+    pub async fn all(&self) -> BTreeMap<String, Target> {
+        self.0.lock().await.targets.clone()
+    }
+
+    /// The target being followed, and its name.
+    pub async fn active(&self) -> Option<(String, Target)> {
+        let targets = self.0.lock().await;
+        let name = targets.active.as_ref()?;
+
+        targets.targets.get(name).map(|target| (name.clone(), *target))
+    }
+
+    /// Removes targets which haven't been pushed for `stale_after`, returning
+    /// their names.
+    async fn remove_stale(&self, stale_after: Duration) -> Vec<String> {
+        let mut targets = self.0.lock().await;
+        let stale: Vec<_> = targets
+            .targets
+            .iter()
+            .filter(|(_, target)| target.received.elapsed() > stale_after)
+            .map(|(name, _)| name.clone())
+            .collect();
+
+        for name in &stale {
+            targets.targets.remove(name);
+        }
+
+        stale
+    }
+
+    /// Chooses which target to follow, see the [module docs](self), and
+    /// records it as the active one. `None` if every target is behind the
+    /// horizon mask.
+    async fn select(&self, horizon_mask: &HorizonMask) -> Option<(String, Target)> {
+        let mut targets = self.0.lock().await;
+        let Targets { targets: all, active, .. } = &mut *targets;
+
+        let (name, target) = all
+            .iter()
+            .filter(|(_, t)| !horizon_mask.is_obstructed(t.position.horizontal.into(), t.position.vertical.into()))
+            .max_by_key(|(name, t)| (t.priority, active.as_ref() == Some(*name), Reverse(t.first_received)))?;
+
+        *active = Some(name.clone());
+        Some((name.clone(), *target))
+    }
+}
+
+/// A summary of how well a stream of targets was followed, from the first
+/// target until they stopped.
+#[derive(Debug, Clone, Serialize)]
+pub struct TrackingReport {
+    /// When the first target arrived, in RFC 3339 format.
+    pub started_at: String,
+    pub duration_ms: f64,
+    /// Positions sent to the rotator, counting each axis separately.
+    pub setpoints: u64,
+    /// The furthest either axis was from its target after being sent toward
+    /// it, in degrees. `None` if the position was never read.
+    pub max_pointing_error: Option<f32>,
+    /// Updates where no target was followed because every one was behind
+    /// the horizon mask.
+    pub horizon_skips: u64,
+    /// How many times following switched from one target to another.
+    pub handoffs: u64,
+    /// The longest from a target arriving to the position being read after
+    /// the rotator was sent toward it, in milliseconds. `None` if the
+    /// position was never read.
+    pub max_sample_latency_ms: Option<f64>,
+    /// Whether the targets stopped on their own or following was stopped.
+    pub ended_by: EndReason,
+}
+
+/// Collects the statistics for a [`TrackingReport`] while following.
+struct ReportBuilder {
+    started: Instant,
+    report: TrackingReport,
+}
+
+impl ReportBuilder {
+    fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            report: TrackingReport {
+                started_at: Utc::now().to_rfc3339(),
+                duration_ms: 0.0,
+                setpoints: 0,
+                max_pointing_error: None,
+                horizon_skips: 0,
+                handoffs: 0,
+                max_sample_latency_ms: None,
+                ended_by: EndReason::Completed,
+            },
+        }
+    }
+
+    /// Record how far `actual`, read at `sampled_at`, was from `target`,
+    /// which arrived at `received`.
+    fn pointing_error(&mut self, target: Position, received: Instant, actual: Position, sampled_at: Instant) {
+        let latency = sampled_at.saturating_duration_since(received).as_secs_f64() * 1000.0;
+        let max_latency = self.report.max_sample_latency_ms.get_or_insert(latency);
+        *max_latency = max_latency.max(latency);
+
+        let error = (target.vertical - actual.vertical)
+            .abs()
+            .max(AzimuthConvention::Signed.wrap(target.horizontal - actual.horizontal).abs());
+
+        let max = self.report.max_pointing_error.get_or_insert(error);
+        *max = max.max(error);
+    }
+
+    fn finish(mut self, ended_by: EndReason) -> TrackingReport {
+        self.report.ended_by = ended_by;
+        self.report.duration_ms = self.started.elapsed().as_secs_f64() * 1000.0;
+        self.report
+    }
+}
+
+/// The report for the last stream of targets that was followed.
+pub type LastReport = Arc<Mutex<Option<TrackingReport>>>;
+
+/// Slews the rotator toward the chosen target, paced by the tracking settings
+/// for each axis. Targets are dropped once they go stale, and the rotator is
+/// halted when the last one does.
+///
+/// While there are targets, following owns the rotator in `active`, taking it
+/// over from rocket tracking, and gives it back once they stop. Once they
+/// stop, whether by going stale or being cleared, a [`TrackingReport`] is
+/// saved to `last_report`. When following is stopped
+/// with [`FollowTargets::stop`], this is checked between each axis's
+/// setpoint, and the rotator is halted or parked as `on_stop` says.
+pub async fn follow_loop(
+    rotator: Arc<Mutex<Rotator>>,
+    targets: Arc<FollowTargets>,
+    last_report: LastReport,
+    active_tracking: Arc<ActiveTracking>,
+    axes: PerAxis<AxisTracking>,
+    horizon_mask: HorizonMask,
+    flip: FlipConfig,
+    config: FollowConfig,
+) {
+    let mut ticker = tokio::time::interval(Duration::from_millis(config.interval_ms));
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+    let stale_after = Duration::from_millis(config.stale_after_ms);
+    let new_trackers = || {
+        [
+            AxisTracker::new(Axis::Vertical, axes.vertical),
+            AxisTracker::new(Axis::Horizontal, axes.horizontal),
+        ]
+    };
+    let mut trackers = new_trackers();
+    let mut flip = Flip::new(flip);
+    let mut session = None;
+    let mut active: Option<String> = None;
+
+    loop {
+        ticker.tick().await;
+
+        let stale = targets.remove_stale(stale_after).await;
+        for name in &stale {
+            info!("No position for follow target `{name}` for {}ms, dropping it", config.stale_after_ms);
+        }
+
+        if targets.all().await.is_empty() {
+            let stop = targets.take_stop().await;
+            if stop.is_some() {
+                info!("Following stopped, stopping the rotator");
+                let result = match config.on_stop {
+                    StopAction::Halt => rotator.lock().await.halt().await,
+                    StopAction::Park => Rotator::park(&rotator).await,
+                };
+                if let Err(e) = result {
+                    warn!("Failed to stop the rotator after following was stopped: {e}");
+                }
+            } else if !stale.is_empty() {
+                info!("Every follow target went stale, halting");
+                if let Err(e) = rotator.lock().await.halt().await {
+                    warn!("Failed to halt after the follow targets went stale: {e}");
+                }
+            }
+
+            let ended_by = if stop.is_some() { EndReason::Stopped } else { EndReason::Completed };
+            let report = session.take().map(|session| session.finish(ended_by));
+            if let Some(report) = &report {
+                *last_report.lock().await = Some(report.clone());
+                trackers = new_trackers();
+            }
+            if let Some(stop) = stop {
+                let _ = stop.send(report);
+            }
+            active = None;
+            active_tracking.release(TrackingMode::Follow);
+            continue;
+        }
+        let report = session.get_or_insert_with(|| {
+            if let Some(previous) = active_tracking.take_over(TrackingMode::Follow) {
+                info!("Following pushed targets, taking the rotator over from {previous:?} tracking");
+            }
+            ReportBuilder::new()
+        });
+
+        let Some((name, target)) = targets.select(&horizon_mask).await else {
+            report.report.horizon_skips += 1;
+            continue;
+        };
+        if active.as_ref() != Some(&name) {
+            if let Some(previous) = &active {
+                info!("Handing off following from `{previous}` to `{name}`");
+                report.report.handoffs += 1;
+            }
+            active = Some(name);
+        }
+        let position = target.position;
+
+        let mut rotator_lock = rotator.lock().await;
+        let (horizontal, vertical) = flip.update(position.horizontal.into(), position.vertical.into(), &rotator_lock);
+        let position = Position {
+            vertical: vertical as f32,
+            horizontal: horizontal as f32,
+        };
+        for tracker in &mut trackers {
+            if targets.is_stopping().await {
+                break;
+            }
+            let axis = tracker.axis();
+
+            if let Some(degrees) = tracker.next(position.get(axis), &mut rotator_lock)
+                && rotator_lock.set_position(axis, degrees).await.is_ok()
+            {
+                tracker.sent(degrees);
+                report.report.setpoints += 1;
+            }
+        }
+
+        if let Ok(((vertical, horizontal), sampled_at)) = rotator_lock.position_with_timestamp().await {
+            report.pointing_error(position, target.received, Position { vertical, horizontal }, sampled_at);
+        }
+    }
+}
+
+/// A position pushed for a named target.
+#[derive(Deserialize)]
+pub struct TargetUpdate {
+    #[serde(flatten)]
+    pub position: Position,
+    #[serde(default)]
+    pub priority: i32,
+}
+
+fn describe(name: &str, target: &Target) -> serde_json::Value {
+    json!({
+        "name": name,
+        "position": target.position,
+        "priority": target.priority,
+        "age_ms": target.received.elapsed().as_millis(),
+    })
+}
+
+/// Sets the position of the `default` target, with priority 0. This must be
+/// sent again more often than `stale_after_ms`, or the target is dropped.
+#[post("/track/target", data = "<position>")]
+pub async fn set_target(targets: &State<Arc<FollowTargets>>, position: Json<Position>) -> Result<Success, BadRequest> {
+    let position = check_position_input(position.into_inner(), &PerAxis::default()).map_err(BadRequest::new)?;
+    targets.set(DEFAULT_TARGET, position, 0).await;
+
+    Ok(Success::empty())
+}
+
+/// Sets the position and priority of a named target, e.g. one of several
+/// satellite passes. Like `POST /track/target`, this must keep being sent.
+#[post("/track/targets/<name>", data = "<update>")]
+pub async fn set_named_target(
+    targets: &State<Arc<FollowTargets>>,
+    name: &str,
+    update: Json<TargetUpdate>,
+) -> Result<Success, BadRequest> {
+    let position = check_position_input(update.position, &PerAxis::default()).map_err(BadRequest::new)?;
+    targets.set(name, position, update.priority).await;
+
+    Ok(Success::empty())
+}
+
+/// Gets the target being followed, if any, and how long ago it was sent.
+#[get("/track/target")]
+pub async fn get_target(targets: &State<Arc<FollowTargets>>) -> Success {
+    let active = targets.active().await.map(|(name, target)| describe(&name, &target));
+
+    Success::data(json!(active))
+}
+
+/// Gets every target being pushed, which is being followed, and what is
+/// tracking with the rotator, see [`ActiveTracking`].
+#[get("/track/targets")]
+pub async fn list_targets(targets: &State<Arc<FollowTargets>>, active_tracking: &State<Arc<ActiveTracking>>) -> Success {
+    let active = targets.active().await.map(|(name, _)| name);
+    let all: Vec<_> = targets
+        .all()
+        .await
+        .iter()
+        .map(|(name, target)| describe(name, target))
+        .collect();
+
+    Success::data(json!({
+        "active": active,
+        "mode": active_tracking.get(),
+        "targets": all,
+    }))
+}
+
+/// Stops pushing a named target, handing off to the next if it was being
+/// followed. The rotator is not halted.
+#[delete("/track/targets/<name>")]
+pub async fn remove_target(targets: &State<Arc<FollowTargets>>, name: &str) -> Result<Success, BadRequest> {
+    match targets.remove(name).await {
+        Some(_) => Ok(Success::empty()),
+        None => Err(BadRequest::new(format!("No follow target named `{name}`"))),
+    }
+}
+
+/// Gets what is tracking with the rotator: `"rocket"`, `"follow"`, or `null`
+/// if nothing is.
+#[get("/track/mode")]
+pub async fn tracking_mode(active_tracking: &State<Arc<ActiveTracking>>) -> Success {
+    Success::data(json!({
+        "mode": active_tracking.get(),
+    }))
+}
+
+/// Gets the report for the last stream of targets followed, once it has
+/// stopped.
+#[get("/track/last-report")]
+pub async fn last_report(last_report: &State<LastReport>) -> Success {
+    Success::data(json!(*last_report.lock().await))
+}
+
+/// Stops following every target and halts or parks the rotator, as
+/// `POST /track/stop` does, but without responding with the report.
+#[delete("/track/target")]
+pub async fn stop_following(targets: &State<Arc<FollowTargets>>) -> Result<Success, Error> {
+    if let Some(stopped) = targets.stop().await {
+        stopped.await.map_err(|_| Error("Following stopped without a report".to_string()))?;
+    }
+
+    Ok(Success::empty())
+}
+
+/// Stops following every target, halting or parking the rotator as
+/// `tracking.follow.on_stop` says, and responds with the report once it
+/// has.
+#[post("/track/stop")]
+pub async fn stop_tracking(targets: &State<Arc<FollowTargets>>) -> Result<Success, Failure> {
+    let Some(stopped) = targets.stop().await else {
+        return Err(BadRequest::new("Nothing is being followed").into());
+    };
+    let report = stopped.await.map_err(|_| Error("Following stopped without a report".to_string()))?;
+
+    Ok(Success::data(json!(report)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{control_loop::MaskSegment, rotator::mock::{self, MockFirmware}};
+
+    /// Pushed targets being followed by a rotator connected to `firmware`.
+    struct Following {
+        targets: Arc<FollowTargets>,
+        last_report: LastReport,
+        active: Arc<ActiveTracking>,
+    }
+
+    fn follow(firmware: &MockFirmware, config: FollowConfig, horizon_mask: HorizonMask) -> Following {
+        let following = Following {
+            targets: Arc::default(),
+            last_report: LastReport::default(),
+            active: Arc::default(),
+        };
+        tokio::spawn(follow_loop(
+            Arc::new(firmware.shared(mock::config())),
+            Arc::clone(&following.targets),
+            Arc::clone(&following.last_report),
+            Arc::clone(&following.active),
+            PerAxis::default(),
+            horizon_mask,
+            FlipConfig::default(),
+            config,
+        ));
+
+        following
+    }
+
+    fn config(stale_after_ms: u64) -> FollowConfig {
+        FollowConfig { interval_ms: 10, stale_after_ms, ..FollowConfig::default() }
+    }
+
+    async fn wait_for(condition: impl Fn() -> bool) {
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while !condition() {
+            assert!(Instant::now() < deadline, "timed out waiting for the follow loop");
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+
+    fn sent(firmware: &MockFirmware, line: &str) -> bool {
+        firmware.received().iter().any(|received| received == line)
+    }
+
+    #[rocket::async_test]
+    async fn the_rotator_follows_the_latest_target() {
+        let firmware = MockFirmware::new();
+        let following = follow(&firmware, config(1_000), HorizonMask::default());
+        // Pushed targets take the rotator over from rocket tracking
+        assert!(following.active.claim(TrackingMode::Rocket));
+
+        following.targets.set(DEFAULT_TARGET, Position { vertical: 10.0, horizontal: 20.0 }, 0).await;
+        // Azimuths are inverted on the way to the firmware
+        wait_for(|| sent(&firmware, "DVER 10.000") && sent(&firmware, "DHOR -20.000")).await;
+        assert_eq!(following.active.get(), Some(TrackingMode::Follow));
+
+        following.targets.set(DEFAULT_TARGET, Position { vertical: 30.0, horizontal: 40.0 }, 0).await;
+        wait_for(|| sent(&firmware, "DVER 30.000") && sent(&firmware, "DHOR -40.000")).await;
+        assert_eq!(following.targets.active().await.unwrap().0, DEFAULT_TARGET);
+    }
+
+    #[rocket::async_test]
+    async fn a_stale_target_halts_the_rotator_once() {
+        let firmware = MockFirmware::new();
+        let following = follow(&firmware, config(100), HorizonMask::default());
+
+        following.targets.set(DEFAULT_TARGET, Position { vertical: 10.0, horizontal: 20.0 }, 0).await;
+        wait_for(|| sent(&firmware, "DVER 10.000")).await;
+        assert!(!sent(&firmware, "HALT"));
+
+        wait_for(|| sent(&firmware, "HALT") && following.active.get().is_none()).await;
+        assert!(following.targets.all().await.is_empty());
+        let report = following.last_report.lock().await.clone().unwrap();
+        assert_eq!(report.ended_by, EndReason::Completed);
+        assert_eq!(report.setpoints, 2);
+
+        // Nothing more is sent until another target arrives
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(firmware.commands().iter().filter(|code| *code == "HALT").count(), 1);
+    }
+
+    #[test]
+    fn the_report_keeps_the_worst_error_and_latency() {
+        let mut report = ReportBuilder::new();
+        let received = Instant::now();
+        let target = Position { vertical: 10.0, horizontal: 359.0 };
+
+        let actual = Position { vertical: 9.0, horizontal: 358.5 };
+        report.pointing_error(target, received, actual, received + Duration::from_millis(250));
+        // Either side of north is only two degrees out, the worst so far
+        let actual = Position { vertical: 10.5, horizontal: 1.0 };
+        report.pointing_error(target, received, actual, received + Duration::from_millis(125));
+        let actual = Position { vertical: 10.0, horizontal: 359.0 };
+        report.pointing_error(target, received, actual, received + Duration::from_millis(200));
+
+        let report = report.finish(EndReason::Stopped);
+        assert_eq!(report.max_pointing_error, Some(2.0));
+        assert_eq!(report.max_sample_latency_ms, Some(250.0));
+        assert_eq!(report.ended_by, EndReason::Stopped);
+    }
+
+    #[rocket::async_test]
+    async fn targets_behind_the_mask_are_counted_as_skipped() {
+        let firmware = MockFirmware::new();
+        let mask = HorizonMask(vec![MaskSegment { from: 0.0, to: 180.0, min_elevation: 20.0 }]);
+        let following = follow(&firmware, config(100), mask);
+
+        following.targets.set(DEFAULT_TARGET, Position { vertical: 10.0, horizontal: 90.0 }, 0).await;
+        wait_for(|| following.last_report.try_lock().is_ok_and(|report| report.is_some())).await;
+
+        let report = following.last_report.lock().await.clone().unwrap();
+        assert_eq!(report.setpoints, 0);
+        assert!(report.horizon_skips > 0);
+        assert_eq!(report.max_pointing_error, None);
+        assert!(report.duration_ms > 0.0);
+        assert!(!sent(&firmware, "DVER 10.000"));
+    }
+
+    #[rocket::async_test]
+    async fn the_highest_priority_visible_target_is_chosen() {
+        let targets = FollowTargets::default();
+        let mask = HorizonMask(vec![MaskSegment { from: 180.0, to: 360.0, min_elevation: 20.0 }]);
+
+        targets.set("low", Position { vertical: 30.0, horizontal: 90.0 }, 1).await;
+        targets.set("high", Position { vertical: 10.0, horizontal: 270.0 }, 2).await;
+        // The higher priority target hasn't risen above the mask yet
+        assert_eq!(targets.select(&mask).await.unwrap().0, "low");
+
+        targets.set("high", Position { vertical: 25.0, horizontal: 270.0 }, 2).await;
+        assert_eq!(targets.select(&mask).await.unwrap().0, "high");
+        assert_eq!(targets.active().await.unwrap().0, "high");
+
+        targets.set("low", Position { vertical: 5.0, horizontal: 200.0 }, 1).await;
+        targets.set("high", Position { vertical: 5.0, horizontal: 270.0 }, 2).await;
+        assert!(targets.select(&mask).await.is_none());
+    }
+
+    #[rocket::async_test]
+    async fn overlapping_targets_of_the_same_priority_keep_the_one_followed() {
+        let targets = FollowTargets::default();
+        let mask = HorizonMask(vec![MaskSegment { from: 180.0, to: 360.0, min_elevation: 20.0 }]);
+
+        targets.set("first", Position { vertical: 10.0, horizontal: 270.0 }, 0).await;
+        targets.set("second", Position { vertical: 30.0, horizontal: 90.0 }, 0).await;
+        assert_eq!(targets.select(&mask).await.unwrap().0, "second");
+
+        // Being pushed first isn't enough to take over once it rises
+        targets.set("first", Position { vertical: 30.0, horizontal: 270.0 }, 0).await;
+        assert_eq!(targets.select(&mask).await.unwrap().0, "second");
+
+        targets.remove("second").await;
+        assert_eq!(targets.select(&mask).await.unwrap().0, "first");
+        targets.set("second", Position { vertical: 30.0, horizontal: 90.0 }, 0).await;
+        assert_eq!(targets.select(&mask).await.unwrap().0, "first");
+
+        // Without one being followed, the one pushed first is chosen
+        let targets = FollowTargets::default();
+        targets.set("first", Position { vertical: 30.0, horizontal: 90.0 }, 0).await;
+        tokio::time::sleep(Duration::from_millis(1)).await;
+        targets.set("second", Position { vertical: 30.0, horizontal: 270.0 }, 0).await;
+        assert_eq!(targets.select(&HorizonMask::default()).await.unwrap().0, "first");
+    }
+
+    #[rocket::async_test]
+    async fn following_hands_off_between_targets() {
+        let firmware = MockFirmware::new();
+        let following = follow(&firmware, config(1_000), HorizonMask::default());
+        let sent_count = |line: &str| firmware.received().iter().filter(|received| *received == line).count();
+
+        following.targets.set("low", Position { vertical: 10.0, horizontal: 20.0 }, 0).await;
+        wait_for(|| sent(&firmware, "DVER 10.000")).await;
+        following.targets.set("high", Position { vertical: 30.0, horizontal: 40.0 }, 1).await;
+        wait_for(|| sent(&firmware, "DVER 30.000")).await;
+        following.targets.remove("high").await;
+        wait_for(|| sent_count("DVER 10.000") == 2).await;
+
+        let report = following.targets.stop().await.unwrap().await.unwrap().unwrap();
+        assert_eq!(report.handoffs, 2);
+        assert_eq!(report.ended_by, EndReason::Stopped);
+    }
+
+    #[rocket::async_test]
+    async fn stopping_mid_pass_halts_and_says_it_was_stopped() {
+        let firmware = MockFirmware::new();
+        let following = follow(&firmware, config(1_000), HorizonMask::default());
+
+        following.targets.set(DEFAULT_TARGET, Position { vertical: 10.0, horizontal: 20.0 }, 0).await;
+        wait_for(|| sent(&firmware, "DVER 10.000")).await;
+        assert!(!sent(&firmware, "HALT"));
+
+        let report = following.targets.stop().await.unwrap().await.unwrap().unwrap();
+
+        assert_eq!(report.ended_by, EndReason::Stopped);
+        assert!(sent(&firmware, "HALT"));
+        assert!(following.targets.all().await.is_empty());
+        assert_eq!(following.last_report.lock().await.clone().unwrap().ended_by, EndReason::Stopped);
+        // The rotator is free for anything else once stopped
+        wait_for(|| following.active.get().is_none()).await;
+
+        // Nothing is sent once stopped
+        let received = firmware.received().len();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(firmware.received().len(), received);
+    }
+
+    #[rocket::async_test]
+    async fn stopping_can_park_the_rotator_instead() {
+        let firmware = MockFirmware::new();
+        let config = FollowConfig { on_stop: StopAction::Park, ..config(1_000) };
+        let following = follow(&firmware, config, HorizonMask::default());
+
+        following.targets.set(DEFAULT_TARGET, Position { vertical: 10.0, horizontal: 20.0 }, 0).await;
+        wait_for(|| sent(&firmware, "DVER 10.000")).await;
+
+        let report = following.targets.stop().await.unwrap().await.unwrap().unwrap();
+
+        assert_eq!(report.ended_by, EndReason::Stopped);
+        assert!(!sent(&firmware, "HALT"));
+        assert!(sent(&firmware, "DVER 0.000"));
+        assert_eq!(firmware.lock().position, PerAxis { vertical: 0.0, horizontal: 0.0 });
+    }
+
+    #[rocket::async_test]
+    async fn track_stop_responds_with_the_report() {
+        use rocket::{http::Status, local::asynchronous::Client};
+
+        let firmware = MockFirmware::new();
+        let following = follow(&firmware, config(1_000), HorizonMask::default());
+        let rocket = rocket::build()
+            .manage(Arc::clone(&following.targets))
+            .mount("/", rocket::routes![stop_tracking]);
+        let client = Client::tracked(rocket).await.unwrap();
+
+        let response = client.post("/track/stop").dispatch().await;
+        assert_eq!(response.status(), Status::BadRequest);
+
+        following.targets.set(DEFAULT_TARGET, Position { vertical: 10.0, horizontal: 20.0 }, 0).await;
+        wait_for(|| sent(&firmware, "DVER 10.000")).await;
+
+        let response = client.post("/track/stop").dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        let body: serde_json::Value = serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+        assert_eq!(body["data"]["ended_by"], "stopped");
+        assert!(sent(&firmware, "HALT"));
+    }
+}
@@ -0,0 +1,277 @@
+//! A gRPC interface to the default rotator, defined in `proto/archerd.proto`,
+//! for services elsewhere in a ground station which would rather not speak
+//! HTTP and JSON. It shares the same [`RotatorHandle`] as the REST routes.
+
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+use log::{info, warn};
+use rocket::tokio::{self, sync::mpsc};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status, transport::Server};
+
+use crate::rotator::{self, Axis, registry::RotatorHandle, units};
+
+use proto::rotator_server::RotatorServer;
+
+mod proto;
+
+/// How often `PositionUpdates` checks for a new position if the client
+/// doesn't say.
+const DEFAULT_UPDATE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How many position updates may be waiting for a slow client.
+const UPDATE_CAPACITY: usize = 16;
+
+/// Serves the gRPC interface on `port` until the server stops.
+pub async fn serve(handle: RotatorHandle, port: u16) {
+    let address = SocketAddr::from(([0, 0, 0, 0], port));
+    info!("Serving gRPC on {address}");
+
+    let service = RotatorServer::new(RotatorService { handle });
+    if let Err(e) = Server::builder().add_service(service).serve(address).await {
+        warn!("gRPC server stopped: {e}");
+    }
+}
+
+/// Rotator errors are reported with the closest gRPC code.
+fn status(error: rotator::Error) -> Status {
+    match error {
+        rotator::Error::OutOfRange { .. } => Status::invalid_argument(error.to_string()),
+        rotator::Error::Unsupported(_) => Status::unimplemented(error.to_string()),
+        rotator::Error::Timeout => Status::deadline_exceeded(error.to_string()),
+        _ => Status::internal(error.to_string()),
+    }
+}
+
+impl From<rotator::Position> for proto::Position {
+    fn from(value: rotator::Position) -> Self {
+        Self {
+            vertical: value.vertical,
+            horizontal: value.horizontal,
+        }
+    }
+}
+
+pub struct RotatorService {
+    handle: RotatorHandle,
+}
+
+#[tonic::async_trait]
+impl proto::rotator_server::Rotator for RotatorService {
+    async fn get_position(
+        &self,
+        _request: Request<proto::GetPositionRequest>,
+    ) -> Result<Response<proto::Position>, Status> {
+        let (vertical, horizontal) = self.handle.lock().await.position().await.map_err(status)?;
+
+        Ok(Response::new(proto::Position { vertical, horizontal }))
+    }
+
+    async fn set_position(
+        &self,
+        request: Request<proto::SetPositionRequest>,
+    ) -> Result<Response<proto::SetPositionReply>, Status> {
+        let request = request.into_inner();
+        if request.vertical.is_none() && request.horizontal.is_none() {
+            return Err(Status::invalid_argument("expected `vertical` and/or `horizontal`"));
+        }
+
+        for (axis, value) in [(Axis::Vertical, request.vertical), (Axis::Horizontal, request.horizontal)] {
+            if let Some(value) = value
+                && let Err(e) = units::check_angle(axis, value, None)
+            {
+                return Err(Status::invalid_argument(e));
+            }
+        }
+
+        let mut rotator = self.handle.lock().await;
+        if let Some(v) = request.vertical {
+            rotator.set_position_vertical(v).await.map_err(status)?;
+        }
+        if let Some(h) = request.horizontal {
+            rotator.set_position_horizontal(h).await.map_err(status)?;
+        }
+
+        Ok(Response::new(proto::SetPositionReply {}))
+    }
+
+    async fn halt(&self, _request: Request<proto::HaltRequest>) -> Result<Response<proto::HaltReply>, Status> {
+        let position = self.handle.lock().await.halt_and_report().await.map_err(status)?;
+
+        Ok(Response::new(proto::HaltReply { position: position.map(Into::into) }))
+    }
+
+    async fn calibrate(
+        &self,
+        request: Request<proto::CalibrateRequest>,
+    ) -> Result<Response<proto::CalibrateReply>, Status> {
+        let request = request.into_inner();
+
+        let mut rotator = self.handle.lock().await;
+        let result = match proto::Axis::try_from(request.axis) {
+            Ok(proto::Axis::Vertical) => rotator.calibrate_vertical(request.set).await,
+            Ok(proto::Axis::Horizontal) => rotator.calibrate(Axis::Horizontal).await,
+            _ => return Err(Status::invalid_argument("expected an axis")),
+        };
+        result.map_err(status)?;
+
+        Ok(Response::new(proto::CalibrateReply {}))
+    }
+
+    async fn version(&self, _request: Request<proto::VersionRequest>) -> Result<Response<proto::VersionReply>, Status> {
+        let version = self.handle.lock().await.version().await.map_err(status)?;
+
+        Ok(Response::new(proto::VersionReply { version }))
+    }
+
+    type PositionUpdatesStream = ReceiverStream<Result<proto::Position, Status>>;
+
+    /// Sends the position from the poller's telemetry each time it changes,
+    /// so streaming doesn't add any serial traffic of its own.
+    async fn position_updates(
+        &self,
+        request: Request<proto::PositionUpdatesRequest>,
+    ) -> Result<Response<Self::PositionUpdatesStream>, Status> {
+        let interval = match request.into_inner().interval_ms {
+            0 => DEFAULT_UPDATE_INTERVAL,
+            ms => Duration::from_millis(ms.into()),
+        };
+        let telemetry = Arc::clone(&self.handle.telemetry);
+        let (updates, receiver) = mpsc::channel(UPDATE_CAPACITY);
+
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            let mut last = None;
+            loop {
+                ticker.tick().await;
+
+                let Some(position) = telemetry.lock().await.position else {
+                    continue;
+                };
+                if last == Some(position) {
+                    continue;
+                }
+                last = Some(position);
+
+                // Stop once the client has gone
+                if updates.send(Ok(position.into())).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(receiver)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rocket::tokio::sync::Mutex;
+    use tokio_stream::StreamExt;
+    use tonic::Code;
+
+    use super::{proto::rotator_server::Rotator as _, *};
+    use crate::rotator::{Position, mock::{self, MockFirmware}};
+
+    /// The service, backed by `firmware` without a poller.
+    fn service(firmware: &MockFirmware) -> RotatorService {
+        let rotator = Arc::new(Mutex::new(firmware.rotator(mock::config())));
+
+        RotatorService { handle: RotatorHandle::unpolled(rotator) }
+    }
+
+    #[rocket::async_test]
+    async fn get_position_is_in_azimuth_and_elevation() {
+        let firmware = MockFirmware::new();
+        firmware.lock().position.vertical = 10.0;
+        firmware.lock().position.horizontal = -20.0;
+
+        let reply = service(&firmware).get_position(Request::new(proto::GetPositionRequest {})).await.unwrap();
+
+        assert_eq!(reply.into_inner(), proto::Position { vertical: 10.0, horizontal: 20.0 });
+    }
+
+    #[rocket::async_test]
+    async fn set_position_sends_only_the_axes_given() {
+        let firmware = MockFirmware::new();
+        let service = service(&firmware);
+
+        let request = proto::SetPositionRequest { vertical: Some(45.0), horizontal: None };
+        service.set_position(Request::new(request)).await.unwrap();
+        assert_eq!(firmware.received(), ["DVER 45.000"]);
+
+        for request in [
+            proto::SetPositionRequest { vertical: None, horizontal: None },
+            proto::SetPositionRequest { vertical: Some(f32::NAN), horizontal: None },
+        ] {
+            let error = service.set_position(Request::new(request)).await.unwrap_err();
+            assert_eq!(error.code(), Code::InvalidArgument);
+        }
+        assert_eq!(firmware.received(), ["DVER 45.000"]);
+    }
+
+    #[rocket::async_test]
+    async fn halt_reports_where_it_stopped() {
+        let firmware = MockFirmware::new();
+        firmware.lock().position.vertical = 12.0;
+        let service = service(&firmware);
+
+        let reply = service.halt(Request::new(proto::HaltRequest {})).await.unwrap().into_inner();
+        assert_eq!(reply.position, Some(proto::Position { vertical: 12.0, horizontal: 0.0 }));
+        assert_eq!(firmware.commands(), ["HALT", "GETP"]);
+
+        firmware.reply("HALT", "ERR busy");
+        let error = service.halt(Request::new(proto::HaltRequest {})).await.unwrap_err();
+        assert_eq!(error.code(), Code::Internal);
+    }
+
+    #[rocket::async_test]
+    async fn calibrate_needs_an_axis() {
+        let firmware = MockFirmware::new();
+        let service = service(&firmware);
+
+        let calibrate = |axis: proto::Axis, set| Request::new(proto::CalibrateRequest { axis: axis.into(), set });
+        service.calibrate(calibrate(proto::Axis::Vertical, true)).await.unwrap();
+        service.calibrate(calibrate(proto::Axis::Horizontal, false)).await.unwrap();
+        assert_eq!(firmware.received(), ["CALV SET", "CALH"]);
+
+        let error = service.calibrate(calibrate(proto::Axis::Unspecified, false)).await.unwrap_err();
+        assert_eq!(error.code(), Code::InvalidArgument);
+        assert_eq!(firmware.received(), ["CALV SET", "CALH"]);
+    }
+
+    #[rocket::async_test]
+    async fn version_and_timeouts() {
+        let firmware = MockFirmware::new();
+        firmware.lock().version = "v1.4.0".to_string();
+        let service = service(&firmware);
+
+        let reply = service.version(Request::new(proto::VersionRequest {})).await.unwrap();
+        assert_eq!(reply.into_inner().version, "v1.4.0");
+
+        firmware.lock().silent = true;
+        let error = service.get_position(Request::new(proto::GetPositionRequest {})).await.unwrap_err();
+        assert_eq!(error.code(), Code::DeadlineExceeded);
+    }
+
+    #[rocket::async_test]
+    async fn position_updates_follow_the_telemetry() {
+        let firmware = MockFirmware::new();
+        let service = service(&firmware);
+        let telemetry = Arc::clone(&service.handle.telemetry);
+
+        let request = Request::new(proto::PositionUpdatesRequest { interval_ms: 10 });
+        let mut updates = service.position_updates(request).await.unwrap().into_inner();
+
+        telemetry.lock().await.position = Some(Position { vertical: 1.0, horizontal: 2.0 });
+        let update = updates.next().await.unwrap().unwrap();
+        assert_eq!(update, proto::Position { vertical: 1.0, horizontal: 2.0 });
+
+        // An unchanged position isn't sent again
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        telemetry.lock().await.position = Some(Position { vertical: 3.0, horizontal: 2.0 });
+        let update = updates.next().await.unwrap().unwrap();
+        assert_eq!(update, proto::Position { vertical: 3.0, horizontal: 2.0 });
+        assert!(firmware.received().is_empty());
+    }
+}
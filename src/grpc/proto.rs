@@ -0,0 +1,519 @@
+// This file is @generated by prost-build.
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct Position {
+    #[prost(float, tag = "1")]
+    pub vertical: f32,
+    #[prost(float, tag = "2")]
+    pub horizontal: f32,
+}
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct GetPositionRequest {}
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct SetPositionRequest {
+    #[prost(float, optional, tag = "1")]
+    pub vertical: ::core::option::Option<f32>,
+    #[prost(float, optional, tag = "2")]
+    pub horizontal: ::core::option::Option<f32>,
+}
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct SetPositionReply {}
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct HaltRequest {}
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct HaltReply {
+    /// Unset if the position couldn't be read after halting.
+    #[prost(message, optional, tag = "1")]
+    pub position: ::core::option::Option<Position>,
+}
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct CalibrateRequest {
+    #[prost(enumeration = "Axis", tag = "1")]
+    pub axis: i32,
+    /// Calibrate the vertical axis with `CALV SET`.
+    #[prost(bool, tag = "2")]
+    pub set: bool,
+}
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct CalibrateReply {}
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct VersionRequest {}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct VersionReply {
+    #[prost(string, tag = "1")]
+    pub version: ::prost::alloc::string::String,
+}
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct PositionUpdatesRequest {
+    /// How often to check for a new position. Defaults to 500ms if unset.
+    #[prost(uint32, tag = "1")]
+    pub interval_ms: u32,
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum Axis {
+    Unspecified = 0,
+    Vertical = 1,
+    Horizontal = 2,
+}
+impl Axis {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            Self::Unspecified => "AXIS_UNSPECIFIED",
+            Self::Vertical => "AXIS_VERTICAL",
+            Self::Horizontal => "AXIS_HORIZONTAL",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "AXIS_UNSPECIFIED" => Some(Self::Unspecified),
+            "AXIS_VERTICAL" => Some(Self::Vertical),
+            "AXIS_HORIZONTAL" => Some(Self::Horizontal),
+            _ => None,
+        }
+    }
+}
+/// Generated server implementations.
+pub mod rotator_server {
+    #![allow(
+        unused_variables,
+        dead_code,
+        missing_docs,
+        clippy::wildcard_imports,
+        clippy::let_unit_value,
+    )]
+    use tonic::codegen::*;
+    /// Generated trait containing gRPC methods that should be implemented for use with RotatorServer.
+    #[async_trait]
+    pub trait Rotator: std::marker::Send + std::marker::Sync + 'static {
+        /// The current position, in degrees.
+        async fn get_position(
+            &self,
+            request: tonic::Request<super::GetPositionRequest>,
+        ) -> std::result::Result<tonic::Response<super::Position>, tonic::Status>;
+        /// Sends either or both axes to a position, without waiting for it to be
+        /// reached.
+        async fn set_position(
+            &self,
+            request: tonic::Request<super::SetPositionRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::SetPositionReply>,
+            tonic::Status,
+        >;
+        /// Halts both motors, returning where they stopped if it could be read.
+        async fn halt(
+            &self,
+            request: tonic::Request<super::HaltRequest>,
+        ) -> std::result::Result<tonic::Response<super::HaltReply>, tonic::Status>;
+        async fn calibrate(
+            &self,
+            request: tonic::Request<super::CalibrateRequest>,
+        ) -> std::result::Result<tonic::Response<super::CalibrateReply>, tonic::Status>;
+        /// The version of the rotator's firmware.
+        async fn version(
+            &self,
+            request: tonic::Request<super::VersionRequest>,
+        ) -> std::result::Result<tonic::Response<super::VersionReply>, tonic::Status>;
+        /// Server streaming response type for the PositionUpdates method.
+        type PositionUpdatesStream: tonic::codegen::tokio_stream::Stream<
+                Item = std::result::Result<super::Position, tonic::Status>,
+            >
+            + std::marker::Send
+            + 'static;
+        /// The position each time it changes, as sampled by the poller.
+        async fn position_updates(
+            &self,
+            request: tonic::Request<super::PositionUpdatesRequest>,
+        ) -> std::result::Result<
+            tonic::Response<Self::PositionUpdatesStream>,
+            tonic::Status,
+        >;
+    }
+    #[derive(Debug)]
+    pub struct RotatorServer<T> {
+        inner: Arc<T>,
+        accept_compression_encodings: EnabledCompressionEncodings,
+        send_compression_encodings: EnabledCompressionEncodings,
+        max_decoding_message_size: Option<usize>,
+        max_encoding_message_size: Option<usize>,
+    }
+    impl<T> RotatorServer<T> {
+        pub fn new(inner: T) -> Self {
+            Self::from_arc(Arc::new(inner))
+        }
+        pub fn from_arc(inner: Arc<T>) -> Self {
+            Self {
+                inner,
+                accept_compression_encodings: Default::default(),
+                send_compression_encodings: Default::default(),
+                max_decoding_message_size: None,
+                max_encoding_message_size: None,
+            }
+        }
+        pub fn with_interceptor<F>(
+            inner: T,
+            interceptor: F,
+        ) -> InterceptedService<Self, F>
+        where
+            F: tonic::service::Interceptor,
+        {
+            InterceptedService::new(Self::new(inner), interceptor)
+        }
+        /// Enable decompressing requests with the given encoding.
+        #[must_use]
+        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.accept_compression_encodings.enable(encoding);
+            self
+        }
+        /// Compress responses with the given encoding, if the client supports it.
+        #[must_use]
+        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.send_compression_encodings.enable(encoding);
+            self
+        }
+        /// Limits the maximum size of a decoded message.
+        ///
+        /// Default: `4MB`
+        #[must_use]
+        pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
+            self.max_decoding_message_size = Some(limit);
+            self
+        }
+        /// Limits the maximum size of an encoded message.
+        ///
+        /// Default: `usize::MAX`
+        #[must_use]
+        pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
+            self.max_encoding_message_size = Some(limit);
+            self
+        }
+    }
+    impl<T, B> tonic::codegen::Service<http::Request<B>> for RotatorServer<T>
+    where
+        T: Rotator,
+        B: Body + std::marker::Send + 'static,
+        B::Error: Into<StdError> + std::marker::Send + 'static,
+    {
+        type Response = http::Response<tonic::body::BoxBody>;
+        type Error = std::convert::Infallible;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+        fn poll_ready(
+            &mut self,
+            _cx: &mut Context<'_>,
+        ) -> Poll<std::result::Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+        fn call(&mut self, req: http::Request<B>) -> Self::Future {
+            match req.uri().path() {
+                "/archerd.v1.Rotator/GetPosition" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetPositionSvc<T: Rotator>(pub Arc<T>);
+                    impl<
+                        T: Rotator,
+                    > tonic::server::UnaryService<super::GetPositionRequest>
+                    for GetPositionSvc<T> {
+                        type Response = super::Position;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::GetPositionRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Rotator>::get_position(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = GetPositionSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/archerd.v1.Rotator/SetPosition" => {
+                    #[allow(non_camel_case_types)]
+                    struct SetPositionSvc<T: Rotator>(pub Arc<T>);
+                    impl<
+                        T: Rotator,
+                    > tonic::server::UnaryService<super::SetPositionRequest>
+                    for SetPositionSvc<T> {
+                        type Response = super::SetPositionReply;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::SetPositionRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Rotator>::set_position(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = SetPositionSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/archerd.v1.Rotator/Halt" => {
+                    #[allow(non_camel_case_types)]
+                    struct HaltSvc<T: Rotator>(pub Arc<T>);
+                    impl<T: Rotator> tonic::server::UnaryService<super::HaltRequest>
+                    for HaltSvc<T> {
+                        type Response = super::HaltReply;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::HaltRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Rotator>::halt(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = HaltSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/archerd.v1.Rotator/Calibrate" => {
+                    #[allow(non_camel_case_types)]
+                    struct CalibrateSvc<T: Rotator>(pub Arc<T>);
+                    impl<
+                        T: Rotator,
+                    > tonic::server::UnaryService<super::CalibrateRequest>
+                    for CalibrateSvc<T> {
+                        type Response = super::CalibrateReply;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::CalibrateRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Rotator>::calibrate(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = CalibrateSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/archerd.v1.Rotator/Version" => {
+                    #[allow(non_camel_case_types)]
+                    struct VersionSvc<T: Rotator>(pub Arc<T>);
+                    impl<
+                        T: Rotator,
+                    > tonic::server::UnaryService<super::VersionRequest>
+                    for VersionSvc<T> {
+                        type Response = super::VersionReply;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::VersionRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Rotator>::version(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = VersionSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/archerd.v1.Rotator/PositionUpdates" => {
+                    #[allow(non_camel_case_types)]
+                    struct PositionUpdatesSvc<T: Rotator>(pub Arc<T>);
+                    impl<
+                        T: Rotator,
+                    > tonic::server::ServerStreamingService<
+                        super::PositionUpdatesRequest,
+                    > for PositionUpdatesSvc<T> {
+                        type Response = super::Position;
+                        type ResponseStream = T::PositionUpdatesStream;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::ResponseStream>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::PositionUpdatesRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Rotator>::position_updates(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = PositionUpdatesSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.server_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                _ => {
+                    Box::pin(async move {
+                        let mut response = http::Response::new(empty_body());
+                        let headers = response.headers_mut();
+                        headers
+                            .insert(
+                                tonic::Status::GRPC_STATUS,
+                                (tonic::Code::Unimplemented as i32).into(),
+                            );
+                        headers
+                            .insert(
+                                http::header::CONTENT_TYPE,
+                                tonic::metadata::GRPC_CONTENT_TYPE,
+                            );
+                        Ok(response)
+                    })
+                }
+            }
+        }
+    }
+    impl<T> Clone for RotatorServer<T> {
+        fn clone(&self) -> Self {
+            let inner = self.inner.clone();
+            Self {
+                inner,
+                accept_compression_encodings: self.accept_compression_encodings,
+                send_compression_encodings: self.send_compression_encodings,
+                max_decoding_message_size: self.max_decoding_message_size,
+                max_encoding_message_size: self.max_encoding_message_size,
+            }
+        }
+    }
+    /// Generated gRPC service name
+    pub const SERVICE_NAME: &str = "archerd.v1.Rotator";
+    impl<T> tonic::server::NamedService for RotatorServer<T> {
+        const NAME: &'static str = SERVICE_NAME;
+    }
+}
@@ -0,0 +1,211 @@
+//! Support for the `Idempotency-Key` header on movement commands.
+//!
+//! A client which retries a request after a dropped connection can't tell
+//! whether the first attempt moved the rotator. If it sends the same
+//! `Idempotency-Key` with each attempt, only the first is carried out, and
+//! the retries get its result.
+
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    time::{Duration, Instant},
+};
+
+use rocket::{
+    Request,
+    request::{FromRequest, Outcome},
+    tokio::sync::Mutex,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::response::{Conflict, Failure, Success};
+
+/// How many results are kept, and for how long.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct IdempotencyConfig {
+    pub capacity: usize,
+    pub ttl_ms: u64,
+}
+
+impl Default for IdempotencyConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 256,
+            ttl_ms: 10 * 60 * 1000,
+        }
+    }
+}
+
+/// Request guard for the optional `Idempotency-Key` header.
+pub struct IdempotencyKey(pub Option<String>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for IdempotencyKey {
+    type Error = Infallible;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(Self(req.headers().get_one("Idempotency-Key").map(str::to_string)))
+    }
+}
+
+enum Entry {
+    /// The first request with this key, started at this time, hasn't
+    /// finished yet.
+    Pending(Instant),
+    Done(Instant, Result<Success, Failure>),
+}
+
+/// Results of recent requests, by idempotency key.
+pub struct IdempotencyCache {
+    config: IdempotencyConfig,
+    entries: Mutex<HashMap<String, Entry>>,
+}
+
+impl IdempotencyCache {
+    pub fn new(config: IdempotencyConfig) -> Self {
+        Self {
+            config,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Run `request` unless a request with the same key has already been
+    /// run, in which case its result is returned instead. Requests without a
+    /// key are always run.
+    ///
+    /// A retry which arrives while the first request is still running is
+    /// refused with a [`Conflict`], rather than waiting for it.
+    pub async fn run<F>(&self, key: IdempotencyKey, request: F) -> Result<Success, Failure>
+    where
+        F: Future<Output = Result<Success, Failure>>,
+    {
+        let IdempotencyKey(Some(key)) = key else {
+            return request.await;
+        };
+
+        {
+            let mut entries = self.entries.lock().await;
+            self.expire(&mut entries);
+
+            match entries.get(&key) {
+                Some(Entry::Done(_, result)) => return result.clone(),
+                Some(Entry::Pending(_)) => {
+                    let message = format!("A request with idempotency key `{key}` is still in progress, retry once it has finished");
+                    return Err(Conflict::new(message).into());
+                }
+                None => {
+                    entries.insert(key.clone(), Entry::Pending(Instant::now()));
+                }
+            }
+        }
+
+        let result = request.await;
+        self.entries
+            .lock()
+            .await
+            .insert(key, Entry::Done(Instant::now(), result.clone()));
+
+        result
+    }
+
+    /// Drop expired results, then the oldest ones until there is room for
+    /// another.
+    fn expire(&self, entries: &mut HashMap<String, Entry>) {
+        let ttl = Duration::from_millis(self.config.ttl_ms);
+        entries.retain(|_, (Entry::Pending(at) | Entry::Done(at, _))| at.elapsed() < ttl);
+
+        while entries.len() >= self.config.capacity.max(1) {
+            let oldest = entries
+                .iter()
+                .filter_map(|(k, e)| match e {
+                    Entry::Done(at, _) => Some((k.clone(), *at)),
+                    Entry::Pending(_) => None,
+                })
+                .min_by_key(|(_, at)| *at);
+
+            match oldest {
+                Some((key, _)) => entries.remove(&key),
+                None => break,
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+
+    fn key(key: &str) -> IdempotencyKey {
+        IdempotencyKey(Some(key.to_string()))
+    }
+
+    /// Runs a request under `key` which counts how often it is run.
+    async fn run(cache: &IdempotencyCache, key: IdempotencyKey, runs: &Cell<u32>) {
+        cache
+            .run(key, async {
+                runs.set(runs.get() + 1);
+                Ok(Success::empty())
+            })
+            .await
+            .unwrap();
+    }
+
+    #[rocket::async_test]
+    async fn requests_run_once_per_key() {
+        let cache = IdempotencyCache::new(IdempotencyConfig::default());
+        let runs = Cell::new(0);
+
+        run(&cache, key("a"), &runs).await;
+        run(&cache, key("a"), &runs).await;
+        assert_eq!(runs.get(), 1);
+
+        run(&cache, key("b"), &runs).await;
+        run(&cache, IdempotencyKey(None), &runs).await;
+        run(&cache, IdempotencyKey(None), &runs).await;
+        assert_eq!(runs.get(), 4);
+    }
+
+    #[rocket::async_test]
+    async fn results_expire_after_the_ttl() {
+        let cache = IdempotencyCache::new(IdempotencyConfig { ttl_ms: 50, ..IdempotencyConfig::default() });
+        let runs = Cell::new(0);
+
+        run(&cache, key("a"), &runs).await;
+        rocket::tokio::time::sleep(Duration::from_millis(60)).await;
+        run(&cache, key("a"), &runs).await;
+
+        assert_eq!(runs.get(), 2);
+    }
+
+    #[rocket::async_test]
+    async fn the_oldest_results_make_room_for_new_ones() {
+        let cache = IdempotencyCache::new(IdempotencyConfig { capacity: 2, ..IdempotencyConfig::default() });
+        let runs = Cell::new(0);
+
+        for name in ["a", "b", "c"] {
+            run(&cache, key(name), &runs).await;
+        }
+        run(&cache, key("c"), &runs).await;
+        assert_eq!(runs.get(), 3);
+
+        run(&cache, key("a"), &runs).await;
+        assert_eq!(runs.get(), 4);
+    }
+
+    #[rocket::async_test]
+    async fn failures_are_replayed_too() {
+        let cache = IdempotencyCache::new(IdempotencyConfig::default());
+        let runs = Cell::new(0);
+        let failing = || async {
+            runs.set(runs.get() + 1);
+            Err::<Success, Failure>(Conflict::new("not armed").into())
+        };
+
+        assert!(cache.run(key("a"), failing()).await.is_err());
+        assert!(cache.run(key("a"), failing()).await.is_err());
+        assert_eq!(runs.get(), 1);
+    }
+}
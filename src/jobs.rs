@@ -0,0 +1,208 @@
+//! Long-running operations run in the background, so the request starting
+//! them can return straight away. Their progress is polled at `/jobs/<id>`.
+
+use std::{
+    collections::BTreeMap,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use chrono::Utc;
+use rocket::{
+    State, get,
+    tokio::{self, sync::Mutex},
+};
+use serde::Serialize;
+use serde_json::json;
+
+use crate::{
+    response::{NotFound, Success},
+    rotator,
+};
+
+/// How far along a job is.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub enum JobState {
+    Running,
+    Succeeded,
+    Failed { error: String },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Job {
+    /// What the job is doing, e.g. `calibrate_vertical`.
+    pub kind: String,
+    #[serde(flatten)]
+    pub state: JobState,
+    /// The last step the job said it had reached, if it reports any.
+    pub progress: Option<String>,
+    /// When the job started, in RFC 3339 format.
+    pub started_at: String,
+    /// When the job finished, in RFC 3339 format.
+    pub finished_at: Option<String>,
+}
+
+/// Every recent job, keyed by id.
+#[derive(Debug, Default)]
+pub struct Jobs {
+    next_id: AtomicU64,
+    jobs: Mutex<BTreeMap<u64, Job>>,
+}
+
+impl Jobs {
+    /// How many finished jobs are kept. Running jobs are always kept.
+    const FINISHED_CAPACITY: usize = 100;
+
+    /// Run `job` in the background, returning its id. The job is given a
+    /// [`Progress`] to report how far it has got.
+    pub async fn spawn<F, Fut>(self: &Arc<Self>, kind: impl Into<String>, job: F) -> u64
+    where
+        F: FnOnce(Progress) -> Fut,
+        Fut: Future<Output = Result<(), rotator::Error>> + Send + 'static,
+    {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.jobs.lock().await.insert(id, Job {
+            kind: kind.into(),
+            state: JobState::Running,
+            progress: None,
+            started_at: Utc::now().to_rfc3339(),
+            finished_at: None,
+        });
+
+        let job = job(Progress { jobs: Arc::clone(self), id });
+        let jobs = Arc::clone(self);
+        tokio::spawn(async move {
+            let state = match job.await {
+                Ok(()) => JobState::Succeeded,
+                Err(e) => JobState::Failed { error: e.to_string() },
+            };
+            jobs.finish(id, state).await;
+        });
+
+        id
+    }
+
+    async fn finish(&self, id: u64, state: JobState) {
+        let mut jobs = self.jobs.lock().await;
+        if let Some(job) = jobs.get_mut(&id) {
+            job.state = state;
+            job.finished_at = Some(Utc::now().to_rfc3339());
+        }
+
+        // Ids only go up, so the first finished jobs are the oldest
+        let finished: Vec<_> = jobs
+            .iter()
+            .filter(|(_, job)| job.finished_at.is_some())
+            .map(|(&id, _)| id)
+            .collect();
+        for id in finished.iter().take(finished.len().saturating_sub(Self::FINISHED_CAPACITY)) {
+            jobs.remove(id);
+        }
+    }
+
+    pub async fn get(&self, id: u64) -> Option<Job> {
+        self.jobs.lock().await.get(&id).cloned()
+    }
+}
+
+/// Lets a running job say which step it has reached.
+#[derive(Debug, Clone)]
+pub struct Progress {
+    jobs: Arc<Jobs>,
+    id: u64,
+}
+
+impl Progress {
+    pub async fn report(&self, step: impl Into<String>) {
+        if let Some(job) = self.jobs.jobs.lock().await.get_mut(&self.id) {
+            job.progress = Some(step.into());
+        }
+    }
+}
+
+/// Gets the status of a background job, and its error if it failed.
+#[get("/jobs/<id>")]
+pub async fn job(jobs: &State<Arc<Jobs>>, id: u64) -> Result<Success, NotFound> {
+    let Some(job) = jobs.get(id).await else {
+        return Err(NotFound::new(format!("No job with id {id}")));
+    };
+
+    Ok(Success::data(json!(job)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use rocket::tokio::sync::oneshot;
+
+    use super::*;
+
+    /// Waits for a job to finish, returning it.
+    async fn finished(jobs: &Jobs, id: u64) -> Job {
+        for _ in 0..100 {
+            let job = jobs.get(id).await.unwrap();
+            if job.finished_at.is_some() {
+                return job;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        panic!("job {id} never finished");
+    }
+
+    #[rocket::async_test]
+    async fn jobs_run_until_they_succeed() {
+        let jobs = Arc::new(Jobs::default());
+        let (done, wait) = oneshot::channel::<()>();
+
+        let (reported, wait_reported) = oneshot::channel::<()>();
+
+        let id = jobs.spawn("test", |progress| async move {
+            progress.report("waiting").await;
+            let _ = reported.send(());
+            let _ = wait.await;
+            Ok(())
+        }).await;
+
+        wait_reported.await.unwrap();
+        let job = jobs.get(id).await.unwrap();
+        assert_eq!(job.kind, "test");
+        assert!(matches!(job.state, JobState::Running));
+        assert_eq!(job.progress.as_deref(), Some("waiting"));
+        assert!(job.finished_at.is_none());
+
+        done.send(()).unwrap();
+        assert!(matches!(finished(&jobs, id).await.state, JobState::Succeeded));
+    }
+
+    #[rocket::async_test]
+    async fn failed_jobs_keep_their_error() {
+        let jobs = Arc::new(Jobs::default());
+
+        let id = jobs.spawn("test", |_| async { Err(rotator::Error::Timeout) }).await;
+
+        let job = finished(&jobs, id).await;
+        assert!(matches!(&job.state, JobState::Failed { error } if *error == rotator::Error::Timeout.to_string()));
+        assert_eq!(serde_json::to_value(&job).unwrap()["status"], "failed");
+    }
+
+    #[rocket::async_test]
+    async fn only_the_latest_finished_jobs_are_kept() {
+        let jobs = Arc::new(Jobs::default());
+
+        let mut ids = Vec::new();
+        for _ in 0..=Jobs::FINISHED_CAPACITY {
+            let id = jobs.spawn("test", |_| async { Ok(()) }).await;
+            finished(&jobs, id).await;
+            ids.push(id);
+        }
+
+        assert!(jobs.get(ids[0]).await.is_none());
+        assert!(jobs.get(ids[1]).await.is_some());
+        assert!(jobs.get(*ids.last().unwrap()).await.is_some());
+    }
+}
@@ -7,16 +7,33 @@ use serialport::SerialPort;
 use num_traits::FromPrimitive;
 use tokio::fs::File as AsyncFile;
 use chrono::offset::Utc;
+use log::warn;
 
 use num_derive::{FromPrimitive, ToPrimitive};
 use rocket::figment::Source::File;
 use crate::{
-    control_loop::{ControlInfo, rfd_receive_loop, rotator_control_loop}, response::{Error, Success}, rotator::{Rotator, dummyport::DummyPort}
+    config::{Config, SharedConfig}, control_loop::{ActiveTracking, ControlInfo, rfd_receive_loop, rotator_control_loop}, response::{Error, Success}, rotator::{Rotator, dummyport::DummyPort, registry::{RotatorHandle, RotatorScope, Rotators, list_rotators}}, status::StartupProbe, idempotency::IdempotencyCache, rotator::presets::Presets, follow::{FollowTargets, LastReport}, orbit::Satellites, observer::Observer, jobs::Jobs
 };
 
+mod admin;
+mod aim;
+mod auth;
+mod backoff;
+mod config;
 mod response;
 mod rotator;
 mod control_loop;
+mod follow;
+#[cfg(feature = "grpc")]
+mod grpc;
+mod idempotency;
+mod jobs;
+mod observer;
+mod orbit;
+mod rpc;
+mod scheduler;
+mod status;
+mod supervisor;
 
 const ROTATOR_SERIAL_USB: (u16, u16) = (0x10C4, 0xEA60);
 const RFD_SERIAL_USB: (u16, u16) = (0x0403, 0x6001);
@@ -43,26 +60,45 @@ pub enum Commands {
 async fn main() {
     env_logger::init();
 
+    let config = Config::load().expect("Failed to load configuration");
+
     let rocket_config = rocket::Config {
         address: [0, 0, 0, 0].into(),
         ..Default::default()
     };
 
-    let rotator_serial = autofind_serial_port(ROTATOR_SERIAL_USB.0, ROTATOR_SERIAL_USB.1, 115_200)
-        .await
-        .unwrap_or_else(|_| Box::new(DummyPort::default()));
+    let retry_for = std::time::Duration::from_millis(config.startup.retry_for_ms);
+
+    let mut probe = StartupProbe::default();
+
+    let rotator_serial = backoff::retry_for("finding the rotator", retry_for, &config.startup.backoff, || {
+        autofind_serial_port(ROTATOR_SERIAL_USB.0, ROTATOR_SERIAL_USB.1, 115_200)
+    })
+    .await
+    .map(|port| {
+        probe.connected(port.name());
+        port
+    })
+    .unwrap_or_else(|e| {
+        warn!("Rotator not found, starting in degraded mode with a dummy port");
+        probe.error(format!("rotator not found: {e}"));
+        Box::new(DummyPort::default())
+    });
 
     dbg!(&rotator_serial);
 
-    let rotator = Arc::new(Mutex::new(Rotator::new(rotator_serial).unwrap()));
+    let rotator = Arc::new(Mutex::new(Rotator::with_config(rotator_serial, config.rotator.clone()).unwrap()));
 
-    let version = rotator.lock().await.version().await.unwrap_or_else(|_| "0.0.0".to_string());
-    let protocol_version = env!("PROTOCOL_VERSION");
-    if !(protocol_version == version) {
+    let version = probe.probe(&mut *rotator.lock().await, env!("PROTOCOL_VERSION")).await;
+    if !probe.compatible {
         println!("Protocol Version Mismatch please use a version of this program compatible with protocol Version {version}");
     }
 
-    let rotator_position = Arc::new(Mutex::new(None));
+    seed_limits(&mut *rotator.lock().await, rotator::registry::DEFAULT_ROTATOR).await;
+    startup_move(&rotator, rotator::registry::DEFAULT_ROTATOR).await;
+
+    let observer = Observer::load(&config);
+    let rotator_position = Arc::new(Mutex::new(observer.and_then(|o| o.point())));
     let rocket_position = Arc::new(Mutex::new(None));
 
     let last_packet = Arc::new(Mutex::new(None));
@@ -80,20 +116,121 @@ async fn main() {
     }
 
     // Spawn Rotator control loop
+    let active_tracking = Arc::new(ActiveTracking::default());
     {
-        let control_info = ControlInfo { rocket_position, rotator_position: Arc::clone(&rotator_position) };
+        let control_info = ControlInfo {
+            rocket_position,
+            rotator_position: Arc::clone(&rotator_position),
+            horizon_mask: config.tracking.horizon_mask.clone(),
+            axes: config.tracking.axes,
+            lead_time: std::time::Duration::from_millis(config.tracking.lead_time_ms),
+            flip: config.tracking.flip,
+            active: Arc::clone(&active_tracking),
+        };
         let loop_rotator = Arc::clone(&rotator);
 
         tokio::spawn(rotator_control_loop(loop_rotator, control_info));
     }
 
+    // Spawn the loop following pushed targets
+    let follow_targets = Arc::new(FollowTargets::default());
+    let follow_report = LastReport::default();
+    tokio::spawn(follow::follow_loop(
+        Arc::clone(&rotator),
+        Arc::clone(&follow_targets),
+        Arc::clone(&follow_report),
+        Arc::clone(&active_tracking),
+        config.tracking.axes,
+        config.tracking.horizon_mask.clone(),
+        config.tracking.flip,
+        config.tracking.follow,
+    ));
+
+    // Spawn a poller for each rotator
+    let jobs = Arc::new(Jobs::default());
+    let default_rotator = RotatorHandle::spawn(Arc::clone(&rotator), Arc::clone(&jobs));
+    if let Some(port) = config.grpc_port {
+        #[cfg(feature = "grpc")]
+        tokio::spawn(grpc::serve(default_rotator.clone(), port));
+        #[cfg(not(feature = "grpc"))]
+        warn!("`grpc_port` is set to {port}, but archerd was built without the `grpc` feature");
+    }
+    let mut rotators = Rotators::new(default_rotator);
+    for (id, entry) in &config.rotators {
+        let what = format!("opening {} for rotator `{id}`", entry.port);
+        let port = backoff::retry_for(&what, retry_for, &config.startup.backoff, || async {
+            serialport::new(&entry.port, Rotator::BAUD).open()
+        })
+        .await
+        .unwrap_or_else(|e| {
+            warn!("Failed to open {} for rotator `{id}`, using a dummy port: {e}", entry.port);
+            Box::new(DummyPort::default())
+        });
+
+        let handle = match Rotator::with_config(port, entry.config.clone()) {
+            Ok(mut r) => {
+                seed_limits(&mut r, id).await;
+                let r = Arc::new(Mutex::new(r));
+                startup_move(&r, id).await;
+
+                if config.supervisor.enabled {
+                    let port = entry.port.clone();
+                    tokio::spawn(supervisor::supervise(id.clone(), Arc::clone(&r), config.supervisor.clone(), move || {
+                        let port = port.clone();
+                        async move { serialport::new(port, Rotator::BAUD).open().map_err(|e| e.to_string()) }
+                    }));
+                }
+
+                RotatorHandle::spawn(r, Arc::clone(&jobs))
+            }
+            Err(e) => {
+                warn!("Failed to set up rotator `{id}`: {e}");
+                continue;
+            }
+        };
+
+        if !rotators.insert(id.clone(), handle) {
+            warn!("Rotator id `{id}` is reserved, ignoring it");
+        }
+    }
+
+    if config.supervisor.enabled {
+        tokio::spawn(supervisor::supervise(
+            rotator::registry::DEFAULT_ROTATOR.to_string(),
+            Arc::clone(&rotator),
+            config.supervisor.clone(),
+            || async {
+                autofind_serial_port(ROTATOR_SERIAL_USB.0, ROTATOR_SERIAL_USB.1, Rotator::BAUD)
+                    .await
+                    .map_err(|e| e.to_string())
+            },
+        ));
+    }
+
+    if let Some(hours) = config.rotator.exercise.interval_hours {
+        let interval = std::time::Duration::from_secs(hours * 60 * 60);
+        tokio::spawn(scheduler::exercise_loop(Arc::clone(&rotator), interval));
+    }
+
     let rocket = rocket::build()
         .manage(rotator)
         .manage(rotator_position)
+        .manage(rotators)
         .manage(rfd)
+        .manage(follow_targets)
+        .manage(follow_report)
+        .manage(active_tracking)
+        .manage(Satellites::default())
+        .manage(jobs)
         .manage(last_packet)
-        .mount("/", routes![index, get_serialports, get_rotator_port, set_rotator_port, set_rotator_position, get_rotator_position, send_rfd_command, get_last_packet])
+        .manage(IdempotencyCache::new(config.idempotency.clone()))
+        .manage(Presets::load(&config.presets_path))
+        .manage(SharedConfig::new(config))
+        .manage(probe)
+        .mount("/", routes![index, get_serialports, get_rotator_port, set_rotator_port, set_rotator_position, get_rotator_position, send_rfd_command, get_last_packet, rpc::rpc, list_rotators, status::startup, follow::set_target, follow::set_named_target, follow::get_target, follow::list_targets, follow::remove_target, follow::stop_following, follow::stop_tracking, follow::last_report, follow::tracking_mode, orbit::predict_pass, orbit::follow_satellite, orbit::stop_satellite, observer::get_observer, observer::set_observer, jobs::job, config::effective_config, aim::aim])
         .mount("/rotator", rotator::endpoints::endpoints())
+        .mount("/admin", admin::endpoints())
+        .attach(RotatorScope)
         .configure(rocket_config)
         .launch()
         .await;
@@ -137,15 +274,28 @@ async fn set_rotator_port(
         None => todo!(),
     }
     .open()
-    .map_err(|e| io::Error::other(e.to_string()))?;
+    .map_err(rotator::Error::from)?;
 
-    let rotator = Rotator::new(rotator_port)?;
-
-    *rotator_state.lock().await = rotator;
+    rotator_state.lock().await.reconnect(rotator_port)?;
 
     Ok(Success::empty())
 }
 
+/// Move to the configured startup position.
+async fn startup_move(rotator: &Mutex<Rotator>, id: &str) {
+    if let Err(e) = Rotator::startup_move(rotator).await {
+        warn!("Failed to move rotator `{id}` to its startup position: {e}");
+    }
+}
+
+/// Fill in any unset limits from the firmware, if it stores them.
+async fn seed_limits(rotator: &mut Rotator, id: &str) {
+    match rotator.seed_limits().await {
+        Ok(_) | Err(rotator::Error::Unsupported(_)) => {}
+        Err(e) => warn!("Failed to read the firmware limits of rotator `{id}`: {e}"),
+    }
+}
+
 async fn autofind_serial_port(vid: u16, pid: u16, baud: u32) -> Result<Box<dyn SerialPort>, Box<dyn std::error::Error>> {
     for port in serialport::available_ports()? {
         if let serialport::SerialPortType::UsbPort(u) = port.port_type && (u.vid == vid && u.pid == pid) {
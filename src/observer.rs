@@ -0,0 +1,211 @@
+//! Where the rotator is, which tracking needs to point it at anything.
+
+use std::{io, path::Path, sync::Arc};
+
+use aerospace_rocketry_lib::geospatial::Point;
+use log::warn;
+use rocket::{
+    State, get, put,
+    serde::json::Json,
+    tokio::{self, sync::Mutex},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{
+    auth::Admin,
+    config::{Config, SharedConfig},
+    response::{BadRequest, Error, Failure, Success},
+};
+
+/// The location of the rotator.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Observer {
+    /// Degrees north of the equator.
+    pub lat: f64,
+    /// Degrees east of the prime meridian.
+    pub lon: f64,
+    /// Metres above sea level.
+    pub alt_m: f64,
+}
+
+impl Observer {
+    /// Checks the coordinates are on the Earth, returning why not if they
+    /// aren't.
+    pub fn validate(&self) -> Result<(), String> {
+        if !(-90.0..=90.0).contains(&self.lat) {
+            return Err(format!("latitude {} must be between -90 and 90", self.lat));
+        }
+        if !(-180.0..=180.0).contains(&self.lon) {
+            return Err(format!("longitude {} must be between -180 and 180", self.lon));
+        }
+        if !self.alt_m.is_finite() {
+            return Err(format!("altitude {} must be finite", self.alt_m));
+        }
+
+        Ok(())
+    }
+
+    pub fn point(&self) -> Option<Point> {
+        Point::new_3d(self.lat, self.lon, self.alt_m).ok()
+    }
+
+    pub fn from_point(point: &Point) -> Self {
+        Self {
+            lat: point.latitude(),
+            lon: point.longitude(),
+            alt_m: point.altitude(),
+        }
+    }
+
+    /// The observer saved by `PUT /observer` if there is one, otherwise the
+    /// one in the configuration.
+    pub fn load(config: &Config) -> Option<Self> {
+        let saved = match std::fs::read_to_string(&config.observer_path) {
+            Ok(json) => serde_json::from_str::<Self>(&json)
+                .map_err(|e| e.to_string())
+                .and_then(|o| o.validate().map(|()| o))
+                .inspect_err(|e| warn!("Ignoring invalid observer in {}: {e}", config.observer_path))
+                .ok(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => {
+                warn!("Failed to read the observer from {}: {e}", config.observer_path);
+                None
+            }
+        };
+
+        saved.or(config.observer)
+    }
+
+    async fn save(&self, path: &Path) -> Result<(), io::Error> {
+        let json = serde_json::to_string_pretty(self)?;
+        let partial = path.with_extension("json.partial");
+        tokio::fs::write(&partial, json).await?;
+        tokio::fs::rename(&partial, path).await
+    }
+}
+
+/// Gets the location of the rotator, or `null` if it hasn't been set.
+#[get("/observer")]
+pub async fn get_observer(rotator_position: &State<Arc<Mutex<Option<Point>>>>) -> Success {
+    let observer = rotator_position.lock().await.as_ref().map(Observer::from_point);
+
+    Success::data(json!(observer))
+}
+
+/// Sets the location of the rotator, and saves it so it is kept across
+/// restarts. Requires [`Admin`] authentication, as it changes where
+/// everything is tracked from.
+#[put("/observer", data = "<observer>")]
+pub async fn set_observer(
+    _admin: Admin,
+    rotator_position: &State<Arc<Mutex<Option<Point>>>>,
+    config: &State<SharedConfig>,
+    observer: Json<Observer>,
+) -> Result<Success, Failure> {
+    let observer = observer.into_inner();
+    observer.validate().map_err(BadRequest::new)?;
+    let point = observer
+        .point()
+        .ok_or_else(|| BadRequest::new("invalid observer location"))?;
+
+    observer.save(Path::new(&config.get().observer_path)).await.map_err(Error::from)?;
+    *rotator_position.lock().await = Some(point);
+
+    Ok(Success::empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use rocket::{
+        http::{Header, Status},
+        local::asynchronous::Client,
+        routes,
+    };
+    use serde_json::Value;
+
+    use super::*;
+
+    const LINCOLN: Observer = Observer { lat: 40.8, lon: -96.7, alt_m: 358.0 };
+
+    fn observer_path(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("archerd-observer-{name}-{}.json", std::process::id()));
+
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn observers_must_be_on_the_earth() {
+        assert!(LINCOLN.validate().is_ok());
+
+        for observer in [
+            Observer { lat: 90.5, ..LINCOLN },
+            Observer { lat: f64::NAN, ..LINCOLN },
+            Observer { lon: -180.5, ..LINCOLN },
+            Observer { alt_m: f64::INFINITY, ..LINCOLN },
+        ] {
+            assert!(observer.validate().is_err(), "{observer:?}");
+        }
+    }
+
+    #[test]
+    fn a_saved_observer_replaces_the_configured_one() {
+        let config = Config { observer: Some(LINCOLN), observer_path: observer_path("load"), ..Config::default() };
+        assert_eq!(Observer::load(&config), Some(LINCOLN));
+
+        let saved = Observer { lat: -33.9, lon: 18.4, alt_m: 10.0 };
+        std::fs::write(&config.observer_path, serde_json::to_string(&saved).unwrap()).unwrap();
+        assert_eq!(Observer::load(&config), Some(saved));
+
+        // An invalid saved observer is ignored
+        let invalid = Observer { lat: 100.0, ..saved };
+        std::fs::write(&config.observer_path, serde_json::to_string(&invalid).unwrap()).unwrap();
+        assert_eq!(Observer::load(&config), Some(LINCOLN));
+
+        std::fs::remove_file(&config.observer_path).unwrap();
+    }
+
+    #[rocket::async_test]
+    async fn the_observer_is_validated_and_saved() {
+        let config = Config {
+            admin_token: Some("secret".to_string()),
+            observer_path: observer_path("put"),
+            ..Config::default()
+        };
+        let rocket = rocket::build()
+            .manage(Arc::new(Mutex::new(None::<Point>)))
+            .manage(SharedConfig::new(config.clone()))
+            .mount("/", routes![get_observer, set_observer]);
+        let client = Client::tracked(rocket).await.unwrap();
+        let put = |observer: Observer| {
+            client
+                .put("/observer")
+                .header(Header::new("Authorization", "Bearer secret"))
+                .body(serde_json::to_string(&observer).unwrap())
+                .dispatch()
+        };
+
+        let response = client.get("/observer").dispatch().await;
+        let body: Value = serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+        assert_eq!(body["data"], Value::Null);
+
+        let response = client.put("/observer").body(serde_json::to_string(&LINCOLN).unwrap()).dispatch().await;
+        assert_eq!(response.status(), Status::Unauthorized);
+
+        let response = put(Observer { lat: 91.0, ..LINCOLN }).await;
+        assert_eq!(response.status(), Status::BadRequest);
+        assert!(!Path::new(&config.observer_path).exists());
+
+        let response = put(LINCOLN).await;
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(Observer::load(&config), Some(LINCOLN));
+
+        let response = client.get("/observer").dispatch().await;
+        let body: Value = serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+        let observer: Observer = serde_json::from_value(body["data"].clone()).unwrap();
+        assert!((observer.lat - LINCOLN.lat).abs() < 1e-9 && (observer.lon - LINCOLN.lon).abs() < 1e-9);
+        assert!((observer.alt_m - LINCOLN.alt_m).abs() < 1e-6);
+
+        std::fs::remove_file(&config.observer_path).unwrap();
+    }
+}
@@ -0,0 +1,612 @@
+//! Satellites, propagated with SGP4 from their two-line elements, so that a
+//! pass can be previewed with `POST /track/predict` and followed like any
+//! other pushed target with `POST /track/satellites/<name>`.
+
+use std::{collections::HashMap, f64::consts::TAU, sync::Arc, time::Duration};
+
+use aerospace_rocketry_lib::geospatial::Point;
+use chrono::{DateTime, TimeDelta, Utc};
+use log::{info, warn};
+use rocket::{
+    State, delete, post,
+    serde::json::Json,
+    tokio::{self, sync::Mutex, task::JoinHandle},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{
+    config::SharedConfig,
+    follow::FollowTargets,
+    observer::Observer,
+    response::{BadRequest, Failure, Success},
+    rotator::Position,
+};
+
+/// WGS 84 equatorial radius, in kilometres.
+const EARTH_RADIUS_KM: f64 = 6378.137;
+/// WGS 84 flattening.
+const EARTH_FLATTENING: f64 = 1.0 / 298.257_223_563;
+/// The most samples a single prediction may return.
+const MAX_SAMPLES: u64 = 10_000;
+
+/// Two-line elements for a satellite, as published by e.g. CelesTrak.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Tle {
+    pub name: Option<String>,
+    pub line1: String,
+    pub line2: String,
+}
+
+/// A satellite which can be propagated to any time near its epoch.
+pub struct Satellite {
+    elements: sgp4::Elements,
+    constants: sgp4::Constants,
+}
+
+impl Satellite {
+    /// # Errors
+    /// Returns a message if the elements can't be parsed or propagated.
+    pub fn from_tle(tle: &Tle) -> Result<Self, String> {
+        let elements = sgp4::Elements::from_tle(tle.name.clone(), tle.line1.trim().as_bytes(), tle.line2.trim().as_bytes())
+            .map_err(|e| format!("Invalid TLE: {e}"))?;
+        let constants = sgp4::Constants::from_elements(&elements).map_err(|e| format!("Invalid TLE: {e}"))?;
+
+        Ok(Self { elements, constants })
+    }
+
+    /// Where the satellite is at `at`, in kilometres in the TEME frame.
+    fn position_teme(&self, at: DateTime<Utc>) -> Result<[f64; 3], String> {
+        let minutes = self
+            .elements
+            .datetime_to_minutes_since_epoch(&at.naive_utc())
+            .map_err(|e| format!("Can't propagate to {at}: {e}"))?;
+        let prediction = self
+            .constants
+            .propagate(minutes)
+            .map_err(|e| format!("Can't propagate to {at}: {e}"))?;
+
+        Ok(prediction.position)
+    }
+
+    /// The azimuth (degrees clockwise from north) and elevation of the
+    /// satellite as seen from `observer` at `at`.
+    ///
+    /// # Errors
+    /// Returns a message if the satellite can't be propagated that far, e.g.
+    /// because its orbit has decayed by then.
+    pub fn look_angles(&self, observer: &Observer, at: DateTime<Utc>) -> Result<(f64, f64), String> {
+        let teme = self.position_teme(at)?;
+
+        Ok(topocentric(observer, teme_to_ecef(teme, gmst(at))))
+    }
+}
+
+/// Greenwich mean sidereal time at `at`, in radians, by the IAU 1982 model
+/// that SGP4 is defined against. UT1 is taken to be UTC, which is well within
+/// a pointing tolerance.
+fn gmst(at: DateTime<Utc>) -> f64 {
+    let julian_date = at.timestamp_millis() as f64 / 86_400_000.0 + 2_440_587.5;
+    let t = (julian_date - 2_451_545.0) / 36_525.0;
+
+    let seconds = 67_310.548_41 + (876_600.0 * 3_600.0 + 8_640_184.812_866) * t + 0.093_104 * t * t - 6.2e-6 * t * t * t;
+
+    (seconds % 86_400.0 / 240.0).to_radians().rem_euclid(TAU)
+}
+
+/// Rotates a TEME position into the Earth-fixed frame, ignoring polar motion.
+fn teme_to_ecef([x, y, z]: [f64; 3], gmst: f64) -> [f64; 3] {
+    let (sin, cos) = gmst.sin_cos();
+
+    [cos * x + sin * y, -sin * x + cos * y, z]
+}
+
+/// Where `observer` is in the Earth-fixed frame, in kilometres.
+fn observer_ecef(observer: &Observer) -> [f64; 3] {
+    let (lat, lon) = (observer.lat.to_radians(), observer.lon.to_radians());
+    let altitude = observer.alt_m / 1000.0;
+    let e2 = EARTH_FLATTENING * (2.0 - EARTH_FLATTENING);
+    let n = EARTH_RADIUS_KM / (1.0 - e2 * lat.sin().powi(2)).sqrt();
+
+    [
+        (n + altitude) * lat.cos() * lon.cos(),
+        (n + altitude) * lat.cos() * lon.sin(),
+        (n * (1.0 - e2) + altitude) * lat.sin(),
+    ]
+}
+
+/// The azimuth and elevation, in degrees, of an Earth-fixed position as seen
+/// from `observer`.
+fn topocentric(observer: &Observer, target: [f64; 3]) -> (f64, f64) {
+    let site = observer_ecef(observer);
+    let [dx, dy, dz] = [target[0] - site[0], target[1] - site[1], target[2] - site[2]];
+    let (sin_lat, cos_lat) = observer.lat.to_radians().sin_cos();
+    let (sin_lon, cos_lon) = observer.lon.to_radians().sin_cos();
+
+    let east = -sin_lon * dx + cos_lon * dy;
+    let north = -sin_lat * cos_lon * dx - sin_lat * sin_lon * dy + cos_lat * dz;
+    let up = cos_lat * cos_lon * dx + cos_lat * sin_lon * dy + sin_lat * dz;
+
+    let azimuth = east.atan2(north).to_degrees().rem_euclid(360.0);
+    let elevation = up.atan2(east.hypot(north)).to_degrees();
+
+    (azimuth, elevation)
+}
+
+/// A satellite's direction from the observer at one time.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Sample {
+    /// In RFC 3339 format.
+    pub time: String,
+    pub azimuth: f64,
+    pub elevation: f64,
+    #[serde(skip)]
+    at: DateTime<Utc>,
+}
+
+/// A satellite crossing the horizon, with the time interpolated between
+/// samples.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Crossing {
+    /// In RFC 3339 format.
+    pub time: String,
+    pub azimuth: f64,
+}
+
+/// One pass of a satellite over the horizon within a prediction.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Pass {
+    /// `None` if the satellite was already up when the prediction started.
+    pub rise: Option<Crossing>,
+    /// `None` if the satellite was still up when the prediction ended.
+    pub set: Option<Crossing>,
+    /// The highest sample.
+    pub max: Sample,
+}
+
+/// Samples a satellite's direction every `step` from `start` for `duration`.
+///
+/// # Errors
+/// Returns a message if the satellite can't be propagated over the window.
+pub fn predict(
+    satellite: &Satellite,
+    observer: &Observer,
+    start: DateTime<Utc>,
+    duration: TimeDelta,
+    step: TimeDelta,
+) -> Result<Vec<Sample>, String> {
+    let mut samples = Vec::new();
+    let mut at = start;
+    while at <= start + duration {
+        let (azimuth, elevation) = satellite.look_angles(observer, at)?;
+        samples.push(Sample {
+            time: at.to_rfc3339(),
+            azimuth,
+            elevation,
+            at,
+        });
+        at += step;
+    }
+
+    Ok(samples)
+}
+
+/// Splits samples into passes above the horizon.
+pub fn passes(samples: &[Sample]) -> Vec<Pass> {
+    let mut passes = Vec::new();
+    let mut current: Option<Pass> = None;
+    let mut previous: Option<&Sample> = None;
+
+    for sample in samples {
+        let up = sample.elevation >= 0.0;
+        if let Some(pass) = &mut current {
+            if !up {
+                pass.set = previous.map(|previous| crossing(previous, sample));
+                passes.extend(current.take());
+            } else if sample.elevation > pass.max.elevation {
+                pass.max = sample.clone();
+            }
+        } else if up {
+            current = Some(Pass {
+                rise: previous.map(|previous| crossing(previous, sample)),
+                set: None,
+                max: sample.clone(),
+            });
+        }
+        previous = Some(sample);
+    }
+    passes.extend(current);
+
+    passes
+}
+
+/// Where between two samples on either side of the horizon it was crossed.
+fn crossing(before: &Sample, after: &Sample) -> Crossing {
+    let fraction = before.elevation / (before.elevation - after.elevation);
+    let span_ms = (after.at - before.at).num_milliseconds() as f64;
+    let at = before.at + TimeDelta::milliseconds((span_ms * fraction) as i64);
+
+    let turn = (after.azimuth - before.azimuth + 180.0).rem_euclid(360.0) - 180.0;
+    let azimuth = (before.azimuth + turn * fraction).rem_euclid(360.0);
+
+    Crossing {
+        time: at.to_rfc3339(),
+        azimuth,
+    }
+}
+
+/// The observer given in a request, or otherwise the one set with
+/// `PUT /observer`.
+async fn observer_or_default(
+    observer: Option<Observer>,
+    default: &Mutex<Option<Point>>,
+) -> Result<Observer, BadRequest> {
+    if let Some(observer) = observer {
+        observer.validate().map_err(BadRequest::new)?;
+        return Ok(observer);
+    }
+
+    default
+        .lock()
+        .await
+        .as_ref()
+        .map(Observer::from_point)
+        .ok_or_else(|| BadRequest::new("The observer's location isn't set, see `PUT /observer`"))
+}
+
+/// A satellite pass to predict.
+#[derive(Deserialize)]
+pub struct PredictRequest {
+    #[serde(flatten)]
+    pub tle: Tle,
+    /// Where to predict from. The observer set with `PUT /observer` if unset.
+    pub observer: Option<Observer>,
+    /// When to start, in RFC 3339 format. Now if unset.
+    pub start: Option<String>,
+    /// How long to predict for.
+    pub duration_s: u64,
+    /// How far apart the samples are.
+    #[serde(default = "PredictRequest::default_step_s")]
+    pub step_s: u64,
+}
+
+impl PredictRequest {
+    const fn default_step_s() -> u64 {
+        10
+    }
+}
+
+/// Predicts the azimuth and elevation of a satellite from its TLE over a
+/// window, without moving the rotator, along with each pass over the horizon
+/// in it: when it rises and sets, and its highest point.
+#[post("/track/predict", data = "<request>")]
+pub async fn predict_pass(
+    observer: &State<Arc<Mutex<Option<Point>>>>,
+    request: Json<PredictRequest>,
+) -> Result<Success, Failure> {
+    let request = request.into_inner();
+    let satellite = Satellite::from_tle(&request.tle).map_err(BadRequest::new)?;
+    let observer = observer_or_default(request.observer, observer).await?;
+
+    if request.step_s == 0 {
+        return Err(BadRequest::new("step_s must be at least 1").into());
+    }
+    if request.duration_s / request.step_s > MAX_SAMPLES {
+        return Err(BadRequest::new(format!("At most {MAX_SAMPLES} samples can be predicted at once")).into());
+    }
+    let start = match &request.start {
+        Some(start) => DateTime::parse_from_rfc3339(start)
+            .map_err(|e| BadRequest::new(format!("Invalid start `{start}`: {e}")))?
+            .to_utc(),
+        None => Utc::now(),
+    };
+
+    let duration = TimeDelta::seconds(request.duration_s.try_into().unwrap_or(i64::MAX));
+    let step = TimeDelta::seconds(request.step_s.try_into().unwrap_or(i64::MAX));
+    let samples = predict(&satellite, &observer, start, duration, step).map_err(BadRequest::new)?;
+
+    Ok(Success::data(json!({
+        "passes": passes(&samples),
+        "samples": samples,
+    })))
+}
+
+/// The task feeding each satellite being followed to the follow targets.
+#[derive(Default)]
+pub struct Satellites(Mutex<HashMap<String, JoinHandle<()>>>);
+
+/// A satellite to follow.
+#[derive(Deserialize)]
+pub struct FollowSatellite {
+    #[serde(flatten)]
+    pub tle: Tle,
+    /// Like a pushed target's, see `POST /track/targets/<name>`.
+    #[serde(default)]
+    pub priority: i32,
+}
+
+/// Pushes the satellite's direction as the follow target `name` every
+/// `interval` while it is above the horizon. Below it, nothing is pushed, so
+/// the target goes stale and following hands off or stops until it rises.
+async fn feed(
+    name: String,
+    satellite: Satellite,
+    priority: i32,
+    observer: Arc<Mutex<Option<Point>>>,
+    targets: Arc<FollowTargets>,
+    interval: Duration,
+) {
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+    loop {
+        ticker.tick().await;
+
+        let Some(point) = *observer.lock().await else {
+            continue;
+        };
+        let (azimuth, elevation) = match satellite.look_angles(&Observer::from_point(&point), Utc::now()) {
+            Ok(angles) => angles,
+            Err(e) => {
+                warn!("Stopped following satellite `{name}`: {e}");
+                return;
+            }
+        };
+
+        if elevation >= 0.0 {
+            let position = Position {
+                vertical: elevation as f32,
+                horizontal: azimuth as f32,
+            };
+            targets.set(&name, position, priority).await;
+        }
+    }
+}
+
+/// Follows a satellite from its TLE as the follow target `name`, replacing
+/// any satellite already followed under that name. It is only pushed while
+/// it is above the horizon, so passes of several satellites hand off by
+/// priority like any other pushed targets.
+#[post("/track/satellites/<name>", data = "<satellite>")]
+pub async fn follow_satellite(
+    satellites: &State<Satellites>,
+    targets: &State<Arc<FollowTargets>>,
+    observer: &State<Arc<Mutex<Option<Point>>>>,
+    config: &State<SharedConfig>,
+    name: &str,
+    satellite: Json<FollowSatellite>,
+) -> Result<Success, BadRequest> {
+    let request = satellite.into_inner();
+    let satellite = Satellite::from_tle(&request.tle).map_err(BadRequest::new)?;
+    if observer.lock().await.is_none() {
+        return Err(BadRequest::new("The observer's location isn't set, see `PUT /observer`"));
+    }
+
+    let interval = Duration::from_millis(config.get().tracking.follow.interval_ms);
+    let task = tokio::spawn(feed(
+        name.to_string(),
+        satellite,
+        request.priority,
+        Arc::clone(observer),
+        Arc::clone(targets),
+        interval,
+    ));
+
+    if let Some(previous) = satellites.0.lock().await.insert(name.to_string(), task) {
+        previous.abort();
+    }
+    info!("Following satellite `{name}`");
+
+    Ok(Success::empty())
+}
+
+/// Stops following a satellite, handing off to the next target if it was
+/// being followed.
+#[delete("/track/satellites/<name>")]
+pub async fn stop_satellite(
+    satellites: &State<Satellites>,
+    targets: &State<Arc<FollowTargets>>,
+    name: &str,
+) -> Result<Success, BadRequest> {
+    let Some(task) = satellites.0.lock().await.remove(name) else {
+        return Err(BadRequest::new(format!("No satellite named `{name}` is being followed")));
+    };
+    task.abort();
+    targets.remove(name).await;
+
+    Ok(Success::empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use rocket::{http::Status, local::asynchronous::Client, routes};
+    use serde_json::Value;
+
+    use super::*;
+
+    /// The first of the SGP4 verification cases in Vallado et al., "Revisiting
+    /// Spacetrack Report #3" (2006).
+    fn vanguard() -> Tle {
+        Tle {
+            name: None,
+            line1: "1 00005U 58002B   00179.78495062  .00000023  00000-0  28098-4 0  4753".to_string(),
+            line2: "2 00005  34.2682 348.7242 1859667 331.7664  19.3264 10.82419157413667".to_string(),
+        }
+    }
+
+    fn epoch(satellite: &Satellite) -> DateTime<Utc> {
+        satellite.elements.datetime.and_utc()
+    }
+
+    fn assert_near(got: [f64; 3], expected: [f64; 3], tolerance: f64) {
+        for (got, expected) in got.into_iter().zip(expected) {
+            assert!((got - expected).abs() <= tolerance, "{got} is not within {tolerance} of {expected}");
+        }
+    }
+
+    /// The point on the ground directly below the satellite at `at`, which is
+    /// only exact on the equator, where geocentric and geodetic latitude agree.
+    fn below(satellite: &Satellite, at: DateTime<Utc>) -> Observer {
+        let [x, y, z] = teme_to_ecef(satellite.position_teme(at).unwrap(), gmst(at));
+
+        Observer {
+            lat: z.atan2(x.hypot(y)).to_degrees(),
+            lon: y.atan2(x).to_degrees(),
+            alt_m: 0.0,
+        }
+    }
+
+    fn sample(seconds: i64, azimuth: f64, elevation: f64) -> Sample {
+        let at = DateTime::UNIX_EPOCH + TimeDelta::seconds(seconds);
+
+        Sample { time: at.to_rfc3339(), azimuth, elevation, at }
+    }
+
+    #[test]
+    fn propagation_matches_the_reference_vectors() {
+        let satellite = Satellite::from_tle(&vanguard()).unwrap();
+        let epoch = epoch(&satellite);
+
+        assert_near(satellite.position_teme(epoch).unwrap(), [7022.465_292_66, -1400.082_967_55, 0.039_951_55], 1e-3);
+        assert_near(
+            satellite.position_teme(epoch + TimeDelta::minutes(360)).unwrap(),
+            [-7154.031_202_02, -3783.176_825_04, -3536.194_122_94],
+            1e-3,
+        );
+    }
+
+    #[test]
+    fn sidereal_time_at_j2000() {
+        let j2000 = DateTime::parse_from_rfc3339("2000-01-01T12:00:00Z").unwrap().to_utc();
+
+        assert!((gmst(j2000).to_degrees() - 280.460_618_37).abs() < 1e-4);
+    }
+
+    #[test]
+    fn look_angles_from_the_ground() {
+        let observer = Observer { lat: 0.0, lon: 0.0, alt_m: 0.0 };
+
+        let (_, elevation) = topocentric(&observer, [EARTH_RADIUS_KM + 500.0, 0.0, 0.0]);
+        assert!((elevation - 90.0).abs() < 1e-9);
+
+        let (azimuth, elevation) = topocentric(&observer, [EARTH_RADIUS_KM, 0.0, 1000.0]);
+        assert!(azimuth.abs() < 1e-9 && elevation.abs() < 1e-9);
+
+        let (azimuth, elevation) = topocentric(&observer, [EARTH_RADIUS_KM, 1000.0, 0.0]);
+        assert!((azimuth - 90.0).abs() < 1e-9 && elevation.abs() < 1e-9);
+
+        let (azimuth, elevation) = topocentric(&observer, [EARTH_RADIUS_KM, -1000.0, -1000.0]);
+        assert!((azimuth - 225.0).abs() < 1e-9 && elevation.abs() < 1e-9);
+    }
+
+    #[test]
+    fn satellite_is_overhead_from_directly_below() {
+        let satellite = Satellite::from_tle(&vanguard()).unwrap();
+        let epoch = epoch(&satellite);
+
+        let (_, elevation) = satellite.look_angles(&below(&satellite, epoch), epoch).unwrap();
+
+        assert!((elevation - 90.0).abs() < 0.01, "{elevation}");
+    }
+
+    #[test]
+    fn invalid_elements_are_refused() {
+        let mut tle = vanguard();
+        tle.line2.replace_range(8..16, "garbage!");
+
+        assert!(Satellite::from_tle(&tle).is_err());
+    }
+
+    #[test]
+    fn passes_interpolate_rise_and_set() {
+        let samples = [
+            sample(0, 350.0, -10.0),
+            sample(10, 10.0, 10.0),
+            sample(20, 90.0, 30.0),
+            sample(30, 170.0, 10.0),
+            sample(40, 190.0, -10.0),
+        ];
+
+        let passes = passes(&samples);
+
+        assert_eq!(passes.len(), 1);
+        let rise = passes[0].rise.as_ref().unwrap();
+        assert_eq!(rise.time, sample(5, 0.0, 0.0).time);
+        // Halfway round through north, not back the long way
+        assert!(rise.azimuth.abs() < 1e-9);
+        let set = passes[0].set.as_ref().unwrap();
+        assert_eq!(set.time, sample(35, 0.0, 0.0).time);
+        assert!((set.azimuth - 180.0).abs() < 1e-9);
+        assert_eq!(passes[0].max, samples[2]);
+    }
+
+    #[test]
+    fn passes_cut_off_by_the_window_have_no_rise_or_set() {
+        let samples = [sample(0, 0.0, 5.0), sample(10, 0.0, -5.0), sample(20, 0.0, -1.0), sample(30, 0.0, 1.0)];
+
+        let passes = passes(&samples);
+
+        assert_eq!(passes.len(), 2);
+        assert!(passes[0].rise.is_none() && passes[0].set.is_some());
+        assert!(passes[1].rise.is_some() && passes[1].set.is_none());
+    }
+
+    async fn client() -> Client {
+        let rocket = rocket::build()
+            .manage(Arc::new(Mutex::new(None::<Point>)))
+            .mount("/", routes![predict_pass]);
+
+        Client::tracked(rocket).await.unwrap()
+    }
+
+    #[rocket::async_test]
+    async fn predict_samples_the_window() {
+        let satellite = Satellite::from_tle(&vanguard()).unwrap();
+        let epoch = epoch(&satellite);
+        let observer = below(&satellite, epoch);
+        let request = json!({
+            "line1": vanguard().line1,
+            "line2": vanguard().line2,
+            "observer": {"lat": observer.lat, "lon": observer.lon, "alt_m": 0.0},
+            "start": (epoch - TimeDelta::minutes(5)).to_rfc3339(),
+            "duration_s": 600,
+            "step_s": 60,
+        });
+
+        let response = client().await.post("/track/predict").body(request.to_string()).dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        let body: Value = serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+
+        let samples = body["data"]["samples"].as_array().unwrap();
+        assert_eq!(samples.len(), 11);
+        assert_eq!(samples[5]["time"], json!(epoch.to_rfc3339()));
+
+        // Up for the whole window, highest when overhead
+        let passes = body["data"]["passes"].as_array().unwrap();
+        assert_eq!(passes.len(), 1);
+        assert_eq!(passes[0]["rise"], Value::Null);
+        assert_eq!(passes[0]["set"], Value::Null);
+        assert_eq!(passes[0]["max"], samples[5]);
+        assert!(passes[0]["max"]["elevation"].as_f64().unwrap() > 89.99);
+    }
+
+    #[rocket::async_test]
+    async fn predict_refuses_bad_requests() {
+        let client = client().await;
+        let observer = json!({"lat": 40.8, "lon": -96.7, "alt_m": 350.0});
+
+        let requests = [
+            // No observer given or set
+            json!({"line1": vanguard().line1, "line2": vanguard().line2, "duration_s": 60}),
+            json!({"line1": "1 garbage", "line2": vanguard().line2, "observer": observer, "duration_s": 60}),
+            json!({"line1": vanguard().line1, "line2": vanguard().line2, "observer": observer, "duration_s": 60, "step_s": 0}),
+            json!({"line1": vanguard().line1, "line2": vanguard().line2, "observer": observer, "duration_s": 1_000_000, "step_s": 1}),
+            json!({"line1": vanguard().line1, "line2": vanguard().line2, "observer": observer, "duration_s": 60, "start": "soon"}),
+        ];
+
+        for request in requests {
+            let response = client.post("/track/predict").body(request.to_string()).dispatch().await;
+            assert_eq!(response.status(), Status::BadRequest, "{request}");
+        }
+    }
+}
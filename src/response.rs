@@ -4,20 +4,59 @@ use rocket::Responder;
 use serde::Serialize;
 use serde_json::Value;
 
+use crate::rotator;
+
 #[derive(Serialize)]
 pub struct InnerResponse {
     message: String,
     data: Option<Value>,
 }
 
-#[derive(Responder)]
+#[derive(Responder, Clone)]
 #[response(status = 200, content_type = "json")]
 pub struct Success(pub String);
 
-#[derive(Responder, Debug)]
+#[derive(Responder, Debug, Clone)]
 #[response(status = 500, content_type = "json")]
 pub struct Error(pub String);
 
+/// A request which was accepted, but is still being carried out.
+#[derive(Responder, Debug, Clone)]
+#[response(status = 202, content_type = "json")]
+pub struct Accepted(pub String);
+
+/// A request refused before anything was attempted, because of a problem
+/// with the request itself.
+#[derive(Responder, Debug, Clone)]
+#[response(status = 400, content_type = "json")]
+pub struct BadRequest(pub String);
+
+/// A request refused because of the state the rotator is in, such as moving
+/// it while it is disarmed, which may succeed once that changes.
+#[derive(Responder, Debug, Clone)]
+#[response(status = 409, content_type = "json")]
+pub struct Conflict(pub String);
+
+/// A request for something which doesn't exist, such as an unknown preset.
+#[derive(Responder, Debug, Clone)]
+#[response(status = 404, content_type = "json")]
+pub struct NotFound(pub String);
+
+/// A request the rotator's firmware has no command for.
+#[derive(Responder, Debug, Clone)]
+#[response(status = 501, content_type = "json")]
+pub struct NotImplemented(pub String);
+
+/// Any way a request can fail.
+#[derive(Responder, Debug, Clone)]
+pub enum Failure {
+    BadRequest(BadRequest),
+    Conflict(Conflict),
+    NotFound(NotFound),
+    NotImplemented(NotImplemented),
+    Error(Error),
+}
+
 impl Success {
     pub fn empty() -> Self {
         Self(
@@ -40,6 +79,131 @@ impl Success {
     }
 }
 
+impl Accepted {
+    pub fn data(data: Value) -> Self {
+        Self(
+            serde_json::ser::to_string(&InnerResponse {
+                message: "accepted".to_string(),
+                data: Some(data),
+            })
+            .unwrap(),
+        )
+    }
+}
+
+impl BadRequest {
+    pub fn new(message: impl ToString) -> Self {
+        Self(
+            serde_json::ser::to_string(&InnerResponse {
+                message: message.to_string(),
+                data: None,
+            })
+            .unwrap(),
+        )
+    }
+}
+
+impl Conflict {
+    pub fn new(message: impl ToString) -> Self {
+        Self(
+            serde_json::ser::to_string(&InnerResponse {
+                message: message.to_string(),
+                data: None,
+            })
+            .unwrap(),
+        )
+    }
+}
+
+impl NotFound {
+    pub fn new(message: impl ToString) -> Self {
+        Self(
+            serde_json::ser::to_string(&InnerResponse {
+                message: message.to_string(),
+                data: None,
+            })
+            .unwrap(),
+        )
+    }
+}
+
+impl NotImplemented {
+    pub fn new(message: impl ToString) -> Self {
+        Self(
+            serde_json::ser::to_string(&InnerResponse {
+                message: message.to_string(),
+                data: None,
+            })
+            .unwrap(),
+        )
+    }
+}
+
+impl From<BadRequest> for Failure {
+    fn from(value: BadRequest) -> Self {
+        Self::BadRequest(value)
+    }
+}
+
+impl From<Conflict> for Failure {
+    fn from(value: Conflict) -> Self {
+        Self::Conflict(value)
+    }
+}
+
+impl From<NotFound> for Failure {
+    fn from(value: NotFound) -> Self {
+        Self::NotFound(value)
+    }
+}
+
+impl From<Error> for Failure {
+    fn from(value: Error) -> Self {
+        Self::Error(value)
+    }
+}
+
+/// Rotator errors which weren't the server's fault are reported as such.
+impl From<rotator::Error> for Failure {
+    fn from(value: rotator::Error) -> Self {
+        match Fault::of(&value) {
+            Fault::Request => Self::BadRequest(BadRequest::new(value)),
+            Fault::State => Self::Conflict(Conflict::new(value)),
+            Fault::Unsupported => Self::NotImplemented(NotImplemented::new(value)),
+            Fault::Server => Self::Error(value.into()),
+        }
+    }
+}
+
+/// Why the rotator refused a request, which decides how it is reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Fault {
+    /// Because of what it asked for, such as moving outside the limits.
+    Request,
+    /// Because of the state the rotator is in.
+    State,
+    /// Because the firmware doesn't have the command needed.
+    Unsupported,
+    Server,
+}
+
+impl Fault {
+    /// A move on both axes is only put down to the request or the state if
+    /// neither axis was sent, for the same reason.
+    fn of(error: &rotator::Error) -> Self {
+        match error {
+            rotator::Error::OutOfRange { .. } => Self::Request,
+            rotator::Error::NotArmed | rotator::Error::HaltCooldown(_) | rotator::Error::Interrupted => Self::State,
+            rotator::Error::Unsupported(_) => Self::Unsupported,
+            rotator::Error::PartialMove {
+                vertical: Some(vertical),
+                horizontal: Some(horizontal),
+            } if Self::of(vertical) == Self::of(horizontal) => Self::of(vertical),
+            _ => Self::Server,
+        }
+    }
+}
+
 impl From<io::Error> for Error {
     fn from(value: io::Error) -> Self {
         Self(
@@ -51,3 +215,25 @@ impl From<io::Error> for Error {
         )
     }
 }
+
+impl From<rotator::Error> for Error {
+    fn from(value: rotator::Error) -> Self {
+        // Say which axes failed, so a client can retry just those, or what
+        // kind of problem the serial port had
+        let data = match &value {
+            rotator::Error::PartialMove { vertical, horizontal } => Some(serde_json::json!({
+                "vertical": vertical.as_ref().map(ToString::to_string),
+                "horizontal": horizontal.as_ref().map(ToString::to_string),
+            })),
+            e => e.serial_fault().map(|fault| serde_json::json!({ "serial_fault": fault })),
+        };
+
+        Self(
+            serde_json::ser::to_string(&InnerResponse {
+                message: value.to_string(),
+                data,
+            })
+            .unwrap(),
+        )
+    }
+}
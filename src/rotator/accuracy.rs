@@ -0,0 +1,210 @@
+//! Checking how accurately the mount points, by sending it to a set of known
+//! positions and reading back where it ends up at each.
+
+use std::time::Duration;
+
+use rocket::tokio::sync::Mutex;
+use serde::{Deserialize, Serialize};
+
+use super::{Axis, Error, Position, Rotator, frame::AzimuthConvention};
+
+/// Settings for [`Rotator::accuracy_check`].
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AccuracyConfig {
+    /// The positions to visit, in order. Any outside the limits are skipped.
+    pub points: Vec<Position>,
+    /// How long to wait for each position to be reached.
+    pub timeout_ms: u64,
+}
+
+impl Default for AccuracyConfig {
+    fn default() -> Self {
+        let points = [(10.0, 0.0), (45.0, 90.0), (80.0, 180.0), (45.0, 270.0)]
+            .into_iter()
+            .map(|(vertical, horizontal)| Position { vertical, horizontal })
+            .collect();
+
+        Self {
+            points,
+            timeout_ms: 30_000,
+        }
+    }
+}
+
+/// Where the rotator ended up when sent to one of the positions.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct PointResult {
+    pub target: Position,
+    pub achieved: Position,
+    /// The further of the two axes from the target, in degrees.
+    pub error: f32,
+    /// Whether the rotator settled within the position tolerance of the
+    /// target before `accuracy.timeout_ms`. If not, `achieved` is where it
+    /// was when the time ran out.
+    pub settled: bool,
+}
+
+impl PointResult {
+    pub fn new(target: Position, achieved: Position, settled: bool) -> Self {
+        let error = (target.vertical - achieved.vertical)
+            .abs()
+            .max(AzimuthConvention::Signed.wrap(target.horizontal - achieved.horizontal).abs());
+
+        Self { target, achieved, error, settled }
+    }
+}
+
+/// The results of [`Rotator::accuracy_check`].
+#[derive(Debug, Clone, Serialize)]
+pub struct AccuracyReport {
+    pub points: Vec<PointResult>,
+    /// Positions not visited because they are outside the limits.
+    pub skipped: Vec<Position>,
+    /// `None` if no positions were visited.
+    pub mean_error: Option<f32>,
+    pub max_error: Option<f32>,
+}
+
+impl AccuracyReport {
+    pub fn new(points: Vec<PointResult>, skipped: Vec<Position>) -> Self {
+        let max_error = points.iter().map(|p| p.error).reduce(f32::max);
+        let mean_error = max_error.map(|_| points.iter().map(|p| p.error).sum::<f32>() / points.len() as f32);
+
+        Self { points, skipped, mean_error, max_error }
+    }
+}
+
+impl Rotator {
+    /// Visits each of the configured `accuracy.points` within the limits,
+    /// waiting for each to be reached and reading back where the rotator
+    /// ended up, and then returns to where it started.
+    ///
+    /// A position which isn't reached within the tolerance in time is still
+    /// recorded, as where the rotator had got to, and the check carries on.
+    ///
+    /// The rotator is only locked for each move and reading, so it can be
+    /// halted partway. A return to the starting position is attempted if a
+    /// move fails, unless it was halted or stopped.
+    pub async fn accuracy_check(rotator: &Mutex<Self>) -> Result<AccuracyReport, Error> {
+        let (start, stops, targets, skipped, timeout) = {
+            let mut rotator = rotator.lock().await;
+            let (vertical, horizontal) = rotator.position().await?;
+            let start = Position { vertical, horizontal };
+            let timeout = Duration::from_millis(rotator.config.accuracy.timeout_ms);
+
+            let (targets, skipped): (Vec<_>, Vec<_>) = rotator.config.accuracy.points.iter().copied().partition(|point| {
+                rotator.check_position(Axis::Vertical, point.vertical).is_ok()
+                    && rotator.check_position(Axis::Horizontal, point.horizontal).is_ok()
+            });
+
+            (start, rotator.stops(), targets, skipped, timeout)
+        };
+
+        let mut results = Vec::new();
+        let mut result = Ok(());
+        for target in targets {
+            result = Self::visit(rotator, stops, target, timeout).await.map(|point| results.push(point));
+            if result.is_err() {
+                break;
+            }
+        }
+        let restored = Self::goto_step(rotator, stops, start, timeout).await;
+
+        result.and(restored)?;
+
+        Ok(AccuracyReport::new(results, skipped))
+    }
+
+    /// Moves to `target` and waits for it to be reached, returning where the
+    /// rotator settled, or where it was if it didn't settle in time.
+    async fn visit(rotator: &Mutex<Self>, stops: u64, target: Position, timeout: Duration) -> Result<PointResult, Error> {
+        // Missing the target is what this checks for, so it isn't an error
+        let settled = match Self::goto_step(rotator, stops, target, timeout).await {
+            Ok(()) => true,
+            Err(Error::Timeout) => false,
+            Err(e) => return Err(e),
+        };
+        let (vertical, horizontal) = rotator.lock().await.position().await?;
+
+        Ok(PointResult::new(target, Position { vertical, horizontal }, settled))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::{config::{Limits, PerAxis, RotatorConfig}, mock::{self, MockFirmware}}, *};
+
+    fn position(vertical: f32, horizontal: f32) -> Position {
+        Position { vertical, horizontal }
+    }
+
+    #[test]
+    fn the_error_is_the_further_axis_from_the_target() {
+        assert_eq!(PointResult::new(position(10.0, 20.0), position(11.0, 17.5), true).error, 2.5);
+        assert_eq!(PointResult::new(position(10.0, 20.0), position(6.0, 20.5), true).error, 4.0);
+        // Across north, not the long way around
+        assert_eq!(PointResult::new(position(10.0, 359.0), position(10.0, 1.0), true).error, 2.0);
+    }
+
+    #[test]
+    fn the_report_gives_the_mean_and_max_error() {
+        let points = vec![
+            PointResult::new(position(10.0, 0.0), position(11.0, 0.0), true),
+            PointResult::new(position(20.0, 0.0), position(20.0, 3.0), true),
+            PointResult::new(position(30.0, 0.0), position(32.0, 0.0), false),
+        ];
+
+        let report = AccuracyReport::new(points, Vec::new());
+
+        assert_eq!(report.mean_error, Some(2.0));
+        assert_eq!(report.max_error, Some(3.0));
+
+        let report = AccuracyReport::new(Vec::new(), vec![position(95.0, 0.0)]);
+        assert_eq!(report.mean_error, None);
+        assert_eq!(report.max_error, None);
+    }
+
+    #[rocket::async_test]
+    async fn points_outside_the_limits_are_skipped_and_it_returns_to_the_start() {
+        let firmware = MockFirmware::new();
+        firmware.lock().position = PerAxis { vertical: 5.0, horizontal: -10.0 };
+        let config = RotatorConfig {
+            limits: PerAxis { vertical: Some(Limits { min: 0.0, max: 60.0 }), horizontal: None },
+            accuracy: AccuracyConfig {
+                points: vec![position(10.0, 20.0), position(80.0, 90.0), position(45.0, 90.0)],
+                timeout_ms: 1000,
+            },
+            ..mock::config()
+        };
+        let rotator = firmware.shared(config);
+
+        let report = Rotator::accuracy_check(&rotator).await.unwrap();
+
+        let targets: Vec<_> = report.points.iter().map(|point| point.target).collect();
+        assert_eq!(targets, [position(10.0, 20.0), position(45.0, 90.0)]);
+        assert!(report.points.iter().all(|point| point.settled && point.error == 0.0));
+        assert_eq!(report.skipped, [position(80.0, 90.0)]);
+        assert_eq!(report.max_error, Some(0.0));
+        assert_eq!(firmware.lock().position, PerAxis { vertical: 5.0, horizontal: -10.0 });
+    }
+
+    #[rocket::async_test]
+    async fn points_never_reached_are_reported_where_the_rotator_got_to() {
+        let firmware = MockFirmware::new();
+        firmware.lock().stalled.horizontal = true;
+        let config = RotatorConfig {
+            accuracy: AccuracyConfig { points: vec![position(10.0, 20.0), position(30.0, 40.0)], timeout_ms: 200 },
+            ..mock::config()
+        };
+        let rotator = firmware.shared(config);
+
+        let report = Rotator::accuracy_check(&rotator).await.unwrap();
+
+        let achieved: Vec<_> = report.points.iter().map(|point| (point.achieved.vertical, point.settled)).collect();
+        assert_eq!(achieved, [(10.0, false), (30.0, false)]);
+        assert_eq!(report.mean_error, Some(30.0));
+        assert_eq!(report.max_error, Some(40.0));
+        assert_eq!(firmware.lock().position.vertical, 0.0);
+    }
+}
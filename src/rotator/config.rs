@@ -0,0 +1,433 @@
+//! Configuration for a [`Rotator`](super::Rotator) connection.
+
+use serde::{Deserialize, Serialize};
+use serialport::{DataBits, FlowControl, Parity, StopBits};
+
+use super::{
+    Axis, accuracy::AccuracyConfig, exchange_log::ExchangeLogConfig, frame::Frame, shape::ResponseShape, startup::StartupPosition,
+    interlock::InterlockConfig, test_pattern::TestPatternConfig, value::ValueFormat,
+};
+
+/// Settings applied to the rotator's serial port when it is opened. The
+/// defaults are 8N1 with no flow control, which is what the controller
+/// firmware expects over its native USB port.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RotatorConfig {
+    pub data_bits: DataBits,
+    pub flow_control: FlowControl,
+    pub parity: Parity,
+    pub stop_bits: StopBits,
+    /// Terminator written after each command and used to split responses
+    /// into lines. Some serial bridges need `"\r\n"`.
+    pub line_terminator: String,
+    /// Address written before every command, e.g. `"@1"`, for rotators on a
+    /// shared bus. Response lines not starting with it are ignored.
+    pub address: Option<String>,
+    /// Whether the firmware echoes each command back before its response.
+    /// Detected when the rotator is connected if unset.
+    pub echo_enabled: Option<bool>,
+    /// Which lines make up a response. Probed when the rotator is connected
+    /// if unset, and takes precedence over `echo_enabled` if set.
+    pub response_shape: Option<ResponseShape>,
+    /// How the firmware writes counts, such as steps, in its responses.
+    pub value_format: ValueFormat,
+    /// How the firmware's `ERR` response to a command it doesn't implement
+    /// starts, ignoring case. Only this reply marks an optional feature as
+    /// unsupported; any other `ERR` is passed on as it is.
+    pub unknown_command_reply: String,
+    /// How long to wait for the rotator to respond to a command.
+    pub command_timeout_ms: u64,
+    /// How long to wait after sending a command before reading the response,
+    /// for firmware which is slow to start replying.
+    pub response_delay_ms: u64,
+    /// How many bytes are read from the port at a time. Responses of any
+    /// length are read in full; a larger buffer needs fewer reads for long
+    /// responses, at the cost of memory for every read.
+    pub read_buffer_size: usize,
+    /// The longest timeout a single request may ask for with `timeout_ms`.
+    pub max_command_timeout_ms: u64,
+    /// The furthest `POST /position` may move either axis from where it is,
+    /// in degrees, unless the request gives `allow_large=true`. Unlimited if
+    /// unset.
+    pub max_move_degrees_per_request: Option<f32>,
+    /// The most steps a single step move may request on each axis, in either
+    /// direction. Unlimited if unset.
+    pub max_steps: PerAxis<Option<u32>>,
+    /// How many decimal places positions are sent to the rotator with. Some
+    /// firmware only accepts whole degrees, which needs `0`.
+    pub position_decimals: usize,
+    /// How many steps [`Rotator::nudge`](super::Rotator::nudge) moves.
+    pub nudge_steps: u32,
+    /// How close, in degrees, each axis must be to a target to have reached it.
+    pub position_tolerance: f32,
+    /// How long the position must stay within tolerance of a target before
+    /// it is considered settled.
+    pub settle_time_ms: u64,
+    /// How often the background poller refreshes the cached telemetry.
+    pub poll_interval_ms: u64,
+    /// Send a harmless command whenever nothing has been sent for this long,
+    /// for USB serial adapters which fall asleep on an idle link. Off if
+    /// unset.
+    pub keepalive_ms: Option<u64>,
+    /// How far, in degrees, the position must move for the poller to publish
+    /// another position change event.
+    pub position_event_threshold: f32,
+    /// How many polls in a row an axis may stay put while it should be moving
+    /// before it is considered stalled and halted. `0` disables the check.
+    pub stall_samples: u32,
+    /// How many recent command exchanges to keep in the history.
+    pub history_size: usize,
+    /// Send `HALT` as soon as the rotator is connected. After a crash or
+    /// restart the motors may still be running a move from the previous
+    /// session, which nothing is watching any more.
+    pub halt_on_connect: bool,
+    /// Drain anything still arriving from the rotator before every command,
+    /// see [`Rotator::flush`](super::Rotator::flush), rather than only
+    /// discarding what has already been received. This costs a few
+    /// milliseconds per command, but keeps a noisy link from falling out of
+    /// step.
+    pub flush_before_command: bool,
+    /// Power an axis's motor back on when it is sent somewhere after being
+    /// disabled, rather than refusing to move it.
+    pub auto_enable_motors: bool,
+    /// The slew speed, in degrees per second, for moves which wait to arrive
+    /// and don't give their own, such as presets and parking. The previous
+    /// speed is put back after each such move, and left alone if unset.
+    pub default_slew_rate: Option<f32>,
+    /// The first firmware version which accepts `CALV SET`. Against older
+    /// firmware, [`Rotator::calibrate_vertical`](super::Rotator::calibrate_vertical)
+    /// refuses to set the reference rather than sending it. Always sent if
+    /// unset, or if the firmware's version can't be compared.
+    pub calibrate_set_min_version: Option<String>,
+    /// How long after a halt to refuse to move, unless resumed with
+    /// [`Rotator::resume`](super::Rotator::resume). Moves are accepted
+    /// straight away if unset.
+    pub halt_cooldown_ms: Option<u64>,
+    /// Requiring the rotator to be armed before it moves.
+    pub interlock: InterlockConfig,
+    /// Enable debugging endpoints such as `/rotator/debug/last`.
+    pub debug: bool,
+    /// How the rotator's axes relate to azimuth and elevation.
+    pub frame: Frame,
+    /// Soft limits on the position of each axis, in degrees. Unlimited if unset.
+    pub limits: PerAxis<Option<Limits>>,
+    /// Log each position clamped to the limits.
+    pub log_clamped: bool,
+    /// Log each command refused for being out of range.
+    pub log_rejected: bool,
+    pub park: ParkConfig,
+    /// Where to send the rotator once the server has connected to it.
+    pub startup_position: StartupPosition,
+    /// Where the last position is saved, for `startup_position = "last"`.
+    pub last_position_path: String,
+    pub home: HomeConfig,
+    pub fine: FineConfig,
+    pub drift: DriftConfig,
+    pub exercise: ExerciseConfig,
+    pub test_pattern: TestPatternConfig,
+    pub accuracy: AccuracyConfig,
+    /// Logging every exchange to a file.
+    pub exchange_log: ExchangeLogConfig,
+}
+
+impl RotatorConfig {
+    /// The settings which differ in `new` but are only read when the rotator
+    /// is connected or its poller started, so need a restart to change.
+    pub fn restart_required(&self, new: &Self) -> Vec<&'static str> {
+        [
+            ("data_bits", self.data_bits != new.data_bits),
+            ("flow_control", self.flow_control != new.flow_control),
+            ("parity", self.parity != new.parity),
+            ("stop_bits", self.stop_bits != new.stop_bits),
+            ("address", self.address != new.address),
+            ("echo_enabled", self.echo_enabled != new.echo_enabled),
+            ("response_shape", self.response_shape != new.response_shape),
+            ("command_timeout_ms", self.command_timeout_ms != new.command_timeout_ms),
+            ("poll_interval_ms", self.poll_interval_ms != new.poll_interval_ms),
+            ("keepalive_ms", self.keepalive_ms != new.keepalive_ms),
+            ("history_size", self.history_size != new.history_size),
+            ("exercise.interval_hours", self.exercise.interval_hours != new.exercise.interval_hours),
+            ("exchange_log", self.exchange_log != new.exchange_log),
+            ("last_position_path", self.last_position_path != new.last_position_path),
+        ]
+        .into_iter()
+        .filter_map(|(name, changed)| changed.then_some(name))
+        .collect()
+    }
+
+    /// Apply every other setting from `new`, returning the names of those
+    /// which changed. Check [`Self::restart_required`] first, as any changes
+    /// to those settings are ignored.
+    pub fn reload(&mut self, new: &Self) -> Vec<&'static str> {
+        let mut names = Vec::new();
+
+        macro_rules! reload {
+            ($($field:ident),+ $(,)?) => {
+                $(
+                    if self.$field != new.$field {
+                        self.$field.clone_from(&new.$field);
+                        names.push(stringify!($field));
+                    }
+                )+
+            };
+        }
+
+        reload!(
+            line_terminator,
+            value_format,
+            unknown_command_reply,
+            response_delay_ms,
+            read_buffer_size,
+            max_command_timeout_ms,
+            max_move_degrees_per_request,
+            max_steps,
+            position_decimals,
+            nudge_steps,
+            position_tolerance,
+            settle_time_ms,
+            stall_samples,
+            position_event_threshold,
+            halt_on_connect,
+            flush_before_command,
+            auto_enable_motors,
+            default_slew_rate,
+            calibrate_set_min_version,
+            halt_cooldown_ms,
+            interlock,
+            debug,
+            frame,
+            limits,
+            log_clamped,
+            log_rejected,
+            park,
+            startup_position,
+            home,
+            fine,
+            drift,
+            exercise,
+            test_pattern,
+            accuracy,
+        );
+
+        names
+    }
+}
+
+impl Default for RotatorConfig {
+    fn default() -> Self {
+        Self {
+            data_bits: DataBits::Eight,
+            flow_control: FlowControl::None,
+            parity: Parity::None,
+            stop_bits: StopBits::One,
+            line_terminator: "\n".to_string(),
+            address: None,
+            echo_enabled: None,
+            response_shape: None,
+            value_format: ValueFormat::default(),
+            unknown_command_reply: "unknown command".to_string(),
+            command_timeout_ms: 25,
+            response_delay_ms: 0,
+            read_buffer_size: 2048,
+            max_command_timeout_ms: 5_000,
+            max_move_degrees_per_request: None,
+            max_steps: PerAxis::default(),
+            position_decimals: 3,
+            nudge_steps: 10,
+            position_tolerance: 0.5,
+            settle_time_ms: 200,
+            poll_interval_ms: 500,
+            keepalive_ms: None,
+            position_event_threshold: 1.0,
+            stall_samples: 6,
+            history_size: 100,
+            halt_on_connect: true,
+            flush_before_command: false,
+            auto_enable_motors: false,
+            default_slew_rate: None,
+            calibrate_set_min_version: None,
+            halt_cooldown_ms: None,
+            interlock: InterlockConfig::default(),
+            debug: false,
+            frame: Frame::default(),
+            limits: PerAxis::default(),
+            log_clamped: true,
+            log_rejected: true,
+            park: ParkConfig::default(),
+            startup_position: StartupPosition::default(),
+            last_position_path: "last_position.json".to_string(),
+            home: HomeConfig::default(),
+            fine: FineConfig::default(),
+            drift: DriftConfig::default(),
+            exercise: ExerciseConfig::default(),
+            test_pattern: TestPatternConfig::default(),
+            accuracy: AccuracyConfig::default(),
+            exchange_log: ExchangeLogConfig::default(),
+        }
+    }
+}
+
+/// An additional rotator, served at `/rotators/<id>`.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct RotatorEntry {
+    /// Path of the serial port the rotator is connected to.
+    pub port: String,
+    /// Settings for this rotator. These do not inherit from `[rotator]`.
+    #[serde(flatten)]
+    pub config: RotatorConfig,
+}
+
+/// The position the rotator is sent to by [`Rotator::park`](super::Rotator::park).
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ParkConfig {
+    pub vertical: f32,
+    pub horizontal: f32,
+    /// How long to wait for the rotator to reach the park position.
+    pub timeout_ms: u64,
+}
+
+impl Default for ParkConfig {
+    fn default() -> Self {
+        Self {
+            vertical: 0.0,
+            horizontal: 0.0,
+            timeout_ms: 30_000,
+        }
+    }
+}
+
+/// The allowed range of positions for an axis, in degrees.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Limits {
+    pub min: f32,
+    pub max: f32,
+}
+
+impl Limits {
+    pub fn contains(&self, degrees: f32) -> bool {
+        (self.min..=self.max).contains(&degrees)
+    }
+
+    pub fn clamp(&self, degrees: f32) -> f32 {
+        degrees.clamp(self.min, self.max)
+    }
+
+    /// Whether this range lies entirely within `other`.
+    pub fn within(&self, other: &Self) -> bool {
+        self.min >= other.min && self.max <= other.max
+    }
+}
+
+/// Settings for [`Rotator::exercise`](super::Rotator::exercise).
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ExerciseConfig {
+    /// How far to either side of the current position each axis is swept.
+    pub sweep_degrees: f32,
+    /// How long to wait for each leg of the sweep to complete.
+    pub timeout_ms: u64,
+    /// Run the exercise automatically this often. Disabled if unset.
+    pub interval_hours: Option<u64>,
+}
+
+impl Default for ExerciseConfig {
+    fn default() -> Self {
+        Self {
+            sweep_degrees: 10.0,
+            timeout_ms: 30_000,
+            interval_hours: None,
+        }
+    }
+}
+
+/// How [`Rotator::home`](super::Rotator::home) finds each axis's end-stop.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct HomeConfig {
+    /// How long an axis must stay still to have reached its end-stop.
+    pub settle_time_ms: u64,
+    /// How long each axis may take to reach its end-stop.
+    pub timeout_ms: u64,
+    /// How far, in degrees of the rotator's reading, to back each axis off
+    /// its end-stop once it is there. Axes without this stay on it.
+    pub offset: PerAxis<Option<f32>>,
+    /// Calibrate each axis once it has backed off, so that where it stops is
+    /// the working zero.
+    pub set_zero: bool,
+}
+
+impl Default for HomeConfig {
+    fn default() -> Self {
+        Self {
+            settle_time_ms: 1_000,
+            timeout_ms: 60_000,
+            offset: PerAxis::default(),
+            set_zero: false,
+        }
+    }
+}
+
+/// Settings for the fine positioning pass, see
+/// [`Rotator::refine`](super::Rotator::refine).
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct FineConfig {
+    /// Run the fine pass after every move which waits to arrive.
+    pub enabled: bool,
+    /// How close, in degrees, each axis must be to the target to stop.
+    pub tolerance: f32,
+    /// The most corrective moves to make before giving up.
+    pub max_iterations: u32,
+    /// Steps per degree of each axis. Axes without this are not refined.
+    pub steps_per_degree: PerAxis<Option<f32>>,
+}
+
+impl Default for FineConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            tolerance: 0.05,
+            max_iterations: 3,
+            steps_per_degree: PerAxis::default(),
+        }
+    }
+}
+
+/// Watching for an axis's position drifting away from its sensor feedback,
+/// see [`Rotator::feedback`](super::Rotator::feedback). Only useful for firmware
+/// which reports feedback in the same units as its positions.
+#[derive(Debug, Clone, PartialEq, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct DriftConfig {
+    /// How far the feedback may be from the position of an axis which isn't
+    /// moving. Drift is not checked if unset.
+    pub threshold: Option<f32>,
+    /// Recalibrate an axis which has drifted, rather than only reporting it.
+    pub auto_recalibrate: bool,
+}
+
+/// A setting which is configured separately for each axis.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PerAxis<T> {
+    pub vertical: T,
+    pub horizontal: T,
+}
+
+impl<T> PerAxis<T> {
+    pub const fn get(&self, axis: Axis) -> &T {
+        match axis {
+            Axis::Vertical => &self.vertical,
+            Axis::Horizontal => &self.horizontal,
+        }
+    }
+
+    pub const fn get_mut(&mut self, axis: Axis) -> &mut T {
+        match axis {
+            Axis::Vertical => &mut self.vertical,
+            Axis::Horizontal => &mut self.horizontal,
+        }
+    }
+}
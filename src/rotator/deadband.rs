@@ -0,0 +1,105 @@
+//! The firmware's positional deadband: how far from its target an axis may
+//! sit before the firmware corrects it. A wider deadband stops the motors
+//! hunting back and forth around a target, at the cost of precision.
+
+use super::{Axis, Command, Error, Rotator, config::PerAxis, exact_values};
+
+impl Axis {
+    /// How the axis is named to `SETD`.
+    const fn deadband_code(self) -> &'static str {
+        match self {
+            Self::Vertical => "V",
+            Self::Horizontal => "H",
+        }
+    }
+}
+
+impl Rotator {
+    /// Gets the deadband of each axis, in degrees.
+    ///
+    /// # Errors
+    /// Returns [`Error::Unsupported`] if the firmware has no deadband.
+    pub async fn deadband(&mut self) -> Result<PerAxis<f32>, Error> {
+        let values = self
+            .send_optional(Command::GetDeadband, &[]).await?
+            .ok_or(Error::ExpectedValue(Command::GetDeadband))?;
+
+        let [vertical, horizontal] = exact_values(values)?;
+
+        Ok(PerAxis {
+            vertical: self.parse_reading(&vertical)?,
+            horizontal: self.parse_reading(&horizontal)?,
+        })
+    }
+
+    /// Sets the deadband of an axis, in degrees. `0` corrects every error
+    /// the firmware can detect.
+    ///
+    /// # Errors
+    /// Returns [`Error::OutOfRange`] without sending anything if `degrees` is
+    /// negative, or [`Error::Unsupported`] if the firmware has no deadband.
+    pub async fn set_deadband(&mut self, axis: Axis, degrees: f32) -> Result<(), Error> {
+        if !degrees.is_finite() || degrees < 0.0 {
+            return Err(Error::OutOfRange {
+                requested: degrees.into(),
+                min: 0.0,
+                max: f64::INFINITY,
+            });
+        }
+
+        let decimals = self.config.position_decimals;
+        self.send_optional(Command::SetDeadband, &[axis.deadband_code(), &format!("{degrees:0.decimals$}")]).await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::mock::{self, MockFirmware}, *};
+
+    #[rocket::async_test]
+    async fn the_deadband_is_read_for_each_axis() {
+        let firmware = MockFirmware::new();
+        firmware.reply("GETD", "OK 0.25 1.5");
+        let mut rotator = firmware.rotator(mock::config());
+
+        assert_eq!(rotator.deadband().await.unwrap(), PerAxis { vertical: 0.25, horizontal: 1.5 });
+        assert_eq!(firmware.received(), ["GETD"]);
+    }
+
+    #[rocket::async_test]
+    async fn the_deadband_is_set_per_axis() {
+        let firmware = MockFirmware::new();
+        firmware.reply("SETD", "OK");
+        let mut rotator = firmware.rotator(mock::config());
+
+        rotator.set_deadband(Axis::Vertical, 0.5).await.unwrap();
+        rotator.set_deadband(Axis::Horizontal, 0.0).await.unwrap();
+
+        assert_eq!(firmware.received(), ["SETD V 0.500", "SETD H 0.000"]);
+    }
+
+    #[rocket::async_test]
+    async fn deadbands_must_not_be_negative() {
+        let firmware = MockFirmware::new();
+        let mut rotator = firmware.rotator(mock::config());
+
+        for degrees in [-0.1, f32::NAN] {
+            let error = rotator.set_deadband(Axis::Vertical, degrees).await.unwrap_err();
+            assert!(matches!(error, Error::OutOfRange { .. }), "{error:?}");
+        }
+        assert!(firmware.received().is_empty());
+    }
+
+    #[rocket::async_test]
+    async fn firmware_without_a_deadband_is_unsupported() {
+        let firmware = MockFirmware::new();
+        let mut rotator = firmware.rotator(mock::config());
+
+        let error = rotator.deadband().await.unwrap_err();
+        assert!(matches!(error, Error::Unsupported(Command::GetDeadband)), "{error:?}");
+        let error = rotator.set_deadband(Axis::Horizontal, 1.0).await.unwrap_err();
+        assert!(matches!(error, Error::Unsupported(Command::SetDeadband)), "{error:?}");
+    }
+}
@@ -1,12 +1,32 @@
 //! Rocket endpoints for managing the rotator remotely.
 
-use std::sync::Arc;
+use std::{collections::BTreeMap, sync::Arc, time::{Duration, Instant}};
 
-use rocket::{Route, State, get, routes, tokio::sync::Mutex};
+use rocket::{
+    Route, Shutdown, State, get, post, put,
+    http::ContentType,
+    response::stream::{Event, EventStream, TextStream},
+    routes,
+    serde::json::Json,
+    tokio::{self, select, sync::broadcast::error::RecvError},
+};
+use log::warn;
+use serde::Deserialize;
 use serde_json::json;
-use crate::response::{Error, Success};
+use crate::{
+    auth::Admin,
+    idempotency::{IdempotencyCache, IdempotencyKey},
+    jobs::Jobs,
+    response::{Accepted, BadRequest, Error, Failure, NotFound, Success},
+};
 
-use super::Rotator;
+use super::{
+    Axis, POSITION_POLL_INTERVAL, Position, Rotator,
+    presets::Presets,
+    registry::RotatorHandle,
+    test_pattern::TestPattern,
+    units::{Units, check_angle, check_input, check_position_input},
+};
 
 pub fn endpoints() -> Vec<Route> {
     routes![
@@ -14,22 +34,58 @@ pub fn endpoints() -> Vec<Route> {
         set_position_horizontal,
         calibrate_vertical,
         calibrate_horizontal,
+        reset_calibration,
+        calibrate_job,
         move_direction,
         move_vertical_steps,
         move_horizontal_steps,
+        nudge,
+        mode,
+        set_mode,
+        set_motor,
+        armed,
+        arm,
+        disarm,
+        resume,
+        speed,
+        set_speed,
         position,
+        limits,
+        feedback,
+        deadband,
+        set_deadband,
+        goto_position,
+        goto_position_stream,
+        presets,
+        replace_presets,
+        goto_preset,
         calibrated,
         halt,
+        stop,
+        flush,
         errors,
         version,
+        ping,
+        self_test,
+        exercise,
+        test_pattern,
+        accuracy_check,
+        home,
+        telemetry,
+        events,
+        history,
+        export_history,
+        metrics,
+        last_exchange,
     ]
 }
 
-type StatePort = State<Arc<Mutex<Rotator>>>;
-
 /// Set a defined position for the rotator to move tow
-#[get("/dver?<degrees>")]
-pub async fn set_position_vertical(serial: &StatePort, degrees: f32) -> Result<Success, Error> {
+#[get("/dver?<degrees>&<units>")]
+pub async fn set_position_vertical(serial: RotatorHandle, degrees: f32, units: Option<Units>) -> Result<Success, Failure> {
+    let degrees = units.unwrap_or_default().to_degrees(degrees);
+    let degrees = check_angle(Axis::Vertical, degrees, serial.limits().await.vertical).map_err(BadRequest::new)?;
+
     let mut rotator = serial.lock().await;
     rotator.set_position_vertical(degrees).await?;
 
@@ -37,8 +93,11 @@ pub async fn set_position_vertical(serial: &StatePort, degrees: f32) -> Result<S
 }
 
 /// Set a defined position for the rotator in the horizontal axis.
-#[get("/dhor?<degrees>")]
-pub async fn set_position_horizontal(serial: &StatePort, degrees: f32) -> Result<Success, Error> {
+#[get("/dhor?<degrees>&<units>")]
+pub async fn set_position_horizontal(serial: RotatorHandle, degrees: f32, units: Option<Units>) -> Result<Success, Failure> {
+    let degrees = units.unwrap_or_default().to_degrees(degrees);
+    let degrees = check_angle(Axis::Horizontal, degrees, serial.limits().await.horizontal).map_err(BadRequest::new)?;
+
     let mut rotator = serial.lock().await;
     rotator.set_position_horizontal(degrees).await?;
 
@@ -47,28 +106,79 @@ pub async fn set_position_horizontal(serial: &StatePort, degrees: f32) -> Result
 
 /// Calibrates the vertical axis.
 #[get("/calv?<set>")]
-pub async fn calibrate_vertical(serial: &StatePort, set: bool) -> Result<Success, Error> {
+pub async fn calibrate_vertical(serial: RotatorHandle, set: bool) -> Result<Success, Failure> {
     let mut rotator = serial.lock().await;
-    let _ = rotator.calibrate_vertical(set).await;
+    rotator.calibrate_vertical(set).await?;
 
     Ok(Success::empty())
 }
 
 /// Calibrates the horizontal axis.
 #[get("/calh")]
-pub async fn calibrate_horizontal(serial: &StatePort) -> Result<Success, Error> {
+pub async fn calibrate_horizontal(serial: RotatorHandle) -> Result<Success, Failure> {
     let mut rotator = serial.lock().await;
     rotator.calibrate_horizontal().await?;
 
     Ok(Success::empty())
 }
 
+/// Calibrates an axis in the background, responding straight away with the
+/// id of a job to poll at `/jobs/<id>`. The command is sent with `timeout_ms`
+/// if given, as calibration can take a while to be acknowledged.
+///
+/// The job's progress is `waiting` until the rotator is free, then
+/// `calibrating` while the command is in flight. The rotator is only locked
+/// for that one exchange, as nothing else can use the port until the
+/// firmware has answered it anyway.
+#[post("/calibrate/<axis>?<set>&<timeout_ms>")]
+pub async fn calibrate_job(
+    serial: RotatorHandle,
+    jobs: &State<Arc<Jobs>>,
+    axis: Axis,
+    set: Option<bool>,
+    timeout_ms: Option<u64>,
+) -> Accepted {
+    let rotator = Arc::clone(&serial.rotator);
+    let set = set.unwrap_or(false);
+
+    let kind = match axis {
+        Axis::Vertical => "calibrate_vertical",
+        Axis::Horizontal => "calibrate_horizontal",
+    };
+    let id = jobs
+        .spawn(kind, move |progress| async move {
+            progress.report("waiting").await;
+            let mut rotator = rotator.lock().await;
+            let mut rotator = rotator.with_timeout(timeout_ms)?;
+            progress.report("calibrating").await;
+
+            match axis {
+                Axis::Vertical => rotator.calibrate_vertical(set).await,
+                Axis::Horizontal => rotator.calibrate(Axis::Horizontal).await,
+            }
+        })
+        .await;
+
+    Accepted::data(json!({
+        "job": id,
+    }))
+}
+
+/// Clears the calibration of both axes.
+#[post("/calibration/reset")]
+pub async fn reset_calibration(serial: RotatorHandle) -> Result<Success, Failure> {
+    let mut rotator = serial.lock().await;
+    rotator.reset_calibration().await?;
+
+    Ok(Success::empty())
+}
+
 /// Moves in a direction indefinitely specified by the command, or stops, if the command is to stop.
 #[get("/movc?<direction>")]
 pub async fn move_direction(
-    serial: &StatePort,
+    serial: RotatorHandle,
     direction: super::Direction,
-) -> Result<Success, Error> {
+) -> Result<Success, Failure> {
     let mut rotator = serial.lock().await;
     rotator.move_direction(direction).await?;
 
@@ -77,7 +187,7 @@ pub async fn move_direction(
 
 /// Moves by the specified number of steps in the vertical axis.
 #[get("/movv?<steps>")]
-pub async fn move_vertical_steps(serial: &StatePort, steps: i32) -> Result<Success, Error> {
+pub async fn move_vertical_steps(serial: RotatorHandle, steps: i32) -> Result<Success, Failure> {
     let mut rotator = serial.lock().await;
     rotator.move_vertical_steps(steps).await?;
 
@@ -86,30 +196,401 @@ pub async fn move_vertical_steps(serial: &StatePort, steps: i32) -> Result<Succe
 
 /// Moves by the specified number of steps in the horizontal axis.
 #[get("/movh?<steps>")]
-pub async fn move_horizontal_steps(serial: &StatePort, steps: i32) -> Result<Success, Error> {
+pub async fn move_horizontal_steps(serial: RotatorHandle, steps: i32) -> Result<Success, Failure> {
     let mut rotator = serial.lock().await;
     rotator.move_horizontal_steps(steps).await?;
 
     Ok(Success::empty())
 }
 
-#[get("/position")]
+/// Moves an axis a few steps for fine adjustment, up or clockwise if `positive`.
+#[post("/nudge?<axis>&<positive>")]
+pub async fn nudge(serial: RotatorHandle, axis: super::Axis, positive: bool) -> Result<Success, Failure> {
+    let mut rotator = serial.lock().await;
+    rotator.nudge(axis, positive).await?;
+
+    Ok(Success::empty())
+}
+
+/// Gets the firmware's operating mode.
+#[get("/mode")]
+pub async fn mode(serial: RotatorHandle) -> Result<Success, Failure> {
+    let mut rotator = serial.lock().await;
+    let mode = rotator.mode().await?;
+
+    Ok(Success::data(json!({
+        "mode": mode,
+    })))
+}
+
+/// Switches the firmware to another operating mode.
+#[post("/mode?<mode>")]
+pub async fn set_mode(serial: RotatorHandle, mode: super::mode::Mode) -> Result<Success, Failure> {
+    let mut rotator = serial.lock().await;
+    rotator.set_mode(mode).await?;
+
+    Ok(Success::empty())
+}
+
+/// Gets whether the rotator may move, and for how much longer it stays armed
+/// without moving, if the interlock is on.
+#[get("/arm")]
+pub async fn armed(serial: RotatorHandle) -> Success {
+    let rotator = serial.lock().await;
+    let remaining = rotator
+        .armed_until()
+        .filter(|_| rotator.config().interlock.enabled)
+        .map(|until| until.saturating_duration_since(Instant::now()).as_millis());
+
+    Success::data(json!({
+        "armed": rotator.is_armed(),
+        "interlock": rotator.config().interlock.enabled,
+        "disarms_in_ms": remaining,
+    }))
+}
+
+/// Arms the rotator, so it can be moved while the interlock is on.
+#[post("/arm")]
+pub async fn arm(_admin: Admin, serial: RotatorHandle) -> Success {
+    serial.lock().await.arm();
+
+    Success::empty()
+}
+
+/// Disarms the rotator. Anything already moving is not halted.
+#[post("/disarm")]
+pub async fn disarm(_admin: Admin, serial: RotatorHandle) -> Success {
+    serial.lock().await.disarm();
+
+    Success::empty()
+}
+
+/// Ends the cooldown after a halt, so the rotator can be moved again straight
+/// away.
+#[post("/resume")]
+pub async fn resume(_admin: Admin, serial: RotatorHandle) -> Success {
+    serial.lock().await.resume();
+
+    Success::empty()
+}
+
+/// Gets the slew speed, in degrees per second.
+#[get("/speed")]
+pub async fn speed(serial: RotatorHandle) -> Result<Success, Failure> {
+    let speed = serial.lock().await.speed().await?;
+
+    Ok(Success::data(json!({
+        "speed": speed,
+    })))
+}
+
+/// Sets the slew speed, in degrees per second.
+#[post("/speed?<speed>")]
+pub async fn set_speed(serial: RotatorHandle, speed: f32) -> Result<Success, Failure> {
+    let speed = check_input("speed", speed).map_err(BadRequest::new)?;
+
+    let mut rotator = serial.lock().await;
+    rotator.set_speed(speed).await?;
+
+    Ok(Success::empty())
+}
+
+/// Powers an axis's motor on or off, e.g. to turn the axis by hand.
+#[post("/motor/<axis>?<enabled>")]
+pub async fn set_motor(serial: RotatorHandle, axis: Axis, enabled: bool) -> Result<Success, Failure> {
+    let mut rotator = serial.lock().await;
+    rotator.set_motor_enabled(axis, enabled).await?;
+
+    Ok(Success::empty())
+}
+
 /// Gets the current position for both the vertical and horizontal axes.
-pub async fn position(serial: &StatePort) -> Result<Success, Error> {
+/// With `raw=true`, returns the untransformed values reported by the firmware.
+/// Positions are in degrees unless `units=mils` is given.
+#[get("/position?<raw>&<timeout_ms>&<units>")]
+pub async fn position(
+    serial: RotatorHandle,
+    raw: Option<bool>,
+    timeout_ms: Option<u64>,
+    units: Option<Units>,
+) -> Result<Success, Failure> {
     let mut rotator = serial.lock().await;
-    let (v, h) = rotator.position().await?;
+    let mut rotator = rotator.with_timeout(timeout_ms)?;
+    let (vertical, horizontal) = if raw.unwrap_or(false) {
+        rotator.position_raw().await?
+    } else {
+        rotator.position().await?
+    };
+    let position = units.unwrap_or_default().position_from_degrees(Position { vertical, horizontal });
+
+    Ok(Success::data(json!({
+        "vertical": position.vertical,
+        "horizontal": position.horizontal,
+    })))
+}
+
+/// A position to move to, with how long to wait for it to be reached.
+#[derive(Deserialize)]
+pub struct PositionTarget {
+    #[serde(flatten)]
+    pub position: Position,
+    pub timeout_ms: Option<u64>,
+    /// Slew speed for just this move, in degrees per second.
+    pub speed: Option<f32>,
+}
+
+impl PositionTarget {
+    const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+
+    fn timeout(&self) -> Duration {
+        self.timeout_ms.map_or(Self::DEFAULT_TIMEOUT, Duration::from_millis)
+    }
+}
+
+/// Gets the software limits in use for each axis, and the limits stored in
+/// the firmware, or `null` if the firmware does not store any.
+#[get("/limits/config")]
+pub async fn limits(serial: RotatorHandle) -> Result<Success, Failure> {
+    let mut rotator = serial.lock().await;
+    let firmware = match rotator.firmware_limits().await {
+        Ok(limits) => Some(limits),
+        Err(super::Error::Unsupported(_)) => None,
+        Err(e) => return Err(e.into()),
+    };
+
+    Ok(Success::data(json!({
+        "software": rotator.config().limits,
+        "firmware": firmware,
+    })))
+}
+
+/// Gets the raw position sensor readings of each axis, if the firmware has
+/// any, to compare with the position.
+#[get("/feedback")]
+pub async fn feedback(serial: RotatorHandle) -> Result<Success, Failure> {
+    let feedback = serial.lock().await.feedback().await?;
+
+    Ok(Success::data(json!({
+        "feedback": feedback,
+    })))
+}
+
+/// Gets how far each axis may sit from its target before the firmware
+/// corrects it, in degrees.
+#[get("/deadband")]
+pub async fn deadband(serial: RotatorHandle) -> Result<Success, Failure> {
+    let deadband = serial.lock().await.deadband().await?;
 
     Ok(Success::data(json!({
-        "vertical": v,
-        "horizontal": h,
+        "deadband": deadband,
     })))
 }
 
+/// Sets an axis's deadband, in degrees. Wider deadbands stop the motors
+/// hunting around a target, at the cost of precision.
+#[post("/deadband/<axis>?<degrees>")]
+pub async fn set_deadband(serial: RotatorHandle, axis: Axis, degrees: f32) -> Result<Success, Failure> {
+    let degrees = check_input("degrees", degrees).map_err(BadRequest::new)?;
+
+    let mut rotator = serial.lock().await;
+    rotator.set_deadband(axis, degrees).await?;
+
+    Ok(Success::empty())
+}
+
+/// Moves to a position on both axes, responding once it has been reached.
+/// The position is in degrees unless `units=mils` is given.
+///
+/// Retries sent with the same `Idempotency-Key` header as an earlier request
+/// get its result instead of moving again, or a 409 if it is still moving.
+///
+/// Moves further than `max_move_degrees_per_request` on either axis are
+/// refused unless `allow_large=true` is given.
+#[post("/position?<units>&<allow_large>", data = "<target>")]
+pub async fn goto_position(
+    serial: RotatorHandle,
+    target: Json<PositionTarget>,
+    units: Option<Units>,
+    allow_large: Option<bool>,
+    key: IdempotencyKey,
+    idempotency: &State<IdempotencyCache>,
+) -> Result<Success, Failure> {
+    let target_position = units.unwrap_or_default().position_to_degrees(target.position);
+    check_position_input(target_position, &serial.limits().await).map_err(BadRequest::new)?;
+    if let Some(speed) = target.speed {
+        check_input("speed", speed).map_err(BadRequest::new)?;
+    }
+
+    let allow_large = allow_large.unwrap_or(false);
+
+    idempotency
+        .run(key, async {
+            // The distance is checked under the same lock as the move is sent,
+            // so the rotator can't be moved elsewhere in between. It is only
+            // locked for that, so it can be halted while the move is waited on.
+            let mut rotator = serial.lock().await;
+            if !allow_large {
+                check_move_distance(&mut rotator, target_position).await?;
+            }
+            let wait = rotator.start_goto(target_position, target.speed).await?;
+            drop(rotator);
+
+            wait.finish(&serial.rotator, target.timeout()).await?;
+
+            Ok(Success::empty())
+        })
+        .await
+}
+
+/// Refuses a move further than `max_move_degrees_per_request` on either axis.
+async fn check_move_distance(rotator: &mut Rotator, target: Position) -> Result<(), Failure> {
+    let Some(max) = rotator.config().max_move_degrees_per_request else {
+        return Ok(());
+    };
+
+    let (vertical, horizontal) = rotator.position().await?;
+    let current = Position { vertical, horizontal };
+
+    for axis in [Axis::Vertical, Axis::Horizontal] {
+        let distance = (target.get(axis) - current.get(axis)).abs();
+        if distance > max {
+            return Err(BadRequest::new(format!(
+                "Moving {axis:?} {distance:.1} degrees is more than the {max} allowed \
+                 in one request, give `allow_large=true` to move anyway"
+            ))
+            .into());
+        }
+    }
+
+    Ok(())
+}
+
+/// Moves to a position on both axes as `POST /position` does, streaming
+/// `position` events with each reading until a final `done` event once it
+/// has settled, or an `error` event if the move is halted, fails, or times
+/// out once under way. A move which can't be started is refused outright.
+///
+/// The rotator is only locked while each reading is taken, so it can still be
+/// halted mid-move. If the client disconnects the stream stops polling, but
+/// the move itself continues.
+#[post("/position/stream?<units>&<allow_large>", data = "<target>")]
+pub async fn goto_position_stream(
+    serial: RotatorHandle,
+    target: Json<PositionTarget>,
+    units: Option<Units>,
+    allow_large: Option<bool>,
+) -> Result<EventStream![], Failure> {
+    let rotator = Arc::clone(&serial.rotator);
+    let target = target.into_inner();
+    let units = units.unwrap_or_default();
+    let target_position = check_position_input(units.position_to_degrees(target.position), &serial.limits().await)
+        .map_err(BadRequest::new)?;
+    if let Some(speed) = target.speed {
+        check_input("speed", speed).map_err(BadRequest::new)?;
+    }
+
+    let mut wait = {
+        let mut rotator = rotator.lock().await;
+        if !allow_large.unwrap_or(false) {
+            check_move_distance(&mut rotator, target_position).await?;
+        }
+        rotator.start_goto(target_position, target.speed).await?
+    };
+
+    Ok(EventStream! {
+        let deadline = Instant::now() + target.timeout();
+        let mut result = Ok(());
+
+        loop {
+            match wait.poll(&rotator).await {
+                Ok((position, settled)) => {
+                    yield Event::json(&units.position_from_degrees(position)).event("position");
+
+                    if settled {
+                        result = wait.refine(&rotator).await;
+                        break;
+                    }
+                }
+                Err(e) => {
+                    result = Err(e);
+                    break;
+                }
+            }
+
+            if Instant::now() >= deadline {
+                result = Err(super::Error::Timeout);
+                break;
+            }
+
+            tokio::time::sleep(POSITION_POLL_INTERVAL).await;
+        }
+
+        match wait.end(&rotator, result).await {
+            Ok(()) => yield Event::empty().event("done"),
+            Err(e) => yield Event::data(e.to_string()).event("error"),
+        }
+    })
+}
+
+/// Gets every named preset position.
+#[get("/presets")]
+pub async fn presets(presets: &State<Presets>) -> Result<Success, Failure> {
+    Ok(Success::data(json!(presets.all().await)))
+}
+
+/// Replaces every preset position, and saves them. Each is checked against
+/// this rotator's limits, and nothing is changed if any are outside them.
+#[put("/presets", data = "<new>")]
+pub async fn replace_presets(
+    serial: RotatorHandle,
+    presets: &State<Presets>,
+    new: Json<BTreeMap<String, Position>>,
+) -> Result<Success, Failure> {
+    let new = new.into_inner();
+    let limits = serial.limits().await;
+    for (name, position) in &new {
+        check_position_input(*position, &limits).map_err(|e| BadRequest::new(format!("preset `{name}`: {e}")))?;
+    }
+
+    let rotator = serial.lock().await;
+    let invalid: Vec<_> = new
+        .iter()
+        .flat_map(|(name, position)| {
+            [Axis::Vertical, Axis::Horizontal]
+                .into_iter()
+                .filter_map(|axis| rotator.check_position(axis, position.get(axis)).err())
+                .map(move |e| format!("preset `{name}`: {e}"))
+        })
+        .collect();
+    drop(rotator);
+
+    if !invalid.is_empty() {
+        return Err(BadRequest::new(invalid.join("; ")).into());
+    }
+
+    presets.replace(new).await.map_err(Error::from)?;
+
+    Ok(Success::empty())
+}
+
+/// Moves to a named preset position, responding once it has been reached.
+#[post("/goto/<name>")]
+pub async fn goto_preset(serial: RotatorHandle, presets: &State<Presets>, name: &str) -> Result<Success, Failure> {
+    let Some(position) = presets.get(name).await else {
+        return Err(NotFound::new(format!("No preset named `{name}`")).into());
+    };
+
+    Rotator::goto_and_wait(&serial.rotator, position, PositionTarget::DEFAULT_TIMEOUT).await?;
+
+    Ok(Success::empty())
+}
+
 /// Gets the calibration status of the rotator. This must be true to use
 /// `set_position_vertical` and `set_position_horizontal`.
-#[get("/calibrated")]
-pub async fn calibrated(serial: &StatePort) -> Result<Success, Error> {
+#[get("/calibrated?<timeout_ms>")]
+pub async fn calibrated(serial: RotatorHandle, timeout_ms: Option<u64>) -> Result<Success, Failure> {
     let mut rotator = serial.lock().await;
+    let mut rotator = rotator.with_timeout(timeout_ms)?;
     let calibrated = rotator.calibrated().await?;
 
     Ok(Success::data(json!({
@@ -117,32 +598,970 @@ pub async fn calibrated(serial: &StatePort) -> Result<Success, Error> {
     })))
 }
 
-/// Immediately stops both motors by locking them to perform an emergency stop.
+/// Immediately stops both motors by locking them to perform an emergency stop,
+/// and returns the position they stopped at, which is null if it couldn't be
+/// read afterwards.
 #[get("/halt")]
-pub async fn halt(serial: &StatePort) -> Result<Success, Error> {
+pub async fn halt(serial: RotatorHandle) -> Result<Success, Failure> {
+    let mut rotator = serial.lock().await;
+    let position = rotator.halt_and_report().await?;
+
+    Ok(Success::data(json!({
+        "position": position,
+    })))
+}
+
+/// Stops both motors by decelerating them, rather than locking them like
+/// `/halt`.
+#[get("/stop")]
+pub async fn stop(serial: RotatorHandle) -> Result<Success, Failure> {
     let mut rotator = serial.lock().await;
-    rotator.halt().await?;
+    rotator.stop(false).await?;
 
     Ok(Success::empty())
 }
 
 ///Gets the oldest unknown error from the rotator
-#[get("/errors")]
-pub async fn errors(serial: &StatePort) -> Result<Success, Error> {
+#[get("/errors?<timeout_ms>")]
+pub async fn errors(serial: RotatorHandle, timeout_ms: Option<u64>) -> Result<Success, Failure> {
     let mut rotator = serial.lock().await;
+    let mut rotator = rotator.with_timeout(timeout_ms)?;
     let error = rotator.errors().await?;
     Ok(Success::data(json!({
         "error": error,
     })))
 }
 
-/// Gets the current version of the software on the rotator.
-#[get("/version")]
-pub async fn version(serial: &StatePort) -> Result<Success, Error> {
+/// Gets the current version of the software on the rotator. The version is
+/// cached after it is first read, unless `refresh=true` is given.
+#[get("/version?<timeout_ms>&<refresh>")]
+pub async fn version(serial: RotatorHandle, timeout_ms: Option<u64>, refresh: Option<bool>) -> Result<Success, Failure> {
     let mut rotator = serial.lock().await;
-    let version = rotator.version().await?;
+    let mut rotator = rotator.with_timeout(timeout_ms)?;
+    let version = if refresh.unwrap_or(false) {
+        rotator.refresh_version().await?
+    } else {
+        rotator.version().await?
+    };
 
     Ok(Success::data(json!({
-        "version": version
+        "version": version,
+        "build": rotator.firmware_build(),
     })))
 }
+
+/// Discards any stale bytes from the rotator, e.g. after a timeout, returning
+/// how many there were.
+#[post("/flush")]
+pub async fn flush(serial: RotatorHandle) -> Result<Success, Failure> {
+    let discarded = serial.lock().await.flush()?;
+
+    Ok(Success::data(json!({
+        "discarded": discarded,
+    })))
+}
+
+/// Checks that the rotator is responding, returning the round-trip time.
+#[get("/ping?<timeout_ms>")]
+pub async fn ping(serial: RotatorHandle, timeout_ms: Option<u64>) -> Result<Success, Failure> {
+    let mut rotator = serial.lock().await;
+    let mut rotator = rotator.with_timeout(timeout_ms)?;
+    let latency = rotator.ping().await?;
+
+    Ok(Success::data(json!({
+        "latency_ms": latency.as_secs_f64() * 1000.0,
+    })))
+}
+
+/// Runs a non-destructive self-test, optionally nudging each axis by `nudge` steps.
+#[post("/selftest?<nudge>")]
+pub async fn self_test(serial: RotatorHandle, nudge: Option<u32>) -> Result<Success, Failure> {
+    let report = Rotator::self_test(&serial.rotator, nudge).await;
+
+    Ok(Success::data(serde_json::to_value(report).map_err(|e| Error(e.to_string()))?))
+}
+
+/// Sweeps each axis through a small range and back, to keep the mount from seizing.
+#[post("/exercise")]
+pub async fn exercise(serial: RotatorHandle) -> Result<Success, Failure> {
+    Rotator::exercise(&serial.rotator).await?;
+
+    Ok(Success::empty())
+}
+
+/// Moves through an inspection pattern around the current position, within
+/// the limits, and back, responding once it has finished.
+#[post("/test-pattern?<pattern>")]
+pub async fn test_pattern(serial: RotatorHandle, pattern: TestPattern) -> Result<Success, Failure> {
+    Rotator::test_pattern(&serial.rotator, pattern).await?;
+
+    Ok(Success::empty())
+}
+
+/// Visits each of the configured reference positions within the limits, and
+/// responds with how far from each the rotator ended up once it is back
+/// where it started.
+#[post("/accuracy-check")]
+pub async fn accuracy_check(serial: RotatorHandle) -> Result<Success, Failure> {
+    let report = Rotator::accuracy_check(&serial.rotator).await?;
+
+    Ok(Success::data(serde_json::to_value(report).map_err(|e| Error(e.to_string()))?))
+}
+
+/// Drives each axis down or left to its end-stop, without recalibrating.
+#[post("/home")]
+pub async fn home(serial: RotatorHandle) -> Result<Success, Failure> {
+    Rotator::home(&serial.rotator).await?;
+
+    Ok(Success::empty())
+}
+
+/// Gets a snapshot of the rotator's state from the poller's cache, without
+/// communicating with the rotator.
+#[get("/telemetry")]
+pub async fn telemetry(serial: RotatorHandle) -> Result<Success, Failure> {
+    let telemetry = serial.telemetry.lock().await.clone();
+
+    Ok(Success::data(serde_json::to_value(telemetry).map_err(|e| Error(e.to_string()))?))
+}
+
+/// Streams events published by the poller, such as `position_changed`
+/// whenever the position moves by more than `position_event_threshold`.
+#[get("/events")]
+pub fn events(serial: RotatorHandle, mut shutdown: Shutdown) -> EventStream![] {
+    let mut events = serial.events.subscribe();
+
+    EventStream! {
+        loop {
+            let event = select! {
+                event = events.recv() => match event {
+                    Ok(event) => event,
+                    Err(RecvError::Closed) => break,
+                    Err(RecvError::Lagged(_)) => continue,
+                },
+                () = &mut shutdown => break,
+            };
+
+            yield Event::json(&event).event(event.name());
+        }
+    }
+}
+
+/// Gets the most recent command exchanges with the rotator, oldest first.
+#[get("/history")]
+pub async fn history(serial: RotatorHandle) -> Result<Success, Failure> {
+    let rotator = serial.lock().await;
+    let history: Vec<_> = rotator.history().entries().cloned().collect();
+
+    Ok(Success::data(json!({
+        "history": history,
+    })))
+}
+
+/// Exports the command history as newline-delimited JSON, one exchange per
+/// line, for archiving or post-mortem analysis.
+///
+/// The rotator is only locked while the history is copied; each line is
+/// serialized as it is sent rather than building the whole body up front.
+#[get("/history/export")]
+pub async fn export_history(serial: RotatorHandle) -> (ContentType, TextStream![String]) {
+    let entries: Vec<_> = serial.lock().await.history().entries().cloned().collect();
+
+    let stream = TextStream! {
+        for exchange in entries {
+            match serde_json::to_string(&exchange) {
+                Ok(line) => yield line + "\n",
+                Err(e) => warn!("Failed to export history entry: {e}"),
+            }
+        }
+    };
+
+    (ContentType::new("application", "x-ndjson"), stream)
+}
+
+/// Gets latency and error metrics for each command sent to the rotator, and
+/// how long requests have waited for and held the rotator.
+#[get("/metrics")]
+pub async fn metrics(serial: RotatorHandle) -> Result<Success, Failure> {
+    let rotator = serial.lock().await;
+    let mut metrics = serde_json::to_value(rotator.metrics()).map_err(|e| Error(e.to_string()))?;
+    drop(rotator);
+    metrics["lock"] = json!(serial.lock_metrics());
+
+    Ok(Success::data(metrics))
+}
+
+/// Gets the last command sent to the rotator, the exact bytes it responded
+/// with, and how the response was understood. Only available if `debug` is
+/// set for the rotator.
+#[get("/debug/last")]
+pub async fn last_exchange(serial: RotatorHandle) -> Result<Success, Failure> {
+    let rotator = serial.lock().await;
+    if !rotator.config().debug {
+        return Err(NotFound::new("Debug endpoints are disabled").into());
+    }
+
+    Ok(Success::data(json!({
+        "last": rotator.last_exchange(),
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{Arc, atomic::{AtomicUsize, Ordering}},
+        time::Duration,
+    };
+
+    use rocket::{
+        http::{ContentType, Header, Status},
+        local::asynchronous::{Client, LocalResponse},
+        tokio::{self, sync::Mutex},
+    };
+    use serde_json::{Value, json};
+
+    use crate::{
+        config::{Config, SharedConfig},
+        idempotency::{IdempotencyCache, IdempotencyConfig},
+        jobs::Jobs,
+        rotator::{
+            config::{Limits, PerAxis, RotatorConfig},
+            mock::{self, MockFirmware},
+            presets::Presets,
+            registry::{RotatorHandle, Rotators},
+        },
+    };
+
+    const TOKEN: &str = "secret";
+
+    /// A server with just the rotator endpoints, for a rotator connected to
+    /// `firmware`.
+    async fn client(firmware: &MockFirmware, config: RotatorConfig) -> Client {
+        static PRESETS: AtomicUsize = AtomicUsize::new(0);
+
+        let presets = std::env::temp_dir().join(format!(
+            "archerd-presets-{}-{}.json",
+            std::process::id(),
+            PRESETS.fetch_add(1, Ordering::Relaxed),
+        ));
+        let handle = RotatorHandle::unpolled(Arc::new(Mutex::new(firmware.rotator(config))));
+        let rocket = rocket::build()
+            .manage(Rotators::new(handle))
+            .manage(Arc::new(Jobs::default()))
+            .manage(IdempotencyCache::new(IdempotencyConfig::default()))
+            .manage(Presets::load(presets))
+            .manage(SharedConfig::new(Config { admin_token: Some(TOKEN.to_string()), ..Config::default() }))
+            .mount("/", rocket::routes![crate::jobs::job])
+            .mount("/rotator", super::endpoints());
+
+        Client::tracked(rocket).await.unwrap()
+    }
+
+    async fn body(response: LocalResponse<'_>) -> Value {
+        serde_json::from_str(&response.into_string().await.unwrap()).unwrap()
+    }
+
+    #[rocket::async_test]
+    async fn position_is_transformed_unless_raw() {
+        let firmware = MockFirmware::new();
+        firmware.lock().position.vertical = 10.0;
+        firmware.lock().position.horizontal = 20.0;
+        let config = RotatorConfig {
+            frame: crate::rotator::frame::Frame { elevation_offset: 5.0, azimuth_offset: 100.0, ..Default::default() },
+            ..mock::config()
+        };
+        let client = client(&firmware, config).await;
+
+        let response = client.get("/rotator/position").dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(body(response).await["data"], json!({"vertical": 15.0, "horizontal": 80.0}));
+
+        let response = client.get("/rotator/position?raw=true").dispatch().await;
+        assert_eq!(body(response).await["data"], json!({"vertical": 10.0, "horizontal": 20.0}));
+    }
+
+    /// The name and data of each server-sent event in a response.
+    async fn events(response: LocalResponse<'_>) -> Vec<(String, String)> {
+        let body = response.into_string().await.unwrap();
+        let mut events = Vec::new();
+
+        for frame in body.split("\n\n") {
+            let mut name = None;
+            let mut data = String::new();
+            for line in frame.lines() {
+                if let Some(value) = line.strip_prefix("event:") {
+                    name = Some(value.trim().to_string());
+                } else if let Some(value) = line.strip_prefix("data:") {
+                    data.push_str(value.trim());
+                }
+            }
+            events.extend(name.map(|name| (name, data)));
+        }
+
+        events
+    }
+
+    #[rocket::async_test]
+    async fn position_stream_reports_progress_until_done() {
+        let firmware = MockFirmware::new();
+        firmware.lock().slew_per_read = Some(10.0);
+        let client = client(&firmware, mock::config()).await;
+
+        let response = client
+            .post("/rotator/position/stream")
+            .body(json!({"vertical": 30.0, "horizontal": 0.0}).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let events = events(response).await;
+
+        let (last, progress) = events.split_last().unwrap();
+        assert_eq!(last.0, "done");
+        assert!(progress.iter().all(|(name, _)| name == "position"));
+        let elevations: Vec<_> = progress
+            .iter()
+            .map(|(_, data)| serde_json::from_str::<Value>(data).unwrap()["vertical"].as_f64().unwrap())
+            .collect();
+        assert_eq!(elevations, [10.0, 20.0, 30.0]);
+    }
+
+    #[rocket::async_test]
+    async fn position_stream_ends_with_an_error_if_not_reached() {
+        let firmware = MockFirmware::new();
+        firmware.lock().stalled.vertical = true;
+        let client = client(&firmware, mock::config()).await;
+
+        let response = client
+            .post("/rotator/position/stream")
+            .body(json!({"vertical": 30.0, "horizontal": 0.0, "timeout_ms": 250}).to_string())
+            .dispatch()
+            .await;
+        let events = events(response).await;
+
+        let (last, progress) = events.split_last().unwrap();
+        assert_eq!(last.0, "error");
+        assert!(!progress.is_empty() && progress.iter().all(|(name, _)| name == "position"));
+    }
+
+    #[rocket::async_test]
+    async fn position_stream_refuses_targets_out_of_range() {
+        let firmware = MockFirmware::new();
+        let client = client(&firmware, mock::config()).await;
+
+        let response = client
+            .post("/rotator/position/stream")
+            .body(json!({"vertical": 500.0, "horizontal": 0.0}).to_string())
+            .dispatch()
+            .await;
+
+        assert_eq!(response.status(), Status::BadRequest);
+        assert!(firmware.received().is_empty());
+    }
+
+    #[rocket::async_test]
+    async fn position_stream_ends_when_the_rotator_is_halted() {
+        let firmware = MockFirmware::new();
+        firmware.lock().stalled.vertical = true;
+        let client = client(&firmware, mock::config()).await;
+
+        let response = client
+            .post("/rotator/position/stream")
+            .body(json!({"vertical": 30.0, "horizontal": 0.0, "timeout_ms": 5000}).to_string())
+            .dispatch()
+            .await;
+        let halt = async {
+            tokio::time::sleep(Duration::from_millis(250)).await;
+            client.get("/rotator/halt").dispatch().await.status()
+        };
+        let (events, halted) = tokio::join!(events(response), halt);
+
+        assert_eq!(halted, Status::Ok);
+        let (name, data) = events.last().unwrap();
+        assert_eq!(name, "error");
+        assert!(data.contains("interrupted"), "{data}");
+    }
+
+    #[rocket::async_test]
+    async fn position_stream_uses_the_speed_given() {
+        let firmware = MockFirmware::new();
+        firmware.reply("GETS", "OK 5.0");
+        firmware.reply("SETS", "OK");
+        let client = client(&firmware, mock::config()).await;
+
+        let response = client
+            .post("/rotator/position/stream")
+            .body(json!({"vertical": 30.0, "horizontal": 20.0, "speed": 2.0}).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(events(response).await.last().unwrap().0, "done");
+
+        let sent: Vec<_> = firmware.received().into_iter().filter(|line| line != "GETP").collect();
+        assert_eq!(sent, ["GETS", "SETS 2.000", "DVER 30.000", "DHOR -20.000", "SETS 5.000"]);
+    }
+
+    #[rocket::async_test]
+    async fn timeout_overrides_are_capped() {
+        let firmware = MockFirmware::new();
+        let config = RotatorConfig { max_command_timeout_ms: 5_000, ..mock::config() };
+        let client = client(&firmware, config).await;
+
+        let response = client.get("/rotator/position?timeout_ms=2000").dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+
+        let response = client.get("/rotator/position?timeout_ms=60000").dispatch().await;
+        assert_eq!(response.status(), Status::BadRequest);
+        assert_eq!(firmware.commands(), ["GETP"]);
+    }
+
+    #[rocket::async_test]
+    async fn resetting_the_calibration_refuses_positioning() {
+        let firmware = MockFirmware::new();
+        let client = client(&firmware, mock::config()).await;
+
+        let response = client.post("/rotator/calibration/reset").dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        assert!(!firmware.lock().calibrated);
+
+        let response = client.get("/rotator/calibrated").dispatch().await;
+        assert_eq!(body(response).await["data"], json!({"calibrated": false}));
+
+        let response = client.get("/rotator/dver?degrees=10").dispatch().await;
+        assert_eq!(response.status(), Status::InternalServerError);
+        assert_eq!(firmware.lock().target.vertical, None);
+    }
+
+    #[rocket::async_test]
+    async fn halts_succeed_even_if_the_position_cant_be_read_afterwards() {
+        let firmware = MockFirmware::new();
+        firmware.lock().unanswered.insert("GETP".to_string(), 3);
+        let client = client(&firmware, mock::config()).await;
+
+        let response = client.get("/rotator/halt").dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(body(response).await["data"], json!({"position": null}));
+        assert_eq!(firmware.commands(), ["HALT", "GETP", "GETP", "GETP"]);
+
+        firmware.lock().unanswered.insert("HALT".to_string(), 1);
+        let response = client.get("/rotator/halt").dispatch().await;
+        assert_eq!(response.status(), Status::InternalServerError);
+    }
+
+    #[rocket::async_test]
+    async fn history_exports_one_json_object_per_line() {
+        let firmware = MockFirmware::new();
+        let client = client(&firmware, mock::config()).await;
+        client.get("/rotator/position").dispatch().await;
+        client.get("/rotator/calibrated").dispatch().await;
+
+        let response = client.get("/rotator/history/export").dispatch().await;
+        assert_eq!(response.content_type(), Some(ContentType::new("application", "x-ndjson")));
+        let body = response.into_string().await.unwrap();
+
+        let exchanges: Vec<Value> = body.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        let commands: Vec<_> = exchanges.iter().map(|exchange| exchange["command"].as_str().unwrap()).collect();
+        assert_eq!(commands, ["GETP", "GETC"]);
+        for exchange in &exchanges {
+            assert!(exchange.is_object());
+            chrono::DateTime::parse_from_rfc3339(exchange["timestamp"].as_str().unwrap()).unwrap();
+        }
+    }
+
+    #[rocket::async_test]
+    async fn nudges_step_the_given_axis() {
+        let firmware = MockFirmware::new();
+        let client = client(&firmware, RotatorConfig { nudge_steps: 25, ..mock::config() }).await;
+
+        let response = client.post("/rotator/nudge?axis=vertical&positive=true").dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        client.post("/rotator/nudge?axis=horizontal&positive=true").dispatch().await;
+
+        assert_eq!(firmware.received(), ["MOVV 25", "MOVH -25"]);
+    }
+
+    #[rocket::async_test]
+    async fn limits_show_the_firmware_limits_if_there_are_any() {
+        let firmware = MockFirmware::new();
+        let client = client(&firmware, mock::config()).await;
+
+        let response = client.get("/rotator/limits/config").dispatch().await;
+        assert_eq!(body(response).await["data"]["firmware"], Value::Null);
+
+        firmware.reply("GETL", "OK 0 90 -180 180");
+        let response = client.get("/rotator/limits/config").dispatch().await;
+        let data = body(response).await["data"].clone();
+        assert_eq!(data["firmware"]["vertical"], json!({"min": 0.0, "max": 90.0}));
+        assert_eq!(data["software"]["vertical"], Value::Null);
+    }
+
+    #[rocket::async_test]
+    async fn positions_can_be_given_and_returned_in_mils() {
+        let firmware = MockFirmware::new();
+        let client = client(&firmware, mock::config()).await;
+
+        let response = client.get("/rotator/dver?degrees=1600&units=mils").dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        client.get("/rotator/dver?degrees=45").dispatch().await;
+        assert_eq!(firmware.received(), ["DVER 90.000", "DVER 45.000"]);
+
+        let response = client.get("/rotator/position?units=mils").dispatch().await;
+        let mils = body(response).await["data"]["vertical"].as_f64().unwrap();
+        assert!((mils - 800.0).abs() < 1e-3, "{mils}");
+        let response = client.get("/rotator/position").dispatch().await;
+        assert_eq!(body(response).await["data"]["vertical"], json!(45.0));
+    }
+
+    #[rocket::async_test]
+    async fn repeated_idempotency_keys_only_move_once() {
+        let firmware = MockFirmware::new();
+        let client = client(&firmware, mock::config()).await;
+        let goto = |key: &'static str, vertical: f32| {
+            client
+                .post("/rotator/position")
+                .header(Header::new("Idempotency-Key", key))
+                .body(json!({"vertical": vertical, "horizontal": 0.0}).to_string())
+                .dispatch()
+        };
+
+        assert_eq!(goto("first", 30.0).await.status(), Status::Ok);
+        assert_eq!(goto("first", 30.0).await.status(), Status::Ok);
+        assert_eq!(firmware.received().iter().filter(|line| line.starts_with("DVER")).count(), 1);
+
+        // Even if the rotator has been moved since
+        firmware.lock().position.vertical = 10.0;
+        assert_eq!(goto("first", 30.0).await.status(), Status::Ok);
+        assert_eq!(firmware.lock().position.vertical, 10.0);
+
+        assert_eq!(goto("second", 30.0).await.status(), Status::Ok);
+        assert_eq!(firmware.lock().position.vertical, 30.0);
+    }
+
+    #[rocket::async_test]
+    async fn retries_while_the_first_request_is_moving_conflict() {
+        let firmware = MockFirmware::new();
+        firmware.lock().slew_per_read = Some(5.0);
+        let client = client(&firmware, mock::config()).await;
+        let goto = || {
+            client
+                .post("/rotator/position")
+                .header(Header::new("Idempotency-Key", "retried"))
+                .body(json!({"vertical": 30.0, "horizontal": 0.0}).to_string())
+                .dispatch()
+        };
+
+        let (first, retry) = tokio::join!(goto(), async {
+            tokio::time::sleep(Duration::from_millis(150)).await;
+            goto().await
+        });
+
+        assert_eq!(first.status(), Status::Ok);
+        assert_eq!(retry.status(), Status::Conflict);
+        assert_eq!(goto().await.status(), Status::Ok);
+        assert_eq!(firmware.received().iter().filter(|line| line.starts_with("DVER")).count(), 1);
+    }
+
+    #[rocket::async_test]
+    async fn presets_are_imported_and_exported_in_bulk() {
+        let firmware = MockFirmware::new();
+        let limits = PerAxis { vertical: Some(Limits { min: 0.0, max: 90.0 }), horizontal: None };
+        let client = client(&firmware, RotatorConfig { limits, ..mock::config() }).await;
+
+        let response = client.get("/rotator/presets").dispatch().await;
+        assert_eq!(body(response).await["data"], json!({}));
+
+        let presets = json!({
+            "park": {"vertical": 90.0, "horizontal": 0.0},
+            "pad": {"vertical": 10.5, "horizontal": 270.0},
+        });
+        let response = client.put("/rotator/presets").body(presets.to_string()).dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+
+        let response = client.get("/rotator/presets").dispatch().await;
+        assert_eq!(body(response).await["data"], presets);
+
+        // One preset outside the limits refuses the whole import
+        let outside = json!({
+            "pad": {"vertical": 10.5, "horizontal": 270.0},
+            "below": {"vertical": -5.0, "horizontal": 0.0},
+        });
+        let response = client.put("/rotator/presets").body(outside.to_string()).dispatch().await;
+        assert_eq!(response.status(), Status::BadRequest);
+        assert!(response.into_string().await.unwrap().contains("preset `below`"));
+
+        let response = client.get("/rotator/presets").dispatch().await;
+        assert_eq!(body(response).await["data"], presets);
+
+        let response = client.post("/rotator/goto/nowhere").dispatch().await;
+        assert_eq!(response.status(), Status::NotFound);
+        assert!(firmware.received().is_empty());
+    }
+
+    #[rocket::async_test]
+    async fn feedback_is_reported_if_the_firmware_has_it() {
+        let firmware = MockFirmware::new();
+        let client = client(&firmware, mock::config()).await;
+
+        let response = client.get("/rotator/feedback").dispatch().await;
+        assert_eq!(response.status(), Status::NotImplemented);
+
+        firmware.reply("GETF", "OK 512.5 1023");
+        let response = client.get("/rotator/feedback").dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(body(response).await["data"]["feedback"], json!({"vertical": 512.5, "horizontal": 1023.0}));
+    }
+
+    #[rocket::async_test]
+    async fn moves_over_the_cap_need_allow_large() {
+        let firmware = MockFirmware::new();
+        let config = RotatorConfig { max_move_degrees_per_request: Some(30.0), ..mock::config() };
+        let client = client(&firmware, config).await;
+        let goto = |uri: &'static str, vertical: f32| {
+            client.post(uri).body(json!({"vertical": vertical, "horizontal": 0.0}).to_string()).dispatch()
+        };
+
+        let response = goto("/rotator/position", 20.0).await;
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(firmware.lock().position.vertical, 20.0);
+
+        firmware.clear_received();
+        let response = goto("/rotator/position", 80.0).await;
+        assert_eq!(response.status(), Status::BadRequest);
+        let message = body(response).await["message"].as_str().unwrap().to_string();
+        assert!(message.contains("Vertical 60.0 degrees is more than the 30 allowed"), "{message}");
+        assert_eq!(firmware.commands(), ["GETP"]);
+
+        let response = goto("/rotator/position?allow_large=true", 80.0).await;
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(firmware.lock().position.vertical, 80.0);
+
+        // Streaming the move is no way around the cap
+        firmware.clear_received();
+        let response = goto("/rotator/position/stream", 20.0).await;
+        assert_eq!(response.status(), Status::BadRequest);
+        assert_eq!(firmware.commands(), ["GETP"]);
+
+        let response = goto("/rotator/position/stream?allow_large=true", 20.0).await;
+        assert_eq!(events(response).await.last().unwrap().0, "done");
+        assert_eq!(firmware.lock().position.vertical, 20.0);
+    }
+
+    #[rocket::async_test]
+    async fn the_last_exchange_is_only_shown_when_debugging() {
+        let firmware = MockFirmware::new();
+        let client = client(&firmware, mock::config()).await;
+
+        let response = client.get("/rotator/debug/last").dispatch().await;
+        assert_eq!(response.status(), Status::NotFound);
+    }
+
+    #[rocket::async_test]
+    async fn the_last_exchange_shows_the_raw_response() {
+        let firmware = MockFirmware::new();
+        let client = client(&firmware, RotatorConfig { debug: true, ..mock::config() }).await;
+
+        let response = client.get("/rotator/debug/last").dispatch().await;
+        assert_eq!(body(response).await["data"]["last"], Value::Null);
+
+        client.get("/rotator/position").dispatch().await;
+        let response = client.get("/rotator/debug/last").dispatch().await;
+        let last = body(response).await["data"]["last"].clone();
+        assert_eq!(last["command"], "GETP");
+        assert_eq!(last["raw"], json!(b"GETP\nOK 0 0\n"));
+        assert_eq!(last["outcome"], "ok");
+
+        firmware.reply("GETP", "ERR not calibrated");
+        client.get("/rotator/position").dispatch().await;
+        let response = client.get("/rotator/debug/last").dispatch().await;
+        let last = body(response).await["data"]["last"].clone();
+        assert_eq!(last["response"], "GETP\nERR not calibrated\n");
+        assert_eq!(last["outcome"], "rotator error: not calibrated");
+    }
+
+    #[rocket::async_test]
+    async fn halt_is_hard_and_stop_is_soft() {
+        let firmware = MockFirmware::new();
+        let client = client(&firmware, mock::config()).await;
+
+        let response = client.get("/rotator/stop").dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(firmware.received(), ["MOVC SV", "MOVC SH"]);
+
+        firmware.clear_received();
+        let response = client.get("/rotator/halt").dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(firmware.commands(), ["HALT", "GETP"]);
+    }
+
+    /// Polls a job until it finishes, returning it.
+    async fn finished_job(client: &Client, id: &Value) -> Value {
+        for _ in 0..100 {
+            let job = body(client.get(format!("/jobs/{id}")).dispatch().await).await["data"].clone();
+            if job["status"] != "running" {
+                return job;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+
+        panic!("job {id} never finished");
+    }
+
+    #[rocket::async_test]
+    async fn calibration_runs_as_a_job() {
+        let firmware = MockFirmware::new();
+        firmware.lock().delay = Duration::from_millis(200);
+        let client = client(&firmware, mock::config()).await;
+
+        let response = client.post("/rotator/calibrate/vertical").dispatch().await;
+        assert_eq!(response.status(), Status::Accepted);
+        let id = body(response).await["data"]["job"].clone();
+
+        let job = body(client.get(format!("/jobs/{id}")).dispatch().await).await["data"].clone();
+        assert_eq!(job["kind"], "calibrate_vertical");
+        assert_eq!(job["status"], "running");
+        assert_eq!(job["finished_at"], Value::Null);
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let job = body(client.get(format!("/jobs/{id}")).dispatch().await).await["data"].clone();
+        assert_eq!(job["status"], "running");
+        assert_eq!(job["progress"], "calibrating");
+
+        let job = finished_job(&client, &id).await;
+        assert_eq!(job["status"], "succeeded");
+        assert!(job["finished_at"].is_string());
+        assert_eq!(firmware.received(), ["CALV"]);
+    }
+
+    #[rocket::async_test]
+    async fn failed_calibration_jobs_report_why() {
+        let firmware = MockFirmware::new();
+        firmware.reply("CALH", "ERR not level");
+        let client = client(&firmware, mock::config()).await;
+
+        let response = client.post("/rotator/calibrate/horizontal").dispatch().await;
+        let id = body(response).await["data"]["job"].clone();
+
+        let job = finished_job(&client, &id).await;
+        assert_eq!(job["status"], "failed");
+        assert_eq!(job["error"], crate::rotator::Error::Firmware("not level".to_string()).to_string());
+
+        let response = client.get("/jobs/1000").dispatch().await;
+        assert_eq!(response.status(), Status::NotFound);
+    }
+
+    #[rocket::async_test]
+    async fn invalid_angles_are_rejected_before_reaching_the_rotator() {
+        let firmware = MockFirmware::new();
+        let client = client(&firmware, mock::config()).await;
+
+        for uri in [
+            "/rotator/dver?degrees=NaN",
+            "/rotator/dhor?degrees=inf",
+            "/rotator/dhor?degrees=-inf",
+            "/rotator/dver?degrees=200",
+            "/rotator/dhor?degrees=400",
+            "/rotator/dver?degrees=3300&units=mils",
+        ] {
+            let response = client.get(uri).dispatch().await;
+            assert_eq!(response.status(), Status::BadRequest, "{uri}");
+        }
+
+        let response = client
+            .post("/rotator/position")
+            .header(ContentType::JSON)
+            .body(r#"{"vertical": 1e9, "horizontal": 0}"#)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::BadRequest);
+        assert_eq!(body(response).await["message"], "vertical of 1000000000 degrees is outside the -90 to 180 allowed");
+
+        assert!(firmware.received().is_empty());
+    }
+
+    #[rocket::async_test]
+    async fn partial_moves_say_which_axis_failed() {
+        let firmware = MockFirmware::new();
+        firmware.reply("DHOR", "ERR limit switch");
+        let client = client(&firmware, mock::config()).await;
+
+        let response = client
+            .post("/rotator/position")
+            .header(ContentType::JSON)
+            .body(r#"{"vertical": 10, "horizontal": 20}"#)
+            .dispatch()
+            .await;
+
+        assert_eq!(response.status(), Status::InternalServerError);
+        assert_eq!(body(response).await["data"], json!({"vertical": null, "horizontal": "rotator error: limit switch"}));
+    }
+
+    #[rocket::async_test]
+    async fn moves_refused_on_both_axes_for_the_same_reason_are_not_server_errors() {
+        let limits = PerAxis {
+            vertical: Some(Limits { min: 0.0, max: 90.0 }),
+            horizontal: Some(Limits { min: 0.0, max: 90.0 }),
+        };
+        let firmware = MockFirmware::new();
+        let client = client(&firmware, RotatorConfig { limits, ..mock::config() }).await;
+
+        let response = client
+            .post("/rotator/position")
+            .header(ContentType::JSON)
+            .body(r#"{"vertical": 95, "horizontal": 95}"#)
+            .dispatch()
+            .await;
+
+        assert_eq!(response.status(), Status::BadRequest);
+        assert!(firmware.received().is_empty());
+    }
+
+    #[rocket::async_test]
+    async fn test_patterns_are_selected_by_name() {
+        let firmware = MockFirmware::new();
+        let client = client(&firmware, mock::config()).await;
+
+        let response = client.post("/rotator/test-pattern?pattern=figure_eight").dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(firmware.commands().into_iter().filter(|command| command == "DVER").count(), 17);
+
+        firmware.clear_received();
+        let response = client.post("/rotator/test-pattern?pattern=spiral").dispatch().await;
+        assert!(response.status().class().is_client_error(), "{}", response.status());
+        assert!(firmware.received().is_empty());
+    }
+
+    #[rocket::async_test]
+    async fn moves_need_the_rotator_armed_with_the_admin_token() {
+        let firmware = MockFirmware::new();
+        let config = RotatorConfig {
+            interlock: crate::rotator::interlock::InterlockConfig { enabled: true, disarm_after_ms: 60_000 },
+            ..mock::config()
+        };
+        let client = client(&firmware, config).await;
+
+        let response = client.get("/rotator/dver?degrees=10").dispatch().await;
+        assert_eq!(response.status(), Status::Conflict);
+        let response = client.get("/rotator/arm").dispatch().await;
+        assert_eq!(body(response).await["data"], json!({"armed": false, "interlock": true, "disarms_in_ms": null}));
+
+        let response = client.post("/rotator/arm").dispatch().await;
+        assert_eq!(response.status(), Status::Unauthorized);
+        let response = client
+            .post("/rotator/arm")
+            .header(Header::new("Authorization", format!("Bearer {TOKEN}")))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+
+        let response = client.get("/rotator/arm").dispatch().await;
+        let data = body(response).await["data"].clone();
+        assert_eq!(data["armed"], json!(true));
+        assert!(data["disarms_in_ms"].as_u64().unwrap() <= 60_000);
+        let response = client.get("/rotator/dver?degrees=10").dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(firmware.received(), ["DVER 10.000"]);
+
+        let response = client
+            .post("/rotator/disarm")
+            .header(Header::new("Authorization", format!("Bearer {TOKEN}")))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let response = client.get("/rotator/dver?degrees=20").dispatch().await;
+        assert_eq!(response.status(), Status::Conflict);
+    }
+
+    #[rocket::async_test]
+    async fn the_version_includes_the_build_if_given() {
+        let firmware = MockFirmware::new();
+        firmware.lock().version = "v1.4.0 a1b2c3d".to_string();
+        let client = client(&firmware, mock::config()).await;
+
+        let response = client.get("/rotator/version").dispatch().await;
+        assert_eq!(
+            body(response).await["data"],
+            json!({"version": "v1.4.0", "build": {"commit": "a1b2c3d", "date": null}}),
+        );
+
+        firmware.lock().version = "v1.4.0".to_string();
+        let response = client.get("/rotator/version?refresh=true").dispatch().await;
+        assert_eq!(body(response).await["data"], json!({"version": "v1.4.0", "build": null}));
+    }
+
+    #[rocket::async_test]
+    async fn serial_faults_are_classified_in_the_response() {
+        let firmware = MockFirmware::new();
+        firmware.lock().unplug_on = Some("GETP".to_string());
+        let client = client(&firmware, mock::config()).await;
+
+        let response = client.get("/rotator/position").dispatch().await;
+
+        assert_eq!(response.status(), Status::InternalServerError);
+        assert_eq!(body(response).await["data"], json!({"serial_fault": "not_found"}));
+    }
+
+    #[rocket::async_test]
+    async fn missing_values_say_which_command_expected_them() {
+        let firmware = MockFirmware::new();
+        firmware.reply("GETP", "OK");
+        let client = client(&firmware, mock::config()).await;
+
+        let response = client.get("/rotator/position").dispatch().await;
+
+        assert_eq!(response.status(), Status::InternalServerError);
+        let message = body(response).await["message"].as_str().unwrap().to_string();
+        assert!(message.starts_with("GETP expected a value"), "{message}");
+    }
+
+    #[rocket::async_test]
+    async fn presets_are_moved_to_at_the_default_slew_rate() {
+        let firmware = MockFirmware::new();
+        firmware.reply("GETS", "OK 5.0");
+        firmware.reply("SETS", "OK");
+        let client = client(&firmware, RotatorConfig { default_slew_rate: Some(3.0), ..mock::config() }).await;
+
+        let presets = json!({"pad": {"vertical": 10.5, "horizontal": 90.0}});
+        client.put("/rotator/presets").body(presets.to_string()).dispatch().await;
+        let response = client.post("/rotator/goto/pad").dispatch().await;
+
+        assert_eq!(response.status(), Status::Ok);
+        let sent: Vec<_> = firmware.received().into_iter().filter(|line| line != "GETP").collect();
+        assert_eq!(sent, ["GETS", "SETS 3.000", "DVER 10.500", "DHOR -90.000", "SETS 5.000"]);
+    }
+
+    #[rocket::async_test]
+    async fn moves_wait_out_the_cooldown_after_a_halt_unless_resumed() {
+        let firmware = MockFirmware::new();
+        let client = client(&firmware, RotatorConfig { halt_cooldown_ms: Some(60_000), ..mock::config() }).await;
+
+        client.get("/rotator/halt").dispatch().await;
+        let response = client.get("/rotator/dver?degrees=10").dispatch().await;
+        assert_eq!(response.status(), Status::Conflict);
+
+        let response = client.post("/rotator/resume").dispatch().await;
+        assert_eq!(response.status(), Status::Unauthorized);
+        let response = client
+            .post("/rotator/resume")
+            .header(Header::new("Authorization", format!("Bearer {TOKEN}")))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+
+        let response = client.get("/rotator/dver?degrees=10").dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(firmware.received().last().unwrap(), "DVER 10.000");
+    }
+
+    #[rocket::async_test]
+    async fn the_deadband_is_read_and_set_per_axis() {
+        let firmware = MockFirmware::new();
+        firmware.reply("GETD", "OK 0.25 1.5");
+        firmware.reply("SETD", "OK");
+        let client = client(&firmware, mock::config()).await;
+
+        let response = client.get("/rotator/deadband").dispatch().await;
+        assert_eq!(body(response).await["data"], json!({"deadband": {"vertical": 0.25, "horizontal": 1.5}}));
+
+        let response = client.post("/rotator/deadband/horizontal?degrees=0.75").dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(firmware.received(), ["GETD", "SETD H 0.750"]);
+    }
+}
@@ -0,0 +1,232 @@
+//! Errors produced while communicating with the rotator.
+
+use core::fmt::Display;
+use std::{io, time::Duration};
+
+use serde::Serialize;
+
+use super::{Axis, Command};
+
+/// An error from a [`Rotator`](super::Rotator) operation.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// Reading from or writing to the port failed.
+    IOError(io::Error),
+    /// The serial port itself reported an error.
+    SerialError(serialport::Error),
+    /// The response could not be understood.
+    InvalidResponse,
+    /// The rotator's echo of a command did not match what was sent, e.g.
+    /// because of a line terminator mismatch or the link being out of sync.
+    EchoMismatch { expected: String, got: String },
+    /// A value was expected in the response to this command, but none was
+    /// received.
+    ExpectedValue(Command),
+    /// The response had a different number of values than the command
+    /// returns.
+    WrongValueCount { expected: usize, got: usize },
+    /// The rotator responded with `ERR` and this message.
+    Firmware(String),
+    /// An operation did not complete in time.
+    Timeout,
+    /// The firmware rejected an optional command it does not implement.
+    Unsupported(Command),
+    /// A command was sent while another was still waiting for its response.
+    Busy,
+    /// A requested value was outside of the configured range.
+    OutOfRange { requested: f64, min: f64, max: f64 },
+    /// An axis stopped moving before reaching where it was sent.
+    Stalled(Axis),
+    /// An axis was sent somewhere while its motor is powered off.
+    MotorDisabled(Axis),
+    /// The rotator was told to move while the interlock is on and it isn't
+    /// armed.
+    NotArmed,
+    /// The rotator was told to move within `halt_cooldown_ms` of being
+    /// halted, with this much of the cooldown left.
+    HaltCooldown(Duration),
+    /// A move which was being waited on was cut short by a halt or stop.
+    Interrupted,
+    /// The port failed mid-command, e.g. because the device was unplugged.
+    /// Every command fails with this until the rotator is reconnected.
+    Disconnected,
+    /// A move on both axes failed on at least one, with the error for each
+    /// axis which failed. An axis without one was sent successfully, and may
+    /// be moving.
+    PartialMove {
+        vertical: Option<Box<Error>>,
+        horizontal: Option<Box<Error>>,
+    },
+}
+
+/// What kind of problem a serial port error is, which decides what an
+/// operator should do about it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SerialFault {
+    /// The port is there but can't be opened, usually because the server's
+    /// user isn't allowed to (e.g. isn't in the `dialout` group).
+    Permission,
+    /// The device isn't there, e.g. because it was unplugged.
+    NotFound,
+    /// The port rejected a setting, such as the baud rate.
+    InvalidSetting,
+    /// Reading or writing failed. Often transient, so worth retrying.
+    Io,
+    Unknown,
+}
+
+impl SerialFault {
+    pub const fn from_io(kind: io::ErrorKind) -> Self {
+        match kind {
+            io::ErrorKind::PermissionDenied => Self::Permission,
+            io::ErrorKind::NotFound | io::ErrorKind::BrokenPipe | io::ErrorKind::NotConnected => Self::NotFound,
+            io::ErrorKind::InvalidInput => Self::InvalidSetting,
+            _ => Self::Io,
+        }
+    }
+
+    pub const fn from_serial(error: &serialport::Error) -> Self {
+        match error.kind {
+            serialport::ErrorKind::NoDevice => Self::NotFound,
+            serialport::ErrorKind::InvalidInput => Self::InvalidSetting,
+            serialport::ErrorKind::Io(kind) => Self::from_io(kind),
+            serialport::ErrorKind::Unknown => Self::Unknown,
+        }
+    }
+
+    /// What to do about it.
+    pub const fn advice(self) -> &'static str {
+        match self {
+            Self::Permission => "check the permissions on the port",
+            Self::NotFound => "check the rotator is plugged in",
+            Self::InvalidSetting => "check the serial settings",
+            Self::Io => "retry, and replug the rotator if it keeps happening",
+            Self::Unknown => "check the logs",
+        }
+    }
+}
+
+impl Error {
+    /// What kind of problem this is, if it came from the serial port.
+    pub fn serial_fault(&self) -> Option<SerialFault> {
+        match self {
+            Self::IOError(e) => Some(SerialFault::from_io(e.kind())),
+            Self::SerialError(e) => Some(SerialFault::from_serial(e)),
+            Self::Disconnected => Some(SerialFault::NotFound),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::IOError(e) => write!(f, "i/o error: {e} ({})", SerialFault::from_io(e.kind()).advice()),
+            Self::SerialError(e) => write!(f, "serial error: {e} ({})", SerialFault::from_serial(e).advice()),
+            Self::InvalidResponse => write!(f, "invalid response"),
+            Self::EchoMismatch { expected, got } => {
+                write!(f, "expected the rotator to echo {expected:?}, but got {got:?}")
+            }
+            Self::ExpectedValue(command) => write!(f, "{command} expected a value in the response, but none was received"),
+            Self::WrongValueCount { expected, got } => {
+                write!(f, "expected {expected} values in the response, but got {got}")
+            }
+            Self::Firmware(m) => write!(f, "rotator error: {m}"),
+            Self::Timeout => write!(f, "timed out"),
+            Self::Unsupported(c) => write!(f, "{c} is not supported by the rotator firmware"),
+            Self::Busy => write!(f, "another command is already in progress"),
+            Self::OutOfRange { requested, min, max } => {
+                write!(f, "{requested} is out of range, must be between {min} and {max}")
+            }
+            Self::Stalled(Axis::Vertical) => write!(f, "the vertical axis stalled"),
+            Self::Stalled(Axis::Horizontal) => write!(f, "the horizontal axis stalled"),
+            Self::MotorDisabled(Axis::Vertical) => write!(f, "the vertical motor is disabled"),
+            Self::MotorDisabled(Axis::Horizontal) => write!(f, "the horizontal motor is disabled"),
+            Self::NotArmed => write!(f, "the rotator is not armed"),
+            Self::HaltCooldown(remaining) => write!(
+                f,
+                "the rotator was just halted, and won't move for another {}ms unless resumed",
+                remaining.as_millis()
+            ),
+            Self::Interrupted => write!(f, "the move was interrupted by a halt or stop"),
+            Self::Disconnected => write!(f, "the rotator is disconnected"),
+            Self::PartialMove { vertical, horizontal } => match (vertical, horizontal) {
+                (Some(v), Some(h)) => write!(f, "both axes failed to move: vertical: {v}; horizontal: {h}"),
+                (Some(v), None) => write!(f, "the vertical axis failed to move, but the horizontal axis was sent: {v}"),
+                (None, Some(h)) => write!(f, "the horizontal axis failed to move, but the vertical axis was sent: {h}"),
+                (None, None) => write!(f, "neither axis failed to move"),
+            },
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(value: io::Error) -> Self {
+        Self::IOError(value)
+    }
+}
+
+impl From<serialport::Error> for Error {
+    fn from(value: serialport::Error) -> Self {
+        Self::SerialError(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn serial(kind: serialport::ErrorKind) -> Error {
+        Error::SerialError(serialport::Error::new(kind, "test"))
+    }
+
+    #[test]
+    fn serial_errors_are_classified_by_kind() {
+        for (kind, fault) in [
+            (serialport::ErrorKind::NoDevice, SerialFault::NotFound),
+            (serialport::ErrorKind::InvalidInput, SerialFault::InvalidSetting),
+            (serialport::ErrorKind::Io(io::ErrorKind::PermissionDenied), SerialFault::Permission),
+            (serialport::ErrorKind::Io(io::ErrorKind::NotFound), SerialFault::NotFound),
+            (serialport::ErrorKind::Io(io::ErrorKind::TimedOut), SerialFault::Io),
+            (serialport::ErrorKind::Unknown, SerialFault::Unknown),
+        ] {
+            assert_eq!(serial(kind).serial_fault(), Some(fault), "{kind:?}");
+        }
+    }
+
+    #[test]
+    fn io_errors_are_classified_by_kind() {
+        for (kind, fault) in [
+            (io::ErrorKind::PermissionDenied, SerialFault::Permission),
+            (io::ErrorKind::BrokenPipe, SerialFault::NotFound),
+            (io::ErrorKind::NotConnected, SerialFault::NotFound),
+            (io::ErrorKind::InvalidInput, SerialFault::InvalidSetting),
+            (io::ErrorKind::Interrupted, SerialFault::Io),
+        ] {
+            assert_eq!(Error::IOError(io::Error::from(kind)).serial_fault(), Some(fault), "{kind:?}");
+        }
+
+        assert_eq!(Error::Disconnected.serial_fault(), Some(SerialFault::NotFound));
+        assert_eq!(Error::Timeout.serial_fault(), None);
+    }
+
+    #[test]
+    fn serial_errors_say_what_to_do() {
+        let error = serial(serialport::ErrorKind::Io(io::ErrorKind::PermissionDenied));
+
+        assert_eq!(error.to_string(), "serial error: test (check the permissions on the port)");
+        assert_eq!(serde_json::to_value(SerialFault::InvalidSetting).unwrap(), "invalid_setting");
+    }
+
+    #[test]
+    fn missing_values_name_the_command_which_expected_them() {
+        assert_eq!(
+            Error::ExpectedValue(Command::GetPosition).to_string(),
+            "GETP expected a value in the response, but none was received",
+        );
+    }
+}
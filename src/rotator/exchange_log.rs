@@ -0,0 +1,213 @@
+//! A durable record of every exchange with the rotator, written as JSON
+//! Lines to a file which is rotated once it grows too large.
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write as _},
+    path::PathBuf,
+};
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use super::history::Exchange;
+
+/// Settings for the exchange log.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ExchangeLogConfig {
+    /// The file to log to. Nothing is logged if unset.
+    pub path: Option<String>,
+    /// How large the file may grow before it is rotated.
+    pub max_bytes: u64,
+    /// How many rotated files to keep, as `<path>.1` (newest) to `<path>.<keep>`.
+    pub keep: u32,
+}
+
+impl Default for ExchangeLogConfig {
+    fn default() -> Self {
+        Self {
+            path: None,
+            max_bytes: 10 * 1024 * 1024,
+            keep: 5,
+        }
+    }
+}
+
+/// An open exchange log.
+#[derive(Debug)]
+pub struct ExchangeLog {
+    path: PathBuf,
+    max_bytes: u64,
+    keep: u32,
+    file: File,
+    /// Bytes in the current file.
+    written: u64,
+}
+
+impl ExchangeLog {
+    /// Open the log configured in `config`, appending to the file if it
+    /// already exists, or `None` if there is no log configured.
+    ///
+    /// # Errors
+    /// Errors if the file can't be opened.
+    pub fn open(config: &ExchangeLogConfig) -> Result<Option<Self>, io::Error> {
+        let Some(path) = &config.path else {
+            return Ok(None);
+        };
+        let path = PathBuf::from(path);
+
+        let file = Self::open_file(&path)?;
+        let written = file.metadata()?.len();
+
+        Ok(Some(Self {
+            path,
+            max_bytes: config.max_bytes,
+            keep: config.keep,
+            file,
+            written,
+        }))
+    }
+
+    fn open_file(path: &PathBuf) -> Result<File, io::Error> {
+        OpenOptions::new().create(true).append(true).open(path)
+    }
+
+    /// Append an exchange. Failures are logged rather than returned, so a full
+    /// disk doesn't stop the rotator being controlled.
+    pub fn write(&mut self, exchange: &Exchange) {
+        if let Err(e) = self.try_write(exchange) {
+            warn!("Failed to write to the exchange log {}: {e}", self.path.display());
+        }
+    }
+
+    fn try_write(&mut self, exchange: &Exchange) -> Result<(), io::Error> {
+        let mut line = serde_json::to_vec(exchange)?;
+        line.push(b'\n');
+
+        if self.written > 0 && self.written + line.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+
+        self.file.write_all(&line)?;
+        self.written += line.len() as u64;
+
+        Ok(())
+    }
+
+    /// Shift each rotated file along one, dropping the oldest, and start a
+    /// new file.
+    fn rotate(&mut self) -> Result<(), io::Error> {
+        let rotated = |n: u32| {
+            let mut path = self.path.clone().into_os_string();
+            path.push(format!(".{n}"));
+            PathBuf::from(path)
+        };
+
+        if self.keep == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            for n in (1..self.keep).rev() {
+                match fs::rename(rotated(n), rotated(n + 1)) {
+                    Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                    _ => {}
+                }
+            }
+            fs::rename(&self.path, rotated(1))?;
+        }
+
+        self.file = Self::open_file(&self.path)?;
+        self.written = 0;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::{super::{config::RotatorConfig, mock::{self, MockFirmware}}, *};
+
+    /// An empty directory to log into.
+    fn log_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("archerd-exchanges-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        dir
+    }
+
+    fn exchange(command: &str) -> Exchange {
+        Exchange {
+            timestamp: "2026-01-01T00:00:00+00:00".to_string(),
+            command: command.to_string(),
+            response: "OK".to_string(),
+            first_byte_ms: Some(1.0),
+            total_ms: 2.0,
+            outcome: "ok".to_string(),
+        }
+    }
+
+    fn lines(path: &PathBuf) -> Vec<String> {
+        fs::read_to_string(path).unwrap().lines().map(str::to_string).collect()
+    }
+
+    #[rocket::async_test]
+    async fn each_exchange_is_a_json_line() {
+        let dir = log_dir("lines");
+        let path = dir.join("exchanges.jsonl");
+        let firmware = MockFirmware::new();
+        firmware.reply("CALV", "ERR not allowed");
+        let config = RotatorConfig {
+            exchange_log: ExchangeLogConfig { path: Some(path.to_string_lossy().into_owned()), ..ExchangeLogConfig::default() },
+            ..mock::config()
+        };
+        let mut rotator = firmware.rotator(config);
+
+        rotator.position().await.unwrap();
+        rotator.calibrate_vertical(false).await.unwrap_err();
+
+        let lines: Vec<Value> = lines(&path).iter().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["command"], "GETP");
+        assert_eq!(lines[0]["response"], "GETP\nOK 0 0\n");
+        assert_eq!(lines[0]["outcome"], "ok");
+        assert!(lines[0]["total_ms"].is_f64() && lines[0]["timestamp"].is_string());
+        assert_eq!(lines[1]["command"], "CALV");
+        assert_eq!(lines[1]["outcome"], "rotator error: not allowed");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn the_log_is_rotated_once_it_is_full() {
+        let dir = log_dir("rotate");
+        let path = dir.join("exchanges.jsonl");
+        let line = serde_json::to_vec(&exchange("GETP")).unwrap().len() as u64 + 1;
+        let config = ExchangeLogConfig {
+            path: Some(path.to_string_lossy().into_owned()),
+            max_bytes: 2 * line,
+            keep: 2,
+        };
+        let mut log = ExchangeLog::open(&config).unwrap().unwrap();
+        let rotated = |n: u32| dir.join(format!("exchanges.jsonl.{n}"));
+
+        for command in ["GETP", "GETP", "GETV"] {
+            log.write(&exchange(command));
+        }
+        assert_eq!(lines(&rotated(1)).len(), 2);
+        assert_eq!(lines(&path).len(), 1);
+        assert!(lines(&path)[0].contains("GETV"));
+
+        // Only `keep` rotated files are kept
+        for command in ["GETP", "GETP", "GETP", "GETP"] {
+            log.write(&exchange(command));
+        }
+        assert!(rotated(2).exists());
+        assert!(!rotated(3).exists());
+        assert_eq!(lines(&path).len(), 1);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,117 @@
+//! A maintenance routine to keep a long-idle mount from seizing.
+
+use std::time::Duration;
+
+use rocket::tokio::sync::Mutex;
+
+use super::{Axis, Error, Position, Rotator};
+
+impl Rotator {
+    /// Sweeps each axis to either side of its current position by the
+    /// configured `sweep_degrees`, bounded by the axis limits, and then
+    /// returns to where it started.
+    ///
+    /// The rotator is only locked for each step, so it can be halted partway.
+    /// A return to the starting position is attempted if the sweep fails,
+    /// unless it was halted or stopped.
+    pub async fn exercise(rotator: &Mutex<Self>) -> Result<(), Error> {
+        let (start, stops, timeout) = {
+            let mut rotator = rotator.lock().await;
+            let (vertical, horizontal) = rotator.position().await?;
+            let timeout = Duration::from_millis(rotator.config.exercise.timeout_ms);
+
+            (Position { vertical, horizontal }, rotator.stops(), timeout)
+        };
+
+        let result = Self::sweep(rotator, start, stops, timeout).await;
+        let restored = Self::goto_step(rotator, stops, start, timeout).await;
+
+        result.and(restored)
+    }
+
+    async fn sweep(rotator: &Mutex<Self>, start: Position, stops: u64, timeout: Duration) -> Result<(), Error> {
+        let sweep = rotator.lock().await.config.exercise.sweep_degrees;
+
+        for axis in [Axis::Vertical, Axis::Horizontal] {
+            let origin = start.get(axis);
+
+            for degrees in [origin + sweep, origin - sweep] {
+                let degrees = rotator.lock().await.clamp_to_limits(axis, degrees);
+
+                Self::goto_step(rotator, stops, start.with(axis, degrees), timeout).await?;
+            }
+
+            Self::goto_step(rotator, stops, start, timeout).await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rocket::tokio::{self, time::sleep};
+
+    use super::{
+        super::{
+            config::{ExerciseConfig, Limits, PerAxis, RotatorConfig},
+            mock::{self, MockFirmware},
+        },
+        *,
+    };
+
+    fn config() -> RotatorConfig {
+        RotatorConfig {
+            exercise: ExerciseConfig { sweep_degrees: 20.0, timeout_ms: 5_000, ..ExerciseConfig::default() },
+            limits: PerAxis { vertical: Some(Limits { min: 0.0, max: 90.0 }), horizontal: None },
+            ..mock::config()
+        }
+    }
+
+    /// The argument of each command with this code which was received.
+    fn sent(firmware: &MockFirmware, code: &str) -> Vec<f32> {
+        firmware
+            .received()
+            .iter()
+            .filter_map(|line| line.strip_prefix(code))
+            .map(|arg| arg.trim().parse().unwrap())
+            .collect()
+    }
+
+    #[rocket::async_test]
+    async fn sweeps_within_the_limits_and_returns_to_the_start() {
+        let firmware = MockFirmware::new();
+        firmware.lock().position.vertical = 80.0;
+        let rotator = firmware.shared(config());
+
+        Rotator::exercise(&rotator).await.unwrap();
+
+        let vertical = sent(&firmware, "DVER");
+        assert!(vertical.iter().all(|&degrees| (0.0..=90.0).contains(&degrees)), "{vertical:?}");
+        // Up to the limit rather than past it, then down, then back
+        assert_eq!(vertical[..3], [90.0, 60.0, 80.0]);
+        let horizontal = sent(&firmware, "DHOR");
+        assert!(horizontal.contains(&20.0) && horizontal.contains(&-20.0), "{horizontal:?}");
+
+        let position = firmware.lock().position;
+        assert_eq!(position, PerAxis { vertical: 80.0, horizontal: 0.0 });
+    }
+
+    #[rocket::async_test]
+    async fn a_halt_stops_the_rest_of_the_exercise() {
+        let firmware = MockFirmware::new();
+        firmware.lock().position.vertical = 80.0;
+        firmware.lock().slew_per_read = Some(1.0);
+        let rotator = firmware.shared(config());
+
+        let (result, halted) = tokio::join!(Rotator::exercise(&rotator), async {
+            sleep(Duration::from_millis(150)).await;
+            rotator.lock().await.halt().await
+        });
+
+        halted.unwrap();
+        assert!(matches!(result, Err(Error::Interrupted)), "{result:?}");
+        // Nor does it try to return to the start
+        assert_eq!(sent(&firmware, "DVER"), [90.0]);
+    }
+}
@@ -0,0 +1,145 @@
+//! An optional second positioning pass, correcting the small residual error
+//! left by mechanical overshoot after a coarse move.
+
+use std::time::Duration;
+
+use log::warn;
+use rocket::tokio::{self, sync::Mutex};
+
+use super::{
+    Axis, Error, Position, Rotator,
+    frame::{AzimuthConvention, Frame},
+};
+
+/// How far an axis is from `target`, in the firmware's frame. Azimuths which
+/// wrap are taken the short way round, so the two sides of north aren't
+/// treated as a whole turn apart.
+fn axis_error(frame: &Frame, axis: Axis, target: f32, actual: f32) -> f32 {
+    let error = frame.to_rotator(axis, target) - frame.to_rotator(axis, actual);
+
+    match axis {
+        Axis::Horizontal if frame.azimuth_convention != AzimuthConvention::Continuous => {
+            AzimuthConvention::Signed.wrap(error)
+        }
+        _ => error,
+    }
+}
+
+impl Rotator {
+    /// Nudges each axis towards `target` with small step moves until it is
+    /// within `fine.tolerance`, giving up after `fine.max_iterations`. Axes
+    /// without a `fine.steps_per_degree` are left alone.
+    ///
+    /// Running out of iterations is only logged, as the rotator is still
+    /// within the coarse tolerance.
+    ///
+    /// The rotator is unlocked between iterations, and this stops with
+    /// [`Error::Interrupted`] if it is halted or stopped after [`Self::stops`]
+    /// was `stops`.
+    pub async fn refine(rotator: &Mutex<Self>, target: Position, stops: u64) -> Result<(), Error> {
+        let fine = rotator.lock().await.config.fine.clone();
+
+        for _ in 0..fine.max_iterations {
+            let mut rotator = rotator.lock().await;
+            rotator.ensure_not_stopped(stops)?;
+            let (vertical, horizontal) = rotator.position().await?;
+            let actual = Position { vertical, horizontal };
+
+            let mut corrected = false;
+            for axis in [Axis::Vertical, Axis::Horizontal] {
+                let Some(steps_per_degree) = *fine.steps_per_degree.get(axis) else {
+                    continue;
+                };
+                // Steps are in the firmware's frame, so the error is too
+                let error = axis_error(&rotator.config.frame, axis, target.get(axis), actual.get(axis));
+                if error.abs() <= fine.tolerance {
+                    continue;
+                }
+
+                let steps = (error * steps_per_degree).round() as i32;
+                if steps != 0 {
+                    rotator.move_steps(axis, steps).await?;
+                    corrected = true;
+                }
+            }
+
+            if !corrected {
+                return Ok(());
+            }
+
+            let settle_time = Duration::from_millis(rotator.config.settle_time_ms);
+            drop(rotator);
+
+            tokio::time::sleep(settle_time).await;
+        }
+
+        warn!("Fine positioning did not converge after {} iterations", fine.max_iterations);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::{config::{FineConfig, PerAxis, RotatorConfig}, mock::{self, MockFirmware}}, *};
+
+    fn config(steps_per_degree: PerAxis<Option<f32>>) -> RotatorConfig {
+        RotatorConfig {
+            fine: FineConfig { enabled: true, steps_per_degree, ..FineConfig::default() },
+            ..mock::config()
+        }
+    }
+
+    #[test]
+    fn azimuth_errors_are_taken_the_short_way_round() {
+        let frame = Frame::default();
+
+        assert!((axis_error(&frame, Axis::Horizontal, 359.9, 0.1) - 0.2).abs() < 1e-3);
+        assert!((axis_error(&frame, Axis::Vertical, 10.0, 10.3) + 0.3).abs() < 1e-3);
+    }
+
+    #[rocket::async_test]
+    async fn residual_errors_are_stepped_out_within_the_cap() {
+        let firmware = MockFirmware::new();
+        // Azimuth 0.2, as the horizontal axis is inverted
+        firmware.lock().position = PerAxis { vertical: 10.3, horizontal: -0.2 };
+        let rotator = firmware.shared(config(PerAxis { vertical: Some(10.0), horizontal: Some(10.0) }));
+
+        let target = Position { vertical: 10.0, horizontal: 359.9 };
+        Rotator::refine(&rotator, target, 0).await.unwrap();
+
+        assert_eq!(firmware.commands(), ["GETP", "MOVV", "MOVH", "GETP"]);
+        assert_eq!(&firmware.received()[1..3], ["MOVV -3", "MOVH 3"]);
+    }
+
+    #[rocket::async_test]
+    async fn an_axis_which_keeps_overshooting_gives_up_after_the_cap() {
+        let firmware = MockFirmware::new();
+        firmware.lock().position.vertical = 10.3;
+        // Twice the real steps per degree, so every correction overshoots
+        let rotator = firmware.shared(config(PerAxis { vertical: Some(20.0), horizontal: None }));
+
+        let target = Position { vertical: 10.0, horizontal: 0.0 };
+        Rotator::refine(&rotator, target, 0).await.unwrap();
+
+        let moves: Vec<_> = firmware.received().into_iter().filter(|line| line.starts_with("MOVV")).collect();
+        assert_eq!(moves, ["MOVV -6", "MOVV 6", "MOVV -6"]);
+        assert!((firmware.lock().position.vertical - 9.7).abs() < 1e-3);
+    }
+
+    #[rocket::async_test]
+    async fn halting_interrupts_the_fine_pass() {
+        let firmware = MockFirmware::new();
+        firmware.lock().position.vertical = 10.3;
+        let rotator = firmware.shared(config(PerAxis { vertical: Some(10.0), horizontal: None }));
+        let stops = rotator.lock().await.stops();
+        rotator.lock().await.halt().await.unwrap();
+        firmware.clear_received();
+
+        let target = Position { vertical: 10.0, horizontal: 0.0 };
+        let error = Rotator::refine(&rotator, target, stops).await.unwrap_err();
+
+        assert!(matches!(error, Error::Interrupted), "{error:?}");
+        assert!(firmware.received().is_empty());
+    }
+}
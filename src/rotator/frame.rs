@@ -0,0 +1,276 @@
+//! Conversions between astronomical azimuth/elevation and the rotator's own
+//! frame of reference.
+//!
+//! Everything outside of the [`Rotator`](super::Rotator) works in azimuth
+//! (degrees clockwise from north) and elevation (degrees above the horizon).
+//! The firmware instead reports its vertical and horizontal axes relative to
+//! wherever it was calibrated, and its horizontal axis may turn the opposite
+//! way. All of that sign and offset handling lives here, along with the
+//! range positions are reported in.
+
+use serde::{Deserialize, Serialize};
+
+use super::{Axis, Error, config::Limits};
+
+/// The range azimuths are reported in, and accepted in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AzimuthConvention {
+    /// Whatever the offset rotator reading is, without wrapping. May go past
+    /// a full turn if the mount does.
+    #[default]
+    Continuous,
+    /// `0` to `360`, clockwise from north.
+    Compass,
+    /// `-180` to `180`, east of north being positive.
+    Signed,
+}
+
+impl AzimuthConvention {
+    /// The lowest and highest azimuth in this convention, if it is bounded.
+    pub const fn range(self) -> Option<(f32, f32)> {
+        match self {
+            Self::Continuous => None,
+            Self::Compass => Some((0.0, 360.0)),
+            Self::Signed => Some((-180.0, 180.0)),
+        }
+    }
+
+    /// Wrap an azimuth into this convention.
+    pub fn wrap(self, azimuth: f32) -> f32 {
+        match self {
+            Self::Continuous => azimuth,
+            Self::Compass => azimuth.rem_euclid(360.0),
+            Self::Signed => {
+                let wrapped = azimuth.rem_euclid(360.0);
+                if wrapped > 180.0 { wrapped - 360.0 } else { wrapped }
+            }
+        }
+    }
+
+    /// How far `azimuth` is past `other`. Bounded conventions go the short
+    /// way round, so either side of the seam is close, while a continuous
+    /// azimuth a full turn away really is, as the mount has to turn back.
+    pub fn difference(self, azimuth: f32, other: f32) -> f32 {
+        match self {
+            Self::Continuous => azimuth - other,
+            Self::Compass | Self::Signed => Self::Signed.wrap(azimuth - other),
+        }
+    }
+}
+
+/// The range elevations are reported in, and accepted in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ElevationConvention {
+    /// Whatever the offset rotator reading is, which may be negative or past
+    /// the zenith.
+    #[default]
+    Signed,
+    /// `0` (the horizon) to `90` (the zenith). Readings outside of this are
+    /// clamped to it.
+    Horizon,
+}
+
+impl ElevationConvention {
+    /// The lowest and highest elevation in this convention, if it is bounded.
+    pub const fn range(self) -> Option<(f32, f32)> {
+        match self {
+            Self::Signed => None,
+            Self::Horizon => Some((0.0, 90.0)),
+        }
+    }
+
+    /// Bring an elevation into this convention.
+    pub fn wrap(self, elevation: f32) -> f32 {
+        match self {
+            Self::Signed => elevation,
+            Self::Horizon => elevation.clamp(0.0, 90.0),
+        }
+    }
+}
+
+/// How the rotator's frame relates to azimuth/elevation.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Frame {
+    /// The azimuth the horizontal axis points at when it reads zero.
+    pub azimuth_offset: f32,
+    /// The elevation the vertical axis points at when it reads zero.
+    pub elevation_offset: f32,
+    /// Whether increasing horizontal readings turn counterclockwise.
+    pub invert_horizontal: bool,
+    pub azimuth_convention: AzimuthConvention,
+    pub elevation_convention: ElevationConvention,
+}
+
+impl Default for Frame {
+    fn default() -> Self {
+        Self {
+            azimuth_offset: 0.0,
+            elevation_offset: 0.0,
+            invert_horizontal: true,
+            azimuth_convention: AzimuthConvention::default(),
+            elevation_convention: ElevationConvention::default(),
+        }
+    }
+}
+
+impl Frame {
+    /// Check that an elevation (for [`Axis::Vertical`]) or azimuth (for
+    /// [`Axis::Horizontal`]) is within the configured convention.
+    ///
+    /// # Errors
+    /// Returns [`Error::OutOfRange`] if it is not.
+    pub fn check(&self, axis: Axis, degrees: f32) -> Result<(), Error> {
+        let range = match axis {
+            Axis::Vertical => self.elevation_convention.range(),
+            Axis::Horizontal => self.azimuth_convention.range(),
+        };
+
+        match range {
+            Some((min, max)) if !(min..=max).contains(&degrees) => Err(Error::OutOfRange {
+                requested: degrees.into(),
+                min: min.into(),
+                max: max.into(),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Convert an elevation (for [`Axis::Vertical`]) or azimuth (for
+    /// [`Axis::Horizontal`]) into the rotator's reading for that axis.
+    pub fn to_rotator(&self, axis: Axis, degrees: f32) -> f32 {
+        match axis {
+            Axis::Vertical => degrees - self.elevation_offset,
+            Axis::Horizontal => self.horizontal_sign() * (degrees - self.azimuth_offset),
+        }
+    }
+
+    /// The inverse of [`Self::to_rotator`], in the configured convention.
+    pub fn from_rotator(&self, axis: Axis, reading: f32) -> f32 {
+        let degrees = self.from_rotator_unwrapped(axis, reading);
+
+        match axis {
+            Axis::Vertical => self.elevation_convention.wrap(degrees),
+            Axis::Horizontal => self.azimuth_convention.wrap(degrees),
+        }
+    }
+
+    /// Like [`Self::from_rotator`], but without wrapping into the convention.
+    pub fn from_rotator_unwrapped(&self, axis: Axis, reading: f32) -> f32 {
+        match axis {
+            Axis::Vertical => reading + self.elevation_offset,
+            Axis::Horizontal => self.horizontal_sign() * reading + self.azimuth_offset,
+        }
+    }
+
+    /// Convert a range of rotator readings into elevation or azimuth limits.
+    /// The ends may swap over if the axis is inverted.
+    pub fn limits_from_rotator(&self, axis: Axis, from: f32, to: f32) -> Limits {
+        let from = self.from_rotator_unwrapped(axis, from);
+        let to = self.from_rotator_unwrapped(axis, to);
+
+        Limits { min: from.min(to), max: from.max(to) }
+    }
+
+    /// Convert an azimuth and elevation into the rotator's `(vertical, horizontal)`.
+    pub fn az_el_to_rotator(&self, azimuth: f32, elevation: f32) -> (f32, f32) {
+        (
+            self.to_rotator(Axis::Vertical, elevation),
+            self.to_rotator(Axis::Horizontal, azimuth),
+        )
+    }
+
+    /// Convert the rotator's `(vertical, horizontal)` into `(azimuth, elevation)`.
+    pub fn rotator_to_az_el(&self, vertical: f32, horizontal: f32) -> (f32, f32) {
+        (
+            self.from_rotator(Axis::Horizontal, horizontal),
+            self.from_rotator(Axis::Vertical, vertical),
+        )
+    }
+
+    const fn horizontal_sign(&self) -> f32 {
+        if self.invert_horizontal { -1.0 } else { 1.0 }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close((a, b): (f32, f32), (x, y): (f32, f32)) {
+        assert!((a - x).abs() < 1e-3 && (b - y).abs() < 1e-3, "{:?} != {:?}", (a, b), (x, y));
+    }
+
+    fn offset(invert_horizontal: bool) -> Frame {
+        Frame {
+            azimuth_offset: 120.0,
+            elevation_offset: -5.0,
+            invert_horizontal,
+            ..Frame::default()
+        }
+    }
+
+    #[test]
+    fn az_el_round_trip_through_the_rotator_frame() {
+        for frame in [Frame::default(), offset(true), offset(false)] {
+            for (azimuth, elevation) in [(0.0, 0.0), (45.0, 30.0), (200.0, 89.0), (359.5, 10.0)] {
+                let (vertical, horizontal) = frame.az_el_to_rotator(azimuth, elevation);
+                assert_close(frame.rotator_to_az_el(vertical, horizontal), (azimuth, elevation));
+            }
+        }
+    }
+
+    #[test]
+    fn the_horizontal_axis_is_inverted_and_offset() {
+        assert_close(Frame::default().az_el_to_rotator(90.0, 30.0), (30.0, -90.0));
+        assert_close(offset(true).az_el_to_rotator(150.0, 30.0), (35.0, -30.0));
+        assert_close(offset(false).az_el_to_rotator(150.0, 30.0), (35.0, 30.0));
+    }
+
+    #[test]
+    fn readings_are_wrapped_into_the_convention() {
+        let frame = |azimuth_convention, elevation_convention| Frame {
+            azimuth_convention,
+            elevation_convention,
+            invert_horizontal: false,
+            ..Frame::default()
+        };
+
+        let compass = frame(AzimuthConvention::Compass, ElevationConvention::Horizon);
+        assert_close(compass.rotator_to_az_el(-3.0, -90.0), (270.0, 0.0));
+        assert_close(compass.rotator_to_az_el(95.0, 370.0), (10.0, 90.0));
+
+        let signed = frame(AzimuthConvention::Signed, ElevationConvention::Signed);
+        assert_close(signed.rotator_to_az_el(-3.0, 270.0), (-90.0, -3.0));
+
+        let continuous = frame(AzimuthConvention::Continuous, ElevationConvention::Signed);
+        assert_close(continuous.rotator_to_az_el(0.0, 400.0), (400.0, 0.0));
+    }
+
+    #[test]
+    fn only_bounded_conventions_are_checked() {
+        let frame = Frame { azimuth_convention: AzimuthConvention::Compass, ..Frame::default() };
+
+        assert!(frame.check(Axis::Horizontal, 360.0).is_ok());
+        assert!(matches!(frame.check(Axis::Horizontal, 361.0), Err(Error::OutOfRange { .. })));
+        assert!(frame.check(Axis::Vertical, -20.0).is_ok());
+    }
+
+    #[test]
+    fn inverted_limits_swap_their_ends() {
+        let limits = offset(true).limits_from_rotator(Axis::Horizontal, -10.0, 100.0);
+
+        assert_eq!((limits.min, limits.max), (20.0, 130.0));
+    }
+
+    #[test]
+    fn bounded_azimuths_differ_the_short_way_round() {
+        assert_eq!(AzimuthConvention::Compass.difference(359.5, 0.5), -1.0);
+        assert_eq!(AzimuthConvention::Signed.difference(-179.5, 179.5), 1.0);
+        assert_eq!(AzimuthConvention::Compass.difference(90.0, 45.0), 45.0);
+        // A continuous mount has to turn all the way back
+        assert_eq!(AzimuthConvention::Continuous.difference(359.5, -0.5), 360.0);
+    }
+}
@@ -0,0 +1,141 @@
+//! A record of recent command/response exchanges with the rotator, and
+//! latency metrics aggregated over every exchange.
+
+use std::{collections::{BTreeMap, VecDeque}, time::Duration};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use super::{Error, Response};
+
+/// A single command and the rotator's response to it.
+#[derive(Debug, Clone, Serialize)]
+pub struct Exchange {
+    /// When the command was sent, in RFC 3339 format.
+    pub timestamp: String,
+    pub command: String,
+    /// Everything received in response, decoded lossily.
+    pub response: String,
+    /// Time from sending the command to receiving the first byte of the response.
+    pub first_byte_ms: Option<f64>,
+    /// Time from sending the command to the end of the response.
+    pub total_ms: f64,
+    /// `ok` if the command was accepted, otherwise why it wasn't.
+    pub outcome: String,
+}
+
+impl Exchange {
+    pub fn new(
+        sent: DateTime<Utc>,
+        command: &str,
+        response: &[u8],
+        first_byte: Option<Duration>,
+        total: Duration,
+        result: &Result<Response, Error>,
+    ) -> Self {
+        let outcome = match result {
+            Ok(r) if r.status == super::Status::Ok => "ok".to_string(),
+            Ok(r) => format!("rotator error: {}", r.message()),
+            Err(e) => e.to_string(),
+        };
+
+        Self {
+            timestamp: sent.to_rfc3339(),
+            command: command.trim().to_string(),
+            response: String::from_utf8_lossy(response).to_string(),
+            first_byte_ms: first_byte.map(|d| d.as_secs_f64() * 1000.0),
+            total_ms: total.as_secs_f64() * 1000.0,
+            outcome,
+        }
+    }
+
+    /// The command code, e.g. `GETP`.
+    pub fn code(&self) -> &str {
+        self.command.split_ascii_whitespace().next().unwrap_or_default()
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.outcome == "ok"
+    }
+}
+
+/// An exchange along with the bytes received, exactly as they arrived.
+#[derive(Debug, Clone, Serialize)]
+pub struct RawExchange {
+    #[serde(flatten)]
+    pub exchange: Exchange,
+    pub raw: Vec<u8>,
+}
+
+/// The most recent exchanges, oldest first.
+#[derive(Debug, Clone)]
+pub struct History {
+    capacity: usize,
+    entries: VecDeque<Exchange>,
+}
+
+impl History {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    pub fn push(&mut self, exchange: Exchange) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(exchange);
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &Exchange> {
+        self.entries.iter()
+    }
+}
+
+/// Latency and error counts for one command code.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CommandMetrics {
+    pub count: u64,
+    pub errors: u64,
+    pub mean_first_byte_ms: f64,
+    pub mean_total_ms: f64,
+    pub max_total_ms: f64,
+    #[serde(skip)]
+    responded: u64,
+}
+
+/// Metrics for every command sent since the rotator was connected.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Metrics {
+    pub commands: BTreeMap<String, CommandMetrics>,
+    /// Positions which were clamped to the limits before being sent.
+    pub clamped: u64,
+    /// Commands which were refused for being out of range, without being sent.
+    pub rejected: u64,
+}
+
+impl Metrics {
+    pub fn record(&mut self, exchange: &Exchange) {
+        let metrics = self.commands.entry(exchange.code().to_string()).or_default();
+
+        metrics.count += 1;
+        if !exchange.is_ok() {
+            metrics.errors += 1;
+        }
+
+        metrics.mean_total_ms += (exchange.total_ms - metrics.mean_total_ms) / metrics.count as f64;
+        metrics.max_total_ms = metrics.max_total_ms.max(exchange.total_ms);
+
+        if let Some(first_byte_ms) = exchange.first_byte_ms {
+            metrics.responded += 1;
+            metrics.mean_first_byte_ms +=
+                (first_byte_ms - metrics.mean_first_byte_ms) / metrics.responded as f64;
+        }
+    }
+}
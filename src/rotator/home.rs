@@ -0,0 +1,227 @@
+//! Driving each axis to its mechanical home, separately from calibration.
+
+use std::time::{Duration, Instant};
+
+use rocket::tokio::{self, sync::Mutex};
+
+use super::{Axis, Error, POSITION_POLL_INTERVAL, Rotator};
+
+impl Rotator {
+    /// Jogs each axis down or left until its end-stop stops it, then stops
+    /// jogging. Unlike calibration, this does not set a new reference, unless
+    /// `home.set_zero` is set.
+    ///
+    /// The end-stop is taken to have tripped once the axis has stayed within
+    /// the position tolerance for the configured `home.settle_time_ms`. An
+    /// axis with a `home.offset` is then backed off the end-stop by that much,
+    /// and calibrated there if `home.set_zero` is set.
+    ///
+    /// The rotator is only locked for each step and reading, so it can be
+    /// halted partway, which stops the rest of the routine.
+    ///
+    /// # Errors
+    /// Returns [`Error::Timeout`] if an axis is still moving after
+    /// `home.timeout_ms`, and [`Error::Interrupted`] if the rotator is halted
+    /// or stopped first. The axis is told to stop either way.
+    pub async fn home(rotator: &Mutex<Self>) -> Result<(), Error> {
+        let (stops, home) = {
+            let rotator = rotator.lock().await;
+
+            (rotator.stops(), rotator.config.home.clone())
+        };
+
+        for axis in [Axis::Vertical, Axis::Horizontal] {
+            {
+                let mut rotator = rotator.lock().await;
+                rotator.ensure_not_stopped(stops)?;
+                rotator.move_direction_on(axis, false).await?;
+            }
+
+            let result = Self::wait_for_end_stop(rotator, stops, axis).await;
+            let stopped = rotator.lock().await.move_direction(axis.stop()).await;
+
+            let end_stop = result.and_then(|reading| stopped.map(|()| reading))?;
+
+            if let Some(offset) = *home.offset.get(axis) {
+                Self::back_off(rotator, stops, axis, end_stop + offset).await?;
+
+                if home.set_zero {
+                    let mut rotator = rotator.lock().await;
+                    rotator.ensure_not_stopped(stops)?;
+                    rotator.calibrate(axis).await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Moves an axis to a reading, waiting for it to settle there.
+    async fn back_off(rotator: &Mutex<Self>, stops: u64, axis: Axis, reading: f32) -> Result<(), Error> {
+        let (tolerance, settle_time, timeout) = {
+            let mut rotator = rotator.lock().await;
+            rotator.ensure_not_stopped(stops)?;
+
+            let degrees = rotator.config.frame.from_rotator(axis, reading);
+            rotator.set_position(axis, degrees).await?;
+
+            (
+                rotator.config.position_tolerance,
+                Duration::from_millis(rotator.config.settle_time_ms),
+                Duration::from_millis(rotator.config.home.timeout_ms),
+            )
+        };
+        let deadline = Instant::now() + timeout;
+
+        let mut within_since = None;
+        loop {
+            let current = Self::poll_reading(rotator, stops, axis).await?;
+            let now = Instant::now();
+
+            if (current - reading).abs() <= tolerance {
+                let since = *within_since.get_or_insert(now);
+                if now.duration_since(since) >= settle_time {
+                    rotator.lock().await.clear_motion(axis);
+                    return Ok(());
+                }
+            } else {
+                within_since = None;
+            }
+
+            if now >= deadline {
+                return Err(Error::Timeout);
+            }
+
+            tokio::time::sleep(POSITION_POLL_INTERVAL).await;
+        }
+    }
+
+    /// Waits for an axis to stop against its end-stop, returning its reading
+    /// there.
+    async fn wait_for_end_stop(rotator: &Mutex<Self>, stops: u64, axis: Axis) -> Result<f32, Error> {
+        let (tolerance, settle_time, timeout) = {
+            let rotator = rotator.lock().await;
+
+            (
+                rotator.config.position_tolerance,
+                Duration::from_millis(rotator.config.home.settle_time_ms),
+                Duration::from_millis(rotator.config.home.timeout_ms),
+            )
+        };
+        let deadline = Instant::now() + timeout;
+
+        let mut anchor: Option<(f32, Instant)> = None;
+        loop {
+            let reading = Self::poll_reading(rotator, stops, axis).await?;
+            let now = Instant::now();
+
+            match anchor {
+                Some((at, since)) if (reading - at).abs() <= tolerance => {
+                    if now.duration_since(since) >= settle_time {
+                        return Ok(reading);
+                    }
+                }
+                _ => anchor = Some((reading, now)),
+            }
+
+            if now >= deadline {
+                return Err(Error::Timeout);
+            }
+
+            tokio::time::sleep(POSITION_POLL_INTERVAL).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::{config::{HomeConfig, PerAxis, RotatorConfig}, mock::{self, MockFirmware}}, *};
+
+    fn config(home: HomeConfig) -> RotatorConfig {
+        RotatorConfig { home, ..mock::config() }
+    }
+
+    #[rocket::async_test]
+    async fn each_axis_jogs_until_its_end_stop_trips() {
+        let firmware = MockFirmware::new();
+        firmware.lock().end_stops = PerAxis { vertical: Some(-5.0), horizontal: Some(-8.0) };
+        let rotator = firmware.shared(config(HomeConfig { settle_time_ms: 50, ..HomeConfig::default() }));
+
+        Rotator::home(&rotator).await.unwrap();
+
+        assert_eq!(firmware.lock().position, PerAxis { vertical: -5.0, horizontal: -8.0 });
+        assert_eq!(firmware.lock().jogging, PerAxis { vertical: 0.0, horizontal: 0.0 });
+        let jogs: Vec<_> = firmware.received().into_iter().filter(|line| line.starts_with("MOVC")).collect();
+        assert_eq!(jogs, ["MOVC DN", "MOVC SV", "MOVC LT", "MOVC SH"]);
+    }
+
+    #[rocket::async_test]
+    async fn axes_back_off_their_end_stop_and_set_zero_there() {
+        let firmware = MockFirmware::new();
+        firmware.lock().end_stops = PerAxis { vertical: Some(-5.0), horizontal: Some(-8.0) };
+        let home = HomeConfig {
+            settle_time_ms: 50,
+            offset: PerAxis { vertical: Some(2.0), horizontal: None },
+            set_zero: true,
+            ..HomeConfig::default()
+        };
+        let rotator = firmware.shared(config(home));
+
+        Rotator::home(&rotator).await.unwrap();
+
+        assert_eq!(firmware.lock().position, PerAxis { vertical: -3.0, horizontal: -8.0 });
+        let commands: Vec<_> = firmware.received().into_iter().filter(|line| !line.starts_with("GETP")).collect();
+        assert_eq!(commands, ["MOVC DN", "MOVC SV", "DVER -3.000", "CALV", "MOVC LT", "MOVC SH"]);
+    }
+
+    #[rocket::async_test]
+    async fn an_axis_which_never_stops_times_out() {
+        let firmware = MockFirmware::new();
+        let rotator = firmware.shared(config(HomeConfig { settle_time_ms: 50, timeout_ms: 200, ..HomeConfig::default() }));
+
+        let error = Rotator::home(&rotator).await.unwrap_err();
+
+        assert!(matches!(error, Error::Timeout), "{error:?}");
+        assert_eq!(firmware.received().last().unwrap(), "MOVC SV");
+        assert_eq!(firmware.lock().jogging.vertical, 0.0);
+    }
+
+    #[rocket::async_test]
+    async fn each_axis_is_backed_off_to_its_working_zero() {
+        let firmware = MockFirmware::new();
+        firmware.lock().end_stops = PerAxis { vertical: Some(-5.0), horizontal: Some(-8.0) };
+        let home = HomeConfig {
+            settle_time_ms: 50,
+            offset: PerAxis { vertical: Some(2.0), horizontal: Some(3.0) },
+            ..HomeConfig::default()
+        };
+        let rotator = firmware.shared(config(home));
+
+        Rotator::home(&rotator).await.unwrap();
+
+        assert_eq!(firmware.lock().position, PerAxis { vertical: -3.0, horizontal: -5.0 });
+        let commands: Vec<_> = firmware.received().into_iter().filter(|line| !line.starts_with("GETP")).collect();
+        assert_eq!(commands, ["MOVC DN", "MOVC SV", "DVER -3.000", "MOVC LT", "MOVC SH", "DHOR -5.000"]);
+        assert_eq!(rotator.lock().await.motion(Axis::Vertical), None);
+    }
+
+    #[rocket::async_test]
+    async fn an_axis_which_cannot_back_off_times_out() {
+        let firmware = MockFirmware::new();
+        firmware.lock().stalled.vertical = true;
+        let home = HomeConfig {
+            settle_time_ms: 50,
+            timeout_ms: 300,
+            offset: PerAxis { vertical: Some(2.0), horizontal: None },
+            set_zero: true,
+        };
+        let rotator = firmware.shared(config(home));
+
+        let error = Rotator::home(&rotator).await.unwrap_err();
+
+        assert!(matches!(error, Error::Timeout), "{error:?}");
+        // Neither zeroed there, nor carried on to the other axis
+        let received = firmware.received();
+        assert!(!received.iter().any(|line| line == "CALV" || line == "MOVC LT"), "{received:?}");
+    }
+}
@@ -0,0 +1,235 @@
+//! A safety interlock for installations near people: with `interlock.enabled`
+//! set, nothing moves until the rotator has been armed, and it disarms itself
+//! once it has sat idle for a while.
+//!
+//! Separately, `halt_cooldown_ms` holds off moves for a while after a halt,
+//! so the rotator isn't jerked straight back into motion.
+
+use std::time::{Duration, Instant};
+
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use super::{Error, Rotator};
+
+/// Settings for the arming interlock.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct InterlockConfig {
+    /// Refuse to move unless armed with [`Rotator::arm`].
+    pub enabled: bool,
+    /// How long the rotator may go without being moved before it disarms.
+    pub disarm_after_ms: u64,
+}
+
+impl Default for InterlockConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            disarm_after_ms: 300_000,
+        }
+    }
+}
+
+impl Rotator {
+    /// Allows the rotator to move, until it is disarmed or sits idle for
+    /// `interlock.disarm_after_ms`.
+    pub fn arm(&mut self) {
+        info!("Rotator armed");
+        self.armed_at = Some(Instant::now());
+    }
+
+    /// Stops the rotator from being moved until it is armed again. Anything
+    /// already moving is not halted.
+    pub fn disarm(&mut self) {
+        if self.armed_at.take().is_some() {
+            info!("Rotator disarmed");
+        }
+    }
+
+    /// Whether the rotator may move, which it always may if the interlock is
+    /// off.
+    pub fn is_armed(&self) -> bool {
+        !self.config.interlock.enabled || self.armed_until().is_some_and(|until| Instant::now() < until)
+    }
+
+    /// When the rotator will disarm itself if it isn't moved, if it is armed.
+    pub fn armed_until(&self) -> Option<Instant> {
+        let disarm_after = Duration::from_millis(self.config.interlock.disarm_after_ms);
+
+        self.armed_at.map(|at| at + disarm_after)
+    }
+
+    /// Makes sure the rotator is armed before moving it, and restarts the idle
+    /// period if so.
+    pub(super) fn ensure_armed(&mut self) -> Result<(), Error> {
+        if !self.config.interlock.enabled {
+            return Ok(());
+        }
+
+        if !self.is_armed() {
+            if self.armed_at.take().is_some() {
+                info!("Rotator disarmed after sitting idle");
+            }
+            return Err(Error::NotArmed);
+        }
+
+        self.armed_at = Some(Instant::now());
+        Ok(())
+    }
+
+    /// How much longer moves are refused for after the last halt, if they
+    /// are.
+    pub fn cooldown_remaining(&self) -> Option<Duration> {
+        let cooldown = Duration::from_millis(self.config.halt_cooldown_ms?);
+
+        self.halted_at
+            .map(|at| cooldown.saturating_sub(at.elapsed()))
+            .filter(|remaining| !remaining.is_zero())
+    }
+
+    /// Ends the cooldown after a halt straight away.
+    pub fn resume(&mut self) {
+        if self.halted_at.take().is_some() {
+            info!("Rotator resumed after halting");
+        }
+    }
+
+    /// Makes sure the rotator isn't cooling down after a halt before moving it.
+    pub(super) fn ensure_cooled_down(&self) -> Result<(), Error> {
+        match self.cooldown_remaining() {
+            Some(remaining) => Err(Error::HaltCooldown(remaining)),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rocket::tokio;
+
+    use super::{super::{Axis, config::RotatorConfig, mock::{self, MockFirmware}}, *};
+
+    fn config(disarm_after_ms: u64) -> RotatorConfig {
+        RotatorConfig { interlock: InterlockConfig { enabled: true, disarm_after_ms }, ..mock::config() }
+    }
+
+    #[rocket::async_test]
+    async fn a_disarmed_rotator_refuses_to_move_but_answers_queries() {
+        let firmware = MockFirmware::new();
+        let mut rotator = firmware.rotator(config(60_000));
+        assert!(!rotator.is_armed());
+
+        let error = rotator.set_position_vertical(10.0).await.unwrap_err();
+        assert!(matches!(error, Error::NotArmed), "{error:?}");
+        let error = rotator.move_steps(Axis::Horizontal, 10).await.unwrap_err();
+        assert!(matches!(error, Error::NotArmed), "{error:?}");
+        let error = rotator.calibrate_vertical(true).await.unwrap_err();
+        assert!(matches!(error, Error::NotArmed), "{error:?}");
+        assert!(firmware.received().is_empty());
+
+        rotator.position().await.unwrap();
+        assert_eq!(firmware.commands(), ["GETP"]);
+    }
+
+    #[rocket::async_test]
+    async fn moves_are_allowed_while_armed() {
+        let firmware = MockFirmware::new();
+        let mut rotator = firmware.rotator(config(60_000));
+
+        rotator.arm();
+        assert!(rotator.is_armed());
+        rotator.set_position_vertical(10.0).await.unwrap();
+        assert_eq!(firmware.received(), ["DVER 10.000"]);
+
+        rotator.disarm();
+        assert!(rotator.armed_until().is_none());
+        let error = rotator.set_position_vertical(20.0).await.unwrap_err();
+        assert!(matches!(error, Error::NotArmed), "{error:?}");
+    }
+
+    #[rocket::async_test]
+    async fn an_idle_rotator_disarms_itself() {
+        let firmware = MockFirmware::new();
+        let mut rotator = firmware.rotator(config(100));
+
+        // Each move restarts the idle period
+        rotator.arm();
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        rotator.set_position_vertical(10.0).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert!(rotator.is_armed());
+
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert!(!rotator.is_armed());
+        let error = rotator.set_position_vertical(20.0).await.unwrap_err();
+        assert!(matches!(error, Error::NotArmed), "{error:?}");
+        assert!(rotator.armed_until().is_none());
+    }
+
+    #[test]
+    fn the_rotator_is_always_armed_without_the_interlock() {
+        let mut rotator = MockFirmware::new().rotator(mock::config());
+
+        assert!(rotator.is_armed());
+        rotator.disarm();
+        assert!(rotator.is_armed());
+        assert!(rotator.ensure_armed().is_ok());
+    }
+
+    fn cooldown(halt_cooldown_ms: u64) -> RotatorConfig {
+        RotatorConfig { halt_cooldown_ms: Some(halt_cooldown_ms), ..mock::config() }
+    }
+
+    #[rocket::async_test]
+    async fn moves_are_refused_while_cooling_down_after_a_halt() {
+        let firmware = MockFirmware::new();
+        let mut rotator = firmware.rotator(cooldown(100));
+
+        rotator.halt().await.unwrap();
+        firmware.clear_received();
+
+        let error = rotator.set_position_vertical(10.0).await.unwrap_err();
+        let Error::HaltCooldown(remaining) = error else { panic!("{error:?}") };
+        assert!(remaining <= Duration::from_millis(100));
+        let error = rotator.calibrate_vertical(false).await.unwrap_err();
+        assert!(matches!(error, Error::HaltCooldown(_)), "{error:?}");
+        assert!(firmware.received().is_empty());
+
+        // Queries are still answered
+        rotator.position().await.unwrap();
+        assert_eq!(firmware.commands(), ["GETP"]);
+
+        tokio::time::sleep(Duration::from_millis(120)).await;
+        assert_eq!(rotator.cooldown_remaining(), None);
+        rotator.set_position_vertical(10.0).await.unwrap();
+        assert_eq!(firmware.received().last().unwrap(), "DVER 10.000");
+    }
+
+    #[rocket::async_test]
+    async fn resuming_ends_the_cooldown_early() {
+        let firmware = MockFirmware::new();
+        let mut rotator = firmware.rotator(cooldown(60_000));
+
+        rotator.halt().await.unwrap();
+        assert!(rotator.cooldown_remaining().is_some());
+
+        rotator.resume();
+        assert_eq!(rotator.cooldown_remaining(), None);
+        rotator.set_position_vertical(10.0).await.unwrap();
+    }
+
+    #[rocket::async_test]
+    async fn only_a_halt_starts_the_cooldown() {
+        let firmware = MockFirmware::new();
+        let mut rotator = firmware.rotator(cooldown(60_000));
+        rotator.stop(false).await.unwrap();
+        rotator.set_position_vertical(10.0).await.unwrap();
+
+        // Nor does a halt without a cooldown configured
+        let mut rotator = firmware.rotator(mock::config());
+        rotator.halt().await.unwrap();
+        assert_eq!(rotator.cooldown_remaining(), None);
+        rotator.set_position_vertical(10.0).await.unwrap();
+    }
+}
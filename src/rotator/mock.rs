@@ -0,0 +1,549 @@
+//! A scripted stand-in for the rotator's firmware, so that a [`Rotator`] can
+//! be driven in tests without any hardware.
+//!
+//! The firmware answers the commands in the protocol itself, moving its axes
+//! as it is told to, and anything else from [`Firmware::replies`]. Positions
+//! are in the firmware's own readings, see [`frame`](super::frame).
+
+use std::{
+    collections::{HashMap, VecDeque},
+    io::{self, Read, Write},
+    mem,
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+use rocket::tokio::sync::Mutex as AsyncMutex;
+use serialport::{ClearBuffer, DataBits, FlowControl, Parity, SerialPort, StopBits};
+
+use super::{
+    Rotator,
+    config::{PerAxis, RotatorConfig},
+    shape::ResponseShape,
+};
+
+/// The firmware's side of the link.
+pub struct Firmware {
+    /// Where each axis reads.
+    pub position: PerAxis<f32>,
+    /// Where each axis was last sent, until it gets there or is halted.
+    pub target: PerAxis<Option<f32>>,
+    /// Which way each axis is jogging, `0.0` if it isn't.
+    pub jogging: PerAxis<f32>,
+    /// How far an axis moves each time the position is read. `None` moves it
+    /// straight to its target.
+    pub slew_per_read: Option<f32>,
+    /// Axes which don't move when told to, as if stalled.
+    pub stalled: PerAxis<bool>,
+    /// The lowest reading each axis can jog to, as if a limit switch stopped
+    /// it there.
+    pub end_stops: PerAxis<Option<f32>>,
+    pub steps_per_degree: f32,
+    pub calibrated: bool,
+    pub version: String,
+    /// Whether each command is echoed before its response.
+    pub echo: bool,
+    /// Whether values are sent on a line of their own after the status.
+    pub verbose: bool,
+    /// Only lines starting with this are answered, and answers start with it.
+    pub address: Option<String>,
+    /// Lines from other devices on a shared bus, sent ahead of each answer.
+    pub bus_chatter: Vec<String>,
+    pub line_terminator: String,
+    /// Replies for commands, by their code, e.g. `"GETS" => "OK 5.0"`. These
+    /// take precedence over the built-in ones, and any command with neither
+    /// is answered as unknown.
+    pub replies: HashMap<String, String>,
+    /// Stops answering anything, as if it had locked up.
+    pub silent: bool,
+    /// How many more times each command, by its code, goes unanswered.
+    pub unanswered: HashMap<String, usize>,
+    /// Unplugs the port when this command is received, before answering it.
+    pub unplug_on: Option<String>,
+    /// Fails every read and write, as if the port was unplugged.
+    pub unplugged: bool,
+    /// How long each answer takes to start arriving.
+    pub delay: Duration,
+    /// How long after a command its answer can be read. Reads before then
+    /// find nothing, as with firmware which is slow to start replying.
+    pub reply_after: Duration,
+    /// Bytes from an earlier exchange which are still arriving, so clearing
+    /// the port's buffers doesn't discard them. They are read ahead of any
+    /// answer.
+    pub stale: Vec<u8>,
+    /// Every line received, without its terminator.
+    pub received: Vec<String>,
+    unread: VecDeque<u8>,
+    partial: Vec<u8>,
+    /// Whether the next read waits out the [`Self::delay`] first.
+    delaying: bool,
+    answered_at: Option<Instant>,
+}
+
+impl Default for Firmware {
+    fn default() -> Self {
+        Self {
+            position: PerAxis::default(),
+            target: PerAxis::default(),
+            jogging: PerAxis::default(),
+            slew_per_read: None,
+            stalled: PerAxis::default(),
+            end_stops: PerAxis::default(),
+            steps_per_degree: 10.0,
+            calibrated: true,
+            version: "v1.4.0".to_string(),
+            echo: true,
+            verbose: false,
+            address: None,
+            bus_chatter: Vec::new(),
+            line_terminator: "\n".to_string(),
+            replies: HashMap::new(),
+            silent: false,
+            unanswered: HashMap::new(),
+            unplug_on: None,
+            unplugged: false,
+            delay: Duration::ZERO,
+            reply_after: Duration::ZERO,
+            stale: Vec::new(),
+            received: Vec::new(),
+            unread: VecDeque::new(),
+            partial: Vec::new(),
+            delaying: false,
+            answered_at: None,
+        }
+    }
+}
+
+impl Firmware {
+    /// Takes in written bytes, answering each complete line.
+    fn receive(&mut self, bytes: &[u8]) {
+        self.partial.extend_from_slice(bytes);
+
+        let terminator = self.line_terminator.clone().into_bytes();
+        while let Some(end) = self
+            .partial
+            .windows(terminator.len())
+            .position(|window| window == terminator)
+        {
+            let line: Vec<_> = self.partial.drain(..end + terminator.len()).take(end).collect();
+            let line = String::from_utf8_lossy(&line).into_owned();
+            self.received.push(line.clone());
+            self.answer(&line);
+        }
+    }
+
+    /// Echoes the command, if [`Self::echo`] is set, then answers it.
+    fn answer(&mut self, line: &str) {
+        if self.silent {
+            return;
+        }
+
+        let command = match &self.address {
+            Some(address) => match line.strip_prefix(address.as_str()) {
+                Some(rest) => rest.trim_start(),
+                None => return,
+            },
+            None => line,
+        };
+
+        let code = command.split_ascii_whitespace().next().unwrap_or_default();
+        if self.unplug_on.as_deref() == Some(code) {
+            self.unplugged = true;
+            return;
+        }
+        if let Some(remaining) = self.unanswered.get_mut(code)
+            && *remaining > 0
+        {
+            *remaining -= 1;
+            return;
+        }
+
+        let reply = self.reply(command);
+        let values = reply.split_once(' ').filter(|_| self.verbose);
+
+        let mut lines = Vec::new();
+        if self.echo {
+            lines.push(command.to_string());
+        }
+        match values {
+            Some((status, values)) => lines.extend([status.to_string(), values.to_string()]),
+            None => lines.push(reply.clone()),
+        }
+
+        for line in &self.bus_chatter {
+            self.unread.extend(line.bytes());
+            self.unread.extend(self.line_terminator.bytes());
+        }
+        for line in lines {
+            if let Some(address) = &self.address {
+                self.unread.extend(format!("{address} ").bytes());
+            }
+            self.unread.extend(line.bytes());
+            self.unread.extend(self.line_terminator.bytes());
+        }
+        self.delaying = true;
+        self.answered_at = Some(Instant::now());
+    }
+
+    fn reply(&mut self, command: &str) -> String {
+        let mut words = command.split_ascii_whitespace();
+        let code = words.next().unwrap_or_default();
+        let arg = words.next();
+
+        if let Some(reply) = self.replies.get(code) {
+            return reply.clone();
+        }
+
+        match (code, arg) {
+            ("GETP", _) => {
+                self.advance();
+                format!("OK {} {}", self.position.vertical, self.position.horizontal)
+            }
+            ("DVER" | "DHOR", _) if !self.calibrated => "ERR not calibrated".to_string(),
+            ("DVER" | "DHOR", Some(reading)) => {
+                let Ok(reading) = reading.parse() else {
+                    return "ERR invalid value".to_string();
+                };
+                let axis = if code == "DVER" { &mut self.target.vertical } else { &mut self.target.horizontal };
+                *axis = Some(reading);
+                "OK".to_string()
+            }
+            ("MOVV" | "MOVH", Some(steps)) => {
+                let Ok(steps) = steps.parse::<f32>() else {
+                    return "ERR invalid value".to_string();
+                };
+                let (position, stalled) = if code == "MOVV" {
+                    (&mut self.position.vertical, self.stalled.vertical)
+                } else {
+                    (&mut self.position.horizontal, self.stalled.horizontal)
+                };
+                if !stalled {
+                    *position += steps / self.steps_per_degree;
+                }
+                "OK".to_string()
+            }
+            ("MOVC", Some(direction)) => {
+                match direction {
+                    "UP" => self.jogging.vertical = 1.0,
+                    "DN" => self.jogging.vertical = -1.0,
+                    "SV" => self.jogging.vertical = 0.0,
+                    "RT" => self.jogging.horizontal = 1.0,
+                    "LT" => self.jogging.horizontal = -1.0,
+                    "SH" => self.jogging.horizontal = 0.0,
+                    _ => return "ERR invalid direction".to_string(),
+                }
+                "OK".to_string()
+            }
+            ("CALV" | "CALH", _) => {
+                self.calibrated = true;
+                "OK".to_string()
+            }
+            ("CALR", _) => {
+                self.calibrated = false;
+                "OK".to_string()
+            }
+            ("GETC", _) => format!("OK {}", self.calibrated),
+            ("VERS", _) => format!("OK {}", self.version),
+            ("GERR", _) => "OK NONE".to_string(),
+            ("HALT", _) => {
+                self.target = PerAxis::default();
+                self.jogging = PerAxis::default();
+                "OK".to_string()
+            }
+            _ => "ERR unknown command".to_string(),
+        }
+    }
+
+    /// Moves each axis on towards its target, or in the direction it is
+    /// jogging.
+    fn advance(&mut self) {
+        let slew = self.slew_per_read;
+        let axes = [
+            (&mut self.position.vertical, &mut self.target.vertical, self.jogging.vertical, self.stalled.vertical, self.end_stops.vertical),
+            (&mut self.position.horizontal, &mut self.target.horizontal, self.jogging.horizontal, self.stalled.horizontal, self.end_stops.horizontal),
+        ];
+
+        for (position, target, jogging, stalled, end_stop) in axes {
+            if stalled {
+                continue;
+            }
+
+            *position += jogging * slew.unwrap_or(1.0);
+            if let Some(end_stop) = end_stop {
+                *position = position.max(end_stop);
+            }
+            if let Some(to) = *target {
+                let step = slew.unwrap_or(f32::INFINITY);
+                *position += (to - *position).clamp(-step, step);
+                if *position == to {
+                    *target = None;
+                }
+            }
+        }
+    }
+}
+
+/// A handle on a [`Firmware`], shared with every port made from it.
+#[derive(Clone, Default)]
+pub struct MockFirmware(Arc<Mutex<Firmware>>);
+
+impl MockFirmware {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn lock(&self) -> MutexGuard<'_, Firmware> {
+        self.0.lock().unwrap()
+    }
+
+    /// Answers a command with `reply` from now on, instead of the usual one.
+    pub fn reply(&self, command: &str, reply: &str) {
+        self.lock().replies.insert(command.to_string(), reply.to_string());
+    }
+
+    /// Every line received so far.
+    pub fn received(&self) -> Vec<String> {
+        self.lock().received.clone()
+    }
+
+    /// The code of each command received so far, e.g. `GETP`.
+    pub fn commands(&self) -> Vec<String> {
+        let firmware = self.lock();
+
+        firmware
+            .received
+            .iter()
+            .map(|line| {
+                let line = match &firmware.address {
+                    Some(address) => line.strip_prefix(address.as_str()).unwrap_or(line),
+                    None => line,
+                };
+                line.split_ascii_whitespace().next().unwrap_or_default().to_string()
+            })
+            .collect()
+    }
+
+    /// Forgets the lines received so far.
+    pub fn clear_received(&self) {
+        self.lock().received.clear();
+    }
+
+    /// A port connected to this firmware.
+    pub fn port(&self) -> Box<dyn SerialPort> {
+        Box::new(MockPort::new(self.clone()))
+    }
+
+    /// A rotator connected to this firmware with `config`, see [`config`].
+    pub fn rotator(&self, config: RotatorConfig) -> Rotator {
+        Rotator::with_config(self.port(), config).unwrap()
+    }
+
+    /// A rotator connected to this firmware with `config`, ready to be shared
+    /// like the server's.
+    pub fn shared(&self, config: RotatorConfig) -> AsyncMutex<Rotator> {
+        AsyncMutex::new(self.rotator(config))
+    }
+}
+
+/// Settings suited to a [`MockFirmware`]: the response shape is given, so
+/// nothing is probed on connect, nothing is halted on connect, and moves
+/// settle as soon as they arrive.
+pub fn config() -> RotatorConfig {
+    RotatorConfig {
+        response_shape: Some(ResponseShape::EchoStatus),
+        halt_on_connect: false,
+        settle_time_ms: 0,
+        ..RotatorConfig::default()
+    }
+}
+
+/// A serial port whose other end is a [`MockFirmware`].
+pub struct MockPort {
+    firmware: MockFirmware,
+    baud: u32,
+    data_bits: DataBits,
+    flow_control: FlowControl,
+    parity: Parity,
+    stop_bits: StopBits,
+    timeout: Duration,
+}
+
+impl MockPort {
+    pub fn new(firmware: MockFirmware) -> Self {
+        Self {
+            firmware,
+            baud: 115_200,
+            data_bits: DataBits::Eight,
+            flow_control: FlowControl::None,
+            parity: Parity::None,
+            stop_bits: StopBits::One,
+            timeout: Duration::from_secs(1),
+        }
+    }
+}
+
+fn unplugged() -> io::Error {
+    io::Error::new(io::ErrorKind::BrokenPipe, "the port was unplugged")
+}
+
+impl Write for MockPort {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut firmware = self.firmware.lock();
+        if firmware.unplugged {
+            return Err(unplugged());
+        }
+
+        firmware.receive(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Reads whatever the firmware has sent, then `0` once there is nothing
+/// left, as a real port does when it times out.
+impl Read for MockPort {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let delay = {
+            let mut firmware = self.firmware.lock();
+            if mem::take(&mut firmware.delaying) { firmware.delay } else { Duration::ZERO }
+        };
+        if !delay.is_zero() {
+            std::thread::sleep(delay);
+        }
+
+        let mut firmware = self.firmware.lock();
+        if firmware.unplugged {
+            return Err(unplugged());
+        }
+        if !firmware.stale.is_empty() {
+            let len = buf.len().min(firmware.stale.len());
+            buf[..len].copy_from_slice(&firmware.stale[..len]);
+            firmware.stale.drain(..len);
+            return Ok(len);
+        }
+        if firmware.answered_at.is_some_and(|at| at.elapsed() < firmware.reply_after) {
+            return Ok(0);
+        }
+
+        let len = buf.len().min(firmware.unread.len());
+        for (byte, unread) in buf.iter_mut().zip(firmware.unread.drain(..len)) {
+            *byte = unread;
+        }
+
+        Ok(len)
+    }
+}
+
+impl SerialPort for MockPort {
+    fn name(&self) -> Option<String> {
+        Some("mock".to_string())
+    }
+
+    fn baud_rate(&self) -> serialport::Result<u32> {
+        Ok(self.baud)
+    }
+
+    fn data_bits(&self) -> serialport::Result<DataBits> {
+        Ok(self.data_bits)
+    }
+
+    fn flow_control(&self) -> serialport::Result<FlowControl> {
+        Ok(self.flow_control)
+    }
+
+    fn parity(&self) -> serialport::Result<Parity> {
+        Ok(self.parity)
+    }
+
+    fn stop_bits(&self) -> serialport::Result<StopBits> {
+        Ok(self.stop_bits)
+    }
+
+    fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    fn set_baud_rate(&mut self, baud_rate: u32) -> serialport::Result<()> {
+        self.baud = baud_rate;
+        Ok(())
+    }
+
+    fn set_data_bits(&mut self, data_bits: DataBits) -> serialport::Result<()> {
+        self.data_bits = data_bits;
+        Ok(())
+    }
+
+    fn set_flow_control(&mut self, flow_control: FlowControl) -> serialport::Result<()> {
+        self.flow_control = flow_control;
+        Ok(())
+    }
+
+    fn set_parity(&mut self, parity: Parity) -> serialport::Result<()> {
+        self.parity = parity;
+        Ok(())
+    }
+
+    fn set_stop_bits(&mut self, stop_bits: StopBits) -> serialport::Result<()> {
+        self.stop_bits = stop_bits;
+        Ok(())
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) -> serialport::Result<()> {
+        self.timeout = timeout;
+        Ok(())
+    }
+
+    fn write_request_to_send(&mut self, _level: bool) -> serialport::Result<()> {
+        Ok(())
+    }
+
+    fn write_data_terminal_ready(&mut self, _level: bool) -> serialport::Result<()> {
+        Ok(())
+    }
+
+    fn read_clear_to_send(&mut self) -> serialport::Result<bool> {
+        Ok(false)
+    }
+
+    fn read_data_set_ready(&mut self) -> serialport::Result<bool> {
+        Ok(false)
+    }
+
+    fn read_ring_indicator(&mut self) -> serialport::Result<bool> {
+        Ok(false)
+    }
+
+    fn read_carrier_detect(&mut self) -> serialport::Result<bool> {
+        Ok(false)
+    }
+
+    fn bytes_to_read(&self) -> serialport::Result<u32> {
+        Ok(u32::try_from(self.firmware.lock().unread.len()).unwrap_or(u32::MAX))
+    }
+
+    fn bytes_to_write(&self) -> serialport::Result<u32> {
+        Ok(0)
+    }
+
+    fn clear(&self, buffer_to_clear: ClearBuffer) -> serialport::Result<()> {
+        if matches!(buffer_to_clear, ClearBuffer::Input | ClearBuffer::All) {
+            self.firmware.lock().unread.clear();
+        }
+        Ok(())
+    }
+
+    fn try_clone(&self) -> serialport::Result<Box<dyn SerialPort>> {
+        Ok(Box::new(Self::new(self.firmware.clone())))
+    }
+
+    fn set_break(&self) -> serialport::Result<()> {
+        Ok(())
+    }
+
+    fn clear_break(&self) -> serialport::Result<()> {
+        Ok(())
+    }
+}
@@ -3,16 +3,49 @@
 //! A connection to the rotator should be made using an automatic selection algorithm,
 //! or by using the web API to connect.
 
+pub mod accuracy;
+pub mod config;
+pub mod deadband;
 pub mod dummyport;
 pub mod endpoints;
+mod error;
+pub mod exchange_log;
+pub mod exercise;
+pub mod fine;
+pub mod frame;
+pub mod history;
+pub mod home;
+pub mod interlock;
+#[cfg(test)]
+pub mod mock;
+pub mod mode;
+pub mod motor;
+pub mod odometer;
+pub mod poller;
+pub mod presets;
+pub mod registry;
+pub mod self_test;
+pub mod shape;
+pub mod speed;
+pub mod startup;
+pub mod test_pattern;
+pub mod units;
+pub mod value;
 
 use core::fmt::Display;
-use rocket::FromFormField;
-use std::{io::{self, Write as _}, num::ParseFloatError, ops::Neg as _, str::ParseBoolError};
-use std::string::ParseError;
+use rocket::{FromFormField, request::FromParam, tokio::{self, sync::{Mutex, watch}}};
+use std::{io::{self, Write as _}, mem, ops::{Deref, DerefMut}, time::{Duration, Instant}};
+use serde::{Deserialize, Serialize};
 use serialport::SerialPort;
 
-use crate::response::Error;
+use chrono::{DateTime, Utc};
+use config::{Limits, PerAxis, RotatorConfig};
+use log::{debug, info, warn};
+pub use error::{Error, SerialFault};
+use exchange_log::ExchangeLog;
+use frame::AzimuthConvention;
+use history::{Exchange, History, Metrics, RawExchange};
+use shape::{ResponseShape, Verbosity};
 
 /// Command that the rotator accepts.
 #[non_exhaustive]
@@ -24,6 +57,32 @@ pub enum Command {
 
     CalibrateVertical,
     CalibrateHorizontal,
+    /// Optional, not all firmware supports this.
+    ResetCalibration,
+    /// Optional, not all firmware supports this.
+    GetLimits,
+    /// Optional, not all firmware supports this.
+    GetMode,
+    /// Optional, not all firmware supports this.
+    SetMode,
+    /// Optional, not all firmware supports this.
+    GetFeedback,
+    /// Optional, not all firmware supports this.
+    MotorVertical,
+    /// Optional, not all firmware supports this.
+    MotorHorizontal,
+    /// Optional, not all firmware supports this.
+    GetSpeed,
+    /// Optional, not all firmware supports this.
+    SetSpeed,
+    /// Optional, not all firmware supports this.
+    GetOdometer,
+    /// Optional, not all firmware supports this.
+    GetVerbosity,
+    /// Optional, not all firmware supports this.
+    GetDeadband,
+    /// Optional, not all firmware supports this.
+    SetDeadband,
 
     Movement,
     MoveVerticalSteps,
@@ -45,6 +104,7 @@ impl Display for Command {
             Self::DegreesHorizontal => "DHOR",
             Self::CalibrateVertical => "CALV",
             Self::CalibrateHorizontal => "CALH",
+            Self::ResetCalibration => "CALR",
             Self::Movement => "MOVC",
             Self::MoveVerticalSteps => "MOVV",
             Self::MoveHorizontalSteps => "MOVH",
@@ -52,6 +112,18 @@ impl Display for Command {
             Self::GetCalibrated => "GETC",
             Self::GetVersion => "VERS",
             Self::GetErrors => "GERR",
+            Self::GetLimits => "GETL",
+            Self::GetMode => "GETM",
+            Self::SetMode => "SETM",
+            Self::GetFeedback => "GETF",
+            Self::MotorVertical => "MOTV",
+            Self::MotorHorizontal => "MOTH",
+            Self::GetSpeed => "GETS",
+            Self::SetSpeed => "SETS",
+            Self::GetOdometer => "GETO",
+            Self::GetVerbosity => "VERB",
+            Self::GetDeadband => "GETD",
+            Self::SetDeadband => "SETD",
             Self::Halt => "HALT",
         };
 
@@ -59,6 +131,155 @@ impl Display for Command {
     }
 }
 
+impl TryFrom<&str> for Command {
+    type Error = ();
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Ok(match value {
+            "DVER" => Self::DegreesVertical,
+            "DHOR" => Self::DegreesHorizontal,
+            "CALV" => Self::CalibrateVertical,
+            "CALH" => Self::CalibrateHorizontal,
+            "CALR" => Self::ResetCalibration,
+            "MOVC" => Self::Movement,
+            "MOVV" => Self::MoveVerticalSteps,
+            "MOVH" => Self::MoveHorizontalSteps,
+            "GETP" => Self::GetPosition,
+            "GETC" => Self::GetCalibrated,
+            "VERS" => Self::GetVersion,
+            "GERR" => Self::GetErrors,
+            "GETL" => Self::GetLimits,
+            "GETM" => Self::GetMode,
+            "SETM" => Self::SetMode,
+            "GETF" => Self::GetFeedback,
+            "MOTV" => Self::MotorVertical,
+            "MOTH" => Self::MotorHorizontal,
+            "GETS" => Self::GetSpeed,
+            "SETS" => Self::SetSpeed,
+            "GETO" => Self::GetOdometer,
+            "VERB" => Self::GetVerbosity,
+            "GETD" => Self::GetDeadband,
+            "SETD" => Self::SetDeadband,
+            "HALT" => Self::Halt,
+            _ => return Err(()),
+        })
+    }
+}
+
+/// Serialized as the 4-letter code used on the wire.
+impl Serialize for Command {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Command {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let code = String::deserialize(deserializer)?;
+
+        Self::try_from(code.as_str())
+            .map_err(|()| serde::de::Error::custom(format!("unknown command `{code}`")))
+    }
+}
+
+/// Whether the rotator accepted a command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Ok,
+    Err,
+}
+
+/// A parsed status line from the rotator, e.g. `OK 12.5 90.0` or `ERR not calibrated`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    pub status: Status,
+    /// The whitespace-separated values after the status. For an `ERR`
+    /// response these make up the error message.
+    pub values: Vec<String>,
+}
+
+impl Response {
+    /// Parse a single status line.
+    pub fn parse(line: &str) -> Result<Self, Error> {
+        let mut words = line.split_ascii_whitespace();
+
+        let status = match words.next() {
+            Some("OK") => Status::Ok,
+            Some("ERR") => Status::Err,
+            _ => return Err(Error::InvalidResponse),
+        };
+
+        Ok(Self {
+            status,
+            values: words.map(str::to_string).collect(),
+        })
+    }
+
+    /// The values joined back together, e.g. for an error message.
+    pub fn message(&self) -> String {
+        self.values.join(" ")
+    }
+}
+
+/// Whether a dotted firmware version such as `v1.2.3` is at least `min`,
+/// comparing each part as a number, or `None` if either can't be read.
+/// Missing parts count as zero, so `1.2` is the same as `1.2.0`.
+fn version_at_least(version: &str, min: &str) -> Option<bool> {
+    fn parts(version: &str) -> Option<Vec<u32>> {
+        version
+            .trim_start_matches(['v', 'V'])
+            .split('.')
+            .map(|part| part.parse().ok())
+            .collect()
+    }
+
+    let (mut version, mut min) = (parts(version)?, parts(min)?);
+    let len = version.len().max(min.len());
+    version.resize(len, 0);
+    min.resize(len, 0);
+
+    Some(version >= min)
+}
+
+/// Build details which some firmware gives after its version, as
+/// `OK <version> <commit> <date>`. Either may be left out, and a lone date
+/// is recognised as one.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct FirmwareBuild {
+    /// The commit the firmware was built from.
+    pub commit: Option<String>,
+    /// When the firmware was built, as `YYYY-MM-DD`.
+    pub date: Option<String>,
+}
+
+impl FirmwareBuild {
+    /// Parse the values after the version, or `None` if there are none.
+    fn parse(values: &[String]) -> Option<Self> {
+        fn is_date(value: &str) -> bool {
+            chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d").is_ok()
+        }
+
+        match values {
+            [] => None,
+            [date] if is_date(date) => Some(Self { commit: None, date: Some(date.clone()) }),
+            [commit, rest @ ..] => Some(Self {
+                commit: Some(commit.clone()),
+                date: rest.first().filter(|date| is_date(date)).cloned(),
+            }),
+        }
+    }
+}
+
+/// The values of a response to a command which always returns `N` of them.
+///
+/// # Errors
+/// Returns [`Error::WrongValueCount`] if there are more or fewer.
+fn exact_values<const N: usize>(values: Vec<String>) -> Result<[String; N], Error> {
+    values
+        .try_into()
+        .map_err(|values: Vec<String>| Error::WrongValueCount { expected: N, got: values.len() })
+}
+
 /// Direction accepted by [`Command::Movement`].
 #[non_exhaustive]
 #[derive(Debug, Clone, Copy, FromFormField)]
@@ -74,6 +295,112 @@ pub enum Direction {
     StopHorizontal,
 }
 
+/// Serialized as the 2-letter code used on the wire.
+impl Serialize for Direction {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Direction {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let code = String::deserialize(deserializer)?;
+
+        Self::try_from(code.as_str())
+            .map_err(|()| serde::de::Error::custom(format!("unknown direction `{code}`")))
+    }
+}
+
+impl Direction {
+    /// The axis this direction moves or stops.
+    pub const fn axis(self) -> Axis {
+        match self {
+            Self::Up | Self::Down | Self::StopVertical => Axis::Vertical,
+            Self::Left | Self::Right | Self::StopHorizontal => Axis::Horizontal,
+        }
+    }
+
+    pub const fn is_vertical(self) -> bool {
+        matches!(self.axis(), Axis::Vertical)
+    }
+
+    pub const fn is_horizontal(self) -> bool {
+        matches!(self.axis(), Axis::Horizontal)
+    }
+
+    pub const fn is_stop(self) -> bool {
+        matches!(self, Self::StopVertical | Self::StopHorizontal)
+    }
+
+    /// The direction which stops the axis this direction belongs to.
+    pub const fn stop(self) -> Self {
+        self.axis().stop()
+    }
+}
+
+/// One of the two axes of the rotator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, FromFormField)]
+#[serde(rename_all = "lowercase")]
+pub enum Axis {
+    Vertical,
+    Horizontal,
+}
+
+/// Parsed from `vertical` or `horizontal` in a route path.
+impl<'a> FromParam<'a> for Axis {
+    type Error = &'a str;
+
+    fn from_param(param: &'a str) -> Result<Self, Self::Error> {
+        match param {
+            "vertical" => Ok(Self::Vertical),
+            "horizontal" => Ok(Self::Horizontal),
+            _ => Err(param),
+        }
+    }
+}
+
+impl Axis {
+    const fn degrees_command(self) -> Command {
+        match self {
+            Self::Vertical => Command::DegreesVertical,
+            Self::Horizontal => Command::DegreesHorizontal,
+        }
+    }
+
+    const fn steps_command(self) -> Command {
+        match self {
+            Self::Vertical => Command::MoveVerticalSteps,
+            Self::Horizontal => Command::MoveHorizontalSteps,
+        }
+    }
+
+    const fn calibrate_command(self) -> Command {
+        match self {
+            Self::Vertical => Command::CalibrateVertical,
+            Self::Horizontal => Command::CalibrateHorizontal,
+        }
+    }
+
+    /// The [`Direction`] which moves this axis, either positively (up or right)
+    /// or negatively (down or left).
+    pub const fn direction(self, positive: bool) -> Direction {
+        match (self, positive) {
+            (Self::Vertical, true) => Direction::Up,
+            (Self::Vertical, false) => Direction::Down,
+            (Self::Horizontal, true) => Direction::Right,
+            (Self::Horizontal, false) => Direction::Left,
+        }
+    }
+
+    /// The [`Direction`] which stops movement on this axis.
+    pub const fn stop(self) -> Direction {
+        match self {
+            Self::Vertical => Direction::StopVertical,
+            Self::Horizontal => Direction::StopHorizontal,
+        }
+    }
+}
+
 impl TryFrom<&str> for Direction {
     type Error = ();
 
@@ -106,258 +433,2800 @@ impl Display for Direction {
     }
 }
 
-/// A two-axis rotator, utilizing the
-/// [protocol specified here](https://github.com/unl-rocketry/tracker-embedded/blob/main-rust/PROTOCOL.md).
-pub struct Rotator {
-    port: Box<dyn SerialPort>,
+/// A position on both axes, in degrees.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Position {
+    pub vertical: f32,
+    pub horizontal: f32,
 }
 
-#[allow(clippy::missing_errors_doc)]
-impl Rotator {
-    pub const BAUD: u32 = 115_200;
+impl Position {
+    pub const fn get(&self, axis: Axis) -> f32 {
+        match axis {
+            Axis::Vertical => self.vertical,
+            Axis::Horizontal => self.horizontal,
+        }
+    }
 
-    /// Create a new rotator based on a serial port.
-    ///
-    /// # Errors
-    /// If the port does not initalize properly or cannot change to
-    /// [`Self::BAUD`] then this function will error.
-    pub fn new(mut port: Box<dyn SerialPort>) -> Result<Self, io::Error> {
-        port.set_baud_rate(Self::BAUD)?;
-        port.set_timeout(std::time::Duration::from_millis(25))?;
+    /// This position with one axis replaced.
+    pub const fn with(mut self, axis: Axis, degrees: f32) -> Self {
+        match axis {
+            Axis::Vertical => self.vertical = degrees,
+            Axis::Horizontal => self.horizontal = degrees,
+        }
 
-        Ok(Self { port })
+        self
     }
 
-    pub fn port(&self) -> &Box<dyn SerialPort> {
-        &self.port
+    /// Whether both axes are within `tolerance` degrees of `other`, with
+    /// azimuths compared as [`AzimuthConvention::difference`] does.
+    pub fn within(&self, other: &Self, tolerance: f32, convention: AzimuthConvention) -> bool {
+        (self.vertical - other.vertical).abs() <= tolerance
+            && convention.difference(self.horizontal, other.horizontal).abs() <= tolerance
     }
+}
 
-    /// Send a command followed by arguments. Returns either an error if sending failed, or the
-    pub fn send_command(
-        &mut self,
-        command: Command,
-        args: &[&str],
-    ) -> Result<String, std::io::Error> {
-        self.port.clear(serialport::ClearBuffer::All)?;
-
-        let mut command_string = Vec::new();
+/// Where to move an axis with [`Rotator::move_axis`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MoveTarget {
+    /// To an absolute position, as for [`Rotator::set_position`].
+    Degrees(f32),
+    /// By a number of steps, as for [`Rotator::move_steps`].
+    Steps(i32),
+    /// By a number of degrees from the current position.
+    RelativeDegrees(f32),
+}
 
-        self.port.write_all(command.to_string().as_bytes())?;
-        command_string.write_all(command.to_string().as_bytes())?;
+/// What an axis was last commanded to do, so that a motor which has stopped
+/// short can be told apart from one which has finished its move.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Motion {
+    /// Moving towards a position set with [`Rotator::set_position`].
+    Toward(f32),
+    /// Moving indefinitely, from [`Rotator::move_direction`].
+    Jogging,
+}
 
-        for arg in args {
-            self.port.write_all(b" ")?;
-            command_string.write_all(b" ")?;
+/// Decides when a moving rotator has settled on a target: every reading must
+/// be within the tolerance for at least the settle time, so an axis which
+/// overshoots and swings back is not considered done on its first pass.
+#[derive(Debug, Clone)]
+pub struct SettleTracker {
+    target: Position,
+    tolerance: f32,
+    convention: AzimuthConvention,
+    settle_time: Duration,
+    within_since: Option<Instant>,
+}
 
-            self.port.write_all(arg.as_bytes())?;
-            command_string.write_all(arg.as_bytes())?;
+impl SettleTracker {
+    pub fn new(target: Position, config: &RotatorConfig) -> Self {
+        Self {
+            target,
+            tolerance: config.position_tolerance,
+            convention: config.frame.azimuth_convention,
+            settle_time: Duration::from_millis(config.settle_time_ms),
+            within_since: None,
         }
+    }
 
-        self.port.write_all(b"\n")?;
-        command_string.write_all(b"\n")?;
+    /// Record a reading taken at `at`, returning whether the position has settled.
+    pub fn update(&mut self, position: Position, at: Instant) -> bool {
+        if position.within(&self.target, self.tolerance, self.convention) {
+            let since = *self.within_since.get_or_insert(at);
+            at.duration_since(since) >= self.settle_time
+        } else {
+            self.within_since = None;
+            false
+        }
+    }
+}
 
-        let command_string = String::from_utf8_lossy(&command_string).to_string();
+/// A move sent with [`Rotator::start_goto`], which is waited on with the
+/// rotator unlocked so that it can still be halted, and polled, meanwhile.
+#[derive(Debug)]
+#[must_use]
+pub struct MoveWait {
+    target: Position,
+    tracker: SettleTracker,
+    /// [`Rotator::stops`] when the move was sent.
+    stops: u64,
+    /// Whether to make a [`Rotator::refine`] pass once it has settled.
+    refine: bool,
+    /// The speed to put back once the move is over, if it was changed for it.
+    restore_speed: Option<f32>,
+}
 
-        Ok(command_string)
+impl MoveWait {
+    /// Keeps the speed the move was made at once it is over, rather than
+    /// putting the previous one back.
+    pub const fn keep_speed(&mut self) {
+        self.restore_speed = None;
     }
 
-    /// Send a raw message.
-    fn _send_message(&mut self, message: &str) -> Result<(), std::io::Error> {
-        dbg!(message);
-        self.port.write_all(message.as_bytes())?;
-        self.port.write_all(b"\n")?;
+    /// Waits until the rotator reports that it has arrived and settled, as
+    /// decided by [`SettleTracker`], locking it only to read each position.
+    /// If `fine.enabled` is set, [`Rotator::refine`] then corrects any
+    /// overshoot.
+    ///
+    /// # Errors
+    /// Errors if reading the position fails, with [`Error::Interrupted`] if
+    /// the rotator is halted or stopped first, or with [`Error::Timeout`] if
+    /// the position is not reached within `timeout`.
+    pub async fn finish(mut self, rotator: &Mutex<Rotator>, timeout: Duration) -> Result<(), Error> {
+        let result = self.settle(rotator, timeout).await;
 
-        Ok(())
+        self.end(rotator, result).await
     }
 
-    /// Read the rotator response and determine errors or validation
-    pub fn validate_parse(&mut self, command_string: &str) -> Result<Option<Vec<String>>, io::Error> {
-        let mut response_string = String::new();
+    async fn settle(&mut self, rotator: &Mutex<Rotator>, timeout: Duration) -> Result<(), Error> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if self.poll(rotator).await?.1 {
+                return self.refine(rotator).await;
+            }
 
-        // Fill up the result string with what the rotator spits out
-        let mut buffer = [0; 2048];
-        while let Ok(num_read) = self.port.read(&mut buffer)
-            && num_read != 0
-        {
-            let Ok(read_buffer) = str::from_utf8(&buffer[..num_read]) else {
-                return Err(io::Error::other("invalid response"));
-            };
+            if Instant::now() >= deadline {
+                return Err(Error::Timeout);
+            }
 
-            response_string.push_str(read_buffer);
+            tokio::time::sleep(POSITION_POLL_INTERVAL).await;
         }
+    }
 
-        // Split the response into "lines" by the newline characters
-        let response_lines: Vec<_> = response_string.split_terminator('\n').collect();
+    /// Reads the position once, locking the rotator only for the reading,
+    /// returning it and whether the move has now settled. For waiting on the
+    /// move step by step instead of with [`Self::finish`], in which case
+    /// [`Self::refine`] and [`Self::end`] are left to the caller.
+    ///
+    /// # Errors
+    /// Errors if reading the position fails, or with [`Error::Interrupted`]
+    /// if the rotator has been halted or stopped since the move was sent.
+    pub async fn poll(&mut self, rotator: &Mutex<Rotator>) -> Result<(Position, bool), Error> {
+        let position = Rotator::poll_position(rotator, self.stops).await?;
 
-        dbg!(&response_lines);
+        Ok((position, self.tracker.update(position, Instant::now())))
+    }
 
-        // The first line should be an echo of what was sent
-        if *response_lines
-            .first()
-            .ok_or_else(|| io::Error::other("response empty"))?
-            != command_string.trim()
-        {
-            return Err(io::Error::other("invalid response"));
+    /// Corrects any overshoot once the move has settled, if `fine.enabled`
+    /// is set.
+    pub async fn refine(&self, rotator: &Mutex<Rotator>) -> Result<(), Error> {
+        if !self.refine {
+            return Ok(());
         }
 
-        // Split the second line into a status followed by the return values
-        if response_lines.len() < 2 {
-            return Err(io::Error::other("response not two lines"))
-        }
+        Rotator::refine(rotator, self.target, self.stops).await
+    }
 
-        let response_list: Vec<&str> = response_lines[1].splitn(2, ' ').collect();
-        dbg!(&response_list);
-        match response_list[0] {
-            "ERR" => return Err(io::Error::other(response_list[1].to_string())),
-            "OK" => (),
-            _ => return Err(io::Error::other("invalid response")),
+    /// Ends the move with `result`, putting the previous speed back if it
+    /// was changed for the move.
+    pub async fn end(self, rotator: &Mutex<Rotator>, result: Result<(), Error>) -> Result<(), Error> {
+        if let Some(previous) = self.restore_speed
+            && let Err(e) = rotator.lock().await.set_speed(previous).await
+        {
+            warn!("Failed to restore the speed to {previous} after moving: {e}");
+            return result.and(Err(e));
         }
 
-        // Split the return values further
-        let response_list: Vec<_> = response_list[1]
-            .split_ascii_whitespace()
-            .map(std::string::ToString::to_string)
-            .collect();
+        result
+    }
+}
 
-        if response_list.is_empty() {
-            Ok(None)
-        } else {
-            Ok(Some(response_list))
-        }
+/// A [`Rotator`] with an overridden command timeout, from
+/// [`Rotator::with_timeout`]. The configured timeout is restored when dropped.
+pub struct TimeoutOverride<'a> {
+    rotator: &'a mut Rotator,
+}
+
+impl Deref for TimeoutOverride<'_> {
+    type Target = Rotator;
+
+    fn deref(&self) -> &Self::Target {
+        self.rotator
     }
+}
 
-    pub async fn set_position_vertical(&mut self, degrees: f32) -> Result<(), Error> {
-        let cmd_string = self.send_command(Command::DegreesVertical, &[&format!("{degrees:0.3}")])?;
-        self.validate_parse(&cmd_string)?;
+impl DerefMut for TimeoutOverride<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.rotator
+    }
+}
 
-        Ok(())
+impl Drop for TimeoutOverride<'_> {
+    fn drop(&mut self) {
+        let timeout = Duration::from_millis(self.rotator.config.command_timeout_ms);
+        let _ = self.rotator.port.set_timeout(timeout);
     }
+}
 
-    /// Set a defined position for the rotator in the horizontal axis.
-    pub async fn set_position_horizontal(&mut self, degrees: f32) -> Result<(), Error> {
-        let cmd_string = self.send_command(
-            Command::DegreesHorizontal,
-            &[&format!("{:0.3}", degrees.neg())],
-        )?;
-        self.validate_parse(&cmd_string)?;
+/// How often [`MoveWait::finish`] polls the position.
+pub const POSITION_POLL_INTERVAL: Duration = Duration::from_millis(100);
 
-        Ok(())
+/// A two-axis rotator, utilizing the
+/// [protocol specified here](https://github.com/unl-rocketry/tracker-embedded/blob/main-rust/PROTOCOL.md).
+pub struct Rotator {
+    port: Box<dyn SerialPort>,
+    config: RotatorConfig,
+    /// The limits as configured, before any unset ones are filled in from
+    /// the firmware by [`Self::seed_limits`].
+    configured_limits: PerAxis<Option<Limits>>,
+    /// Set between sending a command and reading its response.
+    in_transaction: bool,
+    /// How many times the motors have been halted or stopped, see
+    /// [`Self::stops`].
+    stops: u64,
+    /// When the command awaiting a response was sent.
+    sent_at: Option<(Instant, DateTime<Utc>)>,
+    /// When the last command was sent, for keepalives.
+    last_sent: Option<Instant>,
+    history: History,
+    /// Where every exchange is written, if configured.
+    exchange_log: Option<ExchangeLog>,
+    metrics: Metrics,
+    /// What each axis is expected to be doing. Step moves are not tracked.
+    motion: PerAxis<Option<Motion>>,
+    /// The firmware's operating mode, as last seen. `None` if it has not been
+    /// read yet, or the firmware has no modes.
+    mode: Option<mode::Mode>,
+    /// Set when the port fails mid-command, until [`Self::reconnect`].
+    disconnected: bool,
+    /// Axes whose motors were powered off with [`Self::set_motor_enabled`].
+    motors_disabled: PerAxis<bool>,
+    /// When the rotator was last armed or moved while armed, see
+    /// [`Self::arm`].
+    armed_at: Option<Instant>,
+    /// When the rotator was last halted with [`Self::halt`], for
+    /// `halt_cooldown_ms`.
+    halted_at: Option<Instant>,
+    /// Which lines responses are made up of.
+    shape: ResponseShape,
+    /// Whether the firmware counts steps itself. `None` until it is asked.
+    firmware_odometer: Option<bool>,
+    /// Movement counted from the commands sent since connecting.
+    software_odometer: odometer::SoftwareOdometer,
+    /// Where each axis was last sent, kept if the startup position is the
+    /// last one.
+    last_position: PerAxis<Option<f32>>,
+    /// Sends the last position to be saved in the background, once anything
+    /// has moved.
+    last_position_saver: Option<watch::Sender<PerAxis<Option<f32>>>>,
+    /// The last exchange with the rotator, only kept if `debug` is set.
+    last_exchange: Option<RawExchange>,
+    /// The firmware version, once read. It can only change if the rotator is
+    /// replaced or reflashed, which means reconnecting.
+    version: Option<String>,
+    /// The build the firmware gave with its version, if any.
+    firmware_build: Option<FirmwareBuild>,
+}
+
+#[allow(clippy::missing_errors_doc)]
+impl Rotator {
+    pub const BAUD: u32 = 115_200;
+
+    /// Create a new rotator based on a serial port, using the default
+    /// [`RotatorConfig`].
+    ///
+    /// # Errors
+    /// If the port does not initalize properly or cannot change to
+    /// [`Self::BAUD`] then this function will error.
+    pub fn new(port: Box<dyn SerialPort>) -> Result<Self, io::Error> {
+        Self::with_config(port, RotatorConfig::default())
     }
 
-    /// Calibrates the vertical axis.
-    pub async fn calibrate_vertical(&mut self, set: bool) -> Result<(), Error> {
-        let cmd_string = if set {
-            self.send_command(Command::CalibrateVertical, &["SET"])?
-        } else {
-            self.send_command(Command::CalibrateVertical, &[])?
+    /// Create a new rotator based on a serial port, applying the line
+    /// settings from `config`.
+    ///
+    /// If `halt_on_connect` is set, the motors are halted straight away. A
+    /// failed halt is only logged, as the rotator may not be connected yet.
+    ///
+    /// # Errors
+    /// If the port does not initalize properly or rejects any of the
+    /// configured settings then this function will error.
+    pub fn with_config(mut port: Box<dyn SerialPort>, config: RotatorConfig) -> Result<Self, io::Error> {
+        Self::configure_port(&mut port, &config)?;
+
+        let exchange_log = ExchangeLog::open(&config.exchange_log)?;
+        let shape = config
+            .response_shape
+            .unwrap_or_else(|| ResponseShape::new(config.echo_enabled.unwrap_or(true), Verbosity::Terse));
+        let last_position = match config.startup_position {
+            startup::StartupPosition::Last => startup::load_last_position(&config.last_position_path),
+            _ => PerAxis::default(),
         };
 
-        self.validate_parse(&cmd_string)?;
+        let mut rotator = Self {
+            port,
+            history: History::new(config.history_size),
+            exchange_log,
+            configured_limits: config.limits,
+            config,
+            in_transaction: false,
+            stops: 0,
+            sent_at: None,
+            last_sent: None,
+            metrics: Metrics::default(),
+            motion: PerAxis::default(),
+            mode: None,
+            disconnected: false,
+            motors_disabled: PerAxis::default(),
+            armed_at: None,
+            halted_at: None,
+            shape,
+            firmware_odometer: None,
+            software_odometer: odometer::SoftwareOdometer::default(),
+            last_position,
+            last_position_saver: None,
+            last_exchange: None,
+            version: None,
+            firmware_build: None,
+        };
+        rotator.on_connect();
 
-        Ok(())
+        Ok(rotator)
     }
 
-    /// Calibrates the horizontal axis.
-    pub async fn calibrate_horizontal(&mut self) -> Result<(), Error> {
-        let cmd_string = self.send_command(Command::CalibrateHorizontal, &[])?;
-        self.validate_parse(&cmd_string)?;
+    /// Switch to a new serial port, e.g. after the rotator was unplugged,
+    /// keeping the configuration, history, and metrics.
+    ///
+    /// # Errors
+    /// If the port rejects any of the configured settings then this function
+    /// will error, and the previous port is kept.
+    pub fn reconnect(&mut self, mut port: Box<dyn SerialPort>) -> Result<(), io::Error> {
+        Self::configure_port(&mut port, &self.config)?;
+
+        self.port = port;
+        self.disconnected = false;
+        self.in_transaction = false;
+        self.sent_at = None;
+        self.motion = PerAxis::default();
+        self.mode = None;
+        self.motors_disabled = PerAxis::default();
+        self.armed_at = None;
+        self.halted_at = None;
+        self.version = None;
+        self.firmware_build = None;
+        self.firmware_odometer = None;
+        self.software_odometer = odometer::SoftwareOdometer::default();
+        self.on_connect();
 
         Ok(())
     }
 
-    /// Moves in a direction indefinitely specified by the command, or stops, if the command is to stop.
-    pub async fn move_direction(&mut self, direction: Direction) -> Result<(), Error> {
-        let cmd_string =
-            self.send_command(Command::CalibrateHorizontal, &[&direction.to_string()])?;
-        self.validate_parse(&cmd_string)?;
+    fn configure_port(port: &mut Box<dyn SerialPort>, config: &RotatorConfig) -> Result<(), io::Error> {
+        port.set_baud_rate(Self::BAUD)?;
+        port.set_data_bits(config.data_bits)?;
+        port.set_flow_control(config.flow_control)?;
+        port.set_parity(config.parity)?;
+        port.set_stop_bits(config.stop_bits)?;
+        port.set_timeout(Duration::from_millis(config.command_timeout_ms))?;
 
         Ok(())
     }
 
-    /// Moves by the specified number of steps in the vertical axis.
-    pub async fn move_vertical_steps(&mut self, steps: i32) -> Result<(), Error> {
-        let cmd_string = self.send_command(Command::MoveVerticalSteps, &[&steps.to_string()])?;
-        self.validate_parse(&cmd_string)?;
+    fn on_connect(&mut self) {
+        self.shape = match self.config.response_shape {
+            Some(shape) => shape,
+            None => self.probe_protocol().unwrap_or_else(|e| {
+                warn!("Failed to probe the rotator's response format, assuming it is terse: {e}");
+                ResponseShape::new(self.config.echo_enabled.unwrap_or(true), Verbosity::Terse)
+            }),
+        };
 
-        Ok(())
+        if self.config.halt_on_connect
+            && let Err(e) = self
+                .send_command_blocking(Command::Halt, &[])
+                .and_then(|cmd_string| self.read_halt(&cmd_string))
+        {
+            warn!("Failed to halt the rotator on connect: {e}");
+        }
     }
 
-    /// Moves by the specified number of steps in the horizontal axis.
-    pub async fn move_horizontal_steps(&mut self, steps: i32) -> Result<(), Error> {
-        let cmd_string = self.send_command(Command::MoveHorizontalSteps, &[&steps.to_string()])?;
-        self.validate_parse(&cmd_string)?;
+    /// Works out the shape of the firmware's responses: whether it echoes
+    /// commands, unless `echo_enabled` says, and its [`Self::verbosity`].
+    fn probe_protocol(&mut self) -> Result<ResponseShape, Error> {
+        let echo = match self.config.echo_enabled {
+            Some(echo) => echo,
+            None => self.detect_echo()?,
+        };
 
-        Ok(())
+        // Read the verbosity as if it were verbose, which also takes in
+        // values on the status line
+        self.shape = ResponseShape::new(echo, Verbosity::Verbose);
+        let verbosity = match self.verbosity() {
+            Ok(verbosity) => verbosity,
+            Err(Error::Unsupported(_)) => Verbosity::Terse,
+            Err(e) => return Err(e),
+        };
+        if verbosity == Verbosity::Verbose {
+            info!("The rotator sends verbose responses");
+        }
+
+        Ok(ResponseShape::new(echo, verbosity))
     }
 
-    /// Gets the current position for both the vertical and horizontal axes.
-    pub async fn position(&mut self) -> Result<(f32, f32), Error> {
-        let cmd_string = self.send_command(Command::GetPosition, &[])?;
-        let value_list = self
-            .validate_parse(&cmd_string)?
-            .ok_or_else(|| io::Error::other("ExpectedValue"))?;
+    /// Whether the firmware is set to send values on lines of their own
+    /// after the status line. This is only asked while connecting, so it
+    /// blocks, see [`Self::send_command_blocking`].
+    ///
+    /// # Errors
+    /// Returns [`Error::Unsupported`] if the firmware can't say, in which case
+    /// it is most likely terse.
+    pub fn verbosity(&mut self) -> Result<Verbosity, Error> {
+        let cmd_string = self.send_command_blocking(Command::GetVerbosity, &[])?;
+        let values = self
+            .validate_optional(Command::GetVerbosity, &cmd_string)?
+            .ok_or(Error::ExpectedValue(Command::GetVerbosity))?;
+
+        let [verbosity] = exact_values(values)?;
+        match verbosity.as_str() {
+            "TERSE" | "0" => Ok(Verbosity::Terse),
+            "VERBOSE" | "1" => Ok(Verbosity::Verbose),
+            _ => Err(Error::InvalidResponse),
+        }
+    }
 
-        if value_list.len() != 2 {
-            Err(io::Error::other("InvalidResponse"))?
+    /// Works out whether the firmware echoes commands, by sending one and
+    /// seeing whether the response starts with the echo or the status.
+    fn detect_echo(&mut self) -> Result<bool, Error> {
+        self.shape = ResponseShape::EchoStatus;
+
+        let cmd_string = self.send_command_blocking(Command::GetVersion, &[])?;
+        match self.validate_parse(&cmd_string) {
+            Ok(_) | Err(Error::Firmware(_)) => Ok(true),
+            Err(Error::EchoMismatch { got, .. }) if Response::parse(&got).is_ok() => {
+                info!("The rotator does not echo commands");
+                Ok(false)
+            }
+            Err(e) => Err(e),
         }
+    }
 
-        let (v, h) = (
-            value_list[0]
-                .parse::<f32>()
-                .map_err(|e: ParseFloatError| io::Error::other(e.to_string()))?,
-            value_list[1]
-                .parse::<f32>()
-                .map_err(|e: ParseFloatError| io::Error::other(e.to_string()))?,
-        );
+    /// How long since the last command was sent, or `None` if none has been.
+    pub fn idle_for(&self) -> Option<Duration> {
+        self.last_sent.map(|sent| sent.elapsed())
+    }
 
-        Ok((v, h))
+    /// Whether the port failed mid-command, see [`Error::Disconnected`].
+    pub const fn is_disconnected(&self) -> bool {
+        self.disconnected
     }
 
-    /// Gets the calibration status of the rotator. This must be true to use
-    /// `set_position_vertical` and `set_position_horizontal`.
-    pub async fn calibrated(&mut self) -> Result<bool, Error> {
-        let cmd_string = self.send_command(Command::GetCalibrated, &[])?;
+    /// Whether an I/O error means the port itself has gone, rather than the
+    /// rotator just not answering in time.
+    fn is_disconnect(e: &io::Error) -> bool {
+        !matches!(
+            e.kind(),
+            io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock | io::ErrorKind::Interrupted
+        )
+    }
 
-        let value_list = self
-            .validate_parse(&cmd_string)?
-            .ok_or_else(|| io::Error::other("ExpectedValue"))?;
+    /// Mark the rotator as disconnected if `e` means the port has gone.
+    fn check_disconnect(&mut self, e: io::Error) -> Error {
+        if Self::is_disconnect(&e) {
+            warn!("Rotator port failed, marking it disconnected: {e}");
+            self.disconnected = true;
+            self.in_transaction = false;
+            self.sent_at = None;
 
+            Error::Disconnected
+        } else {
+            Error::IOError(e)
+        }
+    }
 
-        let calibrated = value_list[0]
-            .parse::<bool>()
-            .map_err(|e: ParseBoolError| io::Error::other(e.to_string()))?;
+    pub fn port(&self) -> &Box<dyn SerialPort> {
+        &self.port
+    }
 
-        Ok(calibrated)
+    pub fn config(&self) -> &RotatorConfig {
+        &self.config
     }
 
-    /// Immediately stops both motors by locking them to perform an emergency stop.
-    pub async fn halt(&mut self) -> Result<(), Error> {
-        let cmd_string = self.send_command(Command::Halt, &[])?;
-        self.validate_parse(&cmd_string)?;
+    /// Apply new settings while running, see [`RotatorConfig::reload`].
+    ///
+    /// The limits are compared as configured, so ones filled in from the
+    /// firmware aren't reported as changed, and are kept unless the
+    /// configured limits did change. Then they need seeding again.
+    pub fn reload_config(&mut self, new: &RotatorConfig) -> Vec<&'static str> {
+        let seeded = mem::replace(&mut self.config.limits, self.configured_limits);
+
+        let names = self.config.reload(new);
+        if names.contains(&"limits") {
+            self.configured_limits = self.config.limits;
+        } else {
+            self.config.limits = seeded;
+        }
 
-        Ok(())
+        names
     }
 
-    /// Gets the current version of the software on the rotator.
-    pub async fn version(&mut self) -> Result<String, Error> {
-        let cmd_string = self.send_command(Command::GetVersion, &[])?;
+    /// What an axis was last commanded to do, if it should still be moving.
+    pub const fn motion(&self, axis: Axis) -> Option<Motion> {
+        *self.motion.get(axis)
+    }
 
-        Ok(self
-            .validate_parse(&cmd_string)?
-            .ok_or_else(|| io::Error::other("ExpectedValue"))?[0].clone())
+    /// Forget about an axis's commanded motion, e.g. once it has arrived.
+    pub const fn clear_motion(&mut self, axis: Axis) {
+        *self.motion.get_mut(axis) = None;
     }
 
-    pub async fn errors(&mut self) -> Result<String, Error> {
-        let cmd_string = self.send_command(Command::GetErrors, &[])?;
+    /// Temporarily use a different command timeout, for as long as the
+    /// returned guard is held. `None` keeps the configured timeout.
+    ///
+    /// # Errors
+    /// Returns [`Error::OutOfRange`] if `timeout_ms` is longer than the
+    /// configured `max_command_timeout_ms`.
+    pub fn with_timeout(&mut self, timeout_ms: Option<u64>) -> Result<TimeoutOverride<'_>, Error> {
+        if let Some(timeout_ms) = timeout_ms {
+            let max = self.config.max_command_timeout_ms;
+            if timeout_ms > max {
+                return Err(Error::OutOfRange {
+                    requested: timeout_ms as f64,
+                    min: 0.0,
+                    max: max as f64,
+                });
+            }
+
+            self.port.set_timeout(Duration::from_millis(timeout_ms))?;
+        }
 
-        let value_list = self
-            .validate_parse(&cmd_string)?
-            .ok_or_else(|| io::Error::other("ExpectedValue"))?;
+        Ok(TimeoutOverride { rotator: self })
+    }
 
+    /// Send a command followed by arguments. Returns either an error if sending failed, or the
+    /// command string which was sent, to be passed to [`Self::validate_parse`].
+    ///
+    /// # Errors
+    /// Returns [`Error::Busy`] if the response to a previous command has not
+    /// been read yet, rather than interleaving the two exchanges.
+    pub async fn send_command(
+        &mut self,
+        command: Command,
+        args: &[&str],
+    ) -> Result<String, Error> {
+        let command_string = self.write_command(command, args)?;
+        let sent_at = (Instant::now(), Utc::now());
+
+        // Some firmware needs a moment before it starts replying, and reading
+        // too early only sees part of the response. The exchange only counts
+        // as started afterwards, so if the caller gives up during the wait the
+        // unread response is discarded by the next command, rather than
+        // leaving the rotator busy.
+        if self.config.response_delay_ms > 0 {
+            tokio::time::sleep(Duration::from_millis(self.config.response_delay_ms)).await;
+        }
 
-        let error = value_list
-            .join(" ");
+        self.in_transaction = true;
+        self.sent_at = Some(sent_at);
 
-        Ok(error)
+        Ok(command_string)
+    }
+
+    /// Like [`Self::send_command`], but blocks the thread for
+    /// `response_delay_ms`. Only for probing the firmware while connecting,
+    /// which happens outside of any async context.
+    fn send_command_blocking(&mut self, command: Command, args: &[&str]) -> Result<String, Error> {
+        let command_string = self.write_command(command, args)?;
+        let sent_at = (Instant::now(), Utc::now());
+
+        if self.config.response_delay_ms > 0 {
+            std::thread::sleep(Duration::from_millis(self.config.response_delay_ms));
+        }
+
+        self.in_transaction = true;
+        self.sent_at = Some(sent_at);
+
+        Ok(command_string)
+    }
+
+    /// Send a command without waiting for, or reading, its response, e.g. to
+    /// stop an axis as quickly as possible in a tight jogging loop.
+    ///
+    /// The echo and response are left unread, and are discarded when the next
+    /// command is sent, so whether the rotator accepted the command is never
+    /// known. Nothing is recorded in the history or metrics.
+    ///
+    /// # Errors
+    /// Returns [`Error::Busy`] if the response to a previous command has not
+    /// been read yet.
+    pub fn send_no_wait(&mut self, command: Command, args: &[&str]) -> Result<(), Error> {
+        self.write_command(command, args)?;
+
+        Ok(())
+    }
+
+    /// Write a command to the port, after discarding anything left unread.
+    fn write_command(&mut self, command: Command, args: &[&str]) -> Result<String, Error> {
+        if self.disconnected {
+            return Err(Error::Disconnected);
+        }
+        if self.in_transaction {
+            return Err(Error::Busy);
+        }
+
+        if self.config.flush_before_command {
+            self.flush()?;
+        } else {
+            self.port.clear(serialport::ClearBuffer::All)?;
+        }
+
+        let mut command_string = String::new();
+        if let Some(address) = &self.config.address {
+            command_string.push_str(address);
+            command_string.push(' ');
+        }
+        command_string.push_str(&command.to_string());
+        for arg in args {
+            command_string.push(' ');
+            command_string.push_str(arg);
+        }
+        command_string.push_str(&self.config.line_terminator);
+
+        if let Err(e) = self.port.write_all(command_string.as_bytes()) {
+            return Err(self.check_disconnect(e));
+        }
+        self.last_sent = Some(Instant::now());
+
+        Ok(command_string)
+    }
+
+    /// Discards everything waiting to be read or written, including bytes
+    /// still arriving from an exchange which timed out partway, so they can't
+    /// be mistaken for the response to the next command. Returns how many
+    /// bytes were read and discarded.
+    ///
+    /// Any response still waiting to be read is given up on, so this also
+    /// recovers from [`Error::Busy`].
+    ///
+    /// # Errors
+    /// Returns [`Error::Disconnected`] if the port has failed.
+    pub fn flush(&mut self) -> Result<usize, Error> {
+        /// How long the port must stay quiet for it to be considered drained.
+        const QUIET: Duration = Duration::from_millis(5);
+        /// The longest to keep draining a port which won't stop sending.
+        const MAX_DRAIN: Duration = Duration::from_millis(200);
+
+        if self.disconnected {
+            return Err(Error::Disconnected);
+        }
+
+        self.in_transaction = false;
+        self.sent_at = None;
+        self.port.clear(serialport::ClearBuffer::All)?;
+
+        let timeout = self.port.timeout();
+        self.port.set_timeout(QUIET)?;
+
+        let started = Instant::now();
+        let mut buffer = [0; 256];
+        let mut discarded = 0;
+        let result = loop {
+            if started.elapsed() >= MAX_DRAIN {
+                break Ok(());
+            }
+
+            match self.port.read(&mut buffer) {
+                Ok(0) => break Ok(()),
+                Ok(num_read) => discarded += num_read,
+                Err(e) if !Self::is_disconnect(&e) => break Ok(()),
+                Err(e) => break Err(self.check_disconnect(e)),
+            }
+        };
+
+        self.port.set_timeout(timeout)?;
+        result?;
+
+        if discarded > 0 {
+            debug!("Discarded {discarded} stale bytes from the rotator");
+        }
+
+        Ok(discarded)
+    }
+
+    /// Read the rotator response and determine errors or validation
+    pub fn validate_parse(&mut self, command_string: &str) -> Result<Option<Vec<String>>, Error> {
+        let response = self.read_response(command_string)?;
+
+        match response.status {
+            Status::Err => Err(Error::Firmware(response.message())),
+            Status::Ok if response.values.is_empty() => Ok(None),
+            Status::Ok => Ok(Some(response.values)),
+        }
+    }
+
+    /// Read the rotator's response to a command. A well-formed `ERR` response
+    /// is returned as a [`Response`] with [`Status::Err`], rather than as an error.
+    ///
+    /// The exchange is recorded in the [`History`] and [`Metrics`].
+    pub fn read_response(&mut self, command_string: &str) -> Result<Response, Error> {
+        self.in_transaction = false;
+        let (sent_at, sent) = self.sent_at.take().unwrap_or_else(|| (Instant::now(), Utc::now()));
+
+        let mut response_bytes = Vec::new();
+        let mut first_byte = None;
+
+        // Fill up the result with what the rotator spits out. This is only
+        // decoded once everything is read, as a single read can stop partway
+        // through a line or a multibyte character.
+        let mut buffer = vec![0; self.config.read_buffer_size.max(1)];
+        let mut read_error = None;
+        loop {
+            match self.port.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(num_read) => {
+                    first_byte.get_or_insert_with(|| sent_at.elapsed());
+                    response_bytes.extend_from_slice(&buffer[..num_read]);
+                }
+                // Reading until the port times out is how the end of a
+                // response is found
+                Err(e) if !Self::is_disconnect(&e) => break,
+                Err(e) => {
+                    read_error = Some(e);
+                    break;
+                }
+            }
+        }
+        let total = sent_at.elapsed();
+
+        let result = match read_error {
+            Some(e) => Err(self.check_disconnect(e)),
+            None => self.parse_response(command_string, &response_bytes),
+        };
+
+        let exchange = Exchange::new(sent, command_string, &response_bytes, first_byte, total, &result);
+        self.metrics.record(&exchange);
+        if let Some(log) = &mut self.exchange_log {
+            log.write(&exchange);
+        }
+        if self.config.debug {
+            self.last_exchange = Some(RawExchange {
+                exchange: exchange.clone(),
+                raw: response_bytes,
+            });
+        }
+        self.history.push(exchange);
+
+        result
+    }
+
+    fn parse_response(&self, command_string: &str, response_bytes: &[u8]) -> Result<Response, Error> {
+        // Nothing at all arriving before the port timed out means the
+        // rotator isn't responding
+        if response_bytes.is_empty() {
+            return Err(Error::Timeout);
+        }
+
+        let Ok(response_string) = str::from_utf8(response_bytes) else {
+            return Err(Error::InvalidResponse);
+        };
+
+        // Split the response into "lines" by the line terminator
+        let response_lines: Vec<_> = response_string
+            .split_terminator(self.config.line_terminator.as_str())
+            .collect();
+
+        dbg!(&response_lines);
+
+        // On a shared bus, only lines starting with this rotator's address
+        // are meant for it, and the address is stripped from those
+        let mut command_string = command_string.trim();
+        let response_lines: Vec<_> = match &self.config.address {
+            Some(address) => {
+                command_string = Self::strip_address(command_string, address).unwrap_or(command_string);
+                response_lines
+                    .into_iter()
+                    .filter_map(|line| Self::strip_address(line, address))
+                    .collect()
+            }
+            None => response_lines,
+        };
+
+        self.shape.parse(command_string, response_lines)
+    }
+
+    /// A line without the leading `address`, or `None` if it is addressed to
+    /// something else.
+    fn strip_address<'a>(line: &'a str, address: &str) -> Option<&'a str> {
+        let rest = line.strip_prefix(address)?;
+
+        // `@1` should not match a line for `@12`
+        (rest.is_empty() || rest.starts_with(' ')).then(|| rest.trim_start())
+    }
+
+    /// Recent exchanges with the rotator, oldest first.
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    /// The last exchange with the rotator, if `debug` is set.
+    pub const fn last_exchange(&self) -> Option<&RawExchange> {
+        self.last_exchange.as_ref()
+    }
+
+    /// Send a command which not all firmware implements, and read its response.
+    ///
+    /// # Errors
+    /// If the firmware rejects the command as unknown, with the configured
+    /// `unknown_command_reply`, it is reported as [`Error::Unsupported`].
+    /// Any other `ERR` is returned as [`Error::Firmware`], as the command is
+    /// implemented but failed this time.
+    async fn send_optional(&mut self, command: Command, args: &[&str]) -> Result<Option<Vec<String>>, Error> {
+        let cmd_string = self.send_command(command, args).await?;
+
+        self.validate_optional(command, &cmd_string)
+    }
+
+    /// [`Self::validate_parse`] for a command sent by [`Self::send_optional`].
+    fn validate_optional(&mut self, command: Command, cmd_string: &str) -> Result<Option<Vec<String>>, Error> {
+        self.validate_parse(cmd_string).map_err(|e| match e {
+            Error::Firmware(message) if self.is_unknown_command(&message) => Error::Unsupported(command),
+            e => e,
+        })
+    }
+
+    /// Whether an `ERR` message is the firmware saying it doesn't know the
+    /// command, see `unknown_command_reply`.
+    fn is_unknown_command(&self, message: &str) -> bool {
+        let reply = &self.config.unknown_command_reply;
+
+        message
+            .get(..reply.len())
+            .is_some_and(|start| start.eq_ignore_ascii_case(reply))
+    }
+
+    /// Clamps a position to the configured limits for an axis, counting it in
+    /// the metrics, and logging it if `log_clamped` is set, if it had to be.
+    pub fn clamp_to_limits(&mut self, axis: Axis, degrees: f32) -> f32 {
+        let Some(limits) = self.config.limits.get(axis) else {
+            return degrees;
+        };
+
+        let clamped = limits.clamp(degrees);
+        if clamped != degrees {
+            self.metrics.clamped += 1;
+            if self.config.log_clamped {
+                info!("Clamped {axis:?} position from {degrees} to {clamped}");
+            }
+        }
+
+        clamped
+    }
+
+    /// Checks that [`Self::set_position`] would accept a position, without
+    /// sending it.
+    ///
+    /// # Errors
+    /// Returns [`Error::OutOfRange`] if the position is outside the configured
+    /// convention or limits for the axis.
+    pub fn check_position(&self, axis: Axis, degrees: f32) -> Result<(), Error> {
+        self.config.frame.check(axis, degrees)?;
+
+        match self.config.limits.get(axis) {
+            Some(limits) if !limits.contains(degrees) => Err(Error::OutOfRange {
+                requested: degrees.into(),
+                min: limits.min.into(),
+                max: limits.max.into(),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Counts a command rejected for being out of range in the metrics, and
+    /// logs it if `log_rejected` is set.
+    fn reject(&mut self, axis: Axis, error: Error) -> Error {
+        self.metrics.rejected += 1;
+        if self.config.log_rejected {
+            warn!("Rejected {axis:?} command: {error}");
+        }
+
+        error
+    }
+
+    /// Set a defined position for the rotator on an axis, as an elevation for
+    /// the vertical axis or an azimuth for the horizontal axis. See [`frame`].
+    ///
+    /// # Errors
+    /// Returns [`Error::OutOfRange`] without moving if `degrees` is outside the
+    /// configured limits for the axis.
+    pub async fn set_position(&mut self, axis: Axis, degrees: f32) -> Result<(), Error> {
+        if let Err(e) = self.check_position(axis, degrees) {
+            return Err(self.reject(axis, e));
+        }
+        self.ensure_cooled_down()?;
+        self.ensure_armed()?;
+        self.ensure_motor_enabled(axis).await?;
+
+        // Positioning is refused in some modes, so switch out of them first
+        if let Some(mode) = self.mode
+            && !mode.accepts_positioning()
+        {
+            info!("Switching rotator from {mode} to {} mode to set its position", mode::Mode::Manual);
+            self.set_mode(mode::Mode::Manual).await?;
+        }
+
+        let reading = self.config.frame.to_rotator(axis, degrees);
+
+        let decimals = self.config.position_decimals;
+        let cmd_string = self.send_command(axis.degrees_command(), &[&format!("{reading:0.decimals$}")]).await?;
+        self.validate_parse(&cmd_string)?;
+        *self.motion.get_mut(axis) = Some(Motion::Toward(degrees));
+        self.software_odometer.record_target(axis, degrees);
+        self.save_last_position(axis, degrees);
+
+        Ok(())
+    }
+
+    /// Set a defined position for the rotator in the vertical axis.
+    pub async fn set_position_vertical(&mut self, degrees: f32) -> Result<(), Error> {
+        self.set_position(Axis::Vertical, degrees).await
+    }
+
+    /// Set a defined position for the rotator in the horizontal axis.
+    pub async fn set_position_horizontal(&mut self, degrees: f32) -> Result<(), Error> {
+        self.set_position(Axis::Horizontal, degrees).await
+    }
+
+    /// Calibrates an axis.
+    pub async fn calibrate(&mut self, axis: Axis) -> Result<(), Error> {
+        self.ensure_cooled_down()?;
+        self.ensure_armed()?;
+
+        let cmd_string = self.send_command(axis.calibrate_command(), &[]).await?;
+        self.validate_parse(&cmd_string)?;
+
+        Ok(())
+    }
+
+    /// Calibrates the vertical axis.
+    ///
+    /// # Errors
+    /// Returns [`Error::Unsupported`] without sending anything if `set` is
+    /// true and the firmware is older than `calibrate_set_min_version`.
+    pub async fn calibrate_vertical(&mut self, set: bool) -> Result<(), Error> {
+        self.ensure_cooled_down()?;
+        self.ensure_armed()?;
+
+        if !set {
+            return self.calibrate(Axis::Vertical).await;
+        }
+
+        if let Some(min) = self.config.calibrate_set_min_version.clone() {
+            let version = self.version().await?;
+            if version_at_least(&version, &min) == Some(false) {
+                return Err(Error::Unsupported(Command::CalibrateVertical));
+            }
+        }
+
+        let cmd_string = self.send_command(Command::CalibrateVertical, &["SET"]).await?;
+        self.validate_parse(&cmd_string)?;
+
+        Ok(())
+    }
+
+    /// Calibrates the horizontal axis.
+    pub async fn calibrate_horizontal(&mut self) -> Result<(), Error> {
+        self.calibrate(Axis::Horizontal).await
+    }
+
+    /// Clears the firmware's calibration, so that [`Self::calibrated`] reports
+    /// `false` and positioning is refused until both axes are recalibrated.
+    pub async fn reset_calibration(&mut self) -> Result<(), Error> {
+        self.send_optional(Command::ResetCalibration, &[]).await?;
+
+        Ok(())
+    }
+
+    /// Moves in a direction indefinitely specified by the command, or stops, if the command is to stop.
+    pub async fn move_direction(&mut self, direction: Direction) -> Result<(), Error> {
+        if !direction.is_stop() {
+            self.ensure_cooled_down()?;
+            self.ensure_armed()?;
+            self.ensure_motor_enabled(direction.axis()).await?;
+        }
+
+        let cmd_string = self.send_command(Command::Movement, &[&direction.to_string()]).await?;
+        self.validate_parse(&cmd_string)?;
+        *self.motion.get_mut(direction.axis()) = (!direction.is_stop()).then_some(Motion::Jogging);
+
+        Ok(())
+    }
+
+    /// Moves an axis indefinitely, see [`Axis::direction`] for which way is
+    /// positive.
+    pub async fn move_direction_on(&mut self, axis: Axis, positive: bool) -> Result<(), Error> {
+        self.move_direction(axis.direction(positive)).await
+    }
+
+    /// Moves by the specified number of steps on an axis.
+    ///
+    /// Steps are passed to the firmware as-is, so positive steps move towards
+    /// increasing values of [`Self::position_raw`] on both axes. Note that this
+    /// is the opposite of [`Self::set_position`] on the horizontal axis.
+    ///
+    /// # Errors
+    /// Returns [`Error::OutOfRange`] without moving if `steps` exceeds the
+    /// configured `max_steps` for the axis.
+    pub async fn move_steps(&mut self, axis: Axis, steps: i32) -> Result<(), Error> {
+        if let Some(max) = *self.config.max_steps.get(axis)
+            && steps.unsigned_abs() > max
+        {
+            let error = Error::OutOfRange {
+                requested: steps.into(),
+                min: -f64::from(max),
+                max: max.into(),
+            };
+            return Err(self.reject(axis, error));
+        }
+        self.ensure_cooled_down()?;
+        self.ensure_armed()?;
+        self.ensure_motor_enabled(axis).await?;
+
+        let cmd_string = self.send_command(axis.steps_command(), &[&steps.to_string()]).await?;
+        self.validate_parse(&cmd_string)?;
+        self.software_odometer.record_steps(axis, steps);
+
+        Ok(())
+    }
+
+    /// Moves an axis to a target given in any of the supported units.
+    pub async fn move_axis(&mut self, axis: Axis, target: MoveTarget) -> Result<(), Error> {
+        match target {
+            MoveTarget::Degrees(degrees) => self.set_position(axis, degrees).await,
+            MoveTarget::Steps(steps) => self.move_steps(axis, steps).await,
+            MoveTarget::RelativeDegrees(offset) => {
+                let (vertical, horizontal) = self.position().await?;
+                let current = Position { vertical, horizontal }.get(axis);
+
+                self.set_position(axis, current + offset).await
+            }
+        }
+    }
+
+    /// Moves by the specified number of steps in the vertical axis.
+    pub async fn move_vertical_steps(&mut self, steps: i32) -> Result<(), Error> {
+        self.move_steps(Axis::Vertical, steps).await
+    }
+
+    /// Moves by the specified number of steps in the horizontal axis.
+    pub async fn move_horizontal_steps(&mut self, steps: i32) -> Result<(), Error> {
+        self.move_steps(Axis::Horizontal, steps).await
+    }
+
+    /// Moves an axis a small, configured number of steps (`nudge_steps`) for
+    /// fine adjustment. Positive is up or clockwise, as for [`Axis::direction`].
+    pub async fn nudge(&mut self, axis: Axis, positive: bool) -> Result<(), Error> {
+        // Steps are in the firmware's frame, which may turn the other way
+        let increasing = match axis {
+            Axis::Vertical => positive,
+            Axis::Horizontal => positive != self.config.frame.invert_horizontal,
+        };
+
+        let steps = i32::try_from(self.config.nudge_steps).unwrap_or(i32::MAX);
+        self.move_steps(axis, if increasing { steps } else { -steps }).await
+    }
+
+    /// Gets the current position for both the vertical and horizontal axes,
+    /// in the same frame accepted by [`Self::set_position_vertical`] and
+    /// [`Self::set_position_horizontal`].
+    pub async fn position(&mut self) -> Result<(f32, f32), Error> {
+        let (v, h) = self.position_raw().await?;
+        let (azimuth, elevation) = self.config.frame.rotator_to_az_el(v, h);
+
+        Ok((elevation, azimuth))
+    }
+
+    /// Gets the current position, as [`Self::position`], along with when it
+    /// was read.
+    pub async fn position_with_timestamp(&mut self) -> Result<((f32, f32), Instant), Error> {
+        let ((v, h), at) = self.position_raw_with_timestamp().await?;
+        let (azimuth, elevation) = self.config.frame.rotator_to_az_el(v, h);
+
+        Ok(((elevation, azimuth), at))
+    }
+
+    /// Gets the position as [`Self::position_raw`], along with when it was
+    /// read. The firmware doesn't say when it sampled the position, so this
+    /// is halfway between sending the command and receiving the response.
+    pub async fn position_raw_with_timestamp(&mut self) -> Result<((f32, f32), Instant), Error> {
+        let sent = Instant::now();
+        let position = self.position_raw().await?;
+        let received = Instant::now();
+
+        Ok((position, sent + received.duration_since(sent) / 2))
+    }
+
+    /// Gets the current position exactly as the firmware reports it from
+    /// `GETP`, without any of the transforms applied by [`Self::position`].
+    pub async fn position_raw(&mut self) -> Result<(f32, f32), Error> {
+        let cmd_string = self.send_command(Command::GetPosition, &[]).await?;
+        let value_list = self
+            .validate_parse(&cmd_string)?
+            .ok_or(Error::ExpectedValue(Command::GetPosition))?;
+
+        let [v, h] = exact_values(value_list)?;
+        let (v, h) = (self.parse_reading(&v)?, self.parse_reading(&h)?);
+
+        Ok((v, h))
+    }
+
+    /// Points at an azimuth and elevation.
+    ///
+    /// # Errors
+    /// See [`Self::goto`].
+    pub async fn set_az_el(&mut self, azimuth: f32, elevation: f32) -> Result<(), Error> {
+        self.goto(Position { vertical: elevation, horizontal: azimuth }).await
+    }
+
+    /// Moves to a position on both axes without waiting for it to be reached.
+    /// Each axis is sent even if the other fails, so one can be retried alone.
+    ///
+    /// # Errors
+    /// Returns [`Error::PartialMove`], with the error for each axis which
+    /// failed, if either does.
+    pub async fn goto(&mut self, target: Position) -> Result<(), Error> {
+        let vertical = self.set_position(Axis::Vertical, target.vertical).await.err();
+        let horizontal = self.set_position(Axis::Horizontal, target.horizontal).await.err();
+
+        if vertical.is_none() && horizontal.is_none() {
+            return Ok(());
+        }
+
+        Err(Error::PartialMove {
+            vertical: vertical.map(Box::new),
+            horizontal: horizontal.map(Box::new),
+        })
+    }
+
+    /// Whether the rotator is currently moving, judged by whether its position
+    /// changes by more than the position tolerance over the settle time.
+    ///
+    /// This takes two readings, so it waits for the settle time before returning.
+    pub async fn is_moving(&mut self) -> Result<bool, Error> {
+        let (v, h) = self.position().await?;
+        tokio::time::sleep(Duration::from_millis(self.config.settle_time_ms)).await;
+        let (v2, h2) = self.position().await?;
+
+        Ok(!Position { vertical: v, horizontal: h }.within(
+            &Position { vertical: v2, horizontal: h2 },
+            self.config.position_tolerance,
+            self.config.frame.azimuth_convention,
+        ))
+    }
+
+    /// Moves to a position on both axes, returning a [`MoveWait`] to wait for
+    /// it to be reached with once the rotator is unlocked.
+    ///
+    /// The move is made at `speed` if given, otherwise at `default_slew_rate`
+    /// if set and the firmware has speed control. Either way, the previous
+    /// speed is put back once the move is over.
+    ///
+    /// # Errors
+    /// Returns [`Error::Unsupported`] without moving if `speed` is given and
+    /// the firmware has no speed control, otherwise errors like [`Self::goto`].
+    pub async fn start_goto(&mut self, target: Position, speed: Option<f32>) -> Result<MoveWait, Error> {
+        let restore_speed = match speed {
+            Some(speed) => {
+                let previous = self.speed().await?;
+                self.set_speed(speed).await?;
+                Some(previous)
+            }
+            None => self.apply_default_slew_rate().await?,
+        };
+
+        if let Err(e) = self.goto(target).await {
+            if let Some(previous) = restore_speed
+                && let Err(e) = self.set_speed(previous).await
+            {
+                warn!("Failed to restore the speed to {previous} after a failed move: {e}");
+            }
+            return Err(e);
+        }
+
+        Ok(MoveWait {
+            target,
+            tracker: SettleTracker::new(target, &self.config),
+            stops: self.stops,
+            refine: self.config.fine.enabled,
+            restore_speed,
+        })
+    }
+
+    /// Moves to a position on both axes with [`Self::start_goto`], then waits
+    /// for it to be reached with [`MoveWait::finish`]. The rotator is only
+    /// locked to send the move and to read each position, so it can still be
+    /// halted and polled meanwhile.
+    ///
+    /// # Errors
+    /// See [`Self::start_goto`] and [`MoveWait::finish`].
+    pub async fn goto_and_wait(rotator: &Mutex<Self>, target: Position, timeout: Duration) -> Result<(), Error> {
+        let wait = rotator.lock().await.start_goto(target, None).await?;
+
+        wait.finish(rotator, timeout).await
+    }
+
+    /// Moves to a position as one step of a longer routine, like
+    /// [`Self::goto_and_wait`], unless the rotator has been halted or stopped
+    /// since the routine began, when [`Self::stops`] was `stops`. A halt
+    /// partway through a routine then stops the rest of it from moving,
+    /// including any return to where it started.
+    ///
+    /// # Errors
+    /// Returns [`Error::Interrupted`] without moving if it has been.
+    pub(super) async fn goto_step(
+        rotator: &Mutex<Self>,
+        stops: u64,
+        target: Position,
+        timeout: Duration,
+    ) -> Result<(), Error> {
+        let wait = {
+            let mut rotator = rotator.lock().await;
+            rotator.ensure_not_stopped(stops)?;
+            rotator.start_goto(target, None).await?
+        };
+
+        wait.finish(rotator, timeout).await
+    }
+
+    /// How many times the motors have been halted or stopped since starting,
+    /// so that a move which is being waited on can tell it was interrupted.
+    pub const fn stops(&self) -> u64 {
+        self.stops
+    }
+
+    /// Makes sure the rotator hasn't been halted or stopped since
+    /// [`Self::stops`] was `stops`, before carrying on with a move.
+    pub(super) const fn ensure_not_stopped(&self, stops: u64) -> Result<(), Error> {
+        if self.stops != stops {
+            return Err(Error::Interrupted);
+        }
+
+        Ok(())
+    }
+
+    /// Reads the position during a move which is being waited on, locking
+    /// the rotator only for the reading.
+    ///
+    /// # Errors
+    /// Returns [`Error::Interrupted`] if the rotator has been halted or
+    /// stopped since [`Self::stops`] was `stops`.
+    async fn poll_position(rotator: &Mutex<Self>, stops: u64) -> Result<Position, Error> {
+        let mut rotator = rotator.lock().await;
+        rotator.ensure_not_stopped(stops)?;
+
+        let (vertical, horizontal) = rotator.position().await?;
+
+        Ok(Position { vertical, horizontal })
+    }
+
+    /// Reads an axis as [`Self::position_raw`], locking the rotator only for
+    /// the reading.
+    async fn poll_reading(rotator: &Mutex<Self>, stops: u64, axis: Axis) -> Result<f32, Error> {
+        let mut rotator = rotator.lock().await;
+        rotator.ensure_not_stopped(stops)?;
+
+        let (v, h) = rotator.position_raw().await?;
+
+        Ok(match axis {
+            Axis::Vertical => v,
+            Axis::Horizontal => h,
+        })
+    }
+
+    /// Moves to the configured park position and waits for it to be reached.
+    pub async fn park(rotator: &Mutex<Self>) -> Result<(), Error> {
+        let park = rotator.lock().await.config.park.clone();
+        let target = Position { vertical: park.vertical, horizontal: park.horizontal };
+
+        Self::goto_and_wait(rotator, target, Duration::from_millis(park.timeout_ms)).await
+    }
+
+    /// Gets the calibration status of the rotator. This must be true to use
+    /// `set_position_vertical` and `set_position_horizontal`.
+    pub async fn calibrated(&mut self) -> Result<bool, Error> {
+        let cmd_string = self.send_command(Command::GetCalibrated, &[]).await?;
+
+        let value_list = self
+            .validate_parse(&cmd_string)?
+            .ok_or(Error::ExpectedValue(Command::GetCalibrated))?;
+
+        let [calibrated] = exact_values(value_list)?;
+        let calibrated = calibrated
+            .parse::<bool>()
+            .map_err(|_| Error::InvalidResponse)?;
+
+        Ok(calibrated)
+    }
+
+    /// Gets the limits stored in the firmware for each axis, converted into
+    /// elevation and azimuth like [`Self::position`].
+    ///
+    /// # Errors
+    /// Returns [`Error::Unsupported`] if the firmware does not store limits.
+    pub async fn firmware_limits(&mut self) -> Result<PerAxis<Limits>, Error> {
+        let values = self
+            .send_optional(Command::GetLimits, &[]).await?
+            .ok_or(Error::ExpectedValue(Command::GetLimits))?;
+
+        let [v_min, v_max, h_min, h_max] = exact_values(values)?;
+        let parse = |v: String| self.parse_reading(&v);
+        let frame = &self.config.frame;
+
+        Ok(PerAxis {
+            vertical: frame.limits_from_rotator(Axis::Vertical, parse(v_min)?, parse(v_max)?),
+            horizontal: frame.limits_from_rotator(Axis::Horizontal, parse(h_min)?, parse(h_max)?),
+        })
+    }
+
+    /// Gets the raw readings of each axis's position sensor, such as an
+    /// absolute encoder or potentiometer, in whatever units the firmware
+    /// reports them. Unlike [`Self::position`], these are not derived from the
+    /// steps commanded, so comparing the two shows drift.
+    ///
+    /// # Errors
+    /// Returns [`Error::Unsupported`] if the firmware has no sensor feedback.
+    pub async fn feedback(&mut self) -> Result<PerAxis<f32>, Error> {
+        let values = self
+            .send_optional(Command::GetFeedback, &[]).await?
+            .ok_or(Error::ExpectedValue(Command::GetFeedback))?;
+
+        let [vertical, horizontal] = exact_values(values)?;
+        let parse = |v: String| self.parse_reading(&v);
+
+        Ok(PerAxis {
+            vertical: parse(vertical)?,
+            horizontal: parse(horizontal)?,
+        })
+    }
+
+    /// Reads the firmware's limits, and uses them for any axis without
+    /// software limits configured, so the two stay consistent. Software limits
+    /// which go beyond the firmware's are kept, but logged.
+    ///
+    /// # Errors
+    /// Errors if [`Self::firmware_limits`] does.
+    pub async fn seed_limits(&mut self) -> Result<PerAxis<Limits>, Error> {
+        let firmware = self.firmware_limits().await?;
+
+        for axis in [Axis::Vertical, Axis::Horizontal] {
+            let firmware = firmware.get(axis);
+
+            match self.config.limits.get(axis) {
+                Some(software) if !software.within(firmware) => warn!(
+                    "Configured {axis:?} limits {}..{} go beyond the firmware's {}..{}",
+                    software.min, software.max, firmware.min, firmware.max,
+                ),
+                Some(_) => {}
+                None => *self.config.limits.get_mut(axis) = Some(*firmware),
+            }
+        }
+
+        Ok(firmware)
+    }
+
+    /// Immediately stops both motors by locking them to perform an emergency stop.
+    /// If `halt_cooldown_ms` is set, the rotator then won't move for that long,
+    /// unless [`Self::resume`]d.
+    pub async fn halt(&mut self) -> Result<(), Error> {
+        self.send_halt().await?;
+        self.halted_at = Some(Instant::now());
+
+        Ok(())
+    }
+
+    /// Stops both motors. A hard stop is a [`Self::halt`], while a soft stop
+    /// sends the stop direction for each axis so the motors decelerate, which
+    /// is gentler on the mount.
+    pub async fn stop(&mut self, hard: bool) -> Result<(), Error> {
+        if hard {
+            return self.halt().await;
+        }
+
+        self.move_direction(Direction::StopVertical).await?;
+        self.move_direction(Direction::StopHorizontal).await?;
+        self.stops += 1;
+
+        Ok(())
+    }
+
+    async fn send_halt(&mut self) -> Result<(), Error> {
+        let cmd_string = self.send_command(Command::Halt, &[]).await?;
+        self.read_halt(&cmd_string)
+    }
+
+    fn read_halt(&mut self, cmd_string: &str) -> Result<(), Error> {
+        self.validate_parse(cmd_string)?;
+        self.stops += 1;
+        self.motion = PerAxis::default();
+
+        Ok(())
+    }
+
+    /// Halts both motors like [`Self::halt`], waits for them to come to rest,
+    /// then returns the position they stopped at.
+    ///
+    /// The controller can be slow to answer straight after a halt, so a timed
+    /// out position reading is retried a few times before giving up, and
+    /// `None` is returned if it never answers. The motors have been halted
+    /// either way.
+    ///
+    /// # Errors
+    /// Errors if the halt is rejected, or if reading the position fails other
+    /// than by timing out.
+    pub async fn halt_and_report(&mut self) -> Result<Option<Position>, Error> {
+        const ATTEMPTS: usize = 3;
+
+        self.halt().await?;
+        tokio::time::sleep(Duration::from_millis(self.config.settle_time_ms)).await;
+
+        let mut attempt = 1;
+        loop {
+            match self.position().await {
+                Ok((vertical, horizontal)) => return Ok(Some(Position { vertical, horizontal })),
+                Err(Error::Timeout) if attempt < ATTEMPTS => {
+                    attempt += 1;
+                    tokio::time::sleep(POSITION_POLL_INTERVAL).await;
+                }
+                Err(Error::Timeout) => return Ok(None),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Sends a cheap query and returns how long the rotator took to answer,
+    /// to check that the link is alive.
+    ///
+    /// # Errors
+    /// Returns [`Error::Timeout`] if the rotator does not respond.
+    pub async fn ping(&mut self) -> Result<Duration, Error> {
+        let start = Instant::now();
+
+        let cmd_string = self.send_command(Command::GetVersion, &[]).await?;
+        self.validate_parse(&cmd_string)?;
+
+        Ok(start.elapsed())
+    }
+
+    /// Gets the current version of the software on the rotator. This is only
+    /// read from the rotator the first time after connecting, see
+    /// [`Self::refresh_version`].
+    pub async fn version(&mut self) -> Result<String, Error> {
+        match &self.version {
+            Some(version) => Ok(version.clone()),
+            None => self.refresh_version().await,
+        }
+    }
+
+    /// Reads the version of the software on the rotator, even if it has
+    /// already been read, along with its [`FirmwareBuild`] if it gives one.
+    pub async fn refresh_version(&mut self) -> Result<String, Error> {
+        let cmd_string = self.send_command(Command::GetVersion, &[]).await?;
+
+        let values = self
+            .validate_parse(&cmd_string)?
+            .ok_or(Error::ExpectedValue(Command::GetVersion))?;
+        let version = values[0].clone();
+        self.firmware_build = FirmwareBuild::parse(&values[1..]);
+        self.version = Some(version.clone());
+
+        Ok(version)
+    }
+
+    /// The build the firmware gave with its version, as of the last time the
+    /// version was read. `None` if it gave none, or it hasn't been read yet.
+    pub const fn firmware_build(&self) -> Option<&FirmwareBuild> {
+        self.firmware_build.as_ref()
+    }
+
+    pub async fn errors(&mut self) -> Result<String, Error> {
+        let cmd_string = self.send_command(Command::GetErrors, &[]).await?;
+
+        let value_list = self
+            .validate_parse(&cmd_string)?
+            .ok_or(Error::ExpectedValue(Command::GetErrors))?;
+
+
+        let error = value_list
+            .join(" ");
+
+        Ok(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serialport::{DataBits, FlowControl, Parity, StopBits};
+
+    use super::{mock::{self, MockFirmware}, *};
+
+    #[test]
+    fn line_settings_default_to_8n1_without_flow_control() {
+        let rotator = MockFirmware::new().rotator(mock::config());
+        let port = rotator.port();
+
+        assert_eq!(port.baud_rate().unwrap(), Rotator::BAUD);
+        assert_eq!(port.data_bits().unwrap(), DataBits::Eight);
+        assert_eq!(port.flow_control().unwrap(), FlowControl::None);
+        assert_eq!(port.parity().unwrap(), Parity::None);
+        assert_eq!(port.stop_bits().unwrap(), StopBits::One);
+    }
+
+    #[test]
+    fn configured_line_settings_are_applied() {
+        let config = RotatorConfig {
+            data_bits: DataBits::Seven,
+            flow_control: FlowControl::Hardware,
+            parity: Parity::Even,
+            stop_bits: StopBits::Two,
+            command_timeout_ms: 40,
+            ..mock::config()
+        };
+        let rotator = MockFirmware::new().rotator(config);
+        let port = rotator.port();
+
+        assert_eq!(port.data_bits().unwrap(), DataBits::Seven);
+        assert_eq!(port.flow_control().unwrap(), FlowControl::Hardware);
+        assert_eq!(port.parity().unwrap(), Parity::Even);
+        assert_eq!(port.stop_bits().unwrap(), StopBits::Two);
+        assert_eq!(port.timeout(), Duration::from_millis(40));
+    }
+
+    #[test]
+    fn reconnecting_applies_the_line_settings_to_the_new_port() {
+        let config = RotatorConfig { parity: Parity::Odd, ..mock::config() };
+        let mut rotator = MockFirmware::new().rotator(config);
+
+        rotator.reconnect(MockFirmware::new().port()).unwrap();
+
+        assert_eq!(rotator.port().parity().unwrap(), Parity::Odd);
+    }
+
+    #[rocket::async_test]
+    async fn position_raw_bypasses_the_frame() {
+        let firmware = MockFirmware::new();
+        firmware.lock().position.vertical = 10.0;
+        firmware.lock().position.horizontal = 20.0;
+        let config = RotatorConfig {
+            frame: frame::Frame { elevation_offset: 5.0, azimuth_offset: 100.0, ..Default::default() },
+            ..mock::config()
+        };
+        let mut rotator = firmware.rotator(config);
+
+        assert_eq!(rotator.position_raw().await.unwrap(), (10.0, 20.0));
+        // The horizontal axis is inverted by default
+        assert_eq!(rotator.position().await.unwrap(), (15.0, 80.0));
+    }
+
+    #[rocket::async_test]
+    async fn responses_split_across_reads_are_reassembled() {
+        let firmware = MockFirmware::new();
+        firmware.lock().version = "v1.4.0-héllo".to_string();
+        firmware.lock().position.vertical = 12.5;
+
+        // Reading a byte at a time splits every line, and the two-byte `é`
+        for read_buffer_size in [1, 2, 3, 7] {
+            let mut rotator = firmware.rotator(RotatorConfig { read_buffer_size, ..mock::config() });
+
+            assert_eq!(rotator.refresh_version().await.unwrap(), "v1.4.0-héllo");
+            assert_eq!(rotator.position_raw().await.unwrap(), (12.5, 0.0));
+        }
+    }
+
+    #[test]
+    fn directions_are_classified_by_axis() {
+        let cases = [
+            (Direction::Up, Axis::Vertical, false, Direction::StopVertical),
+            (Direction::Down, Axis::Vertical, false, Direction::StopVertical),
+            (Direction::StopVertical, Axis::Vertical, true, Direction::StopVertical),
+            (Direction::Left, Axis::Horizontal, false, Direction::StopHorizontal),
+            (Direction::Right, Axis::Horizontal, false, Direction::StopHorizontal),
+            (Direction::StopHorizontal, Axis::Horizontal, true, Direction::StopHorizontal),
+        ];
+
+        for (direction, axis, is_stop, stop) in cases {
+            assert_eq!(direction.axis(), axis, "{direction}");
+            assert_eq!(direction.is_vertical(), axis == Axis::Vertical, "{direction}");
+            assert_eq!(direction.is_horizontal(), axis == Axis::Horizontal, "{direction}");
+            assert_eq!(direction.is_stop(), is_stop, "{direction}");
+            assert_eq!(direction.stop().to_string(), stop.to_string(), "{direction}");
+        }
+    }
+
+    #[test]
+    fn axes_move_and_stop_in_their_own_directions() {
+        assert_eq!(Axis::Vertical.direction(true).to_string(), "UP");
+        assert_eq!(Axis::Vertical.direction(false).to_string(), "DN");
+        assert_eq!(Axis::Horizontal.direction(true).to_string(), "RT");
+        assert_eq!(Axis::Horizontal.direction(false).to_string(), "LT");
+        assert!(Axis::Vertical.stop().is_stop() && Axis::Vertical.stop().is_vertical());
+        assert!(Axis::Horizontal.stop().is_stop() && Axis::Horizontal.stop().is_horizontal());
+    }
+
+    #[rocket::async_test]
+    async fn axis_methods_send_the_command_for_their_axis() {
+        let firmware = MockFirmware::new();
+        let mut rotator = firmware.rotator(mock::config());
+
+        rotator.set_position(Axis::Vertical, 10.0).await.unwrap();
+        rotator.set_position(Axis::Horizontal, 10.0).await.unwrap();
+        rotator.move_steps(Axis::Vertical, 5).await.unwrap();
+        rotator.move_steps(Axis::Horizontal, -5).await.unwrap();
+        rotator.calibrate(Axis::Vertical).await.unwrap();
+        rotator.calibrate(Axis::Horizontal).await.unwrap();
+        rotator.move_direction_on(Axis::Vertical, true).await.unwrap();
+        rotator.move_direction_on(Axis::Vertical, false).await.unwrap();
+        rotator.move_direction_on(Axis::Horizontal, true).await.unwrap();
+        rotator.move_direction_on(Axis::Horizontal, false).await.unwrap();
+
+        assert_eq!(
+            firmware.received(),
+            [
+                "DVER 10.000",
+                // Horizontal readings increase counterclockwise by default
+                "DHOR -10.000",
+                "MOVV 5",
+                "MOVH -5",
+                "CALV",
+                "CALH",
+                "MOVC UP",
+                "MOVC DN",
+                "MOVC RT",
+                "MOVC LT",
+            ],
+        );
+    }
+
+    #[rocket::async_test]
+    async fn named_methods_match_the_axis_methods() {
+        let firmware = MockFirmware::new();
+        let mut rotator = firmware.rotator(mock::config());
+
+        rotator.set_position_vertical(10.0).await.unwrap();
+        rotator.set_position_horizontal(10.0).await.unwrap();
+        rotator.move_vertical_steps(5).await.unwrap();
+        rotator.move_horizontal_steps(-5).await.unwrap();
+        rotator.calibrate_horizontal().await.unwrap();
+
+        assert_eq!(firmware.received(), ["DVER 10.000", "DHOR -10.000", "MOVV 5", "MOVH -5", "CALH"]);
+    }
+
+    #[rocket::async_test]
+    async fn line_terminators_are_used_for_writing_and_reading() {
+        for terminator in ["\n", "\r\n"] {
+            let firmware = MockFirmware::new();
+            firmware.lock().line_terminator = terminator.to_string();
+            firmware.lock().position.vertical = 45.0;
+            let config = RotatorConfig { line_terminator: terminator.to_string(), ..mock::config() };
+            let mut rotator = firmware.rotator(config);
+
+            assert_eq!(rotator.version().await.unwrap(), "v1.4.0", "{terminator:?}");
+            assert_eq!(rotator.position_raw().await.unwrap(), (45.0, 0.0), "{terminator:?}");
+            rotator.set_position_vertical(10.0).await.unwrap();
+
+            // Each command arrived whole, with nothing left over
+            assert_eq!(firmware.received(), ["VERS", "GETP", "DVER 10.000"], "{terminator:?}");
+        }
+    }
+
+    #[rocket::async_test]
+    async fn steps_over_the_cap_are_rejected_without_moving() {
+        let firmware = MockFirmware::new();
+        let config = RotatorConfig { max_steps: PerAxis { vertical: Some(100), horizontal: None }, ..mock::config() };
+        let mut rotator = firmware.rotator(config);
+
+        for steps in [101, -101] {
+            let error = rotator.move_steps(Axis::Vertical, steps).await.unwrap_err();
+            assert!(
+                matches!(error, Error::OutOfRange { requested, min, max }
+                    if requested == f64::from(steps) && min == -100.0 && max == 100.0),
+                "{error:?}",
+            );
+        }
+        assert!(firmware.received().is_empty());
+        assert_eq!(rotator.metrics().rejected, 2);
+
+        // Up to the cap is fine, as is any count on an axis without one
+        rotator.move_steps(Axis::Vertical, -100).await.unwrap();
+        rotator.move_steps(Axis::Horizontal, 10_000).await.unwrap();
+        assert_eq!(firmware.received(), ["MOVV -100", "MOVH 10000"]);
+    }
+
+    #[rocket::async_test]
+    async fn positive_steps_increase_the_raw_reading() {
+        let firmware = MockFirmware::new();
+        let mut rotator = firmware.rotator(mock::config());
+
+        rotator.move_steps(Axis::Vertical, 50).await.unwrap();
+        rotator.move_steps(Axis::Horizontal, 50).await.unwrap();
+
+        assert_eq!(rotator.position_raw().await.unwrap(), (5.0, 5.0));
+        // Which turns the horizontal axis counterclockwise in the default frame
+        assert_eq!(rotator.position().await.unwrap(), (5.0, -5.0));
+    }
+
+    #[rocket::async_test]
+    async fn nudges_map_positive_to_up_and_clockwise() {
+        let firmware = MockFirmware::new();
+        let mut rotator = firmware.rotator(RotatorConfig { nudge_steps: 10, ..mock::config() });
+
+        rotator.nudge(Axis::Vertical, true).await.unwrap();
+        rotator.nudge(Axis::Horizontal, true).await.unwrap();
+        rotator.nudge(Axis::Horizontal, false).await.unwrap();
+
+        assert_eq!(firmware.received(), ["MOVV 10", "MOVH -10", "MOVH 10"]);
+    }
+
+    #[rocket::async_test]
+    async fn ping_times_a_version_query() {
+        let firmware = MockFirmware::new();
+        let mut rotator = firmware.rotator(mock::config());
+
+        let latency = rotator.ping().await.unwrap();
+
+        assert!(latency < Duration::from_secs(1));
+        assert_eq!(firmware.commands(), ["VERS"]);
+    }
+
+    #[rocket::async_test]
+    async fn ping_times_out_if_nothing_answers() {
+        let firmware = MockFirmware::new();
+        firmware.lock().silent = true;
+        let mut rotator = firmware.rotator(mock::config());
+
+        assert!(matches!(rotator.ping().await, Err(Error::Timeout)));
+        // The rotator isn't left waiting on the lost response
+        firmware.lock().silent = false;
+        rotator.ping().await.unwrap();
+    }
+
+    #[rocket::async_test]
+    async fn overlapping_commands_are_busy() {
+        let firmware = MockFirmware::new();
+        let mut rotator = firmware.rotator(mock::config());
+
+        let cmd_string = rotator.send_command(Command::GetVersion, &[]).await.unwrap();
+        assert!(matches!(rotator.send_command(Command::GetPosition, &[]).await, Err(Error::Busy)));
+        assert!(matches!(rotator.send_no_wait(Command::Halt, &[]), Err(Error::Busy)));
+        assert_eq!(firmware.commands(), ["VERS"]);
+
+        // The first exchange is unaffected, and frees the port
+        assert_eq!(rotator.validate_parse(&cmd_string).unwrap(), Some(vec!["v1.4.0".to_string()]));
+        rotator.position_raw().await.unwrap();
+    }
+
+    #[rocket::async_test]
+    async fn flushing_recovers_from_busy() {
+        let firmware = MockFirmware::new();
+        let mut rotator = firmware.rotator(mock::config());
+
+        rotator.send_command(Command::GetVersion, &[]).await.unwrap();
+        rotator.flush().unwrap();
+
+        // The abandoned response isn't mistaken for this one
+        firmware.lock().position.vertical = 3.0;
+        assert_eq!(rotator.position_raw().await.unwrap(), (3.0, 0.0));
+    }
+
+    const COMMANDS: [Command; 25] = [
+        Command::DegreesVertical,
+        Command::DegreesHorizontal,
+        Command::CalibrateVertical,
+        Command::CalibrateHorizontal,
+        Command::ResetCalibration,
+        Command::GetLimits,
+        Command::GetMode,
+        Command::SetMode,
+        Command::GetFeedback,
+        Command::MotorVertical,
+        Command::MotorHorizontal,
+        Command::GetSpeed,
+        Command::SetSpeed,
+        Command::GetOdometer,
+        Command::GetVerbosity,
+        Command::GetDeadband,
+        Command::SetDeadband,
+        Command::Movement,
+        Command::MoveVerticalSteps,
+        Command::MoveHorizontalSteps,
+        Command::GetPosition,
+        Command::GetCalibrated,
+        Command::GetVersion,
+        Command::GetErrors,
+        Command::Halt,
+    ];
+
+    const DIRECTIONS: [Direction; 6] = [
+        Direction::Up,
+        Direction::Down,
+        Direction::StopVertical,
+        Direction::Left,
+        Direction::Right,
+        Direction::StopHorizontal,
+    ];
+
+    #[test]
+    fn commands_serialize_as_their_wire_codes() {
+        for command in COMMANDS {
+            let json = serde_json::to_string(&command).unwrap();
+            assert_eq!(json, format!("\"{command}\""));
+
+            let back: Command = serde_json::from_str(&json).unwrap();
+            assert_eq!(back.to_string(), command.to_string());
+        }
+
+        assert!(serde_json::from_str::<Command>("\"NOPE\"").is_err());
+        assert!(serde_json::from_str::<Command>("\"getp\"").is_err());
+    }
+
+    #[test]
+    fn directions_serialize_as_their_wire_codes() {
+        for direction in DIRECTIONS {
+            let json = serde_json::to_string(&direction).unwrap();
+            assert_eq!(json, format!("\"{direction}\""));
+
+            let back: Direction = serde_json::from_str(&json).unwrap();
+            assert_eq!(back.to_string(), direction.to_string());
+        }
+
+        assert!(serde_json::from_str::<Direction>("\"XX\"").is_err());
+    }
+
+    #[test]
+    fn overshooting_readings_only_settle_after_the_settle_time() {
+        let config = RotatorConfig { position_tolerance: 0.5, settle_time_ms: 200, ..RotatorConfig::default() };
+        let target = Position { vertical: 30.0, horizontal: 10.0 };
+        let mut tracker = SettleTracker::new(target, &config);
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+
+        // Swinging through the target and back isn't settling on it
+        let readings = [(0, 25.0), (50, 30.2), (100, 31.0), (150, 29.8), (200, 29.0)];
+        for (ms, vertical) in readings {
+            assert!(!tracker.update(Position { vertical, horizontal: 10.0 }, at(ms)), "{ms}");
+        }
+
+        // Within tolerance from 250ms on
+        for ms in [250, 300, 400] {
+            assert!(!tracker.update(Position { vertical: 30.3, horizontal: 10.1 }, at(ms)), "{ms}");
+        }
+        assert!(tracker.update(Position { vertical: 29.7, horizontal: 9.8 }, at(450)));
+    }
+
+    #[test]
+    fn both_axes_must_be_within_tolerance_to_settle() {
+        let config = RotatorConfig { position_tolerance: 0.5, settle_time_ms: 0, ..RotatorConfig::default() };
+        let mut tracker = SettleTracker::new(Position { vertical: 30.0, horizontal: 10.0 }, &config);
+        let now = Instant::now();
+
+        assert!(!tracker.update(Position { vertical: 30.0, horizontal: 11.0 }, now));
+        assert!(tracker.update(Position { vertical: 30.0, horizontal: 10.5 }, now));
+    }
+
+    #[rocket::async_test]
+    async fn goto_and_wait_waits_out_the_settle_time() {
+        let firmware = MockFirmware::new();
+        let rotator = firmware.shared(RotatorConfig { settle_time_ms: 300, ..mock::config() });
+        let target = Position { vertical: 20.0, horizontal: 0.0 };
+
+        let started = Instant::now();
+        Rotator::goto_and_wait(&rotator, target, Duration::from_secs(5)).await.unwrap();
+
+        assert!(started.elapsed() >= Duration::from_millis(300));
+        assert_eq!(firmware.lock().position.vertical, 20.0);
+    }
+
+    #[rocket::async_test]
+    async fn is_moving_compares_readings_a_settle_time_apart() {
+        let firmware = MockFirmware::new();
+        let mut rotator = firmware.rotator(RotatorConfig { settle_time_ms: 10, ..mock::config() });
+
+        assert!(!rotator.is_moving().await.unwrap());
+
+        firmware.lock().jogging.vertical = 1.0;
+        assert!(rotator.is_moving().await.unwrap());
+    }
+
+    #[test]
+    fn timeout_overrides_last_as_long_as_the_guard() {
+        let config = RotatorConfig { command_timeout_ms: 25, max_command_timeout_ms: 5_000, ..mock::config() };
+        let mut rotator = MockFirmware::new().rotator(config);
+
+        {
+            let rotator = rotator.with_timeout(Some(2_000)).unwrap();
+            assert_eq!(rotator.port().timeout(), Duration::from_millis(2_000));
+        }
+        assert_eq!(rotator.port().timeout(), Duration::from_millis(25));
+
+        let rotator = rotator.with_timeout(None).unwrap();
+        assert_eq!(rotator.port().timeout(), Duration::from_millis(25));
+    }
+
+    #[test]
+    fn timeout_overrides_above_the_cap_are_rejected() {
+        let config = RotatorConfig { command_timeout_ms: 25, max_command_timeout_ms: 5_000, ..mock::config() };
+        let mut rotator = MockFirmware::new().rotator(config);
+
+        let error = rotator.with_timeout(Some(5_001)).err().unwrap();
+        assert!(matches!(error, Error::OutOfRange { max, .. } if max == 5_000.0), "{error:?}");
+        assert_eq!(rotator.port().timeout(), Duration::from_millis(25));
+    }
+
+    #[rocket::async_test]
+    async fn resetting_the_calibration_refuses_positioning_until_recalibrated() {
+        let firmware = MockFirmware::new();
+        let mut rotator = firmware.rotator(mock::config());
+        assert!(rotator.calibrated().await.unwrap());
+
+        rotator.reset_calibration().await.unwrap();
+        assert!(!rotator.calibrated().await.unwrap());
+        let error = rotator.set_position(Axis::Vertical, 10.0).await.unwrap_err();
+        assert!(matches!(&error, Error::Firmware(message) if message == "not calibrated"), "{error:?}");
+
+        rotator.calibrate_vertical(false).await.unwrap();
+        rotator.calibrate_horizontal().await.unwrap();
+        assert!(rotator.calibrated().await.unwrap());
+        rotator.set_position(Axis::Vertical, 10.0).await.unwrap();
+
+        assert_eq!(firmware.commands(), ["GETC", "CALR", "GETC", "DVER", "CALV", "CALH", "GETC", "DVER"]);
+    }
+
+    #[rocket::async_test]
+    async fn resetting_the_calibration_needs_firmware_support() {
+        let firmware = MockFirmware::new();
+        firmware.reply("CALR", "ERR unknown command");
+        let mut rotator = firmware.rotator(mock::config());
+
+        let error = rotator.reset_calibration().await.unwrap_err();
+        assert!(matches!(error, Error::Unsupported(Command::ResetCalibration)), "{error:?}");
+    }
+
+    #[test]
+    fn responses_keep_their_status_and_values() {
+        let values = Response::parse("OK 12.5 90.0").unwrap();
+        assert_eq!(values, Response { status: Status::Ok, values: vec!["12.5".to_string(), "90.0".to_string()] });
+
+        let empty = Response::parse("OK").unwrap();
+        assert_eq!(empty, Response { status: Status::Ok, values: Vec::new() });
+
+        let error = Response::parse("ERR not calibrated").unwrap();
+        assert_eq!(error.status, Status::Err);
+        assert_eq!(error.message(), "not calibrated");
+
+        assert!(matches!(Response::parse("12.5 90.0"), Err(Error::InvalidResponse)));
+        assert!(matches!(Response::parse(""), Err(Error::InvalidResponse)));
+    }
+
+    #[rocket::async_test]
+    async fn validate_parse_adapts_each_kind_of_response() {
+        let firmware = MockFirmware::new();
+        let mut rotator = firmware.rotator(mock::config());
+
+        firmware.reply("GETP", "OK 12.5 90.0");
+        let cmd_string = rotator.send_command(Command::GetPosition, &[]).await.unwrap();
+        assert_eq!(rotator.validate_parse(&cmd_string).unwrap(), Some(vec!["12.5".to_string(), "90.0".to_string()]));
+
+        firmware.reply("GETP", "OK");
+        let cmd_string = rotator.send_command(Command::GetPosition, &[]).await.unwrap();
+        assert_eq!(rotator.validate_parse(&cmd_string).unwrap(), None);
+
+        firmware.reply("GETP", "ERR not calibrated");
+        let cmd_string = rotator.send_command(Command::GetPosition, &[]).await.unwrap();
+        let response = rotator.read_response(&cmd_string).unwrap();
+        assert_eq!(response.status, Status::Err);
+        assert_eq!(response.values, ["not", "calibrated"]);
+
+        let cmd_string = rotator.send_command(Command::GetPosition, &[]).await.unwrap();
+        let error = rotator.validate_parse(&cmd_string).unwrap_err();
+        assert!(matches!(&error, Error::Firmware(message) if message == "not calibrated"), "{error:?}");
+    }
+
+    #[rocket::async_test]
+    async fn latency_is_recorded_for_each_command() {
+        let firmware = MockFirmware::new();
+        firmware.lock().delay = Duration::from_millis(50);
+        let mut rotator = firmware.rotator(mock::config());
+
+        rotator.position_raw().await.unwrap();
+
+        let exchange = rotator.history().entries().last().unwrap();
+        assert_eq!(exchange.code(), "GETP");
+        assert!(exchange.first_byte_ms.unwrap() >= 50.0, "{exchange:?}");
+        assert!(exchange.total_ms >= exchange.first_byte_ms.unwrap());
+
+        let metrics = &rotator.metrics().commands["GETP"];
+        assert_eq!(metrics.count, 1);
+        assert!(metrics.mean_first_byte_ms >= 50.0);
+        assert!(metrics.max_total_ms >= 50.0);
+    }
+
+    #[rocket::async_test]
+    async fn halt_and_report_returns_where_it_stopped() {
+        let firmware = MockFirmware::new();
+        firmware.lock().position = PerAxis { vertical: 12.0, horizontal: -30.0 };
+        firmware.lock().target.vertical = Some(40.0);
+        let mut rotator = firmware.rotator(mock::config());
+
+        let position = rotator.halt_and_report().await.unwrap();
+
+        assert_eq!(position, Some(Position { vertical: 12.0, horizontal: 30.0 }));
+        assert_eq!(firmware.commands(), ["HALT", "GETP"]);
+    }
+
+    #[rocket::async_test]
+    async fn halt_and_report_retries_a_timed_out_reading() {
+        let firmware = MockFirmware::new();
+        firmware.lock().unanswered.insert("GETP".to_string(), 2);
+        let mut rotator = firmware.rotator(mock::config());
+
+        assert!(rotator.halt_and_report().await.unwrap().is_some());
+        assert_eq!(firmware.commands(), ["HALT", "GETP", "GETP", "GETP"]);
+
+        // The halt still succeeded if the position never comes back
+        firmware.clear_received();
+        firmware.lock().unanswered.insert("GETP".to_string(), 3);
+
+        assert_eq!(rotator.halt_and_report().await.unwrap(), None);
+        assert_eq!(firmware.commands(), ["HALT", "GETP", "GETP", "GETP"]);
+    }
+
+    #[test]
+    fn connecting_halts_the_motors_if_configured() {
+        let firmware = MockFirmware::new();
+        firmware.lock().jogging.vertical = 1.0;
+        let mut rotator = firmware.rotator(RotatorConfig { halt_on_connect: true, ..mock::config() });
+
+        assert_eq!(firmware.commands(), ["HALT"]);
+        assert_eq!(firmware.lock().jogging.vertical, 0.0);
+
+        rotator.reconnect(firmware.port()).unwrap();
+        assert_eq!(firmware.commands(), ["HALT", "HALT"]);
+
+        let firmware = MockFirmware::new();
+        firmware.rotator(RotatorConfig { halt_on_connect: false, ..mock::config() });
+        assert!(firmware.commands().is_empty());
+    }
+
+    #[test]
+    fn a_failed_halt_on_connect_still_connects() {
+        let firmware = MockFirmware::new();
+        firmware.lock().silent = true;
+
+        let rotator = Rotator::with_config(firmware.port(), RotatorConfig { halt_on_connect: true, ..mock::config() });
+
+        assert!(rotator.is_ok());
+        assert_eq!(firmware.commands(), ["HALT"]);
+    }
+
+    #[rocket::async_test]
+    async fn nudges_move_the_default_steps_in_the_firmware_frame() {
+        let firmware = MockFirmware::new();
+        let frame = frame::Frame { invert_horizontal: false, ..Default::default() };
+        let mut rotator = firmware.rotator(RotatorConfig { frame, ..mock::config() });
+
+        rotator.nudge(Axis::Vertical, false).await.unwrap();
+        rotator.nudge(Axis::Horizontal, true).await.unwrap();
+
+        assert_eq!(firmware.received(), ["MOVV -10", "MOVH 10"]);
+        assert_eq!(firmware.lock().position, PerAxis { vertical: -1.0, horizontal: 1.0 });
+    }
+
+    #[rocket::async_test]
+    async fn positions_are_converted_to_and_from_the_convention() {
+        use frame::{AzimuthConvention, ElevationConvention};
+
+        let firmware = MockFirmware::new();
+        let frame = frame::Frame {
+            azimuth_convention: AzimuthConvention::Compass,
+            elevation_convention: ElevationConvention::Horizon,
+            ..Default::default()
+        };
+        let mut rotator = firmware.rotator(RotatorConfig { frame, ..mock::config() });
+
+        rotator.set_position(Axis::Horizontal, 270.0).await.unwrap();
+        rotator.set_position(Axis::Vertical, 45.0).await.unwrap();
+        assert_eq!(firmware.received(), ["DHOR -270.000", "DVER 45.000"]);
+        assert_eq!(rotator.position().await.unwrap(), (45.0, 270.0));
+
+        rotator.config.frame.azimuth_convention = AzimuthConvention::Signed;
+        assert_eq!(rotator.position().await.unwrap(), (45.0, -90.0));
+        rotator.set_position(Axis::Horizontal, -45.0).await.unwrap();
+        assert_eq!(firmware.received().last().unwrap(), "DHOR 45.000");
+
+        firmware.clear_received();
+        let error = rotator.set_position(Axis::Vertical, -5.0).await.unwrap_err();
+        assert!(matches!(error, Error::OutOfRange { min, max, .. } if min == 0.0 && max == 90.0), "{error:?}");
+        let error = rotator.set_position(Axis::Horizontal, 200.0).await.unwrap_err();
+        assert!(matches!(error, Error::OutOfRange { min, max, .. } if min == -180.0 && max == 180.0), "{error:?}");
+        assert!(firmware.received().is_empty());
+    }
+
+    #[rocket::async_test]
+    async fn moves_settle_on_a_target_across_the_seam() {
+        use frame::Frame;
+
+        let cases = [(AzimuthConvention::Compass, 0.2, 0.0), (AzimuthConvention::Signed, -180.2, 180.0)];
+        for (convention, reading, target) in cases {
+            let firmware = MockFirmware::new();
+            // Reads just the other side of the seam from the target once there
+            firmware.lock().position.horizontal = reading;
+            firmware.lock().stalled.horizontal = true;
+            let frame = Frame { azimuth_convention: convention, ..Frame::default() };
+            let rotator = firmware.shared(RotatorConfig { frame, ..mock::config() });
+
+            let target = Position { vertical: 10.0, horizontal: target };
+            Rotator::goto_and_wait(&rotator, target, Duration::from_millis(500)).await.unwrap();
+
+            assert!(!rotator.lock().await.is_moving().await.unwrap(), "{convention:?}");
+        }
+    }
+
+    #[rocket::async_test]
+    async fn firmware_limits_are_read_in_az_el() {
+        let firmware = MockFirmware::new();
+        firmware.reply("GETL", "OK -10 90 -170 200");
+        let mut rotator = firmware.rotator(mock::config());
+
+        let limits = rotator.firmware_limits().await.unwrap();
+
+        assert_eq!(limits.vertical, Limits { min: -10.0, max: 90.0 });
+        assert_eq!(limits.horizontal, Limits { min: -200.0, max: 170.0 });
+
+        firmware.reply("GETL", "OK -10 90");
+        let error = rotator.firmware_limits().await.unwrap_err();
+        assert!(matches!(error, Error::WrongValueCount { expected: 4, got: 2 }), "{error:?}");
+
+        firmware.reply("GETL", "ERR unknown command");
+        let error = rotator.firmware_limits().await.unwrap_err();
+        assert!(matches!(error, Error::Unsupported(Command::GetLimits)), "{error:?}");
+    }
+
+    #[rocket::async_test]
+    async fn firmware_limits_seed_only_unconfigured_axes() {
+        let firmware = MockFirmware::new();
+        firmware.reply("GETL", "OK 0 90 -180 180");
+        let limits = PerAxis { vertical: Some(Limits { min: -20.0, max: 80.0 }), horizontal: None };
+        let mut rotator = firmware.rotator(RotatorConfig { limits, ..mock::config() });
+
+        rotator.seed_limits().await.unwrap();
+
+        // Wider than the firmware's, which is only warned about
+        assert_eq!(rotator.config().limits.vertical, Some(Limits { min: -20.0, max: 80.0 }));
+        assert_eq!(rotator.config().limits.horizontal, Some(Limits { min: -180.0, max: 180.0 }));
+    }
+
+    #[test]
+    fn limits_beyond_the_firmware_are_not_within_them() {
+        let firmware = Limits { min: 0.0, max: 90.0 };
+
+        assert!(Limits { min: 10.0, max: 80.0 }.within(&firmware));
+        assert!(firmware.within(&firmware));
+        assert!(!Limits { min: -20.0, max: 80.0 }.within(&firmware));
+        assert!(!Limits { min: 10.0, max: 95.0 }.within(&firmware));
+    }
+
+    #[rocket::async_test]
+    async fn move_targets_dispatch_to_their_command() {
+        let firmware = MockFirmware::new();
+        firmware.lock().position = PerAxis { vertical: 20.0, horizontal: -30.0 };
+        let mut rotator = firmware.rotator(mock::config());
+
+        rotator.move_axis(Axis::Vertical, MoveTarget::Degrees(45.0)).await.unwrap();
+        rotator.move_axis(Axis::Horizontal, MoveTarget::Steps(-50)).await.unwrap();
+        rotator.move_axis(Axis::Horizontal, MoveTarget::RelativeDegrees(10.0)).await.unwrap();
+
+        // The relative move reads the position first, after the steps moved
+        // the horizontal reading from -30 to -35, i.e. an azimuth of 35
+        assert_eq!(firmware.received(), ["DVER 45.000", "MOVH -50", "GETP", "DHOR -45.000"]);
+    }
+
+    #[test]
+    fn move_targets_deserialize_by_kind() {
+        let targets: Vec<MoveTarget> =
+            serde_json::from_str(r#"[{"degrees": 10.5}, {"steps": -20}, {"relative_degrees": 2.0}]"#).unwrap();
+
+        assert_eq!(targets, [MoveTarget::Degrees(10.5), MoveTarget::Steps(-20), MoveTarget::RelativeDegrees(2.0)]);
+    }
+
+    #[rocket::async_test]
+    async fn the_response_delay_gives_slow_firmware_time_to_reply() {
+        let slow = || {
+            let firmware = MockFirmware::new();
+            firmware.lock().reply_after = Duration::from_millis(50);
+            firmware
+        };
+
+        let mut rotator = slow().rotator(mock::config());
+        assert!(rotator.ping().await.is_err());
+
+        let mut rotator = slow().rotator(RotatorConfig { response_delay_ms: 60, ..mock::config() });
+        let latency = rotator.ping().await.unwrap();
+        assert!(latency >= Duration::from_millis(60));
+    }
+
+    #[rocket::async_test]
+    async fn the_response_delay_does_not_break_fast_firmware() {
+        assert_eq!(RotatorConfig::default().response_delay_ms, 0);
+
+        let firmware = MockFirmware::new();
+        let mut rotator = firmware.rotator(RotatorConfig { response_delay_ms: 20, ..mock::config() });
+
+        assert_eq!(rotator.version().await.unwrap(), "v1.4.0");
+        rotator.position_raw().await.unwrap();
+        assert_eq!(firmware.commands(), ["VERS", "GETP"]);
+    }
+
+    #[rocket::async_test]
+    async fn a_port_lost_mid_command_disconnects_until_reconnected() {
+        let firmware = MockFirmware::new();
+        firmware.lock().unplug_on = Some("GETP".to_string());
+        let mut rotator = firmware.rotator(mock::config());
+
+        let error = rotator.position_raw().await.unwrap_err();
+        assert!(matches!(error, Error::Disconnected), "{error:?}");
+        assert!(rotator.is_disconnected());
+
+        // Later commands fail straight away, without touching the port
+        firmware.clear_received();
+        let error = rotator.version().await.unwrap_err();
+        assert!(matches!(error, Error::Disconnected), "{error:?}");
+        assert!(firmware.received().is_empty());
+
+        let replacement = MockFirmware::new();
+        rotator.reconnect(replacement.port()).unwrap();
+        assert!(!rotator.is_disconnected());
+        rotator.position_raw().await.unwrap();
+        assert_eq!(replacement.commands(), ["GETP"]);
+    }
+
+    #[rocket::async_test]
+    async fn clamped_and_rejected_positions_are_counted() {
+        let firmware = MockFirmware::new();
+        let limits = PerAxis { vertical: Some(Limits { min: 0.0, max: 90.0 }), horizontal: None };
+        let mut rotator = firmware.rotator(RotatorConfig { limits, ..mock::config() });
+
+        assert_eq!(rotator.clamp_to_limits(Axis::Vertical, 100.0), 90.0);
+        assert_eq!(rotator.clamp_to_limits(Axis::Vertical, -5.0), 0.0);
+        assert_eq!(rotator.metrics().clamped, 2);
+
+        // Positions within the limits, or on an axis without any, are left alone
+        assert_eq!(rotator.clamp_to_limits(Axis::Vertical, 45.0), 45.0);
+        assert_eq!(rotator.clamp_to_limits(Axis::Horizontal, 500.0), 500.0);
+        assert_eq!(rotator.metrics().clamped, 2);
+
+        let error = rotator.set_position(Axis::Vertical, 95.0).await.unwrap_err();
+        assert!(
+            matches!(error, Error::OutOfRange { requested, min, max }
+                if requested == 95.0 && min == 0.0 && max == 90.0),
+            "{error:?}",
+        );
+        assert_eq!(rotator.metrics().rejected, 1);
+        assert!(firmware.received().is_empty());
+    }
+
+    #[rocket::async_test]
+    async fn firmware_which_stops_echoing_is_an_echo_mismatch() {
+        let firmware = MockFirmware::new();
+        let mut rotator = firmware.rotator(mock::config());
+        firmware.lock().echo = false;
+
+        let error = rotator.position().await.unwrap_err();
+
+        assert!(
+            matches!(&error, Error::EchoMismatch { expected, got } if expected == "GETP" && got == "OK 0 0"),
+            "{error:?}",
+        );
+    }
+
+    #[rocket::async_test]
+    async fn responses_longer_than_the_read_buffer_are_read_whole() {
+        let firmware = MockFirmware::new();
+        let mut rotator = firmware.rotator(mock::config());
+
+        let values: Vec<_> = (0..1000).map(|i| format!("{i}.5")).collect();
+        firmware.reply("GETP", &format!("OK {}", values.join(" ")));
+        let cmd_string = rotator.send_command(Command::GetPosition, &[]).await.unwrap();
+
+        assert!(cmd_string.len() + values.join(" ").len() > rotator.config.read_buffer_size);
+        assert_eq!(rotator.validate_parse(&cmd_string).unwrap(), Some(values));
+    }
+
+    #[rocket::async_test]
+    async fn a_tiny_read_buffer_still_decodes_characters_split_between_reads() {
+        let firmware = MockFirmware::new();
+        let mut rotator = firmware.rotator(RotatorConfig { read_buffer_size: 1, ..mock::config() });
+
+        firmware.reply("GETP", "ERR température trop haute");
+        let cmd_string = rotator.send_command(Command::GetPosition, &[]).await.unwrap();
+        let error = rotator.validate_parse(&cmd_string).unwrap_err();
+
+        assert!(matches!(&error, Error::Firmware(message) if message == "température trop haute"), "{error:?}");
+    }
+
+    #[rocket::async_test]
+    async fn feedback_is_read_for_each_axis() {
+        let firmware = MockFirmware::new();
+        let mut rotator = firmware.rotator(mock::config());
+
+        firmware.reply("GETF", "OK 512.5 1023");
+        assert_eq!(rotator.feedback().await.unwrap(), PerAxis { vertical: 512.5, horizontal: 1023.0 });
+
+        firmware.reply("GETF", "OK 512.5");
+        let error = rotator.feedback().await.unwrap_err();
+        assert!(matches!(error, Error::WrongValueCount { expected: 2, got: 1 }), "{error:?}");
+    }
+
+    #[rocket::async_test]
+    async fn feedback_needs_firmware_support() {
+        let firmware = MockFirmware::new();
+        let mut rotator = firmware.rotator(mock::config());
+
+        let error = rotator.feedback().await.unwrap_err();
+
+        assert!(matches!(error, Error::Unsupported(Command::GetFeedback)), "{error:?}");
+        assert_eq!(firmware.commands(), ["GETF"]);
+    }
+
+    #[rocket::async_test]
+    async fn the_version_is_only_read_once_per_connection() {
+        let firmware = MockFirmware::new();
+        let mut rotator = firmware.rotator(mock::config());
+
+        assert_eq!(rotator.version().await.unwrap(), "v1.4.0");
+        firmware.lock().version = "v1.5.0".to_string();
+        assert_eq!(rotator.version().await.unwrap(), "v1.4.0");
+        assert_eq!(firmware.commands(), ["VERS"]);
+
+        // Refreshing reads it again, and caches the new version
+        assert_eq!(rotator.refresh_version().await.unwrap(), "v1.5.0");
+        assert_eq!(rotator.version().await.unwrap(), "v1.5.0");
+        assert_eq!(firmware.commands(), ["VERS", "VERS"]);
+    }
+
+    #[rocket::async_test]
+    async fn reconnecting_forgets_the_version() {
+        let firmware = MockFirmware::new();
+        let mut rotator = firmware.rotator(mock::config());
+        assert_eq!(rotator.version().await.unwrap(), "v1.4.0");
+
+        firmware.lock().version = "v2.0.0".to_string();
+        rotator.reconnect(firmware.port()).unwrap();
+
+        assert_eq!(rotator.version().await.unwrap(), "v2.0.0");
+        assert_eq!(firmware.commands(), ["VERS", "VERS"]);
+    }
+
+    #[rocket::async_test]
+    async fn positions_are_sent_with_the_configured_decimals() {
+        for (decimals, expected) in [
+            (0, ["DVER 90", "DHOR -45"]),
+            (3, ["DVER 89.600", "DHOR -45.250"]),
+            (5, ["DVER 89.60000", "DHOR -45.25000"]),
+        ] {
+            let firmware = MockFirmware::new();
+            let mut rotator = firmware.rotator(RotatorConfig { position_decimals: decimals, ..mock::config() });
+
+            rotator.set_position(Axis::Vertical, 89.6).await.unwrap();
+            rotator.set_position(Axis::Horizontal, 45.25).await.unwrap();
+
+            assert_eq!(firmware.received(), expected);
+        }
+    }
+
+    #[rocket::async_test]
+    async fn whether_the_firmware_echoes_is_detected_on_connect() {
+        for echo in [true, false] {
+            let firmware = MockFirmware::new();
+            firmware.lock().echo = echo;
+            firmware.lock().position.vertical = 10.0;
+
+            let mut rotator = firmware.rotator(RotatorConfig { response_shape: None, ..mock::config() });
+
+            assert_eq!(rotator.shape, ResponseShape::new(echo, Verbosity::Terse), "echo: {echo}");
+            assert_eq!(rotator.position().await.unwrap().0, 10.0, "echo: {echo}");
+            assert_eq!(firmware.commands(), ["VERS", "VERB", "GETP"], "echo: {echo}");
+        }
+    }
+
+    #[rocket::async_test]
+    async fn a_configured_echo_is_not_detected() {
+        let firmware = MockFirmware::new();
+        firmware.lock().echo = false;
+
+        let config = RotatorConfig { response_shape: None, echo_enabled: Some(false), ..mock::config() };
+        let rotator = firmware.rotator(config);
+
+        assert_eq!(rotator.shape, ResponseShape::Status);
+        assert_eq!(firmware.commands(), ["VERB"]);
+    }
+
+    #[rocket::async_test]
+    async fn verbose_firmware_is_detected_on_connect() {
+        let firmware = MockFirmware::new();
+        firmware.lock().verbose = true;
+        firmware.reply("VERB", "OK VERBOSE");
+        firmware.lock().position.vertical = 10.0;
+
+        let mut rotator = firmware.rotator(RotatorConfig { response_shape: None, ..mock::config() });
+
+        assert_eq!(rotator.shape, ResponseShape::EchoStatusData);
+        assert_eq!(rotator.position().await.unwrap().0, 10.0);
+    }
+
+    #[rocket::async_test]
+    async fn soft_stops_decelerate_and_hard_stops_halt() {
+        let firmware = MockFirmware::new();
+        let mut rotator = firmware.rotator(mock::config());
+
+        rotator.stop(false).await.unwrap();
+        assert_eq!(firmware.received(), ["MOVC SV", "MOVC SH"]);
+        assert_eq!(rotator.stops(), 1);
+
+        firmware.clear_received();
+        rotator.stop(true).await.unwrap();
+        assert_eq!(firmware.received(), ["HALT"]);
+        assert_eq!(rotator.stops(), 2);
+    }
+
+    #[rocket::async_test]
+    async fn positions_with_the_wrong_number_of_values_say_so() {
+        let firmware = MockFirmware::new();
+        let mut rotator = firmware.rotator(mock::config());
+
+        for (reply, got) in [("OK 1.0", 1), ("OK 1.0 2.0 3.0", 3)] {
+            firmware.reply("GETP", reply);
+
+            let error = rotator.position().await.unwrap_err();
+            assert!(matches!(error, Error::WrongValueCount { expected: 2, got: g } if g == got), "{error:?}");
+        }
+    }
+
+    #[test]
+    fn exact_values_checks_the_count() {
+        let values = |values: &[&str]| values.iter().map(ToString::to_string).collect::<Vec<_>>();
+
+        assert_eq!(exact_values::<2>(values(&["1", "2"])).unwrap(), ["1", "2"]);
+        assert!(matches!(exact_values::<4>(values(&["1", "2"])), Err(Error::WrongValueCount { expected: 4, got: 2 })));
+        assert!(matches!(exact_values::<1>(values(&[])), Err(Error::WrongValueCount { expected: 1, got: 0 })));
+    }
+
+    #[rocket::async_test]
+    async fn send_no_wait_leaves_the_response_unread() {
+        let firmware = MockFirmware::new();
+        let mut rotator = firmware.rotator(mock::config());
+
+        rotator.send_no_wait(Command::Halt, &[]).unwrap();
+
+        assert_eq!(firmware.received(), ["HALT"]);
+        assert!(rotator.port().bytes_to_read().unwrap() > 0);
+        assert_eq!(rotator.history().entries().count(), 0);
+        assert!(rotator.metrics().commands.is_empty());
+
+        // The leftover response is discarded rather than read as the next one
+        assert_eq!(rotator.position().await.unwrap(), (0.0, 0.0));
+        assert_eq!(rotator.port().bytes_to_read().unwrap(), 0);
+    }
+
+    #[rocket::async_test]
+    async fn send_no_wait_is_busy_until_the_last_response_is_read() {
+        let firmware = MockFirmware::new();
+        let mut rotator = firmware.rotator(mock::config());
+
+        let cmd_string = rotator.send_command(Command::GetPosition, &[]).await.unwrap();
+        let error = rotator.send_no_wait(Command::Halt, &[]).unwrap_err();
+        assert!(matches!(error, Error::Busy), "{error:?}");
+
+        rotator.read_response(&cmd_string).unwrap();
+        rotator.send_no_wait(Command::Halt, &[]).unwrap();
+        assert_eq!(firmware.received(), ["GETP", "HALT"]);
+    }
+
+    #[rocket::async_test]
+    async fn each_response_shape_reads_the_firmware_which_sends_it() {
+        for (echo, verbose, shape) in [
+            (true, false, ResponseShape::EchoStatus),
+            (false, false, ResponseShape::Status),
+            (false, true, ResponseShape::StatusData),
+            (true, true, ResponseShape::EchoStatusData),
+        ] {
+            let firmware = MockFirmware::new();
+            {
+                let mut firmware = firmware.lock();
+                firmware.echo = echo;
+                firmware.verbose = verbose;
+                firmware.position = PerAxis { vertical: 10.0, horizontal: 20.0 };
+            }
+            let mut rotator = firmware.rotator(RotatorConfig { response_shape: Some(shape), ..mock::config() });
+
+            assert_eq!(rotator.position_raw().await.unwrap(), (10.0, 20.0), "{shape:?}");
+            assert_eq!(rotator.version().await.unwrap(), "v1.4.0", "{shape:?}");
+        }
+    }
+
+    #[rocket::async_test]
+    async fn commands_on_a_shared_bus_carry_the_address() {
+        let firmware = MockFirmware::new();
+        firmware.lock().address = Some("@1".to_string());
+        firmware.lock().position = PerAxis { vertical: 10.0, horizontal: 20.0 };
+        let mut rotator = firmware.rotator(RotatorConfig { address: Some("@1".to_string()), ..mock::config() });
+
+        assert_eq!(rotator.position_raw().await.unwrap(), (10.0, 20.0));
+        rotator.set_position_vertical(30.0).await.unwrap();
+
+        assert_eq!(firmware.received(), ["@1 GETP", "@1 DVER 30.000"]);
+        assert_eq!(firmware.commands(), ["GETP", "DVER"]);
+    }
+
+    #[rocket::async_test]
+    async fn lines_addressed_to_other_devices_are_ignored() {
+        let firmware = MockFirmware::new();
+        {
+            let mut firmware = firmware.lock();
+            firmware.address = Some("@1".to_string());
+            firmware.position = PerAxis { vertical: 10.0, horizontal: 20.0 };
+            firmware.bus_chatter = vec!["@12 GETP".to_string(), "@12 OK 5 5".to_string(), "@2 ERR busy".to_string()];
+        }
+        let mut rotator = firmware.rotator(RotatorConfig { address: Some("@1".to_string()), ..mock::config() });
+
+        assert_eq!(rotator.position_raw().await.unwrap(), (10.0, 20.0));
+    }
+
+    #[test]
+    fn addresses_match_whole_prefixes() {
+        assert_eq!(Rotator::strip_address("@1 OK 5 5", "@1"), Some("OK 5 5"));
+        assert_eq!(Rotator::strip_address("@1", "@1"), Some(""));
+        assert_eq!(Rotator::strip_address("@12 OK 5 5", "@1"), None);
+        assert_eq!(Rotator::strip_address("OK 5 5", "@1"), None);
+    }
+
+    #[rocket::async_test]
+    async fn a_failed_axis_does_not_stop_the_other_being_sent() {
+        let firmware = MockFirmware::new();
+        firmware.reply("DHOR", "ERR limit switch");
+        let mut rotator = firmware.rotator(mock::config());
+
+        let error = rotator.goto(Position { vertical: 10.0, horizontal: 20.0 }).await.unwrap_err();
+
+        assert!(
+            matches!(&error, Error::PartialMove { vertical: None, horizontal: Some(h) }
+                if matches!(**h, Error::Firmware(_))),
+            "{error:?}",
+        );
+        assert_eq!(
+            error.to_string(),
+            "the horizontal axis failed to move, but the vertical axis was sent: rotator error: limit switch",
+        );
+        assert_eq!(firmware.received(), ["DVER 10.000", "DHOR -20.000"]);
+
+        let limits = PerAxis { vertical: Some(Limits { min: 0.0, max: 90.0 }), horizontal: None };
+        let firmware = MockFirmware::new();
+        let mut rotator = firmware.rotator(RotatorConfig { limits, ..mock::config() });
+
+        let error = rotator.goto(Position { vertical: 95.0, horizontal: 20.0 }).await.unwrap_err();
+
+        assert!(
+            matches!(&error, Error::PartialMove { vertical: Some(v), horizontal: None }
+                if matches!(**v, Error::OutOfRange { .. })),
+            "{error:?}",
+        );
+        assert_eq!(firmware.received(), ["DHOR -20.000"]);
+    }
+
+    #[rocket::async_test]
+    async fn both_axes_failing_reports_each_error() {
+        let firmware = MockFirmware::new();
+        firmware.lock().calibrated = false;
+        let mut rotator = firmware.rotator(mock::config());
+
+        let error = rotator.goto(Position { vertical: 10.0, horizontal: 20.0 }).await.unwrap_err();
+
+        assert!(matches!(&error, Error::PartialMove { vertical: Some(_), horizontal: Some(_) }), "{error:?}");
+        assert_eq!(
+            error.to_string(),
+            "both axes failed to move: vertical: rotator error: not calibrated; horizontal: rotator error: not calibrated",
+        );
+        assert_eq!(firmware.commands(), ["DVER", "DHOR"]);
+    }
+
+    #[rocket::async_test]
+    async fn verbosity_replies_are_classified() {
+        let firmware = MockFirmware::new();
+        let mut rotator = firmware.rotator(mock::config());
+
+        for (reply, verbosity) in [
+            ("OK TERSE", Verbosity::Terse),
+            ("OK 0", Verbosity::Terse),
+            ("OK VERBOSE", Verbosity::Verbose),
+            ("OK 1", Verbosity::Verbose),
+        ] {
+            firmware.reply("VERB", reply);
+            assert_eq!(rotator.verbosity().unwrap(), verbosity, "{reply}");
+        }
+
+        firmware.reply("VERB", "OK LOUD");
+        let error = rotator.verbosity().unwrap_err();
+        assert!(matches!(error, Error::InvalidResponse), "{error:?}");
+    }
+
+    #[rocket::async_test]
+    async fn verbose_firmware_without_echo_is_detected_on_connect() {
+        let firmware = MockFirmware::new();
+        {
+            let mut firmware = firmware.lock();
+            firmware.echo = false;
+            firmware.verbose = true;
+            firmware.position = PerAxis { vertical: 10.0, horizontal: 20.0 };
+        }
+        firmware.reply("VERB", "OK 1");
+
+        let mut rotator = firmware.rotator(RotatorConfig { response_shape: None, ..mock::config() });
+
+        assert_eq!(rotator.shape, ResponseShape::StatusData);
+        assert_eq!(rotator.position_raw().await.unwrap(), (10.0, 20.0));
+        assert_eq!(firmware.commands(), ["VERS", "VERB", "GETP"]);
+    }
+
+    #[test]
+    fn build_details_are_optional_after_the_version() {
+        let values = |values: &[&str]| values.iter().map(ToString::to_string).collect::<Vec<_>>();
+        let build = |commit: Option<&str>, date: Option<&str>| {
+            Some(FirmwareBuild { commit: commit.map(str::to_string), date: date.map(str::to_string) })
+        };
+
+        assert_eq!(FirmwareBuild::parse(&values(&[])), None);
+        assert_eq!(FirmwareBuild::parse(&values(&["a1b2c3d"])), build(Some("a1b2c3d"), None));
+        assert_eq!(FirmwareBuild::parse(&values(&["2024-05-01"])), build(None, Some("2024-05-01")));
+        assert_eq!(
+            FirmwareBuild::parse(&values(&["a1b2c3d", "2024-05-01"])),
+            build(Some("a1b2c3d"), Some("2024-05-01")),
+        );
+        assert_eq!(FirmwareBuild::parse(&values(&["a1b2c3d", "yesterday"])), build(Some("a1b2c3d"), None));
+    }
+
+    #[rocket::async_test]
+    async fn the_build_is_read_with_the_version() {
+        let firmware = MockFirmware::new();
+        firmware.lock().version = "v1.4.0 a1b2c3d 2024-05-01".to_string();
+        let mut rotator = firmware.rotator(mock::config());
+
+        assert_eq!(rotator.version().await.unwrap(), "v1.4.0");
+        let build = rotator.firmware_build().unwrap();
+        assert_eq!(build.commit.as_deref(), Some("a1b2c3d"));
+        assert_eq!(build.date.as_deref(), Some("2024-05-01"));
+
+        firmware.lock().version = "v1.5.0".to_string();
+        assert_eq!(rotator.refresh_version().await.unwrap(), "v1.5.0");
+        assert!(rotator.firmware_build().is_none());
+    }
+
+    #[rocket::async_test]
+    async fn flushing_discards_stale_bytes_before_the_next_command() {
+        let firmware = MockFirmware::new();
+        firmware.lock().position = PerAxis { vertical: 10.0, horizontal: 20.0 };
+        let mut rotator = firmware.rotator(mock::config());
+
+        // Without a flush, a late response is read as the next one
+        firmware.lock().stale = b"GETP\nOK 5 5\n".to_vec();
+        assert_eq!(rotator.position_raw().await.unwrap(), (5.0, 5.0));
+
+        rotator.flush().unwrap();
+        firmware.lock().stale = b"GETP\nOK 5 5\n".to_vec();
+        assert_eq!(rotator.flush().unwrap(), 12);
+        assert_eq!(rotator.position_raw().await.unwrap(), (10.0, 20.0));
+    }
+
+    #[rocket::async_test]
+    async fn flushing_gives_up_on_an_unread_response() {
+        let firmware = MockFirmware::new();
+        let mut rotator = firmware.rotator(mock::config());
+
+        rotator.send_command(Command::GetPosition, &[]).await.unwrap();
+        let error = rotator.version().await.unwrap_err();
+        assert!(matches!(error, Error::Busy), "{error:?}");
+
+        rotator.flush().unwrap();
+        assert_eq!(rotator.version().await.unwrap(), "v1.4.0");
+        assert_eq!(rotator.port().bytes_to_read().unwrap(), 0);
+    }
+
+    #[rocket::async_test]
+    async fn stray_bytes_cause_no_echo_mismatch_when_flushing_before_each_command() {
+        let firmware = MockFirmware::new();
+        firmware.lock().position = PerAxis { vertical: 10.0, horizontal: 20.0 };
+        let mut rotator = firmware.rotator(mock::config());
+        assert!(!rotator.config().flush_before_command);
+
+        firmware.lock().stale = b"\x00GETV\n".to_vec();
+        let error = rotator.position_raw().await.unwrap_err();
+        assert!(matches!(&error, Error::EchoMismatch { got, .. } if got == "\0GETV"), "{error:?}");
+
+        let firmware = MockFirmware::new();
+        firmware.lock().position = PerAxis { vertical: 10.0, horizontal: 20.0 };
+        let mut rotator = firmware.rotator(RotatorConfig { flush_before_command: true, ..mock::config() });
+
+        firmware.lock().stale = b"\x00GETV\n".to_vec();
+        assert_eq!(rotator.position_raw().await.unwrap(), (10.0, 20.0));
+        firmware.lock().stale = b"OK 5".to_vec();
+        assert_eq!(rotator.version().await.unwrap(), "v1.4.0");
+    }
+
+    #[rocket::async_test]
+    async fn positions_are_timestamped_halfway_through_the_read() {
+        let firmware = MockFirmware::new();
+        firmware.lock().position = PerAxis { vertical: 10.0, horizontal: 20.0 };
+        firmware.lock().delay = Duration::from_millis(100);
+        let mut rotator = firmware.rotator(mock::config());
+
+        let before = Instant::now();
+        let (position, at) = rotator.position_with_timestamp().await.unwrap();
+        let after = Instant::now();
+
+        assert!(at >= before + Duration::from_millis(50), "{:?} after starting", at - before);
+        assert!(at + Duration::from_millis(50) <= after, "{:?} before finishing", after - at);
+        assert_eq!(position, rotator.position().await.unwrap());
+
+        let (raw, _) = rotator.position_raw_with_timestamp().await.unwrap();
+        assert_eq!(raw, (10.0, 20.0));
+    }
+
+    #[test]
+    fn versions_are_compared_part_by_part() {
+        assert_eq!(version_at_least("v1.4.0", "1.4"), Some(true));
+        assert_eq!(version_at_least("v1.10.0", "v1.9.2"), Some(true));
+        assert_eq!(version_at_least("1.3.9", "v1.4.0"), Some(false));
+        assert_eq!(version_at_least("V2", "1.99"), Some(true));
+        assert_eq!(version_at_least("v1.4.0-rc1", "v1.4.0"), None);
+    }
+
+    #[rocket::async_test]
+    async fn calibrating_with_set_needs_new_enough_firmware() {
+        let config = RotatorConfig { calibrate_set_min_version: Some("v1.5.0".to_string()), ..mock::config() };
+
+        let firmware = MockFirmware::new();
+        let mut rotator = firmware.rotator(config.clone());
+        let error = rotator.calibrate_vertical(true).await.unwrap_err();
+        assert!(matches!(error, Error::Unsupported(Command::CalibrateVertical)), "{error:?}");
+        assert_eq!(firmware.received(), ["VERS"]);
+
+        // A plain calibration doesn't need it
+        rotator.calibrate_vertical(false).await.unwrap();
+        assert_eq!(firmware.received(), ["VERS", "CALV"]);
+
+        let firmware = MockFirmware::new();
+        firmware.lock().version = "v1.5.0".to_string();
+        let mut rotator = firmware.rotator(config);
+        rotator.calibrate_vertical(true).await.unwrap();
+        assert_eq!(firmware.received(), ["VERS", "CALV SET"]);
+    }
+
+    #[rocket::async_test]
+    async fn calibrating_with_set_is_not_gated_unless_configured() {
+        let firmware = MockFirmware::new();
+        let mut rotator = firmware.rotator(mock::config());
+
+        rotator.calibrate_vertical(true).await.unwrap();
+
+        assert_eq!(firmware.received(), ["CALV SET"]);
+    }
+
+    #[rocket::async_test]
+    async fn queries_answered_without_a_value_say_which_command_it_was() {
+        let firmware = MockFirmware::new();
+        for code in ["GETP", "GETC", "VERS"] {
+            firmware.reply(code, "OK");
+        }
+        let mut rotator = firmware.rotator(mock::config());
+
+        let error = rotator.position().await.unwrap_err();
+        assert!(matches!(error, Error::ExpectedValue(Command::GetPosition)), "{error:?}");
+        assert!(error.to_string().starts_with("GETP "), "{error}");
+
+        let error = rotator.calibrated().await.unwrap_err();
+        assert!(error.to_string().starts_with("GETC "), "{error}");
+        let error = rotator.refresh_version().await.unwrap_err();
+        assert!(error.to_string().starts_with("VERS "), "{error}");
     }
 }
@@ -0,0 +1,155 @@
+//! The firmware's operating mode, on firmware which has them.
+
+use core::fmt::Display;
+
+use rocket::FromFormField;
+use serde::Serialize;
+
+use super::{Command, Error, Rotator, exact_values};
+
+/// An operating mode of the firmware.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, FromFormField)]
+#[serde(rename_all = "lowercase")]
+pub enum Mode {
+    /// Positioned by individual commands.
+    Manual,
+    /// Following a moving target.
+    Tracking,
+    /// Motors are not being driven, and positioning is refused.
+    Idle,
+}
+
+impl Mode {
+    /// Whether the firmware accepts positioning commands in this mode.
+    pub const fn accepts_positioning(self) -> bool {
+        matches!(self, Self::Manual | Self::Tracking)
+    }
+}
+
+/// Formatted as the code used on the wire.
+impl Display for Mode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let code = match self {
+            Self::Manual => "MANUAL",
+            Self::Tracking => "TRACKING",
+            Self::Idle => "IDLE",
+        };
+
+        write!(f, "{code}")
+    }
+}
+
+impl TryFrom<&str> for Mode {
+    type Error = ();
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Ok(match value {
+            "MANUAL" => Self::Manual,
+            "TRACKING" => Self::Tracking,
+            "IDLE" => Self::Idle,
+            _ => return Err(()),
+        })
+    }
+}
+
+impl Rotator {
+    /// Gets the firmware's current operating mode.
+    ///
+    /// # Errors
+    /// Returns [`Error::Unsupported`] if the firmware has no modes.
+    pub async fn mode(&mut self) -> Result<Mode, Error> {
+        let values = self
+            .send_optional(Command::GetMode, &[]).await?
+            .ok_or(Error::ExpectedValue(Command::GetMode))?;
+
+        let [mode] = exact_values(values)?;
+        let mode = Mode::try_from(mode.as_str()).map_err(|()| Error::InvalidResponse)?;
+        self.mode = Some(mode);
+
+        Ok(mode)
+    }
+
+    /// Switches the firmware to another operating mode.
+    ///
+    /// # Errors
+    /// Returns [`Error::Unsupported`] if the firmware has no modes.
+    pub async fn set_mode(&mut self, mode: Mode) -> Result<(), Error> {
+        self.send_optional(Command::SetMode, &[&mode.to_string()]).await?;
+        self.mode = Some(mode);
+
+        Ok(())
+    }
+
+    /// The mode last read from or sent to the firmware, if any.
+    pub const fn last_mode(&self) -> Option<Mode> {
+        self.mode
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::{Axis, mock::{self, MockFirmware}}, *};
+
+    #[test]
+    fn modes_round_trip_through_their_codes() {
+        for mode in [Mode::Manual, Mode::Tracking, Mode::Idle] {
+            assert_eq!(Mode::try_from(mode.to_string().as_str()), Ok(mode));
+        }
+        assert_eq!(Mode::try_from("manual"), Err(()));
+    }
+
+    #[rocket::async_test]
+    async fn modes_are_queried_and_set() {
+        let firmware = MockFirmware::new();
+        firmware.reply("GETM", "OK TRACKING");
+        firmware.reply("SETM", "OK");
+        let mut rotator = firmware.rotator(mock::config());
+
+        assert_eq!(rotator.last_mode(), None);
+        assert_eq!(rotator.mode().await.unwrap(), Mode::Tracking);
+        rotator.set_mode(Mode::Idle).await.unwrap();
+
+        assert_eq!(rotator.last_mode(), Some(Mode::Idle));
+        assert_eq!(firmware.received(), ["GETM", "SETM IDLE"]);
+    }
+
+    #[rocket::async_test]
+    async fn firmware_without_modes_is_unsupported() {
+        let firmware = MockFirmware::new();
+        let mut rotator = firmware.rotator(mock::config());
+
+        let error = rotator.mode().await.unwrap_err();
+        assert!(matches!(error, Error::Unsupported(Command::GetMode)), "{error:?}");
+
+        firmware.reply("GETM", "OK SLEEPING");
+        let error = rotator.mode().await.unwrap_err();
+        assert!(matches!(error, Error::InvalidResponse), "{error:?}");
+    }
+
+    #[rocket::async_test]
+    async fn positioning_switches_out_of_idle_first() {
+        let firmware = MockFirmware::new();
+        firmware.reply("GETM", "OK IDLE");
+        firmware.reply("SETM", "OK");
+        let mut rotator = firmware.rotator(mock::config());
+        rotator.mode().await.unwrap();
+
+        rotator.set_position(Axis::Vertical, 10.0).await.unwrap();
+        rotator.set_position(Axis::Vertical, 20.0).await.unwrap();
+
+        assert_eq!(rotator.last_mode(), Some(Mode::Manual));
+        assert_eq!(firmware.received(), ["GETM", "SETM MANUAL", "DVER 10.000", "DVER 20.000"]);
+    }
+
+    #[rocket::async_test]
+    async fn positioning_in_tracking_mode_does_not_switch() {
+        let firmware = MockFirmware::new();
+        firmware.reply("GETM", "OK TRACKING");
+        let mut rotator = firmware.rotator(mock::config());
+        rotator.mode().await.unwrap();
+
+        rotator.set_position(Axis::Vertical, 10.0).await.unwrap();
+
+        assert_eq!(firmware.received(), ["GETM", "DVER 10.000"]);
+    }
+}
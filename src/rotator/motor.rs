@@ -0,0 +1,123 @@
+//! Switching off the holding torque of an axis, so it can be turned by hand,
+//! on firmware which supports it.
+
+use log::info;
+
+use super::{Axis, Command, Error, Rotator};
+
+impl Axis {
+    const fn motor_command(self) -> Command {
+        match self {
+            Self::Vertical => Command::MotorVertical,
+            Self::Horizontal => Command::MotorHorizontal,
+        }
+    }
+}
+
+impl Rotator {
+    /// Powers an axis's motor on or off. While it is off, the axis can be
+    /// moved by hand, and positioning it fails with
+    /// [`Error::MotorDisabled`] unless `auto_enable_motors` is set.
+    ///
+    /// # Errors
+    /// Returns [`Error::Unsupported`] if the firmware can't power off motors.
+    pub async fn set_motor_enabled(&mut self, axis: Axis, enabled: bool) -> Result<(), Error> {
+        self.send_optional(axis.motor_command(), &[if enabled { "1" } else { "0" }]).await?;
+        *self.motors_disabled.get_mut(axis) = !enabled;
+
+        if !enabled {
+            self.clear_motion(axis);
+        }
+
+        Ok(())
+    }
+
+    /// Whether an axis's motor is powered, as far as this server knows.
+    pub const fn motor_enabled(&self, axis: Axis) -> bool {
+        !*self.motors_disabled.get(axis)
+    }
+
+    /// Makes sure an axis's motor is powered before moving it, turning it
+    /// back on if `auto_enable_motors` is set.
+    pub(super) async fn ensure_motor_enabled(&mut self, axis: Axis) -> Result<(), Error> {
+        if self.motor_enabled(axis) {
+            return Ok(());
+        }
+
+        if !self.config.auto_enable_motors {
+            return Err(Error::MotorDisabled(axis));
+        }
+
+        info!("Enabling the {axis:?} motor to move it");
+        self.set_motor_enabled(axis, true).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::{config::RotatorConfig, mock::{self, MockFirmware}}, *};
+
+    fn firmware() -> MockFirmware {
+        let firmware = MockFirmware::new();
+        firmware.reply("MOTV", "OK");
+        firmware.reply("MOTH", "OK");
+
+        firmware
+    }
+
+    #[rocket::async_test]
+    async fn motors_are_powered_off_and_on_per_axis() {
+        let firmware = firmware();
+        let mut rotator = firmware.rotator(mock::config());
+
+        rotator.set_motor_enabled(Axis::Vertical, false).await.unwrap();
+        assert!(!rotator.motor_enabled(Axis::Vertical));
+        assert!(rotator.motor_enabled(Axis::Horizontal));
+
+        rotator.set_motor_enabled(Axis::Horizontal, false).await.unwrap();
+        rotator.set_motor_enabled(Axis::Vertical, true).await.unwrap();
+        assert!(rotator.motor_enabled(Axis::Vertical));
+
+        assert_eq!(firmware.received(), ["MOTV 0", "MOTH 0", "MOTV 1"]);
+    }
+
+    #[rocket::async_test]
+    async fn positioning_a_powered_off_axis_is_refused() {
+        let firmware = firmware();
+        let mut rotator = firmware.rotator(mock::config());
+        rotator.set_motor_enabled(Axis::Vertical, false).await.unwrap();
+        firmware.clear_received();
+
+        let error = rotator.set_position(Axis::Vertical, 10.0).await.unwrap_err();
+        assert!(matches!(error, Error::MotorDisabled(Axis::Vertical)), "{error:?}");
+        assert!(firmware.received().is_empty());
+
+        // The other axis is unaffected
+        rotator.set_position(Axis::Horizontal, 20.0).await.unwrap();
+        assert_eq!(firmware.received(), ["DHOR -20.000"]);
+    }
+
+    #[rocket::async_test]
+    async fn positioning_a_powered_off_axis_can_power_it_on() {
+        let firmware = firmware();
+        let mut rotator = firmware.rotator(RotatorConfig { auto_enable_motors: true, ..mock::config() });
+        rotator.set_motor_enabled(Axis::Vertical, false).await.unwrap();
+        firmware.clear_received();
+
+        rotator.set_position(Axis::Vertical, 10.0).await.unwrap();
+
+        assert!(rotator.motor_enabled(Axis::Vertical));
+        assert_eq!(firmware.received(), ["MOTV 1", "DVER 10.000"]);
+    }
+
+    #[rocket::async_test]
+    async fn motor_power_needs_firmware_support() {
+        let firmware = MockFirmware::new();
+        let mut rotator = firmware.rotator(mock::config());
+
+        let error = rotator.set_motor_enabled(Axis::Vertical, false).await.unwrap_err();
+
+        assert!(matches!(error, Error::Unsupported(Command::MotorVertical)), "{error:?}");
+        assert!(rotator.motor_enabled(Axis::Vertical));
+    }
+}
@@ -0,0 +1,161 @@
+//! How much the rotator has moved, for scheduling maintenance such as
+//! lubrication.
+
+use serde::Serialize;
+
+use super::{Axis, Command, Error, Rotator, config::PerAxis, exact_values};
+
+/// Where an [`Odometer`] reading came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OdometerSource {
+    /// The firmware's own count, which survives restarts of this server.
+    Firmware,
+    /// Counted by this server from the commands it sent since connecting.
+    Software,
+}
+
+/// Cumulative movement of each axis.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Odometer {
+    pub source: OdometerSource,
+    /// Motor steps taken by each axis. The software count only includes step
+    /// moves.
+    pub steps: PerAxis<u64>,
+    /// Degrees each axis was sent through by positioning commands. Only
+    /// counted by software.
+    pub degrees: PerAxis<f64>,
+    /// How long the motors have run for, in seconds, if the firmware counts it.
+    pub runtime_secs: Option<u64>,
+}
+
+/// The software count kept while connected.
+#[derive(Debug, Clone, Default)]
+pub(super) struct SoftwareOdometer {
+    steps: PerAxis<u64>,
+    degrees: PerAxis<f64>,
+    /// The last position each axis was sent to.
+    last_target: PerAxis<Option<f32>>,
+}
+
+impl SoftwareOdometer {
+    pub(super) fn record_steps(&mut self, axis: Axis, steps: i32) {
+        *self.steps.get_mut(axis) += u64::from(steps.unsigned_abs());
+    }
+
+    /// Count the distance from the last target, as the actual starting
+    /// position isn't known without reading it.
+    pub(super) fn record_target(&mut self, axis: Axis, degrees: f32) {
+        if let Some(last) = self.last_target.get_mut(axis).replace(degrees) {
+            *self.degrees.get_mut(axis) += f64::from((degrees - last).abs());
+        }
+    }
+}
+
+impl Rotator {
+    /// Gets how far each axis has moved, from the firmware if it keeps count,
+    /// otherwise counted from the commands sent since connecting.
+    pub async fn odometer(&mut self) -> Result<Odometer, Error> {
+        if self.firmware_odometer != Some(false) {
+            match self.firmware_odometer().await {
+                Ok(odometer) => {
+                    self.firmware_odometer = Some(true);
+                    return Ok(odometer);
+                }
+                // Only the firmware saying it doesn't know the command means
+                // it never counts; any other failure is worth asking again
+                Err(Error::Unsupported(_)) => self.firmware_odometer = Some(false),
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(Odometer {
+            source: OdometerSource::Software,
+            steps: self.software_odometer.steps,
+            degrees: self.software_odometer.degrees,
+            runtime_secs: None,
+        })
+    }
+
+    /// Reads the firmware's counts of steps taken by each axis and seconds
+    /// the motors have run for.
+    ///
+    /// # Errors
+    /// Returns [`Error::Unsupported`] if the firmware doesn't keep count.
+    async fn firmware_odometer(&mut self) -> Result<Odometer, Error> {
+        let values = self
+            .send_optional(Command::GetOdometer, &[]).await?
+            .ok_or(Error::ExpectedValue(Command::GetOdometer))?;
+
+        let [vertical, horizontal, runtime] = exact_values(values)?;
+        let parse = |v: String| self.parse_count(&v);
+
+        Ok(Odometer {
+            source: OdometerSource::Firmware,
+            steps: PerAxis {
+                vertical: parse(vertical)?,
+                horizontal: parse(horizontal)?,
+            },
+            degrees: PerAxis::default(),
+            runtime_secs: Some(parse(runtime)?),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::mock::{self, MockFirmware}, *};
+
+    #[rocket::async_test]
+    async fn the_firmware_odometer_is_read_if_it_has_one() {
+        let firmware = MockFirmware::new();
+        firmware.reply("GETO", "OK 1200 3400 86400");
+        let mut rotator = firmware.rotator(mock::config());
+
+        let odometer = rotator.odometer().await.unwrap();
+
+        assert_eq!(odometer, Odometer {
+            source: OdometerSource::Firmware,
+            steps: PerAxis { vertical: 1200, horizontal: 3400 },
+            degrees: PerAxis::default(),
+            runtime_secs: Some(86400),
+        });
+    }
+
+    #[rocket::async_test]
+    async fn without_one_the_commands_sent_are_counted() {
+        let firmware = MockFirmware::new();
+        let mut rotator = firmware.rotator(mock::config());
+
+        for degrees in [10.0, 30.0, 20.0] {
+            rotator.set_position(Axis::Vertical, degrees).await.unwrap();
+        }
+        rotator.move_steps(Axis::Vertical, 5).await.unwrap();
+        rotator.move_steps(Axis::Vertical, -5).await.unwrap();
+        rotator.move_steps(Axis::Horizontal, 100).await.unwrap();
+
+        let odometer = rotator.odometer().await.unwrap();
+        assert_eq!(odometer.source, OdometerSource::Software);
+        assert_eq!(odometer.steps, PerAxis { vertical: 10, horizontal: 100 });
+        // The first target is only where counting starts from
+        assert_eq!(odometer.degrees, PerAxis { vertical: 30.0, horizontal: 0.0 });
+        assert_eq!(odometer.runtime_secs, None);
+
+        // The firmware isn't asked again once it said it doesn't count
+        rotator.odometer().await.unwrap();
+        assert_eq!(firmware.commands().iter().filter(|code| *code == "GETO").count(), 1);
+    }
+
+    #[rocket::async_test]
+    async fn the_firmware_is_asked_again_after_a_failed_read() {
+        let firmware = MockFirmware::new();
+        firmware.reply("GETO", "OK 1200 3400 86400");
+        firmware.lock().unanswered.insert("GETO".to_string(), 1);
+        let mut rotator = firmware.rotator(mock::config());
+
+        let error = rotator.odometer().await.unwrap_err();
+        assert!(matches!(error, Error::Timeout), "{error:?}");
+
+        assert_eq!(rotator.odometer().await.unwrap().source, OdometerSource::Firmware);
+    }
+}
@@ -0,0 +1,826 @@
+//! A background task which periodically samples the rotator's state into a
+//! [`Telemetry`] cache, so that dashboards can poll without each request
+//! turning into serial traffic. Notable changes are also published as
+//! [`RotatorEvent`]s.
+
+use std::{sync::Arc, time::{Duration, Instant}};
+
+use chrono::Utc;
+use log::{debug, info, warn};
+use rocket::tokio::{self, sync::{Mutex, broadcast}};
+use serde::Serialize;
+
+use crate::jobs::Jobs;
+
+use super::{Axis, Error, FirmwareBuild, Motion, Position, Rotator, config::{Limits, PerAxis}, frame::AzimuthConvention, mode::Mode, odometer::Odometer};
+
+/// A snapshot of the rotator's state, as of the last poll.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Telemetry {
+    /// Whether the last poll got a response.
+    pub connected: bool,
+    pub position: Option<Position>,
+    /// The position as reported by the firmware, see [`Rotator::position_raw`].
+    pub position_raw: Option<Position>,
+    /// Rate of change of `position` in degrees per second.
+    pub velocity: Option<Position>,
+    /// Whether the position moved by more than the position tolerance since
+    /// the previous poll.
+    pub moving: bool,
+    pub calibrated: Option<bool>,
+    /// The firmware's operating mode, if it has them.
+    pub mode: Option<Mode>,
+    pub version: Option<String>,
+    /// The firmware's build details, if it gives them with its version.
+    pub firmware_build: Option<FirmwareBuild>,
+    /// How far each axis has moved, see [`Rotator::odometer`].
+    pub odometer: Option<Odometer>,
+    pub last_error: Option<String>,
+    /// The most recent stall, see [`StallDetector`].
+    pub last_stall: Option<Stall>,
+    /// The most recent drift, see [`DriftDetector`].
+    pub last_drift: Option<Drift>,
+    /// When this snapshot was last updated, in RFC 3339 format.
+    pub updated: Option<String>,
+    /// The configured limits as of the last poll, so requests can be checked
+    /// against them without waiting for the rotator's lock.
+    #[serde(skip)]
+    pub limits: PerAxis<Option<Limits>>,
+    #[serde(skip)]
+    sampled_at: Option<Instant>,
+}
+
+impl Telemetry {
+    /// Record a successful position reading taken at `at`, both as reported
+    /// by the firmware and converted into azimuth/elevation in `convention`.
+    fn record_position(
+        &mut self,
+        raw: (f32, f32),
+        position: Position,
+        at: Instant,
+        tolerance: f32,
+        convention: AzimuthConvention,
+    ) {
+        match (self.position, self.sampled_at) {
+            (Some(last), Some(last_at)) if at > last_at => {
+                let dt = at.duration_since(last_at).as_secs_f32();
+                self.velocity = Some(Position {
+                    vertical: (position.vertical - last.vertical) / dt,
+                    horizontal: convention.difference(position.horizontal, last.horizontal) / dt,
+                });
+                self.moving = !position.within(&last, tolerance, convention);
+            }
+            _ => {
+                self.velocity = None;
+                self.moving = false;
+            }
+        }
+
+        self.connected = true;
+        self.position = Some(position);
+        self.position_raw = Some(Position { vertical: raw.0, horizontal: raw.1 });
+        self.sampled_at = Some(at);
+    }
+
+    fn record_error(&mut self, error: String) {
+        self.connected = false;
+        self.moving = false;
+        self.velocity = None;
+        self.last_error = Some(error);
+    }
+}
+
+/// An axis which was halted after it stopped moving mid-move.
+#[derive(Debug, Clone, Serialize)]
+pub struct Stall {
+    pub axis: Axis,
+    pub position: Position,
+    /// When the stall was detected, in RFC 3339 format.
+    pub at: String,
+}
+
+/// An axis whose position drifted from its sensor feedback.
+#[derive(Debug, Clone, Serialize)]
+pub struct Drift {
+    pub axis: Axis,
+    /// How far the position was from the feedback.
+    pub drift: f32,
+    /// The id of the job recalibrating the axis because of it, if
+    /// `drift.auto_recalibrate` is set.
+    pub recalibration: Option<u64>,
+    /// When the drift was detected, in RFC 3339 format.
+    pub at: String,
+}
+
+/// Something which happened to the rotator, as seen by the poller.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RotatorEvent {
+    /// The position moved by more than `position_event_threshold` since the
+    /// last of these events.
+    PositionChanged {
+        position: Position,
+        /// When the position was read, in RFC 3339 format.
+        at: String,
+    },
+    Stalled(Stall),
+    Drifted(Drift),
+}
+
+impl RotatorEvent {
+    /// The name of this event in an event stream.
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::PositionChanged { .. } => "position_changed",
+            Self::Stalled(_) => "stalled",
+            Self::Drifted(_) => "drifted",
+        }
+    }
+}
+
+/// Decides when the position has changed enough to be worth an event, so
+/// that jitter in the readings doesn't turn into a stream of them.
+#[derive(Debug, Default)]
+struct ChangeFilter {
+    last: Option<Position>,
+}
+
+impl ChangeFilter {
+    /// Whether `position` is more than `threshold` degrees from the last
+    /// position accepted, on either axis. Azimuths are compared the short way
+    /// round, so crossing north is not a jump of 360 degrees.
+    fn update(&mut self, position: Position, threshold: f32) -> bool {
+        let changed = self.last.is_none_or(|last| {
+            let vertical = (position.vertical - last.vertical).abs();
+            let horizontal = AzimuthConvention::Signed.wrap(position.horizontal - last.horizontal).abs();
+
+            vertical > threshold || horizontal > threshold
+        });
+
+        if changed {
+            self.last = Some(position);
+        }
+
+        changed
+    }
+}
+
+/// Watches for a motor which is stalled or jammed: an axis that should be
+/// moving, but which stays within the position tolerance for `stall_samples`
+/// polls in a row.
+#[derive(Debug, Default)]
+struct StallDetector {
+    last: Option<Position>,
+    unchanged: PerAxis<u32>,
+}
+
+impl StallDetector {
+    /// Check a new reading, returning the first axis which has now stalled.
+    fn update(&mut self, rotator: &mut Rotator, position: Position) -> Option<Axis> {
+        let tolerance = rotator.config().position_tolerance;
+        let limit = rotator.config().stall_samples;
+        let convention = rotator.config().frame.azimuth_convention;
+        let last = self.last.replace(position);
+        // Either side of north is close together, unless the azimuth is continuous
+        let distance = |axis: Axis, degrees: f32, other: f32| match axis {
+            Axis::Vertical => (degrees - other).abs(),
+            Axis::Horizontal => convention.difference(degrees, other).abs(),
+        };
+
+        let mut stalled = None;
+        for axis in [Axis::Vertical, Axis::Horizontal] {
+            let unchanged = self.unchanged.get_mut(axis);
+
+            match rotator.motion(axis) {
+                None => *unchanged = 0,
+                Some(Motion::Toward(target)) if distance(axis, position.get(axis), target) <= tolerance => {
+                    rotator.clear_motion(axis);
+                    *unchanged = 0;
+                }
+                Some(_) => {
+                    let still = last.is_some_and(|l| distance(axis, position.get(axis), l.get(axis)) <= tolerance);
+                    *unchanged = if still { *unchanged + 1 } else { 0 };
+
+                    if limit > 0 && *unchanged >= limit {
+                        *unchanged = 0;
+                        stalled = stalled.or(Some(axis));
+                    }
+                }
+            }
+        }
+
+        stalled
+    }
+}
+
+/// Watches for an axis whose position, as the firmware counts it, has drifted
+/// more than `drift.threshold` from its sensor feedback while it isn't moving.
+/// Each drift is only handled once, until the axis is back within the
+/// threshold.
+#[derive(Debug)]
+struct DriftDetector {
+    /// Cleared once the firmware turns out to have no feedback.
+    supported: bool,
+    drifted: PerAxis<bool>,
+}
+
+impl Default for DriftDetector {
+    fn default() -> Self {
+        Self {
+            supported: true,
+            drifted: PerAxis::default(),
+        }
+    }
+}
+
+impl DriftDetector {
+    /// Check the feedback against a new raw reading, returning each axis
+    /// which has newly drifted.
+    async fn update(&mut self, rotator: &mut Rotator, raw: (f32, f32)) -> Vec<Drift> {
+        let Some(threshold) = rotator.config().drift.threshold else {
+            return Vec::new();
+        };
+        if !self.supported {
+            return Vec::new();
+        }
+
+        let feedback = match rotator.feedback().await {
+            Ok(feedback) => feedback,
+            Err(Error::Unsupported(_)) => {
+                warn!("Drift checking is configured, but the rotator has no sensor feedback");
+                self.supported = false;
+                return Vec::new();
+            }
+            Err(e) => {
+                debug!("Failed to read the feedback to check for drift: {e}");
+                return Vec::new();
+            }
+        };
+        let raw = PerAxis { vertical: raw.0, horizontal: raw.1 };
+
+        let mut drifts = Vec::new();
+        for axis in [Axis::Vertical, Axis::Horizontal] {
+            if rotator.motion(axis).is_some() {
+                continue;
+            }
+
+            let drift = (feedback.get(axis) - raw.get(axis)).abs();
+            let drifted = self.drifted.get_mut(axis);
+            if drift <= threshold {
+                *drifted = false;
+                continue;
+            }
+            if *drifted {
+                continue;
+            }
+            *drifted = true;
+
+            warn!("{axis:?} axis drifted {drift} from its feedback");
+            drifts.push(Drift { axis, drift, recalibration: None, at: Utc::now().to_rfc3339() });
+        }
+
+        drifts
+    }
+}
+
+/// Recalibrates an axis which has drifted in a background job, returning its
+/// id. Calibrating can take a while, so this keeps it from holding up the poll
+/// and everything else waiting for the rotator meanwhile.
+async fn recalibrate(jobs: &Arc<Jobs>, rotator: &Arc<Mutex<Rotator>>, axis: Axis) -> u64 {
+    let kind = match axis {
+        Axis::Vertical => "calibrate_vertical",
+        Axis::Horizontal => "calibrate_horizontal",
+    };
+    let rotator = Arc::clone(rotator);
+
+    jobs.spawn(kind, move |progress| async move {
+        progress.report("waiting").await;
+        let mut rotator = rotator.lock().await;
+        progress.report("calibrating").await;
+
+        let result = rotator.calibrate(axis).await;
+        if let Err(e) = &result {
+            warn!("Failed to recalibrate the drifted {axis:?} axis: {e}");
+        }
+        result
+    })
+    .await
+}
+
+/// Sends a version query if nothing has been sent for `after`, to keep the
+/// serial adapter awake, returning how long until the next one is due.
+///
+/// Anything else sent meanwhile keeps the adapter awake just as well, so
+/// then nothing is sent and the next keepalive is due `after` that traffic
+/// instead. A rotator which is locked is in use, so is left alone too.
+async fn keep_alive(rotator: &Mutex<Rotator>, after: Duration) -> Duration {
+    let Ok(mut rotator) = rotator.try_lock() else {
+        return after;
+    };
+
+    match rotator.idle_for() {
+        Some(idle) if idle < after => after - idle,
+        _ => {
+            debug!("Sending a keepalive to the idle rotator");
+            if let Err(e) = rotator.refresh_version().await {
+                debug!("Keepalive failed: {e}");
+            }
+            after
+        }
+    }
+}
+
+/// Polls the rotator at the configured `poll_interval_ms` forever, also
+/// sending keepalives between polls if `keepalive_ms` is set. Drifted axes are
+/// recalibrated in `jobs`.
+pub async fn poll_loop(
+    rotator: Arc<Mutex<Rotator>>,
+    telemetry: Arc<Mutex<Telemetry>>,
+    events: broadcast::Sender<RotatorEvent>,
+    jobs: Arc<Jobs>,
+) {
+    info!("Started rotator poller");
+    // For the recalibration jobs, as `rotator` is shadowed by its guard below
+    let shared = Arc::clone(&rotator);
+
+    let interval = Duration::from_millis(rotator.lock().await.config().poll_interval_ms);
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    let mut stalls = StallDetector::default();
+    let mut drifts = DriftDetector::default();
+    let mut modes_supported = true;
+    let mut changes = ChangeFilter::default();
+
+    let keepalive_after = rotator.lock().await.config().keepalive_ms.map(Duration::from_millis);
+    let mut keepalive_at = keepalive_after.map(|after| tokio::time::Instant::now() + after);
+
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {}
+            () = async {
+                match keepalive_at {
+                    Some(at) => tokio::time::sleep_until(at).await,
+                    None => std::future::pending().await,
+                }
+            } => {
+                if let Some(after) = keepalive_after {
+                    keepalive_at = Some(tokio::time::Instant::now() + keep_alive(&rotator, after).await);
+                }
+                continue;
+            }
+        }
+
+        let need_version = telemetry.lock().await.version.is_none();
+
+        let mut rotator = rotator.lock().await;
+        let raw = rotator.position_raw_with_timestamp().await;
+        let sampled_at = raw.as_ref().map_or_else(|_| Instant::now(), |&(_, at)| at);
+        let raw = raw.map(|(raw, _)| raw);
+        let tolerance = rotator.config().position_tolerance;
+        let convention = rotator.config().frame.azimuth_convention;
+        let threshold = rotator.config().position_event_threshold;
+        let limits = rotator.config().limits;
+        let position = raw.as_ref().ok().map(|&(v, h)| {
+            let (horizontal, vertical) = rotator.config().frame.rotator_to_az_el(v, h);
+            Position { vertical, horizontal }
+        });
+
+        let stall = match position {
+            Some(position) => match stalls.update(&mut rotator, position) {
+                Some(axis) => {
+                    warn!("{}, halting", Error::Stalled(axis));
+                    if let Err(e) = rotator.halt().await {
+                        warn!("Failed to halt stalled rotator: {e}");
+                    }
+                    Some(Stall { axis, position, at: Utc::now().to_rfc3339() })
+                }
+                None => None,
+            },
+            None => None,
+        };
+
+        let mut new_drifts = match raw {
+            Ok(reading) => drifts.update(&mut rotator, reading).await,
+            Err(_) => Vec::new(),
+        };
+        if rotator.config().drift.auto_recalibrate {
+            for drift in &mut new_drifts {
+                drift.recalibration = Some(recalibrate(&jobs, &shared, drift.axis).await);
+            }
+        }
+
+        let (calibrated, version, mode, odometer) = if raw.is_ok() {
+            let calibrated = rotator.calibrated().await.ok();
+            let version = if need_version {
+                rotator.version().await.ok().map(|v| (v, rotator.firmware_build().cloned()))
+            } else {
+                None
+            };
+            let mode = if modes_supported {
+                match rotator.mode().await {
+                    Ok(mode) => Some(mode),
+                    Err(Error::Unsupported(_)) => {
+                        modes_supported = false;
+                        None
+                    }
+                    Err(_) => None,
+                }
+            } else {
+                None
+            };
+            let odometer = rotator.odometer().await.ok();
+            (calibrated, version, mode, odometer)
+        } else {
+            (None, None, None, None)
+        };
+        drop(rotator);
+
+        let mut telemetry = telemetry.lock().await;
+        match (raw, position) {
+            (Ok(raw), Some(position)) => {
+                telemetry.record_position(raw, position, sampled_at, tolerance, convention);
+                telemetry.calibrated = calibrated.or(telemetry.calibrated);
+                telemetry.mode = mode;
+                telemetry.odometer = odometer.or(telemetry.odometer.take());
+                if let Some((version, build)) = version {
+                    telemetry.version = Some(version);
+                    telemetry.firmware_build = build;
+                }
+                if let Some(stall) = stall {
+                    telemetry.last_error = Some(Error::Stalled(stall.axis).to_string());
+                    telemetry.last_stall = Some(stall.clone());
+                    let _ = events.send(RotatorEvent::Stalled(stall));
+                }
+                for drift in new_drifts {
+                    telemetry.last_drift = Some(drift.clone());
+                    let _ = events.send(RotatorEvent::Drifted(drift));
+                }
+                if changes.update(position, threshold) {
+                    let at = Utc::now().to_rfc3339();
+                    let _ = events.send(RotatorEvent::PositionChanged { position, at });
+                }
+            }
+            (Err(e), _) => {
+                if telemetry.connected {
+                    warn!("Lost contact with rotator: {e}");
+                }
+                telemetry.record_error(e.to_string());
+            }
+        }
+        telemetry.limits = limits;
+        telemetry.updated = Some(Utc::now().to_rfc3339());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::{config::{DriftConfig, RotatorConfig}, frame::Frame, mock::{self, MockFirmware}}, *};
+
+    /// Runs the poller for `firmware` often, returning its telemetry.
+    fn spawn_poller(firmware: &MockFirmware) -> Arc<Mutex<Telemetry>> {
+        spawn_poller_with(firmware, mock::config()).1
+    }
+
+    /// [`spawn_poller`] with `config`, also returning the rotator and its events.
+    fn spawn_poller_with(
+        firmware: &MockFirmware,
+        config: RotatorConfig,
+    ) -> (Arc<Mutex<Rotator>>, Arc<Mutex<Telemetry>>, broadcast::Receiver<RotatorEvent>) {
+        spawn_poller_with_jobs(firmware, config, Arc::default())
+    }
+
+    /// [`spawn_poller_with`], running any jobs in `jobs`.
+    fn spawn_poller_with_jobs(
+        firmware: &MockFirmware,
+        config: RotatorConfig,
+        jobs: Arc<Jobs>,
+    ) -> (Arc<Mutex<Rotator>>, Arc<Mutex<Telemetry>>, broadcast::Receiver<RotatorEvent>) {
+        let config = RotatorConfig { poll_interval_ms: 10, ..config };
+        let rotator = Arc::new(Mutex::new(firmware.rotator(config)));
+        let telemetry = Arc::new(Mutex::new(Telemetry::default()));
+        let (events, receiver) = broadcast::channel(64);
+        tokio::spawn(poll_loop(Arc::clone(&rotator), Arc::clone(&telemetry), events, jobs));
+
+        (rotator, telemetry, receiver)
+    }
+
+    #[rocket::async_test]
+    async fn connected_follows_whether_the_rotator_answers() {
+        let firmware = MockFirmware::new();
+        let telemetry = spawn_poller(&firmware);
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(telemetry.lock().await.connected);
+
+        firmware.lock().silent = true;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let snapshot = telemetry.lock().await.clone();
+        assert!(!snapshot.connected);
+        assert_eq!(snapshot.last_error.as_deref(), Some(Error::Timeout.to_string().as_str()));
+
+        firmware.lock().silent = false;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(telemetry.lock().await.connected);
+    }
+
+    #[test]
+    fn velocity_comes_from_consecutive_positions() {
+        let mut telemetry = Telemetry::default();
+        let start = Instant::now();
+        let position = |vertical, horizontal| Position { vertical, horizontal };
+
+        telemetry.record_position((10.0, -20.0), position(10.0, 20.0), start, 0.5, AzimuthConvention::Continuous);
+        assert!(telemetry.velocity.is_none() && !telemetry.moving);
+
+        let at = start + Duration::from_millis(500);
+        telemetry.record_position((12.0, -21.0), position(12.0, 21.0), at, 0.5, AzimuthConvention::Continuous);
+        assert_eq!(telemetry.velocity, Some(position(4.0, 2.0)));
+        assert!(telemetry.moving);
+        assert_eq!(telemetry.position_raw, Some(position(12.0, -21.0)));
+
+        telemetry.record_error("timed out".to_string());
+        assert!(!telemetry.connected && !telemetry.moving && telemetry.velocity.is_none());
+        // The last known position is kept
+        assert_eq!(telemetry.position, Some(position(12.0, 21.0)));
+    }
+
+    #[rocket::async_test]
+    async fn snapshot_is_assembled_from_the_rotator() {
+        let firmware = MockFirmware::new();
+        firmware.lock().position = PerAxis { vertical: 45.0, horizontal: -90.0 };
+        firmware.lock().calibrated = false;
+        firmware.lock().version = "v2.0.0 abc123 2024-05-01".to_string();
+        let telemetry = spawn_poller(&firmware);
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let snapshot = telemetry.lock().await.clone();
+
+        assert!(snapshot.connected);
+        assert_eq!(snapshot.position, Some(Position { vertical: 45.0, horizontal: 90.0 }));
+        assert_eq!(snapshot.position_raw, Some(Position { vertical: 45.0, horizontal: -90.0 }));
+        assert_eq!(snapshot.calibrated, Some(false));
+        assert_eq!(snapshot.version.as_deref(), Some("v2.0.0"));
+        assert_eq!(snapshot.firmware_build.and_then(|build| build.commit).as_deref(), Some("abc123"));
+        assert!(!snapshot.moving);
+        assert!(snapshot.updated.is_some());
+
+        let json = serde_json::to_value(telemetry.lock().await.clone()).unwrap();
+        for key in ["connected", "position", "position_raw", "velocity", "moving", "calibrated", "version", "last_error"] {
+            assert!(json.get(key).is_some(), "{key}");
+        }
+        assert!(json.get("limits").is_none());
+    }
+
+    #[rocket::async_test]
+    async fn snapshot_shows_movement() {
+        let firmware = MockFirmware::new();
+        firmware.lock().jogging.vertical = 1.0;
+        let telemetry = spawn_poller(&firmware);
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let snapshot = telemetry.lock().await.clone();
+
+        assert!(snapshot.moving);
+        assert!(snapshot.velocity.unwrap().vertical > 0.0);
+    }
+
+    #[rocket::async_test]
+    async fn an_axis_which_does_not_move_is_halted_as_stalled() {
+        let firmware = MockFirmware::new();
+        firmware.lock().stalled.vertical = true;
+        let config = RotatorConfig { stall_samples: 3, ..mock::config() };
+        let (rotator, telemetry, mut events) = spawn_poller_with(&firmware, config);
+
+        rotator.lock().await.set_position(Axis::Vertical, 30.0).await.unwrap();
+
+        let stall = tokio::time::timeout(Duration::from_secs(1), async {
+            loop {
+                if let RotatorEvent::Stalled(stall) = events.recv().await.unwrap() {
+                    break stall;
+                }
+            }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(stall.axis, Axis::Vertical);
+        assert_eq!(stall.position.vertical, 0.0);
+        assert!(firmware.commands().contains(&"HALT".to_string()));
+        assert_eq!(firmware.lock().target.vertical, None);
+        let snapshot = telemetry.lock().await.clone();
+        assert_eq!(snapshot.last_error, Some(Error::Stalled(Axis::Vertical).to_string()));
+        assert!(snapshot.last_stall.is_some());
+    }
+
+    #[rocket::async_test]
+    async fn moving_and_idle_axes_are_not_stalled() {
+        let firmware = MockFirmware::new();
+        firmware.lock().slew_per_read = Some(1.0);
+        let config = RotatorConfig { stall_samples: 3, ..mock::config() };
+        let (rotator, telemetry, _events) = spawn_poller_with(&firmware, config);
+
+        rotator.lock().await.set_position(Axis::Vertical, 10.0).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(300)).await;
+
+        assert_eq!(firmware.lock().position.vertical, 10.0);
+        assert!(!firmware.commands().contains(&"HALT".to_string()));
+        assert!(telemetry.lock().await.last_stall.is_none());
+    }
+
+    #[rocket::async_test]
+    async fn an_axis_at_its_target_across_north_is_not_stalled() {
+        let firmware = MockFirmware::new();
+        // Reads 359.9 degrees once inverted, just short of a target of 0
+        firmware.lock().position.horizontal = 0.1;
+        firmware.lock().stalled.horizontal = true;
+        let frame = Frame { azimuth_convention: AzimuthConvention::Compass, ..Frame::default() };
+        let config = RotatorConfig { stall_samples: 3, frame, ..mock::config() };
+        let (rotator, telemetry, _events) = spawn_poller_with(&firmware, config);
+
+        rotator.lock().await.set_position(Axis::Horizontal, 0.0).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(300)).await;
+
+        assert!(!firmware.commands().contains(&"HALT".to_string()));
+        assert!(telemetry.lock().await.last_stall.is_none());
+        assert_eq!(rotator.lock().await.motion(Axis::Horizontal), None);
+    }
+
+    #[test]
+    fn only_changes_past_the_threshold_are_events() {
+        let mut changes = ChangeFilter::default();
+        let position = |vertical, horizontal| Position { vertical, horizontal };
+
+        assert!(changes.update(position(10.0, 20.0), 1.0));
+        assert!(!changes.update(position(10.5, 20.9), 1.0));
+        // Jitter doesn't add up, as it's compared with the last event
+        assert!(!changes.update(position(10.9, 20.1), 1.0));
+        assert!(changes.update(position(11.5, 20.0), 1.0));
+        assert!(changes.update(position(11.5, 18.0), 1.0));
+    }
+
+    #[test]
+    fn crossing_north_is_not_a_jump() {
+        let mut changes = ChangeFilter::default();
+        let position = |horizontal| Position { vertical: 0.0, horizontal };
+
+        assert!(changes.update(position(359.8), 1.0));
+        assert!(!changes.update(position(0.3), 1.0));
+        assert!(changes.update(position(1.0), 1.0));
+    }
+
+    #[rocket::async_test]
+    async fn position_events_are_sent_as_it_moves() {
+        let firmware = MockFirmware::new();
+        let config = RotatorConfig { position_event_threshold: 2.5, ..mock::config() };
+        let (_rotator, _telemetry, mut events) = spawn_poller_with(&firmware, config);
+        firmware.lock().jogging.vertical = 1.0;
+
+        let mut elevations = Vec::new();
+        while elevations.len() < 3 {
+            let event = tokio::time::timeout(Duration::from_secs(1), events.recv()).await.unwrap().unwrap();
+            if let RotatorEvent::PositionChanged { position, .. } = event {
+                elevations.push(position.vertical);
+            }
+        }
+
+        // Each event is more than 2.5 degrees on from the one before
+        assert!(elevations.windows(2).all(|pair| pair[1] - pair[0] >= 3.0), "{elevations:?}");
+    }
+
+    #[rocket::async_test]
+    async fn keepalives_are_sent_once_the_rotator_is_idle() {
+        let firmware = MockFirmware::new();
+        let rotator = Mutex::new(firmware.rotator(mock::config()));
+        let after = Duration::from_millis(100);
+
+        // Nothing has been sent yet
+        assert_eq!(keep_alive(&rotator, after).await, after);
+        assert_eq!(firmware.commands(), ["VERS"]);
+
+        tokio::time::sleep(Duration::from_millis(120)).await;
+        assert_eq!(keep_alive(&rotator, after).await, after);
+        assert_eq!(firmware.commands(), ["VERS", "VERS"]);
+    }
+
+    #[rocket::async_test]
+    async fn keepalives_wait_for_traffic_to_go_quiet() {
+        let firmware = MockFirmware::new();
+        let rotator = Mutex::new(firmware.rotator(mock::config()));
+        let after = Duration::from_millis(1_000);
+
+        rotator.lock().await.position().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let next = keep_alive(&rotator, after).await;
+        assert!(next < after - Duration::from_millis(40), "{next:?}");
+
+        // A locked rotator is in use, even if it has been idle for longer
+        let _locked = rotator.lock().await;
+        let after = Duration::from_millis(10);
+        assert_eq!(keep_alive(&rotator, after).await, after);
+
+        assert_eq!(firmware.commands(), ["GETP"]);
+    }
+
+    fn drift_config(auto_recalibrate: bool) -> RotatorConfig {
+        RotatorConfig { drift: DriftConfig { threshold: Some(2.0), auto_recalibrate }, ..mock::config() }
+    }
+
+    #[rocket::async_test]
+    async fn drift_beyond_the_threshold_is_found_once() {
+        let firmware = MockFirmware::new();
+        firmware.reply("GETF", "OK 15 20.5");
+        let mut rotator = firmware.rotator(drift_config(true));
+        let mut drifts = DriftDetector::default();
+
+        let found = drifts.update(&mut rotator, (10.0, 20.0)).await;
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].axis, Axis::Vertical);
+        assert_eq!(found[0].drift, 5.0);
+        // Recalibrating is left to the poller, outside of the detector
+        assert_eq!(firmware.commands(), ["GETF"]);
+
+        // Until it is back within the threshold, the same drift is left alone
+        firmware.clear_received();
+        assert!(drifts.update(&mut rotator, (10.0, 20.0)).await.is_empty());
+        assert!(drifts.update(&mut rotator, (14.0, 20.0)).await.is_empty());
+        assert_eq!(drifts.update(&mut rotator, (10.0, 20.0)).await.len(), 1);
+        assert_eq!(firmware.commands(), ["GETF", "GETF", "GETF"]);
+    }
+
+    #[rocket::async_test]
+    async fn each_drifted_axis_is_found() {
+        let firmware = MockFirmware::new();
+        firmware.reply("GETF", "OK 15 25");
+        let mut rotator = firmware.rotator(drift_config(false));
+        let mut drifts = DriftDetector::default();
+
+        let found = drifts.update(&mut rotator, (10.0, 20.0)).await;
+
+        let axes: Vec<_> = found.iter().map(|drift| drift.axis).collect();
+        assert_eq!(axes, [Axis::Vertical, Axis::Horizontal]);
+        assert!(found.iter().all(|drift| drift.recalibration.is_none()));
+    }
+
+    #[rocket::async_test]
+    async fn moving_axes_are_not_checked_for_drift() {
+        let firmware = MockFirmware::new();
+        firmware.reply("GETF", "OK 15 20");
+        let mut rotator = firmware.rotator(drift_config(true));
+        rotator.set_position_vertical(15.0).await.unwrap();
+        let mut drifts = DriftDetector::default();
+
+        assert!(drifts.update(&mut rotator, (10.0, 20.0)).await.is_empty());
+        assert_eq!(firmware.commands(), ["DVER", "GETF"]);
+    }
+
+    #[rocket::async_test]
+    async fn drift_is_not_checked_without_feedback() {
+        let firmware = MockFirmware::new();
+        let mut rotator = firmware.rotator(drift_config(true));
+        let mut drifts = DriftDetector::default();
+
+        assert!(drifts.update(&mut rotator, (10.0, 20.0)).await.is_empty());
+        assert!(drifts.update(&mut rotator, (10.0, 20.0)).await.is_empty());
+        assert_eq!(firmware.commands(), ["GETF"]);
+
+        // Nor is it asked for unless a threshold is configured
+        let firmware = MockFirmware::new();
+        firmware.reply("GETF", "OK 15 20");
+        let mut rotator = firmware.rotator(mock::config());
+        assert!(DriftDetector::default().update(&mut rotator, (10.0, 20.0)).await.is_empty());
+        assert!(firmware.received().is_empty());
+    }
+
+    #[rocket::async_test]
+    async fn the_poller_records_the_last_drift() {
+        let firmware = MockFirmware::new();
+        firmware.lock().position = PerAxis { vertical: 10.0, horizontal: 20.0 };
+        firmware.reply("GETF", "OK 15 20");
+        let (_, telemetry, _) = spawn_poller_with(&firmware, drift_config(false));
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let drift = telemetry.lock().await.last_drift.clone().unwrap();
+        assert_eq!(drift.axis, Axis::Vertical);
+        assert!(drift.recalibration.is_none());
+        assert!(!firmware.commands().contains(&"CALV".to_string()));
+    }
+
+    #[rocket::async_test]
+    async fn drifted_axes_are_recalibrated_in_a_job() {
+        let firmware = MockFirmware::new();
+        firmware.lock().position = PerAxis { vertical: 10.0, horizontal: 20.0 };
+        firmware.reply("GETF", "OK 15 20");
+        let jobs = Arc::new(Jobs::default());
+        let (_, telemetry, _) = spawn_poller_with_jobs(&firmware, drift_config(true), Arc::clone(&jobs));
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let drift = telemetry.lock().await.last_drift.clone().unwrap();
+        let job = jobs.get(drift.recalibration.unwrap()).await.unwrap();
+        assert_eq!(job.kind, "calibrate_vertical");
+        assert!(job.finished_at.is_some(), "{job:?}");
+        let calibrations = firmware.commands().into_iter().filter(|command| command == "CALV").count();
+        assert_eq!(calibrations, 1);
+    }
+}
@@ -0,0 +1,119 @@
+//! Named positions, kept in a JSON file so they survive restarts.
+
+use std::{
+    collections::BTreeMap,
+    io,
+    path::{Path, PathBuf},
+};
+
+use log::warn;
+use rocket::tokio::{self, sync::Mutex};
+
+use super::Position;
+
+/// Every named preset position, shared by all rotators.
+pub struct Presets {
+    path: PathBuf,
+    presets: Mutex<BTreeMap<String, Position>>,
+}
+
+impl Presets {
+    /// Load the presets saved at `path`. A missing or unreadable file is
+    /// logged and treated as having no presets.
+    pub fn load(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+
+        let presets = Self::parse(&path, std::fs::read_to_string(&path)).unwrap_or_else(|e| {
+            warn!("{e}, starting without presets");
+            BTreeMap::new()
+        });
+
+        Self {
+            path,
+            presets: Mutex::new(presets),
+        }
+    }
+
+    /// Re-read the presets from the file, e.g. after it was edited by hand,
+    /// returning whether they changed.
+    ///
+    /// # Errors
+    /// Returns a message if the file can't be read or is invalid, in which
+    /// case the presets in use are kept.
+    pub async fn reload(&self) -> Result<bool, String> {
+        let presets = Self::parse(&self.path, tokio::fs::read_to_string(&self.path).await)?;
+
+        let mut current = self.presets.lock().await;
+        if *current == presets {
+            return Ok(false);
+        }
+        *current = presets;
+
+        Ok(true)
+    }
+
+    /// The presets in a file read from `path`. A missing file has none.
+    fn parse(path: &Path, read: Result<String, io::Error>) -> Result<BTreeMap<String, Position>, String> {
+        match read {
+            Ok(json) => serde_json::from_str(&json).map_err(|e| format!("Invalid presets in {}: {e}", path.display())),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+            Err(e) => Err(format!("Failed to read presets from {}: {e}", path.display())),
+        }
+    }
+
+    pub async fn get(&self, name: &str) -> Option<Position> {
+        self.presets.lock().await.get(name).copied()
+    }
+
+    pub async fn all(&self) -> BTreeMap<String, Position> {
+        self.presets.lock().await.clone()
+    }
+
+    /// Replace every preset, saving them to the file first so the file and
+    /// the presets in use can't disagree.
+    pub async fn replace(&self, presets: BTreeMap<String, Position>) -> Result<(), io::Error> {
+        let mut current = self.presets.lock().await;
+
+        let json = serde_json::to_string_pretty(&presets)?;
+        let partial = self.path.with_extension("json.partial");
+        tokio::fs::write(&partial, json).await?;
+        tokio::fs::rename(&partial, &self.path).await?;
+
+        *current = presets;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[rocket::async_test]
+    async fn replaced_presets_are_saved_to_the_file() {
+        let path = std::env::temp_dir().join(format!("archerd-presets-saved-{}.json", std::process::id()));
+        let presets = Presets::load(&path);
+        assert!(presets.all().await.is_empty());
+
+        let park = Position { vertical: 90.0, horizontal: 0.0 };
+        presets.replace(BTreeMap::from([("park".to_string(), park)])).await.unwrap();
+
+        assert_eq!(Presets::load(&path).get("park").await, Some(park));
+        assert!(!path.with_extension("json.partial").exists());
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[rocket::async_test]
+    async fn invalid_files_are_not_reloaded() {
+        let path = std::env::temp_dir().join(format!("archerd-presets-invalid-{}.json", std::process::id()));
+        std::fs::write(&path, r#"{"park": {"vertical": 90.0, "horizontal": 0.0}}"#).unwrap();
+        let presets = Presets::load(&path);
+
+        std::fs::write(&path, "not json").unwrap();
+        assert!(presets.reload().await.is_err());
+        assert!(presets.get("park").await.is_some());
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
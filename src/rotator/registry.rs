@@ -0,0 +1,374 @@
+//! Management of several rotators from one server.
+//!
+//! Every rotator endpoint is mounted once under `/rotator`, which addresses
+//! the default rotator. Requests to `/rotators/<id>/...` are rewritten by the
+//! [`RotatorScope`] fairing onto the same routes, and the [`RotatorHandle`]
+//! request guard then resolves the rotator with that id.
+
+use std::{
+    collections::HashMap,
+    ops::{Deref, DerefMut},
+    sync::{self, Arc, PoisonError},
+    time::{Duration, Instant},
+};
+
+use rocket::{
+    Data, Request, get,
+    fairing::{Fairing, Info, Kind},
+    http::{Status, uri::Origin},
+    request::{FromRequest, Outcome},
+    State,
+    tokio::{self, sync::{Mutex, MutexGuard, broadcast}},
+};
+use serde::Serialize;
+use serde_json::json;
+
+use super::{
+    Rotator,
+    config::{Limits, PerAxis},
+    poller::{RotatorEvent, Telemetry, poll_loop},
+};
+use crate::{jobs::Jobs, response::Success};
+
+/// The id of the rotator found automatically at startup, which is also the
+/// one addressed by the unscoped `/rotator` routes.
+pub const DEFAULT_ROTATOR: &str = "default";
+
+/// A rotator and the telemetry cache kept by its poller.
+#[derive(Clone)]
+pub struct RotatorHandle {
+    pub rotator: Arc<Mutex<Rotator>>,
+    pub telemetry: Arc<Mutex<Telemetry>>,
+    /// Events published by the poller. Subscribe to receive them.
+    pub events: broadcast::Sender<RotatorEvent>,
+    lock_metrics: Arc<sync::Mutex<LockMetrics>>,
+}
+
+/// How long requests wait for the rotator's lock, and how long they then
+/// hold it, to show how much they contend for the port. Only requests which
+/// lock it through [`RotatorHandle::lock`] are counted, not the poller.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LockMetrics {
+    pub acquisitions: u64,
+    pub mean_wait_ms: f64,
+    pub max_wait_ms: f64,
+    pub mean_hold_ms: f64,
+    pub max_hold_ms: f64,
+    #[serde(skip)]
+    released: u64,
+}
+
+impl LockMetrics {
+    fn record_wait(&mut self, wait: Duration) {
+        let wait_ms = wait.as_secs_f64() * 1000.0;
+
+        self.acquisitions += 1;
+        self.mean_wait_ms += (wait_ms - self.mean_wait_ms) / self.acquisitions as f64;
+        self.max_wait_ms = self.max_wait_ms.max(wait_ms);
+    }
+
+    fn record_hold(&mut self, hold: Duration) {
+        let hold_ms = hold.as_secs_f64() * 1000.0;
+
+        self.released += 1;
+        self.mean_hold_ms += (hold_ms - self.mean_hold_ms) / self.released as f64;
+        self.max_hold_ms = self.max_hold_ms.max(hold_ms);
+    }
+}
+
+/// The locked rotator, which records how long it was held when dropped.
+pub struct RotatorGuard<'a> {
+    guard: MutexGuard<'a, Rotator>,
+    locked_at: Instant,
+    metrics: &'a sync::Mutex<LockMetrics>,
+}
+
+impl Deref for RotatorGuard<'_> {
+    type Target = Rotator;
+
+    fn deref(&self) -> &Self::Target {
+        &self.guard
+    }
+}
+
+impl DerefMut for RotatorGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.guard
+    }
+}
+
+impl Drop for RotatorGuard<'_> {
+    fn drop(&mut self) {
+        let held = self.locked_at.elapsed();
+        self.metrics.lock().unwrap_or_else(PoisonError::into_inner).record_hold(held);
+    }
+}
+
+impl RotatorHandle {
+    /// How many events a slow subscriber may fall behind by before missing some.
+    const EVENT_CAPACITY: usize = 64;
+
+    /// Wrap a rotator and spawn its poller, which runs any recalibrations in
+    /// `jobs`.
+    pub fn spawn(rotator: Arc<Mutex<Rotator>>, jobs: Arc<Jobs>) -> Self {
+        let telemetry = Arc::new(Mutex::new(Telemetry::default()));
+        let (events, _) = broadcast::channel(Self::EVENT_CAPACITY);
+        tokio::spawn(poll_loop(Arc::clone(&rotator), Arc::clone(&telemetry), events.clone(), jobs));
+
+        Self {
+            rotator,
+            telemetry,
+            events,
+            lock_metrics: Arc::default(),
+        }
+    }
+
+    /// Wrap a rotator without a poller, so that tests only see the commands
+    /// they send themselves.
+    #[cfg(test)]
+    pub fn unpolled(rotator: Arc<Mutex<Rotator>>) -> Self {
+        let (events, _) = broadcast::channel(Self::EVENT_CAPACITY);
+
+        Self {
+            rotator,
+            telemetry: Arc::default(),
+            events,
+            lock_metrics: Arc::default(),
+        }
+    }
+
+    /// Lock the rotator, counting the wait and hold times in the
+    /// [`LockMetrics`].
+    pub async fn lock(&self) -> RotatorGuard<'_> {
+        let started = Instant::now();
+        let guard = self.rotator.lock().await;
+        let locked_at = Instant::now();
+        self.lock_metrics
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .record_wait(locked_at - started);
+
+        RotatorGuard {
+            guard,
+            locked_at,
+            metrics: &self.lock_metrics,
+        }
+    }
+
+    /// The rotator's configured limits as of its last poll, for checking a
+    /// request before locking the rotator. Moves are still checked against
+    /// the current limits once it is locked.
+    pub async fn limits(&self) -> PerAxis<Option<Limits>> {
+        self.telemetry.lock().await.limits
+    }
+
+    pub fn lock_metrics(&self) -> LockMetrics {
+        self.lock_metrics.lock().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+}
+
+/// Every rotator managed by the server, keyed by id.
+pub struct Rotators {
+    handles: HashMap<String, RotatorHandle>,
+}
+
+impl Rotators {
+    pub fn new(default: RotatorHandle) -> Self {
+        Self {
+            handles: HashMap::from([(DEFAULT_ROTATOR.to_string(), default)]),
+        }
+    }
+
+    /// Add a rotator, returning `false` if the id is already taken.
+    pub fn insert(&mut self, id: String, handle: RotatorHandle) -> bool {
+        if self.handles.contains_key(&id) {
+            return false;
+        }
+
+        self.handles.insert(id, handle);
+        true
+    }
+
+    pub fn get(&self, id: &str) -> Option<&RotatorHandle> {
+        self.handles.get(id)
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item = &str> {
+        self.handles.keys().map(String::as_str)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &RotatorHandle)> {
+        self.handles.iter().map(|(id, handle)| (id.as_str(), handle))
+    }
+}
+
+/// The rotator id a request was scoped to by [`RotatorScope`].
+struct SelectedRotator(Option<String>);
+
+/// Rewrites `/rotators/<id>/<rest>` to `/rotator/<rest>`, remembering `<id>`
+/// for the [`RotatorHandle`] guard.
+pub struct RotatorScope;
+
+#[rocket::async_trait]
+impl Fairing for RotatorScope {
+    fn info(&self) -> Info {
+        Info {
+            name: "Rotator scope",
+            kind: Kind::Request,
+        }
+    }
+
+    async fn on_request(&self, req: &mut Request<'_>, _data: &mut Data<'_>) {
+        let Some(scoped) = req.uri().path().as_str().strip_prefix("/rotators/") else {
+            return;
+        };
+
+        let (id, rest) = scoped.split_once('/').unwrap_or((scoped, ""));
+        let id = id.to_string();
+
+        let mut uri = format!("/rotator/{rest}");
+        if let Some(query) = req.uri().query() {
+            uri.push('?');
+            uri.push_str(query.as_str());
+        }
+
+        if let Ok(origin) = Origin::parse_owned(uri) {
+            req.set_uri(origin);
+            req.local_cache(|| SelectedRotator(Some(id)));
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for RotatorHandle {
+    type Error = String;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let Some(rotators) = req.rocket().state::<Rotators>() else {
+            return Outcome::Error((Status::InternalServerError, "rotators not managed".to_string()));
+        };
+
+        let selected = req.local_cache(|| SelectedRotator(None));
+        let id = selected.0.as_deref().unwrap_or(DEFAULT_ROTATOR);
+
+        match rotators.get(id) {
+            Some(handle) => Outcome::Success(handle.clone()),
+            None => Outcome::Error((Status::NotFound, format!("no rotator with id `{id}`"))),
+        }
+    }
+}
+
+/// Lists the ids of every managed rotator.
+#[get("/rotators")]
+pub fn list_rotators(rotators: &State<Rotators>) -> Success {
+    let mut ids: Vec<_> = rotators.ids().collect();
+    ids.sort_unstable();
+
+    Success::data(json!({
+        "rotators": ids,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use rocket::{http::Status, local::asynchronous::Client, routes};
+    use serde_json::{Value, json};
+
+    use super::*;
+    use crate::rotator::{endpoints, mock::{self, MockFirmware}};
+
+    fn handle(firmware: &MockFirmware) -> RotatorHandle {
+        RotatorHandle::unpolled(Arc::new(Mutex::new(firmware.rotator(mock::config()))))
+    }
+
+    async fn get(client: &Client, uri: &str) -> (Status, Option<Value>) {
+        let response = client.get(uri.to_string()).dispatch().await;
+        let status = response.status();
+        let body = response.into_string().await.and_then(|body| serde_json::from_str(&body).ok());
+
+        (status, body)
+    }
+
+    #[rocket::async_test]
+    async fn scoped_requests_reach_their_rotator() {
+        let (default, other) = (MockFirmware::new(), MockFirmware::new());
+        default.lock().position.vertical = 10.0;
+        other.lock().position.vertical = 20.0;
+
+        let mut rotators = Rotators::new(handle(&default));
+        assert!(rotators.insert("other".to_string(), handle(&other)));
+        assert!(!rotators.insert("other".to_string(), handle(&other)));
+        let rocket = rocket::build()
+            .manage(rotators)
+            .attach(RotatorScope)
+            .mount("/", routes![list_rotators])
+            .mount("/rotator", routes![endpoints::position]);
+        let client = Client::tracked(rocket).await.unwrap();
+
+        let (_, body) = get(&client, "/rotator/position").await;
+        assert_eq!(body.unwrap()["data"]["vertical"], json!(10.0));
+        let (_, body) = get(&client, "/rotators/default/position").await;
+        assert_eq!(body.unwrap()["data"]["vertical"], json!(10.0));
+        let (_, body) = get(&client, "/rotators/other/position?raw=true").await;
+        assert_eq!(body.unwrap()["data"]["vertical"], json!(20.0));
+
+        assert_eq!(default.commands(), ["GETP", "GETP"]);
+        assert_eq!(other.commands(), ["GETP"]);
+
+        let (_, body) = get(&client, "/rotators").await;
+        assert_eq!(body.unwrap()["data"]["rotators"], json!(["default", "other"]));
+    }
+
+    #[rocket::async_test]
+    async fn unknown_rotators_are_not_found() {
+        let firmware = MockFirmware::new();
+        let rocket = rocket::build()
+            .manage(Rotators::new(handle(&firmware)))
+            .attach(RotatorScope)
+            .mount("/rotator", routes![endpoints::position]);
+        let client = Client::tracked(rocket).await.unwrap();
+
+        let (status, _) = get(&client, "/rotators/missing/position").await;
+
+        assert_eq!(status, Status::NotFound);
+        assert!(firmware.commands().is_empty());
+    }
+
+    #[rocket::async_test]
+    async fn lock_hold_time_covers_a_slow_command() {
+        let firmware = MockFirmware::new();
+        firmware.lock().delay = Duration::from_millis(100);
+        let handle = handle(&firmware);
+
+        handle.lock().await.position_raw().await.unwrap();
+
+        let metrics = handle.lock_metrics();
+        assert_eq!(metrics.acquisitions, 1);
+        assert!(metrics.max_hold_ms >= 100.0, "{metrics:?}");
+        assert!(metrics.mean_hold_ms >= 100.0, "{metrics:?}");
+        assert!(metrics.max_wait_ms < 100.0, "{metrics:?}");
+    }
+
+    #[rocket::async_test]
+    async fn waiting_behind_another_request_is_counted() {
+        let firmware = MockFirmware::new();
+        let handle = handle(&firmware);
+
+        let guard = handle.lock().await;
+        let waiting = tokio::spawn({
+            let handle = handle.clone();
+            async move {
+                let _guard = handle.lock().await;
+            }
+        });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        drop(guard);
+        waiting.await.unwrap();
+
+        let metrics = handle.lock_metrics();
+        assert_eq!(metrics.acquisitions, 2);
+        assert!(metrics.max_wait_ms >= 100.0, "{metrics:?}");
+        assert!(metrics.mean_wait_ms >= 50.0, "{metrics:?}");
+        assert!(metrics.max_hold_ms >= 100.0, "{metrics:?}");
+    }
+}
@@ -0,0 +1,212 @@
+//! A non-destructive diagnostic for checking the wiring and motors of a
+//! newly installed rotator without running a full calibration.
+
+use std::time::{Duration, Instant};
+
+use rocket::tokio::{self, sync::Mutex};
+use serde::Serialize;
+
+use super::{Axis, Error, POSITION_POLL_INTERVAL, Position, Rotator};
+
+/// The largest nudge [`Rotator::self_test`] will perform, in steps.
+pub const MAX_NUDGE_STEPS: u32 = 50;
+
+/// How long each nudge may take to come to rest.
+const NUDGE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Outcome of [`Rotator::self_test`]. A check which failed is left as `None`
+/// and the reason is recorded in `failures`.
+#[derive(Debug, Default, Serialize)]
+pub struct SelfTestReport {
+    pub passed: bool,
+    pub version: Option<String>,
+    pub calibrated: Option<bool>,
+    pub position: Option<Position>,
+    /// The axes which were successfully nudged and returned.
+    pub nudged: Vec<Axis>,
+    pub failures: Vec<String>,
+}
+
+impl Rotator {
+    /// Queries the version, calibration status, and position, and optionally
+    /// nudges each axis forward and back by `nudge_steps` (capped to
+    /// [`MAX_NUDGE_STEPS`]). Each nudge is waited on until the axis comes to
+    /// rest, and the axis must end up back where it started.
+    ///
+    /// Failures are collected into the report rather than stopping the test.
+    /// The rotator is only locked for each step, so it can be halted partway,
+    /// which fails any nudges left.
+    pub async fn self_test(rotator: &Mutex<Self>, nudge_steps: Option<u32>) -> SelfTestReport {
+        let mut report = SelfTestReport::default();
+
+        let stops = {
+            let mut rotator = rotator.lock().await;
+
+            // Always ask, as this is also a check that the rotator responds
+            match rotator.refresh_version().await {
+                Ok(v) => report.version = Some(v),
+                Err(e) => report.failures.push(format!("version: {e}")),
+            }
+
+            match rotator.calibrated().await {
+                Ok(c) => report.calibrated = Some(c),
+                Err(e) => report.failures.push(format!("calibrated: {e}")),
+            }
+
+            match rotator.position().await {
+                Ok((vertical, horizontal)) => report.position = Some(Position { vertical, horizontal }),
+                Err(e) => report.failures.push(format!("position: {e}")),
+            }
+
+            rotator.stops()
+        };
+
+        if let Some(steps) = nudge_steps {
+            let steps = steps.min(MAX_NUDGE_STEPS) as i32;
+
+            for axis in [Axis::Vertical, Axis::Horizontal] {
+                match Self::nudge_and_return(rotator, stops, axis, steps).await {
+                    Ok(()) => report.nudged.push(axis),
+                    Err(failure) => report.failures.push(failure),
+                }
+            }
+        }
+
+        report.passed = report.failures.is_empty();
+
+        report
+    }
+
+    /// Moves an axis forward by `steps` and back again, waiting for it to
+    /// come to rest after each, and checks that it is back where it started.
+    async fn nudge_and_return(rotator: &Mutex<Self>, stops: u64, axis: Axis, steps: i32) -> Result<(), String> {
+        let nudge_failed = |e: Error| format!("nudge {axis:?}: {e}");
+        let return_failed = |e: Error| format!("return {axis:?}: {e}");
+
+        let start = Self::wait_until_still(rotator, stops, axis).await.map_err(nudge_failed)?;
+        Self::step(rotator, stops, axis, steps).await.map_err(nudge_failed)?;
+        Self::wait_until_still(rotator, stops, axis).await.map_err(nudge_failed)?;
+
+        Self::step(rotator, stops, axis, -steps).await.map_err(return_failed)?;
+        let end = Self::wait_until_still(rotator, stops, axis).await.map_err(return_failed)?;
+
+        let tolerance = rotator.lock().await.config.position_tolerance;
+        if (end - start).abs() > tolerance {
+            return Err(format!("return {axis:?}: ended at {end}, rather than where it started at {start}"));
+        }
+
+        Ok(())
+    }
+
+    async fn step(rotator: &Mutex<Self>, stops: u64, axis: Axis, steps: i32) -> Result<(), Error> {
+        let mut rotator = rotator.lock().await;
+        rotator.ensure_not_stopped(stops)?;
+
+        rotator.move_steps(axis, steps).await
+    }
+
+    /// Waits for an axis to stay within the position tolerance for the settle
+    /// time, returning its reading as [`Self::position_raw`] once it has.
+    async fn wait_until_still(rotator: &Mutex<Self>, stops: u64, axis: Axis) -> Result<f32, Error> {
+        let (tolerance, settle_time) = {
+            let rotator = rotator.lock().await;
+
+            (rotator.config.position_tolerance, Duration::from_millis(rotator.config.settle_time_ms))
+        };
+        let deadline = Instant::now() + NUDGE_TIMEOUT;
+
+        let mut anchor: Option<(f32, Instant)> = None;
+        loop {
+            let reading = Self::poll_reading(rotator, stops, axis).await?;
+            let now = Instant::now();
+
+            match anchor {
+                Some((at, since)) if (reading - at).abs() <= tolerance => {
+                    if now.duration_since(since) >= settle_time {
+                        return Ok(at);
+                    }
+                }
+                _ => anchor = Some((reading, now)),
+            }
+
+            if now >= deadline {
+                return Err(Error::Timeout);
+            }
+
+            tokio::time::sleep(POSITION_POLL_INTERVAL).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::{config::PerAxis, mock::{self, MockFirmware}}, *};
+
+    #[rocket::async_test]
+    async fn reports_the_queries() {
+        let firmware = MockFirmware::new();
+        firmware.lock().position.vertical = 12.0;
+        let rotator = firmware.shared(mock::config());
+
+        let report = Rotator::self_test(&rotator, None).await;
+
+        assert!(report.passed, "{:?}", report.failures);
+        assert_eq!(report.version.as_deref(), Some("v1.4.0"));
+        assert_eq!(report.calibrated, Some(true));
+        assert_eq!(report.position, Some(Position { vertical: 12.0, horizontal: 0.0 }));
+        assert!(report.nudged.is_empty());
+        assert_eq!(firmware.commands(), ["VERS", "GETC", "GETP"]);
+    }
+
+    #[rocket::async_test]
+    async fn failed_queries_are_collected() {
+        let firmware = MockFirmware::new();
+        firmware.reply("GETC", "ERR sensor fault");
+        let rotator = firmware.shared(mock::config());
+
+        let report = Rotator::self_test(&rotator, None).await;
+
+        assert!(!report.passed);
+        assert_eq!(report.calibrated, None);
+        assert_eq!(report.failures, ["calibrated: rotator error: sensor fault"]);
+        // The other checks still ran
+        assert!(report.version.is_some() && report.position.is_some());
+    }
+
+    #[rocket::async_test]
+    async fn nudges_each_axis_forward_and_back() {
+        let firmware = MockFirmware::new();
+        let rotator = firmware.shared(mock::config());
+
+        let report = Rotator::self_test(&rotator, Some(20)).await;
+
+        assert!(report.passed, "{:?}", report.failures);
+        assert_eq!(report.nudged, [Axis::Vertical, Axis::Horizontal]);
+        let moves: Vec<_> = firmware.received().into_iter().filter(|line| line.starts_with("MOV")).collect();
+        assert_eq!(moves, ["MOVV 20", "MOVV -20", "MOVH 20", "MOVH -20"]);
+        assert_eq!(firmware.lock().position, PerAxis { vertical: 0.0, horizontal: 0.0 });
+    }
+
+    #[rocket::async_test]
+    async fn nudges_are_bounded() {
+        let firmware = MockFirmware::new();
+        let rotator = firmware.shared(mock::config());
+
+        Rotator::self_test(&rotator, Some(10_000)).await;
+
+        assert!(firmware.received().contains(&format!("MOVV {MAX_NUDGE_STEPS}")));
+    }
+
+    #[rocket::async_test]
+    async fn a_failed_nudge_fails_the_axis() {
+        let firmware = MockFirmware::new();
+        firmware.reply("MOVH", "ERR motor fault");
+        let rotator = firmware.shared(mock::config());
+
+        let report = Rotator::self_test(&rotator, Some(20)).await;
+
+        assert!(!report.passed);
+        assert_eq!(report.nudged, [Axis::Vertical]);
+        assert_eq!(report.failures, ["nudge Horizontal: rotator error: motor fault"]);
+    }
+}
@@ -0,0 +1,135 @@
+//! The shapes of response firmware sends, and reading a [`Response`] out of
+//! its lines.
+
+use serde::{Deserialize, Serialize};
+
+use super::{Error, Response};
+
+/// Which lines make up the firmware's response to a command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResponseShape {
+    /// An echo of the command, then the status line.
+    #[default]
+    EchoStatus,
+    /// Only the status line.
+    Status,
+    /// The status line, then lines of values, which are added to the
+    /// status line's.
+    StatusData,
+    /// An echo of the command, then as for [`Self::StatusData`].
+    EchoStatusData,
+}
+
+/// Whether the firmware sends values on lines of their own after the status
+/// line, see [`Rotator::verbosity`](super::Rotator::verbosity).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Verbosity {
+    Terse,
+    Verbose,
+}
+
+/// The line the parser expects next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Echo,
+    Status,
+    Data,
+    Done,
+}
+
+impl ResponseShape {
+    /// The shape for firmware which does or doesn't echo commands, with the
+    /// given verbosity.
+    pub const fn new(echo: bool, verbosity: Verbosity) -> Self {
+        match (echo, verbosity) {
+            (true, Verbosity::Terse) => Self::EchoStatus,
+            (false, Verbosity::Terse) => Self::Status,
+            (false, Verbosity::Verbose) => Self::StatusData,
+            (true, Verbosity::Verbose) => Self::EchoStatusData,
+        }
+    }
+
+    const fn start(self) -> State {
+        match self {
+            Self::EchoStatus | Self::EchoStatusData => State::Echo,
+            Self::Status | Self::StatusData => State::Status,
+        }
+    }
+
+    const fn has_data(self) -> bool {
+        matches!(self, Self::StatusData | Self::EchoStatusData)
+    }
+
+    /// Parse the lines of a response to `command`, which is the command as
+    /// sent without its terminator. Lines after the end of the response are
+    /// ignored.
+    ///
+    /// # Errors
+    /// Returns [`Error::EchoMismatch`] if the echo is not of `command`, and
+    /// [`Error::InvalidResponse`] if there is no status line or it is malformed.
+    pub fn parse<'a>(self, command: &str, lines: impl IntoIterator<Item = &'a str>) -> Result<Response, Error> {
+        let mut state = self.start();
+        let mut response: Option<Response> = None;
+
+        for line in lines {
+            state = match state {
+                State::Echo if line == command => State::Status,
+                State::Echo => {
+                    return Err(Error::EchoMismatch {
+                        expected: command.to_string(),
+                        got: line.to_string(),
+                    });
+                }
+                State::Status => {
+                    response = Some(Response::parse(line)?);
+                    if self.has_data() { State::Data } else { State::Done }
+                }
+                State::Data => {
+                    if let Some(response) = &mut response {
+                        response.values.extend(line.split_ascii_whitespace().map(str::to_string));
+                    }
+                    State::Data
+                }
+                State::Done => break,
+            };
+        }
+
+        response.ok_or(Error::InvalidResponse)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::Status, *};
+
+    #[test]
+    fn each_shape_reads_its_lines() {
+        let expected = Response { status: Status::Ok, values: vec!["1.0".to_string(), "2.0".to_string()] };
+
+        assert_eq!(ResponseShape::EchoStatus.parse("GETP", ["GETP", "OK 1.0 2.0"]).unwrap(), expected);
+        assert_eq!(ResponseShape::Status.parse("GETP", ["OK 1.0 2.0", "ignored"]).unwrap(), expected);
+        assert_eq!(ResponseShape::StatusData.parse("GETP", ["OK", "1.0", "2.0"]).unwrap(), expected);
+        assert_eq!(ResponseShape::EchoStatusData.parse("GETP", ["GETP", "OK 1.0", "2.0"]).unwrap(), expected);
+    }
+
+    #[test]
+    fn a_mismatched_echo_reports_both_lines() {
+        for (echo, got) in [("GETV", "GETV"), ("GETP\r", "GETP\r"), ("OK 1.0 2.0", "OK 1.0 2.0")] {
+            let error = ResponseShape::EchoStatus.parse("GETP", [echo, "OK 1.0 2.0"]).unwrap_err();
+
+            assert!(
+                matches!(&error, Error::EchoMismatch { expected, got: line } if expected == "GETP" && line == got),
+                "{error:?}",
+            );
+        }
+    }
+
+    #[test]
+    fn a_missing_status_line_is_invalid() {
+        let error = ResponseShape::EchoStatus.parse("GETP", ["GETP"]).unwrap_err();
+
+        assert!(matches!(error, Error::InvalidResponse), "{error:?}");
+    }
+}
@@ -0,0 +1,203 @@
+//! The slew speed of the motors, on firmware which can change it.
+
+use std::time::Duration;
+
+use rocket::tokio::sync::Mutex;
+
+use super::{Command, Error, Position, Rotator, exact_values};
+
+impl Rotator {
+    /// Gets the slew speed the firmware is using, in degrees per second.
+    ///
+    /// # Errors
+    /// Returns [`Error::Unsupported`] if the firmware has no speed control.
+    pub async fn speed(&mut self) -> Result<f32, Error> {
+        let values = self
+            .send_optional(Command::GetSpeed, &[]).await?
+            .ok_or(Error::ExpectedValue(Command::GetSpeed))?;
+
+        let [speed] = exact_values(values)?;
+
+        self.parse_reading(&speed)
+    }
+
+    /// Sets the slew speed used for every following move, in degrees per
+    /// second.
+    ///
+    /// # Errors
+    /// Returns [`Error::Unsupported`] if the firmware has no speed control.
+    pub async fn set_speed(&mut self, speed: f32) -> Result<(), Error> {
+        if !speed.is_finite() || speed <= 0.0 {
+            return Err(Error::OutOfRange {
+                requested: speed.into(),
+                min: 0.0,
+                max: f64::INFINITY,
+            });
+        }
+
+        self.send_optional(Command::SetSpeed, &[&format!("{speed:0.3}")]).await?;
+
+        Ok(())
+    }
+
+    /// Moves to a position at `speed`, waiting for it to be reached with the
+    /// rotator unlocked like [`Self::goto_and_wait`]. If `restore` is set,
+    /// the previous speed is put back afterwards, whether or not the move
+    /// succeeded; otherwise the new speed is kept.
+    ///
+    /// # Errors
+    /// Returns [`Error::Unsupported`] without moving if the firmware has no
+    /// speed control, otherwise errors like [`Self::goto_and_wait`].
+    pub async fn goto_with_speed(
+        rotator: &Mutex<Self>,
+        target: Position,
+        speed: f32,
+        timeout: Duration,
+        restore: bool,
+    ) -> Result<(), Error> {
+        let mut wait = rotator.lock().await.start_goto(target, Some(speed)).await?;
+        if !restore {
+            wait.keep_speed();
+        }
+
+        wait.finish(rotator, timeout).await
+    }
+
+    /// Sets the speed to `default_slew_rate` for a single move, if it is set
+    /// and the firmware has speed control, returning the speed to put back
+    /// once the move is over. The default rate is only for moves which don't
+    /// give their own speed, so any speed set since is not lost.
+    pub(super) async fn apply_default_slew_rate(&mut self) -> Result<Option<f32>, Error> {
+        let Some(rate) = self.config.default_slew_rate else {
+            return Ok(None);
+        };
+
+        let previous = match self.speed().await {
+            Ok(previous) => previous,
+            Err(Error::Unsupported(_)) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        if previous == rate {
+            return Ok(None);
+        }
+
+        self.set_speed(rate).await?;
+
+        Ok(Some(previous))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::{config::{ParkConfig, RotatorConfig}, mock::{self, MockFirmware}}, *};
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    fn firmware() -> MockFirmware {
+        let firmware = MockFirmware::new();
+        firmware.reply("GETS", "OK 5.0");
+        firmware.reply("SETS", "OK");
+
+        firmware
+    }
+
+    /// Every command sent other than position readings.
+    fn sent(firmware: &MockFirmware) -> Vec<String> {
+        firmware.received().into_iter().filter(|line| line != "GETP").collect()
+    }
+
+    #[rocket::async_test]
+    async fn the_speed_is_set_for_the_move_and_then_put_back() {
+        let firmware = firmware();
+        let rotator = firmware.shared(mock::config());
+
+        let target = Position { vertical: 10.0, horizontal: 20.0 };
+        Rotator::goto_with_speed(&rotator, target, 2.0, TIMEOUT, true).await.unwrap();
+
+        assert_eq!(sent(&firmware), ["GETS", "SETS 2.000", "DVER 10.000", "DHOR -20.000", "SETS 5.000"]);
+    }
+
+    #[rocket::async_test]
+    async fn the_speed_can_be_kept_after_the_move() {
+        let firmware = firmware();
+        let rotator = firmware.shared(mock::config());
+
+        let target = Position { vertical: 10.0, horizontal: 20.0 };
+        Rotator::goto_with_speed(&rotator, target, 2.0, TIMEOUT, false).await.unwrap();
+
+        assert_eq!(sent(&firmware), ["GETS", "SETS 2.000", "DVER 10.000", "DHOR -20.000"]);
+    }
+
+    #[rocket::async_test]
+    async fn moving_at_a_speed_needs_speed_control() {
+        let firmware = MockFirmware::new();
+        let rotator = firmware.shared(mock::config());
+
+        let target = Position { vertical: 10.0, horizontal: 20.0 };
+        let error = Rotator::goto_with_speed(&rotator, target, 2.0, TIMEOUT, true).await.unwrap_err();
+
+        assert!(matches!(error, Error::Unsupported(Command::GetSpeed)), "{error:?}");
+        assert_eq!(firmware.received(), ["GETS"]);
+    }
+
+    #[rocket::async_test]
+    async fn speeds_must_be_positive() {
+        let firmware = firmware();
+        let mut rotator = firmware.rotator(mock::config());
+
+        for speed in [0.0, -1.0, f32::NAN] {
+            let error = rotator.set_speed(speed).await.unwrap_err();
+            assert!(matches!(error, Error::OutOfRange { .. }), "{error:?}");
+        }
+        assert!(firmware.received().is_empty());
+    }
+
+    fn default_rate(rate: f32) -> RotatorConfig {
+        RotatorConfig { default_slew_rate: Some(rate), ..mock::config() }
+    }
+
+    #[rocket::async_test]
+    async fn parking_is_at_the_default_slew_rate() {
+        let firmware = firmware();
+        let park = ParkConfig { vertical: 90.0, horizontal: 30.0, ..ParkConfig::default() };
+        let rotator = firmware.shared(RotatorConfig { park, ..default_rate(3.0) });
+
+        Rotator::park(&rotator).await.unwrap();
+
+        assert_eq!(sent(&firmware), ["GETS", "SETS 3.000", "DVER 90.000", "DHOR -30.000", "SETS 5.000"]);
+    }
+
+    #[rocket::async_test]
+    async fn a_speed_given_for_the_move_is_used_over_the_default() {
+        let firmware = firmware();
+        let rotator = firmware.shared(default_rate(3.0));
+
+        let target = Position { vertical: 10.0, horizontal: 20.0 };
+        Rotator::goto_with_speed(&rotator, target, 2.0, TIMEOUT, true).await.unwrap();
+
+        assert_eq!(sent(&firmware), ["GETS", "SETS 2.000", "DVER 10.000", "DHOR -20.000", "SETS 5.000"]);
+    }
+
+    #[rocket::async_test]
+    async fn the_default_slew_rate_is_only_set_if_needed() {
+        let target = Position { vertical: 10.0, horizontal: 20.0 };
+
+        // Already at the default rate
+        let at_rate = firmware();
+        let rotator = at_rate.shared(default_rate(5.0));
+        Rotator::goto_and_wait(&rotator, target, TIMEOUT).await.unwrap();
+        assert_eq!(sent(&at_rate), ["GETS", "DVER 10.000", "DHOR -20.000"]);
+
+        // Without speed control the move is made anyway
+        let without_speed = MockFirmware::new();
+        let rotator = without_speed.shared(default_rate(3.0));
+        Rotator::goto_and_wait(&rotator, target, TIMEOUT).await.unwrap();
+        assert_eq!(sent(&without_speed), ["GETS", "DVER 10.000", "DHOR -20.000"]);
+
+        // Nor without a default rate
+        let without_default = firmware();
+        let rotator = without_default.shared(mock::config());
+        Rotator::goto_and_wait(&rotator, target, TIMEOUT).await.unwrap();
+        assert_eq!(sent(&without_default), ["DVER 10.000", "DHOR -20.000"]);
+    }
+}
@@ -0,0 +1,230 @@
+//! Sending the rotator somewhere known as soon as it is connected.
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use log::{info, warn};
+use rocket::tokio::{
+    self,
+    sync::{Mutex, watch},
+};
+use serde::{Deserialize, Serialize};
+
+use super::{Axis, Error, Position, Rotator, config::PerAxis};
+
+/// Where to send the rotator once it is connected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StartupPosition {
+    /// Leave it where it is.
+    #[default]
+    None,
+    /// The configured park position.
+    Park,
+    /// The last position it was sent to, saved at `last_position_path`.
+    Last,
+}
+
+/// The least time between saves of the last position, so that a stream of
+/// moves, such as while tracking, doesn't write the file for every one.
+const SAVE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The last position saved at `path`, or nothing if none has been saved yet.
+pub(super) fn load_last_position(path: &str) -> PerAxis<Option<f32>> {
+    let saved = match std::fs::read_to_string(path) {
+        Ok(json) => serde_json::from_str(&json).map_err(|e| e.to_string()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return PerAxis::default(),
+        Err(e) => Err(e.to_string()),
+    };
+
+    saved.unwrap_or_else(|e| {
+        warn!("Ignoring the last position saved at {path}: {e}");
+        PerAxis::default()
+    })
+}
+
+/// Writes the last position to `path` whenever it changes, at most once per
+/// [`SAVE_INTERVAL`], until the rotator is dropped. Only the latest position
+/// is written after a wait, however many moves were made meanwhile.
+async fn save_loop(path: PathBuf, mut positions: watch::Receiver<PerAxis<Option<f32>>>) {
+    loop {
+        let position = *positions.borrow_and_update();
+        if let Err(e) = write_last_position(&path, &position).await {
+            warn!("Failed to save the last position to {}: {e}", path.display());
+        }
+
+        tokio::time::sleep(SAVE_INTERVAL).await;
+        if positions.changed().await.is_err() {
+            return;
+        }
+    }
+}
+
+/// Saves the last position by writing it beside `path` and renaming it into
+/// place, so a crash partway through never leaves a truncated file.
+async fn write_last_position(path: &Path, position: &PerAxis<Option<f32>>) -> Result<(), io::Error> {
+    let json = serde_json::to_string(position)?;
+    let partial = path.with_extension("json.partial");
+    tokio::fs::write(&partial, json).await?;
+    tokio::fs::rename(&partial, path).await
+}
+
+impl Rotator {
+    /// Moves to the configured startup position, waiting for it to be reached.
+    pub async fn startup_move(rotator: &Mutex<Self>) -> Result<(), Error> {
+        let (startup_position, last_position, timeout) = {
+            let rotator = rotator.lock().await;
+            let timeout = Duration::from_millis(rotator.config.park.timeout_ms);
+
+            (rotator.config.startup_position, rotator.last_position, timeout)
+        };
+
+        match startup_position {
+            StartupPosition::None => Ok(()),
+            StartupPosition::Park => {
+                info!("Moving the rotator to its park position");
+                Self::park(rotator).await
+            }
+            StartupPosition::Last => {
+                let (Some(vertical), Some(horizontal)) = (last_position.vertical, last_position.horizontal) else {
+                    info!("No last position saved for both axes, leaving the rotator where it is");
+                    return Ok(());
+                };
+
+                info!("Moving the rotator back to its last position");
+                Self::goto_and_wait(rotator, Position { vertical, horizontal }, timeout).await
+            }
+        }
+    }
+
+    /// Remember where an axis was sent, if the startup position is the last
+    /// one. It is saved in the background by [`save_loop`], so moving never
+    /// waits for the disk, and failures are only logged.
+    pub(super) fn save_last_position(&mut self, axis: Axis, degrees: f32) {
+        if self.config.startup_position != StartupPosition::Last {
+            return;
+        }
+        *self.last_position.get_mut(axis) = Some(degrees);
+
+        match &self.last_position_saver {
+            Some(saver) if !saver.is_closed() => {
+                saver.send_replace(self.last_position);
+            }
+            _ => {
+                let (saver, positions) = watch::channel(self.last_position);
+                tokio::spawn(save_loop(PathBuf::from(&self.config.last_position_path), positions));
+                self.last_position_saver = Some(saver);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::{config::{ParkConfig, RotatorConfig}, mock::{self, MockFirmware}}, *};
+
+    /// A path in the temporary directory, with nothing saved at it.
+    fn last_position_path(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("archerd-last-position-{name}-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        path.to_string_lossy().into_owned()
+    }
+
+    fn config(startup_position: StartupPosition, last_position_path: String) -> RotatorConfig {
+        RotatorConfig { startup_position, last_position_path, ..mock::config() }
+    }
+
+    /// Every command sent other than position readings.
+    fn moves(firmware: &MockFirmware) -> Vec<String> {
+        firmware.received().into_iter().filter(|line| line != "GETP").collect()
+    }
+
+    #[test]
+    fn the_last_position_is_read_if_it_was_saved() {
+        let path = last_position_path("load");
+        assert_eq!(load_last_position(&path), PerAxis::default());
+
+        std::fs::write(&path, r#"{"vertical": 10.0, "horizontal": null}"#).unwrap();
+        assert_eq!(load_last_position(&path), PerAxis { vertical: Some(10.0), horizontal: None });
+
+        std::fs::write(&path, "{\"vertical\": 10").unwrap();
+        assert_eq!(load_last_position(&path), PerAxis::default());
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[rocket::async_test]
+    async fn the_rotator_can_start_at_its_last_position() {
+        let path = last_position_path("move");
+        std::fs::write(&path, r#"{"vertical": 10.0, "horizontal": 20.0}"#).unwrap();
+        let firmware = MockFirmware::new();
+        let rotator = firmware.shared(config(StartupPosition::Last, path.clone()));
+
+        Rotator::startup_move(&rotator).await.unwrap();
+
+        assert_eq!(moves(&firmware), ["DVER 10.000", "DHOR -20.000"]);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[rocket::async_test]
+    async fn the_rotator_stays_put_without_a_whole_last_position() {
+        let path = last_position_path("partial");
+        std::fs::write(&path, r#"{"vertical": 10.0}"#).unwrap();
+        let firmware = MockFirmware::new();
+        let rotator = firmware.shared(config(StartupPosition::Last, path.clone()));
+
+        Rotator::startup_move(&rotator).await.unwrap();
+
+        assert!(firmware.received().is_empty());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[rocket::async_test]
+    async fn the_rotator_can_start_parked() {
+        let firmware = MockFirmware::new();
+        let park = ParkConfig { vertical: 30.0, horizontal: 40.0, ..ParkConfig::default() };
+        let config = RotatorConfig { park, ..config(StartupPosition::Park, last_position_path("park")) };
+        let rotator = firmware.shared(config);
+
+        Rotator::startup_move(&rotator).await.unwrap();
+
+        assert_eq!(moves(&firmware), ["DVER 30.000", "DHOR -40.000"]);
+    }
+
+    #[rocket::async_test]
+    async fn the_latest_position_sent_is_saved() {
+        let path = last_position_path("save");
+        let firmware = MockFirmware::new();
+        let mut rotator = firmware.rotator(config(StartupPosition::Last, path.clone()));
+
+        rotator.set_position(Axis::Vertical, 10.0).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert_eq!(load_last_position(&path), PerAxis { vertical: Some(10.0), horizontal: None });
+
+        // Moves in quick succession are saved together, once the interval is up
+        rotator.set_position(Axis::Vertical, 20.0).await.unwrap();
+        rotator.set_position(Axis::Horizontal, 40.0).await.unwrap();
+        tokio::time::sleep(SAVE_INTERVAL).await;
+        assert_eq!(load_last_position(&path), PerAxis { vertical: Some(20.0), horizontal: Some(40.0) });
+        assert!(!Path::new(&path).with_extension("json.partial").exists());
+
+        drop(rotator);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[rocket::async_test]
+    async fn nothing_is_saved_unless_starting_at_the_last_position() {
+        let path = last_position_path("unsaved");
+        let firmware = MockFirmware::new();
+        let mut rotator = firmware.rotator(config(StartupPosition::None, path.clone()));
+
+        rotator.set_position(Axis::Vertical, 10.0).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        assert!(!Path::new(&path).exists());
+    }
+}
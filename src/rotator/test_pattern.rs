@@ -0,0 +1,195 @@
+//! Inspection patterns, so an installer can watch the mount move through its
+//! range and listen for mechanical problems.
+
+use std::{f32::consts::TAU, time::Duration};
+
+use rocket::{FromFormField, tokio::sync::Mutex};
+use serde::{Deserialize, Serialize};
+
+use super::{Axis, Error, Position, Rotator};
+
+/// How many waypoints make up the loops of [`TestPattern::FigureEight`].
+const FIGURE_EIGHT_POINTS: usize = 16;
+
+/// A pattern for [`Rotator::test_pattern`], selected with `?pattern=`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromFormField)]
+pub enum TestPattern {
+    /// The corners of a square around the starting position.
+    #[field(value = "box")]
+    Box,
+    /// Two loops, one above and one below the starting position, crossing
+    /// at it.
+    #[field(value = "figure_eight")]
+    FigureEight,
+}
+
+/// Settings for [`Rotator::test_pattern`].
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TestPatternConfig {
+    /// How far across each pattern is, in degrees on each axis.
+    pub size_degrees: f32,
+    /// How long to wait for each waypoint to be reached.
+    pub timeout_ms: u64,
+}
+
+impl Default for TestPatternConfig {
+    fn default() -> Self {
+        Self {
+            size_degrees: 20.0,
+            timeout_ms: 30_000,
+        }
+    }
+}
+
+impl TestPattern {
+    /// The waypoints of the pattern as offsets from where it starts, ending
+    /// back at the start.
+    fn offsets(self, size: f32) -> Vec<Position> {
+        let half = size / 2.0;
+
+        match self {
+            Self::Box => [(half, half), (half, -half), (-half, -half), (-half, half), (half, half), (0.0, 0.0)]
+                .into_iter()
+                .map(|(vertical, horizontal)| Position { vertical, horizontal })
+                .collect(),
+            Self::FigureEight => (1..=FIGURE_EIGHT_POINTS)
+                .map(|i| {
+                    let t = TAU * i as f32 / FIGURE_EIGHT_POINTS as f32;
+                    Position {
+                        vertical: half * t.sin(),
+                        horizontal: half * (2.0 * t).sin(),
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Rotator {
+    /// The positions `pattern` moves through from `start`, brought within the
+    /// frame's convention and the limits of each axis.
+    pub fn test_pattern_waypoints(&self, pattern: TestPattern, start: Position) -> Vec<Position> {
+        let frame = &self.config.frame;
+        let bound = |axis: Axis, degrees: f32| {
+            let degrees = match axis {
+                Axis::Vertical => frame.elevation_convention.wrap(degrees),
+                Axis::Horizontal => frame.azimuth_convention.wrap(degrees),
+            };
+
+            self.config.limits.get(axis).map_or(degrees, |limits| limits.clamp(degrees))
+        };
+
+        pattern
+            .offsets(self.config.test_pattern.size_degrees)
+            .into_iter()
+            .map(|offset| Position {
+                vertical: bound(Axis::Vertical, start.vertical + offset.vertical),
+                horizontal: bound(Axis::Horizontal, start.horizontal + offset.horizontal),
+            })
+            .collect()
+    }
+
+    /// Moves through `pattern` around the current position, waiting for each
+    /// waypoint to be reached, and then returns to where it started.
+    ///
+    /// The rotator is only locked for each waypoint, so it can be halted
+    /// partway. A return to the starting position is attempted if the pattern
+    /// fails, unless it was halted or stopped.
+    pub async fn test_pattern(rotator: &Mutex<Self>, pattern: TestPattern) -> Result<(), Error> {
+        let (start, stops, waypoints, timeout) = {
+            let mut rotator = rotator.lock().await;
+            let (vertical, horizontal) = rotator.position().await?;
+            let start = Position { vertical, horizontal };
+            let timeout = Duration::from_millis(rotator.config.test_pattern.timeout_ms);
+
+            (start, rotator.stops(), rotator.test_pattern_waypoints(pattern, start), timeout)
+        };
+
+        let mut result = Ok(());
+        for waypoint in waypoints {
+            result = Self::goto_step(rotator, stops, waypoint, timeout).await;
+            if result.is_err() {
+                break;
+            }
+        }
+        let restored = Self::goto_step(rotator, stops, start, timeout).await;
+
+        result.and(restored)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use rocket::tokio;
+
+    use super::{super::{config::{Limits, PerAxis, RotatorConfig}, mock::{self, MockFirmware}}, *};
+
+    #[test]
+    fn a_box_visits_each_corner_and_returns_to_the_start() {
+        let rotator = MockFirmware::new().rotator(mock::config());
+
+        let waypoints = rotator.test_pattern_waypoints(TestPattern::Box, Position { vertical: 45.0, horizontal: 90.0 });
+
+        let expected = [(55.0, 100.0), (55.0, 80.0), (35.0, 80.0), (35.0, 100.0), (55.0, 100.0), (45.0, 90.0)]
+            .map(|(vertical, horizontal)| Position { vertical, horizontal });
+        assert_eq!(waypoints, expected);
+    }
+
+    #[test]
+    fn every_pattern_stays_within_the_limits() {
+        let limits = PerAxis {
+            vertical: Some(Limits { min: 0.0, max: 60.0 }),
+            horizontal: Some(Limits { min: -90.0, max: 90.0 }),
+        };
+        let rotator = MockFirmware::new().rotator(RotatorConfig { limits, ..mock::config() });
+        let start = Position { vertical: 5.0, horizontal: 85.0 };
+
+        for pattern in [TestPattern::Box, TestPattern::FigureEight] {
+            let waypoints = rotator.test_pattern_waypoints(pattern, start);
+
+            for waypoint in &waypoints {
+                assert!(limits.vertical.unwrap().contains(waypoint.vertical), "{pattern:?} {waypoint:?}");
+                assert!(limits.horizontal.unwrap().contains(waypoint.horizontal), "{pattern:?} {waypoint:?}");
+            }
+            let end = waypoints.last().unwrap();
+            assert!((end.vertical - start.vertical).abs() < 1e-3, "{pattern:?} {end:?}");
+            assert!((end.horizontal - start.horizontal).abs() < 1e-3, "{pattern:?} {end:?}");
+        }
+    }
+
+    #[rocket::async_test]
+    async fn the_pattern_ends_back_where_it_started() {
+        let firmware = MockFirmware::new();
+        firmware.lock().position = PerAxis { vertical: 30.0, horizontal: -90.0 };
+        let rotator = firmware.shared(mock::config());
+
+        Rotator::test_pattern(&rotator, TestPattern::Box).await.unwrap();
+
+        assert_eq!(firmware.lock().position, PerAxis { vertical: 30.0, horizontal: -90.0 });
+        let moves = firmware.commands().into_iter().filter(|command| command == "DVER").count();
+        assert_eq!(moves, 7);
+    }
+
+    #[rocket::async_test]
+    async fn halting_stops_the_pattern_where_it_is() {
+        let firmware = MockFirmware::new();
+        firmware.lock().slew_per_read = Some(1.0);
+        let rotator = Arc::new(firmware.shared(mock::config()));
+
+        let pattern = tokio::spawn({
+            let rotator = Arc::clone(&rotator);
+            async move { Rotator::test_pattern(&rotator, TestPattern::Box).await }
+        });
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        rotator.lock().await.halt().await.unwrap();
+
+        let error = pattern.await.unwrap().unwrap_err();
+        assert!(matches!(error, Error::Interrupted), "{error:?}");
+        let commands = firmware.commands();
+        let halted = commands.iter().position(|command| command == "HALT").unwrap();
+        assert!(!commands[halted..].iter().any(|command| command == "DVER" || command == "DHOR"), "{commands:?}");
+    }
+}
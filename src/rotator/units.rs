@@ -0,0 +1,183 @@
+//! Angular units for the HTTP API. Internally, and on the wire to the
+//! firmware, everything is in degrees.
+
+use rocket::FromFormField;
+
+use super::{
+    Axis, Position,
+    config::{Limits, PerAxis},
+};
+
+/// The widest range accepted for an angle given to the API on each axis, in
+/// degrees, when no limits are configured for it: from straight down to over
+/// the top for elevation, and a full turn either way for azimuth. This only
+/// rules out values no real move could need.
+pub const INPUT_BOUNDS: PerAxis<Limits> = PerAxis {
+    vertical: Limits { min: -90.0, max: 180.0 },
+    horizontal: Limits { min: -360.0, max: 360.0 },
+};
+
+/// Check that a value given to the API is a finite number, before anything
+/// is sent to the rotator.
+///
+/// # Errors
+/// Returns a message naming `what` if it is not.
+pub fn check_input(what: &str, value: f32) -> Result<f32, String> {
+    if !value.is_finite() {
+        return Err(format!("{what} must be a finite number, not {value}"));
+    }
+
+    Ok(value)
+}
+
+/// Check that an angle given to the API for an axis, in degrees, is a finite
+/// number within the axis's configured `limits`, or its [`INPUT_BOUNDS`] if
+/// it has none, before anything is sent to the rotator.
+///
+/// # Errors
+/// Returns a message naming the axis if it is not.
+pub fn check_angle(axis: Axis, degrees: f32, limits: Option<Limits>) -> Result<f32, String> {
+    let what = match axis {
+        Axis::Vertical => "vertical",
+        Axis::Horizontal => "horizontal",
+    };
+    check_input(what, degrees)?;
+
+    let bounds = limits.unwrap_or(*INPUT_BOUNDS.get(axis));
+    if !bounds.contains(degrees) {
+        return Err(format!(
+            "{what} of {degrees} degrees is outside the {} to {} allowed",
+            bounds.min, bounds.max
+        ));
+    }
+
+    Ok(degrees)
+}
+
+/// [`check_angle`] for both axes of a position, in degrees.
+///
+/// # Errors
+/// Returns a message if either axis is not a finite number within its
+/// bounds.
+pub fn check_position_input(position: Position, limits: &PerAxis<Option<Limits>>) -> Result<Position, String> {
+    check_angle(Axis::Vertical, position.vertical, limits.vertical)?;
+    check_angle(Axis::Horizontal, position.horizontal, limits.horizontal)?;
+
+    Ok(position)
+}
+
+/// The units positions are given and returned in, selected with `?units=`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, FromFormField)]
+pub enum Units {
+    #[default]
+    Degrees,
+    /// NATO mils, of which there are 6400 in a full turn.
+    Mils,
+}
+
+impl Units {
+    const MILS_PER_DEGREE: f32 = 6400.0 / 360.0;
+
+    /// Convert a value in these units into degrees.
+    pub fn to_degrees(self, value: f32) -> f32 {
+        match self {
+            Self::Degrees => value,
+            Self::Mils => value / Self::MILS_PER_DEGREE,
+        }
+    }
+
+    /// Convert a value in degrees into these units.
+    pub fn from_degrees(self, degrees: f32) -> f32 {
+        match self {
+            Self::Degrees => degrees,
+            Self::Mils => degrees * Self::MILS_PER_DEGREE,
+        }
+    }
+
+    pub fn position_to_degrees(self, position: Position) -> Position {
+        Position {
+            vertical: self.to_degrees(position.vertical),
+            horizontal: self.to_degrees(position.horizontal),
+        }
+    }
+
+    pub fn position_from_degrees(self, position: Position) -> Position {
+        Position {
+            vertical: self.from_degrees(position.vertical),
+            horizontal: self.from_degrees(position.horizontal),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-3, "{a} != {b}");
+    }
+
+    #[test]
+    fn mils_convert_to_and_from_degrees() {
+        for (mils, degrees) in [(6400.0, 360.0), (1600.0, 90.0), (-800.0, -45.0), (0.0, 0.0), (17.777_779, 1.0)] {
+            assert_close(Units::Mils.to_degrees(mils), degrees);
+            assert_close(Units::Mils.from_degrees(degrees), mils);
+        }
+
+        let position = Position { vertical: 30.0, horizontal: 270.0 };
+        let mils = Units::Mils.position_from_degrees(position);
+        assert_close(mils.vertical, 533.333);
+        assert_close(mils.horizontal, 4800.0);
+        let degrees = Units::Mils.position_to_degrees(mils);
+        assert_close(degrees.vertical, 30.0);
+        assert_close(degrees.horizontal, 270.0);
+    }
+
+    #[test]
+    fn values_are_in_degrees_by_default() {
+        assert_eq!(Units::default(), Units::Degrees);
+
+        let position = Position { vertical: 12.5, horizontal: -100.25 };
+        assert_eq!(Units::default().position_from_degrees(position), position);
+        assert_eq!(Units::default().position_to_degrees(position), position);
+    }
+
+    #[test]
+    fn angles_must_be_finite() {
+        for value in [f32::NAN, f32::INFINITY, f32::NEG_INFINITY] {
+            assert!(check_input("speed", value).is_err());
+            assert!(check_angle(Axis::Vertical, value, None).is_err());
+            assert!(check_angle(Axis::Horizontal, value, Some(Limits { min: 0.0, max: 90.0 })).is_err());
+        }
+
+        assert_eq!(check_input("speed", f32::NAN).unwrap_err(), "speed must be a finite number, not NaN");
+        assert_eq!(check_input("speed", 5.0), Ok(5.0));
+    }
+
+    #[test]
+    fn angles_are_bounded_per_axis_unless_limits_are_set() {
+        assert_eq!(check_angle(Axis::Vertical, 180.0, None), Ok(180.0));
+        assert_eq!(check_angle(Axis::Vertical, -90.0, None), Ok(-90.0));
+        assert_eq!(
+            check_angle(Axis::Vertical, 180.5, None).unwrap_err(),
+            "vertical of 180.5 degrees is outside the -90 to 180 allowed",
+        );
+        assert_eq!(check_angle(Axis::Horizontal, -360.0, None), Ok(-360.0));
+        assert!(check_angle(Axis::Horizontal, 361.0, None).is_err());
+
+        let limits = Some(Limits { min: 0.0, max: 90.0 });
+        assert_eq!(check_angle(Axis::Vertical, 90.0, limits), Ok(90.0));
+        assert_eq!(
+            check_angle(Axis::Vertical, 95.0, limits).unwrap_err(),
+            "vertical of 95 degrees is outside the 0 to 90 allowed",
+        );
+
+        let limits = PerAxis { vertical: None, horizontal: Some(Limits { min: -180.0, max: 180.0 }) };
+        let position = Position { vertical: 45.0, horizontal: 270.0 };
+        assert_eq!(
+            check_position_input(position, &limits).unwrap_err(),
+            "horizontal of 270 degrees is outside the -180 to 180 allowed",
+        );
+        assert_eq!(check_position_input(position, &PerAxis::default()), Ok(position));
+    }
+}
@@ -0,0 +1,117 @@
+//! How numbers are written in the firmware's responses. Current firmware
+//! sends plain decimals, but this keeps the parsing in one place should a
+//! revision change it.
+
+use serde::{Deserialize, Serialize};
+
+use super::{Error, Rotator};
+
+/// How the firmware writes step counts and other whole numbers. Positions
+/// and other readings are always decimal.
+///
+/// Either way, a unit suffix after a decimal reading, such as `12.5deg`, is
+/// ignored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValueFormat {
+    /// Counts are decimal, e.g. `200`.
+    #[default]
+    Decimal,
+    /// Counts are hexadecimal, with or without a `0x` prefix, e.g. `0xC8`.
+    Hex,
+}
+
+impl ValueFormat {
+    /// Parse a reading, such as a position in degrees.
+    pub fn reading(self, value: &str) -> Option<f32> {
+        strip_unit(value).parse().ok()
+    }
+
+    /// Parse a count, such as a number of steps.
+    pub fn count(self, value: &str) -> Option<u64> {
+        match self {
+            Self::Decimal => strip_unit(value).parse().ok(),
+            Self::Hex => {
+                let digits = value.strip_prefix("0x").or_else(|| value.strip_prefix("0X")).unwrap_or(value);
+                u64::from_str_radix(digits, 16).ok()
+            }
+        }
+    }
+}
+
+/// `value` without any unit after the number.
+fn strip_unit(value: &str) -> &str {
+    value.trim_end_matches(|c: char| c.is_alphabetic() || c == '°' || c == '%')
+}
+
+impl Rotator {
+    /// Parse a reading from a response in the configured `value_format`.
+    pub(super) fn parse_reading(&self, value: &str) -> Result<f32, Error> {
+        self.config.value_format.reading(value).ok_or(Error::InvalidResponse)
+    }
+
+    /// Parse a count from a response in the configured `value_format`.
+    pub(super) fn parse_count(&self, value: &str) -> Result<u64, Error> {
+        self.config.value_format.count(value).ok_or(Error::InvalidResponse)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::{config::RotatorConfig, mock::{self, MockFirmware}}, *};
+
+    #[test]
+    fn counts_are_parsed_in_either_format() {
+        assert_eq!(ValueFormat::Decimal.count("200"), Some(200));
+        assert_eq!(ValueFormat::Decimal.count("200steps"), Some(200));
+        assert_eq!(ValueFormat::Decimal.count("C8"), None);
+
+        assert_eq!(ValueFormat::Hex.count("C8"), Some(200));
+        assert_eq!(ValueFormat::Hex.count("0xc8"), Some(200));
+        assert_eq!(ValueFormat::Hex.count("0XC8"), Some(200));
+        assert_eq!(ValueFormat::Hex.count("0xZZ"), None);
+    }
+
+    #[test]
+    fn readings_are_decimal_with_any_unit_ignored() {
+        for format in [ValueFormat::Decimal, ValueFormat::Hex] {
+            assert_eq!(format.reading("12.5"), Some(12.5));
+            assert_eq!(format.reading("-3.25deg"), Some(-3.25));
+            assert_eq!(format.reading("45°"), Some(45.0));
+            assert_eq!(format.reading("deg"), None);
+        }
+    }
+
+    #[rocket::async_test]
+    async fn hex_step_counts_are_read_from_the_firmware() {
+        let firmware = MockFirmware::new();
+        firmware.reply("GETO", "OK 4B0 0xD48 15180");
+        let mut rotator = firmware.rotator(RotatorConfig { value_format: ValueFormat::Hex, ..mock::config() });
+
+        let odometer = rotator.odometer().await.unwrap();
+
+        assert_eq!(odometer.steps.vertical, 1200);
+        assert_eq!(odometer.steps.horizontal, 3400);
+        assert_eq!(odometer.runtime_secs, Some(86400));
+    }
+
+    #[rocket::async_test]
+    async fn counts_in_the_wrong_format_are_invalid() {
+        let firmware = MockFirmware::new();
+        firmware.reply("GETO", "OK 4B0 D48 15180");
+        let mut rotator = firmware.rotator(mock::config());
+
+        let error = rotator.odometer().await.unwrap_err();
+
+        assert!(matches!(error, Error::InvalidResponse), "{error:?}");
+    }
+
+    #[rocket::async_test]
+    async fn positions_with_units_are_read() {
+        let firmware = MockFirmware::new();
+        firmware.reply("GETP", "OK 12.5deg -20.0deg");
+        let mut rotator = firmware.rotator(mock::config());
+
+        assert_eq!(rotator.position_raw().await.unwrap(), (12.5, -20.0));
+    }
+}
@@ -0,0 +1,390 @@
+//! A [JSON-RPC 2.0](https://www.jsonrpc.org/specification) interface to the
+//! rotator, for integrators who would rather use a single endpoint than the
+//! REST routes. Both share the same [`Rotator`] backend.
+
+use std::sync::Arc;
+
+use rocket::{Responder, State, post, tokio::sync::Mutex};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+
+use crate::rotator::{self, Axis, Rotator, units};
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const SERVER_ERROR: i64 = -32000;
+
+#[derive(Responder)]
+pub enum RpcResponse {
+    #[response(status = 200, content_type = "json")]
+    Reply(String),
+    /// Returned when every call in the request was a notification.
+    #[response(status = 204)]
+    Empty(()),
+}
+
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    fn into_response(self, id: Value) -> Value {
+        json!({
+            "jsonrpc": "2.0",
+            "error": {
+                "code": self.code,
+                "message": self.message,
+            },
+            "id": id,
+        })
+    }
+}
+
+impl From<rotator::Error> for RpcError {
+    fn from(value: rotator::Error) -> Self {
+        Self::new(SERVER_ERROR, value.to_string())
+    }
+}
+
+#[derive(Deserialize)]
+struct SetPositionParams {
+    vertical: Option<f32>,
+    horizontal: Option<f32>,
+}
+
+#[derive(Deserialize)]
+struct CalibrateParams {
+    axis: Axis,
+    #[serde(default)]
+    set: bool,
+}
+
+/// Accepts a single JSON-RPC call or a batch of them.
+#[post("/rpc", data = "<body>")]
+pub async fn rpc(rotator_state: &State<Arc<Mutex<Rotator>>>, body: String) -> RpcResponse {
+    let Ok(request) = serde_json::from_str::<Value>(&body) else {
+        let error = RpcError::new(PARSE_ERROR, "Parse error").into_response(Value::Null);
+        return RpcResponse::Reply(error.to_string());
+    };
+
+    let response = match request {
+        Value::Array(calls) if calls.is_empty() => {
+            Some(RpcError::new(INVALID_REQUEST, "Invalid Request").into_response(Value::Null))
+        }
+        Value::Array(calls) => {
+            let mut responses = Vec::new();
+            for call in calls {
+                if let Some(response) = handle_call(rotator_state, call).await {
+                    responses.push(response);
+                }
+            }
+
+            (!responses.is_empty()).then_some(Value::Array(responses))
+        }
+        call => handle_call(rotator_state, call).await,
+    };
+
+    match response {
+        Some(r) => RpcResponse::Reply(r.to_string()),
+        None => RpcResponse::Empty(()),
+    }
+}
+
+/// Run a single call, returning its response object, or `None` if the call
+/// was a notification.
+async fn handle_call(rotator: &Mutex<Rotator>, call: Value) -> Option<Value> {
+    let id = call.get("id").cloned();
+
+    let method = call
+        .get("method")
+        .and_then(Value::as_str)
+        .filter(|_| call.get("jsonrpc").and_then(Value::as_str) == Some("2.0"));
+
+    let Some(method) = method else {
+        let error = RpcError::new(INVALID_REQUEST, "Invalid Request");
+        return Some(error.into_response(id.unwrap_or(Value::Null)));
+    };
+
+    let params = call.get("params").cloned().unwrap_or(Value::Null);
+    let result = dispatch(rotator, method, params).await;
+
+    // Calls without an id are notifications and get no response
+    let id = id?;
+
+    Some(match result {
+        Ok(result) => json!({
+            "jsonrpc": "2.0",
+            "result": result,
+            "id": id,
+        }),
+        Err(e) => e.into_response(id),
+    })
+}
+
+async fn dispatch(rotator: &Mutex<Rotator>, method: &str, params: Value) -> Result<Value, RpcError> {
+    match method {
+        "get_position" => {
+            let (v, h) = rotator.lock().await.position().await?;
+
+            Ok(json!({
+                "vertical": v,
+                "horizontal": h,
+            }))
+        }
+        "set_position" => {
+            let params: SetPositionParams = parse_params(params)?;
+            if params.vertical.is_none() && params.horizontal.is_none() {
+                return Err(RpcError::new(INVALID_PARAMS, "expected `vertical` and/or `horizontal`"));
+            }
+
+            for (axis, value) in [(Axis::Vertical, params.vertical), (Axis::Horizontal, params.horizontal)] {
+                if let Some(value) = value
+                    && let Err(e) = units::check_angle(axis, value, None)
+                {
+                    return Err(RpcError::new(INVALID_PARAMS, e));
+                }
+            }
+
+            let mut rotator = rotator.lock().await;
+            if let Some(v) = params.vertical {
+                rotator.set_position_vertical(v).await?;
+            }
+            if let Some(h) = params.horizontal {
+                rotator.set_position_horizontal(h).await?;
+            }
+
+            Ok(Value::Null)
+        }
+        "halt" => {
+            rotator.lock().await.halt().await?;
+
+            Ok(Value::Null)
+        }
+        "calibrate" => {
+            let params: CalibrateParams = parse_params(params)?;
+
+            let mut rotator = rotator.lock().await;
+            match params.axis {
+                Axis::Vertical => rotator.calibrate_vertical(params.set).await?,
+                Axis::Horizontal => rotator.calibrate(Axis::Horizontal).await?,
+            }
+
+            Ok(Value::Null)
+        }
+        "version" => {
+            let version = rotator.lock().await.version().await?;
+
+            Ok(json!(version))
+        }
+        _ => Err(RpcError::new(METHOD_NOT_FOUND, "Method not found")),
+    }
+}
+
+/// Parse by-name parameters, treating omitted parameters as an empty object.
+fn parse_params<T: DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    let params = if params.is_null() { json!({}) } else { params };
+
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use rocket::{http::Status, local::asynchronous::Client, routes, tokio::sync::Mutex};
+    use serde_json::{Value, json};
+
+    use super::{INVALID_PARAMS, INVALID_REQUEST, METHOD_NOT_FOUND, PARSE_ERROR, SERVER_ERROR};
+    use crate::rotator::mock::{self, MockFirmware};
+
+    async fn client(firmware: &MockFirmware) -> Client {
+        let rotator = Arc::new(Mutex::new(firmware.rotator(mock::config())));
+        let rocket = rocket::build().manage(rotator).mount("/", routes![super::rpc]);
+
+        Client::tracked(rocket).await.unwrap()
+    }
+
+    /// Posts `body` as is, returning the status and the parsed reply, if any.
+    async fn post(client: &Client, body: &str) -> (Status, Option<Value>) {
+        let response = client.post("/rpc").body(body).dispatch().await;
+        let status = response.status();
+        let reply = response
+            .into_string()
+            .await
+            .filter(|reply| !reply.is_empty())
+            .map(|reply| serde_json::from_str(&reply).unwrap());
+
+        (status, reply)
+    }
+
+    async fn call(client: &Client, request: Value) -> (Status, Option<Value>) {
+        post(client, &request.to_string()).await
+    }
+
+    fn error_code(reply: &Value) -> i64 {
+        reply["error"]["code"].as_i64().unwrap()
+    }
+
+    #[rocket::async_test]
+    async fn single_call() {
+        let firmware = MockFirmware::new();
+        firmware.lock().position.vertical = 10.0;
+        firmware.lock().position.horizontal = -20.0;
+        let client = client(&firmware).await;
+
+        let (status, reply) = call(&client, json!({"jsonrpc": "2.0", "method": "get_position", "id": 1})).await;
+
+        assert_eq!(status, Status::Ok);
+        assert_eq!(
+            reply.unwrap(),
+            json!({"jsonrpc": "2.0", "result": {"vertical": 10.0, "horizontal": 20.0}, "id": 1}),
+        );
+    }
+
+    #[rocket::async_test]
+    async fn set_position_sends_each_axis() {
+        let firmware = MockFirmware::new();
+        let client = client(&firmware).await;
+
+        let request = json!({"jsonrpc": "2.0", "method": "set_position", "params": {"vertical": 45.0}, "id": "a"});
+        let (_, reply) = call(&client, request).await;
+
+        assert_eq!(reply.unwrap(), json!({"jsonrpc": "2.0", "result": null, "id": "a"}));
+        assert_eq!(firmware.commands(), ["DVER"]);
+    }
+
+    #[rocket::async_test]
+    async fn batch_answers_each_call_in_order_except_notifications() {
+        let firmware = MockFirmware::new();
+        let client = client(&firmware).await;
+
+        let batch = json!([
+            {"jsonrpc": "2.0", "method": "version", "id": 1},
+            {"jsonrpc": "2.0", "method": "halt"},
+            {"jsonrpc": "2.0", "method": "nope", "id": 2},
+        ]);
+        let (status, reply) = call(&client, batch).await;
+
+        assert_eq!(status, Status::Ok);
+        let replies = reply.unwrap();
+        let replies = replies.as_array().unwrap();
+        assert_eq!(replies.len(), 2);
+        assert_eq!(replies[0], json!({"jsonrpc": "2.0", "result": "v1.4.0", "id": 1}));
+        assert_eq!(replies[1]["id"], json!(2));
+        assert_eq!(error_code(&replies[1]), METHOD_NOT_FOUND);
+
+        // The notification still ran
+        assert!(firmware.commands().contains(&"HALT".to_string()));
+    }
+
+    #[rocket::async_test]
+    async fn only_notifications_get_no_content() {
+        let firmware = MockFirmware::new();
+        let client = client(&firmware).await;
+
+        let (status, reply) = call(&client, json!({"jsonrpc": "2.0", "method": "halt"})).await;
+        assert_eq!(status, Status::NoContent);
+        assert_eq!(reply, None);
+
+        let (status, reply) = call(&client, json!([{"jsonrpc": "2.0", "method": "halt"}])).await;
+        assert_eq!(status, Status::NoContent);
+        assert_eq!(reply, None);
+    }
+
+    #[rocket::async_test]
+    async fn error_responses() {
+        let firmware = MockFirmware::new();
+        let client = client(&firmware).await;
+
+        let (_, reply) = post(&client, "{not json").await;
+        let reply = reply.unwrap();
+        assert_eq!(error_code(&reply), PARSE_ERROR);
+        assert_eq!(reply["id"], Value::Null);
+
+        let (_, reply) = call(&client, json!([])).await;
+        assert_eq!(error_code(&reply.unwrap()), INVALID_REQUEST);
+
+        let (_, reply) = call(&client, json!({"method": "version", "id": 3})).await;
+        let reply = reply.unwrap();
+        assert_eq!(error_code(&reply), INVALID_REQUEST);
+        assert_eq!(reply["id"], json!(3));
+
+        let (_, reply) = call(&client, json!({"jsonrpc": "2.0", "method": "set_position", "params": {}, "id": 4})).await;
+        assert_eq!(error_code(&reply.unwrap()), INVALID_PARAMS);
+
+        let request = json!({"jsonrpc": "2.0", "method": "set_position", "params": {"vertical": 1000.0}, "id": 5});
+        let (_, reply) = call(&client, request).await;
+        assert_eq!(error_code(&reply.unwrap()), INVALID_PARAMS);
+        assert!(firmware.commands().is_empty());
+    }
+
+    #[rocket::async_test]
+    async fn firmware_errors_are_server_errors() {
+        let firmware = MockFirmware::new();
+        firmware.reply("DVER", "ERR not calibrated");
+        let client = client(&firmware).await;
+
+        let request = json!({"jsonrpc": "2.0", "method": "set_position", "params": {"vertical": 10.0}, "id": 6});
+        let (status, reply) = call(&client, request).await;
+
+        let reply = reply.unwrap();
+        assert_eq!(status, Status::Ok);
+        assert_eq!(error_code(&reply), SERVER_ERROR);
+        assert!(reply["error"]["message"].as_str().unwrap().contains("not calibrated"));
+        assert_eq!(reply["id"], json!(6));
+    }
+
+    #[rocket::async_test]
+    async fn every_method_reaches_the_rotator() {
+        let firmware = MockFirmware::new();
+        firmware.lock().calibrated = false;
+        let client = client(&firmware).await;
+
+        let calls = json!([
+            {"jsonrpc": "2.0", "method": "calibrate", "params": {"axis": "vertical", "set": true}, "id": 1},
+            {"jsonrpc": "2.0", "method": "calibrate", "params": {"axis": "horizontal"}, "id": 2},
+            {"jsonrpc": "2.0", "method": "set_position", "params": {"vertical": 30.0, "horizontal": 90.0}, "id": 3},
+            {"jsonrpc": "2.0", "method": "get_position", "id": 4},
+            {"jsonrpc": "2.0", "method": "halt", "id": 5},
+            {"jsonrpc": "2.0", "method": "version", "id": 6},
+        ]);
+        let (_, reply) = call(&client, calls).await;
+
+        let results: Vec<_> = reply.unwrap().as_array().unwrap().iter().map(|reply| reply["result"].clone()).collect();
+        assert_eq!(
+            results,
+            [
+                Value::Null,
+                Value::Null,
+                Value::Null,
+                json!({"vertical": 30.0, "horizontal": 90.0}),
+                Value::Null,
+                json!("v1.4.0"),
+            ],
+        );
+        assert_eq!(firmware.received(), ["CALV SET", "CALH", "DVER 30.000", "DHOR -90.000", "GETP", "HALT", "VERS"]);
+    }
+
+    #[rocket::async_test]
+    async fn unknown_parameters_are_invalid() {
+        let firmware = MockFirmware::new();
+        let client = client(&firmware).await;
+
+        let request = json!({"jsonrpc": "2.0", "method": "calibrate", "params": {"axis": "diagonal"}, "id": 1});
+        let (_, reply) = call(&client, request).await;
+
+        assert_eq!(error_code(&reply.unwrap()), INVALID_PARAMS);
+        assert!(firmware.received().is_empty());
+    }
+}
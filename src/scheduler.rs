@@ -0,0 +1,25 @@
+//! Periodic maintenance jobs.
+
+use std::{sync::Arc, time::Duration};
+
+use log::{info, warn};
+use rocket::tokio::{self, sync::Mutex};
+
+use crate::rotator::Rotator;
+
+/// Runs [`Rotator::exercise`] every `interval`, starting one interval from now.
+pub async fn exercise_loop(rotator: Arc<Mutex<Rotator>>, interval: Duration) {
+    info!("Scheduled rotator exercise every {interval:?}");
+
+    let mut ticker = tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+    loop {
+        ticker.tick().await;
+
+        info!("Running scheduled rotator exercise");
+        if let Err(e) = Rotator::exercise(&rotator).await {
+            warn!("Scheduled exercise failed: {e}");
+        }
+    }
+}
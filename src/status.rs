@@ -0,0 +1,128 @@
+//! The outcome of probing the rotator at startup, so operators can see how
+//! the server came up without reading its logs.
+
+use chrono::Utc;
+use rocket::{State, get};
+use serde::Serialize;
+
+use crate::{
+    response::{Error, Success},
+    rotator::Rotator,
+};
+
+/// What was found when the default rotator was probed at startup.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StartupProbe {
+    /// Whether the rotator was found. If not, the server is running in
+    /// degraded mode with a dummy port.
+    pub detected: bool,
+    pub port: Option<String>,
+    /// When the rotator's port was opened, in RFC 3339 format.
+    pub connected_at: Option<String>,
+    /// The protocol version reported by the firmware.
+    pub version: Option<String>,
+    /// Whether `version` matches the protocol this server was built for.
+    pub compatible: bool,
+    pub calibrated: Option<bool>,
+    /// Every error hit while probing.
+    pub errors: Vec<String>,
+}
+
+impl StartupProbe {
+    /// Record that the rotator was found on `port`.
+    pub fn connected(&mut self, port: Option<String>) {
+        self.detected = true;
+        self.port = port;
+        self.connected_at = Some(Utc::now().to_rfc3339());
+    }
+
+    pub fn error(&mut self, error: impl ToString) {
+        self.errors.push(error.to_string());
+    }
+
+    /// Query the firmware's version and calibration, recording any errors.
+    /// Returns the version, or `0.0.0` if it couldn't be read.
+    pub async fn probe(&mut self, rotator: &mut Rotator, protocol_version: &str) -> String {
+        let version = match rotator.version().await {
+            Ok(version) => version,
+            Err(e) => {
+                self.error(format!("version: {e}"));
+                "0.0.0".to_string()
+            }
+        };
+        self.compatible = protocol_version == version;
+        self.version = Some(version.clone());
+
+        match rotator.calibrated().await {
+            Ok(calibrated) => self.calibrated = Some(calibrated),
+            Err(e) => self.error(format!("calibration: {e}")),
+        }
+
+        version
+    }
+}
+
+#[get("/status/startup")]
+pub fn startup(probe: &State<StartupProbe>) -> Result<Success, Error> {
+    Ok(Success::data(serde_json::to_value(probe.inner()).map_err(|e| Error(e.to_string()))?))
+}
+
+#[cfg(test)]
+mod tests {
+    use rocket::{http::Status, local::asynchronous::Client, routes};
+    use serde_json::{Value, json};
+
+    use super::*;
+    use crate::rotator::mock::{self, MockFirmware};
+
+    async fn reported(probe: StartupProbe) -> Value {
+        let rocket = rocket::build().manage(probe).mount("/", routes![startup]);
+        let client = Client::tracked(rocket).await.unwrap();
+
+        let response = client.get("/status/startup").dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        let body: Value = serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+
+        body["data"].clone()
+    }
+
+    #[rocket::async_test]
+    async fn a_successful_probe_is_reported() {
+        let firmware = MockFirmware::new();
+        let mut rotator = firmware.rotator(mock::config());
+        let mut probe = StartupProbe::default();
+
+        probe.connected(Some("/dev/ttyACM0".to_string()));
+        assert_eq!(probe.probe(&mut rotator, "v1.4.0").await, "v1.4.0");
+
+        let reported = reported(probe).await;
+        assert_eq!(reported["detected"], json!(true));
+        assert_eq!(reported["port"], json!("/dev/ttyACM0"));
+        assert!(reported["connected_at"].is_string());
+        assert_eq!(reported["version"], json!("v1.4.0"));
+        assert_eq!(reported["compatible"], json!(true));
+        assert_eq!(reported["calibrated"], json!(true));
+        assert_eq!(reported["errors"], json!([]));
+    }
+
+    #[rocket::async_test]
+    async fn a_failed_probe_is_reported() {
+        let firmware = MockFirmware::new();
+        firmware.lock().silent = true;
+        let mut rotator = firmware.rotator(mock::config());
+        let mut probe = StartupProbe::default();
+
+        probe.error("rotator not found: no such device");
+        assert_eq!(probe.probe(&mut rotator, "v1.4.0").await, "0.0.0");
+
+        let reported = reported(probe).await;
+        assert_eq!(reported["detected"], json!(false));
+        assert_eq!(reported["connected_at"], Value::Null);
+        assert_eq!(reported["compatible"], json!(false));
+        assert_eq!(reported["calibrated"], Value::Null);
+        assert_eq!(
+            reported["errors"],
+            json!(["rotator not found: no such device", "version: timed out", "calibration: timed out"]),
+        );
+    }
+}
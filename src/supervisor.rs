@@ -0,0 +1,173 @@
+//! Reconnecting rotators whose ports fail, so a station recovers from
+//! transient USB problems without anyone calling `set_rotator_port`.
+
+use std::{sync::Arc, time::Duration};
+
+use log::{error, info, warn};
+use rocket::tokio::{self, sync::Mutex};
+use serde::{Deserialize, Serialize};
+use serialport::SerialPort;
+
+use crate::{backoff::Backoff, rotator::Rotator};
+
+/// Settings for the connection supervisor.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SupervisorConfig {
+    pub enabled: bool,
+    /// How often to check whether the rotator is still connected.
+    pub check_interval_ms: u64,
+    pub backoff: Backoff,
+    /// How many times in a row to try reopening the port before giving up
+    /// until the server is restarted. Unlimited if unset.
+    pub max_attempts: Option<u32>,
+}
+
+impl Default for SupervisorConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            check_interval_ms: 1_000,
+            backoff: Backoff::default(),
+            max_attempts: None,
+        }
+    }
+}
+
+/// Watches a rotator, and whenever its port has failed, reopens it with
+/// `reopen`, backing off between failed attempts.
+pub async fn supervise<F, Fut>(id: String, rotator: Arc<Mutex<Rotator>>, config: SupervisorConfig, mut reopen: F)
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<Box<dyn SerialPort>, String>>,
+{
+    let mut ticker = tokio::time::interval(Duration::from_millis(config.check_interval_ms));
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+    loop {
+        ticker.tick().await;
+
+        if !rotator.lock().await.is_disconnected() {
+            continue;
+        }
+
+        warn!("Rotator `{id}` is disconnected, trying to reconnect");
+        let mut delay = config.backoff.initial();
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+
+            let result = match reopen().await {
+                Ok(port) => rotator.lock().await.reconnect(port).map_err(|e| e.to_string()),
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(()) => {
+                    info!("Reconnected rotator `{id}` after {attempts} attempts");
+                    break;
+                }
+                Err(e) if config.max_attempts.is_some_and(|max| attempts >= max) => {
+                    error!("Giving up reconnecting rotator `{id}` after {attempts} attempts: {e}");
+                    return;
+                }
+                Err(e) => {
+                    info!("Attempt {attempts} at reconnecting rotator `{id}` failed, retrying in {delay:?}: {e}");
+                    tokio::time::sleep(delay).await;
+                    delay = config.backoff.next(delay);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use super::*;
+    use crate::rotator::{Error, mock::{self, MockFirmware}};
+
+    fn config(max_attempts: Option<u32>) -> SupervisorConfig {
+        SupervisorConfig {
+            enabled: true,
+            check_interval_ms: 10,
+            backoff: Backoff { initial_ms: 10, max_ms: 40 },
+            max_attempts,
+        }
+    }
+
+    /// A rotator whose port was unplugged partway through a command.
+    async fn disconnected(firmware: &MockFirmware) -> Arc<Mutex<Rotator>> {
+        firmware.lock().unplug_on = Some("GETP".to_string());
+        let mut rotator = firmware.rotator(mock::config());
+
+        let error = rotator.position_raw().await.unwrap_err();
+        assert!(matches!(error, Error::Disconnected), "{error:?}");
+
+        Arc::new(Mutex::new(rotator))
+    }
+
+    #[rocket::async_test]
+    async fn the_port_is_reopened_once_it_comes_back() {
+        let rotator = disconnected(&MockFirmware::new()).await;
+        let replacement = MockFirmware::new();
+        let attempts = Arc::new(AtomicU32::new(0));
+
+        let supervisor = tokio::spawn(supervise("default".to_string(), Arc::clone(&rotator), config(None), {
+            let (replacement, attempts) = (replacement.clone(), Arc::clone(&attempts));
+            move || {
+                let attempt = attempts.fetch_add(1, Ordering::SeqCst) + 1;
+                let port = replacement.port();
+                async move { if attempt < 3 { Err("no such port".to_string()) } else { Ok(port) } }
+            }
+        }));
+
+        for _ in 0..200 {
+            if !rotator.lock().await.is_disconnected() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        supervisor.abort();
+
+        assert!(!rotator.lock().await.is_disconnected());
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+        rotator.lock().await.position_raw().await.unwrap();
+        assert_eq!(replacement.commands(), ["GETP"]);
+    }
+
+    #[rocket::async_test]
+    async fn reconnecting_gives_up_after_the_max_attempts() {
+        let rotator = disconnected(&MockFirmware::new()).await;
+        let attempts = Arc::new(AtomicU32::new(0));
+
+        let supervisor = supervise("default".to_string(), Arc::clone(&rotator), config(Some(3)), {
+            let attempts = Arc::clone(&attempts);
+            move || {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                async { Err::<Box<dyn SerialPort>, _>("no such port".to_string()) }
+            }
+        });
+
+        tokio::time::timeout(Duration::from_secs(2), supervisor).await.unwrap();
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+        assert!(rotator.lock().await.is_disconnected());
+    }
+
+    #[rocket::async_test]
+    async fn a_connected_rotator_is_left_alone() {
+        let rotator = Arc::new(Mutex::new(MockFirmware::new().rotator(mock::config())));
+        let attempts = Arc::new(AtomicU32::new(0));
+
+        let supervisor = supervise("default".to_string(), Arc::clone(&rotator), config(None), {
+            let attempts = Arc::clone(&attempts);
+            move || {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                async { Err::<Box<dyn SerialPort>, _>("no such port".to_string()) }
+            }
+        });
+
+        assert!(tokio::time::timeout(Duration::from_millis(100), supervisor).await.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 0);
+    }
+}